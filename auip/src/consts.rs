@@ -1,2 +1,9 @@
 pub const MAX_IP_FRAGMENT_PACKET_LENGTH: usize = 65536;
+/// Size of buffers for sending a packet, MTU of device is capped to it.
 pub const NO_FRAG_PACKET_LENGTH: usize = 1500;
+
+/// Packets parked in interface waiting for ARP reply.
+pub const MAX_PENDING_PACKETS: usize = 4;
+
+/// TTL of packets originated by this host.
+pub const DEFAULT_TTL: u8 = 64;
@@ -1,3 +1,5 @@
+use auip_pkt::pcap::LinkType;
+
 /// Medium type of device
 #[derive(Debug, Clone)]
 pub enum Medium {
@@ -5,3 +7,12 @@ pub enum Medium {
 
     Ip,
 }
+
+impl From<Medium> for LinkType {
+    fn from(m: Medium) -> Self {
+        match m {
+            Medium::Ethernet => LinkType::Ethernet,
+            Medium::Ip => LinkType::Raw,
+        }
+    }
+}
@@ -3,3 +3,6 @@ pub use medium::*;
 
 mod prelude;
 pub use prelude::*;
+
+mod pcap;
+pub use pcap::*;
@@ -0,0 +1,141 @@
+use auip_pkt::pcap::PacketWriter;
+
+use crate::{Device, Medium, Result};
+
+/// Device wrapper which record every sent and received packet into capture writer.
+///
+/// `clock` return the current timestamp in microseconds. Capture is best-effort,
+/// packets are still sent and received when writer fails.
+pub struct PcapDevice<D, W, C> {
+    inner: D,
+    writer: W,
+    clock: C,
+}
+
+impl<D, W, C> PcapDevice<D, W, C>
+where
+    D: Device,
+    W: PacketWriter,
+    C: FnMut() -> u64,
+{
+    /// Wrap device, writer should be created with link type of `inner.medium()`.
+    pub fn new(inner: D, writer: W, clock: C) -> Self {
+        Self {
+            inner,
+            writer,
+            clock,
+        }
+    }
+
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut D {
+        &mut self.inner
+    }
+
+    pub fn writer(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn writer_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    pub fn into_inner(self) -> (D, W) {
+        (self.inner, self.writer)
+    }
+}
+
+impl<D, W, C> Device for PcapDevice<D, W, C>
+where
+    D: Device,
+    W: PacketWriter,
+    C: FnMut() -> u64,
+{
+    fn send(&mut self, buffer: &[u8]) -> Result<()> {
+        if let Err(e) = self.writer.write_packet((self.clock)(), buffer) {
+            log::debug!("Capture sent packet failed: {:?}.", e);
+        }
+
+        self.inner.send(buffer)
+    }
+
+    fn recv(&mut self) -> Result<Option<&[u8]>> {
+        let packet = self.inner.recv()?;
+
+        if let Some(packet) = packet {
+            if let Err(e) = self.writer.write_packet((self.clock)(), packet) {
+                log::debug!("Capture received packet failed: {:?}.", e);
+            }
+        }
+
+        Ok(packet)
+    }
+
+    fn medium(&self) -> Medium {
+        self.inner.medium()
+    }
+
+    fn mtu(&self) -> usize {
+        self.inner.mtu()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use auip_pkt::pcap::{BufferSink, PcapReader, PcapWriter};
+
+    use super::*;
+
+    struct TestDevice {
+        rx: Vec<u8>,
+        tx: Vec<Vec<u8>>,
+    }
+
+    impl Device for TestDevice {
+        fn send(&mut self, buffer: &[u8]) -> Result<()> {
+            self.tx.push(buffer.into());
+            Ok(())
+        }
+
+        fn recv(&mut self) -> Result<Option<&[u8]>> {
+            Ok(Some(&self.rx))
+        }
+
+        fn medium(&self) -> Medium {
+            Medium::Ip
+        }
+
+        fn mtu(&self) -> usize {
+            576
+        }
+    }
+
+    #[test]
+    fn test_capture_best_effort() {
+        let device = TestDevice {
+            rx: std::vec![0x45; 8],
+            tx: Vec::new(),
+        };
+
+        // Room for file header and one 8 octets packet.
+        let writer = PcapWriter::new(BufferSink::new([0u8; 48]), Medium::Ip.into(), 65535).unwrap();
+        let mut device = PcapDevice::new(device, writer, || 1);
+        assert_eq!(device.mtu(), 576);
+
+        device.send(&[1; 8]).unwrap();
+        device.send(&[2; 8]).unwrap();
+        assert_eq!(device.recv().unwrap(), Some(&[0x45; 8][..]));
+        assert_eq!(device.inner().tx, [[1; 8], [2; 8]]);
+
+        let (_, writer) = device.into_inner();
+        let sink = writer.into_sink();
+        let mut reader = PcapReader::new(sink.data()).unwrap();
+        assert_eq!(reader.next().unwrap().unwrap(), (1, &[1; 8][..]));
+        assert!(reader.next().is_none());
+    }
+}
@@ -1,6 +1,10 @@
 use auip_pkt::{layer2, layer3};
 
-use crate::{Medium, Result};
+use crate::{
+    consts,
+    time::{Duration, Instant},
+    AnySocket, Medium, Reassembly, Result, Socket, SocketHandle,
+};
 
 /// A device for sending and receiving raw packet.
 pub trait Device {
@@ -12,6 +16,11 @@ pub trait Device {
 
     /// Medium type for this device
     fn medium(&self) -> Medium;
+
+    /// Maximum length of IP packet can be sent by this device, link header excluded.
+    fn mtu(&self) -> usize {
+        consts::NO_FRAG_PACKET_LENGTH
+    }
 }
 
 /// Storage for address
@@ -23,19 +32,251 @@ pub trait AddrsStorage {
 
     /// Checking ip address is exist in address storage
     fn has_ip_addr(&self, ip_addr: &layer3::Address) -> bool;
+
+    /// Get all ip addresses, unassigned slot is unspecified address.
+    fn ip_addrs(&self) -> &[layer3::Cidr];
+
+    /// Checking multicast group is joined.
+    fn has_multicast_group(&self, group: &layer3::ipv4::Address) -> bool;
+
+    /// Checking link layer address is mapped from a joined multicast group.
+    fn has_multicast_link_addr(&self, addr: &layer2::Address) -> bool;
+}
+
+/// Get link layer address of multicast `group`, low 23 bits of group are mapped into
+/// 01:00:5e:00:00:00, RFC 1112 section 6.4.
+pub(crate) fn multicast_link_addr(group: &layer3::ipv4::Address) -> layer2::Address {
+    let bytes = group.as_bytes();
+    layer2::Address::new(0x01, 0x00, 0x5e, bytes[1] & 0x7f, bytes[2], bytes[3])
+}
+
+/// Entry of arp table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArpEntry {
+    pub mac_addr: layer2::Address,
+    /// Time the map is confirmed, `None` for static entry which never expires.
+    pub confirmed_at: Option<Instant>,
+    /// Time the entry is last used, the least recently used entry is evicted first.
+    pub used_at: Instant,
+    /// A request to refresh the entry is sent.
+    pub refreshing: bool,
+}
+
+impl ArpEntry {
+    /// Entry confirmed by arp packet received at `now`.
+    pub fn new(mac_addr: layer2::Address, now: Instant) -> Self {
+        Self {
+            mac_addr,
+            confirmed_at: Some(now),
+            used_at: now,
+            refreshing: false,
+        }
+    }
+
+    /// Entry pinned by user, it never expires or gets evicted.
+    pub fn new_static(mac_addr: layer2::Address) -> Self {
+        Self {
+            mac_addr,
+            confirmed_at: None,
+            used_at: Instant::ZERO,
+            refreshing: false,
+        }
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.confirmed_at.is_none()
+    }
+
+    /// Query whether entry is older than `timeout` at `now`.
+    pub fn is_expired(&self, now: Instant, timeout: Duration) -> bool {
+        match self.confirmed_at {
+            Some(confirmed_at) => now >= confirmed_at + timeout,
+            None => false,
+        }
+    }
 }
 
 /// Storage for arp table.
 pub trait ArpStorage {
-    /// Set map of mac address and ip address.
-    fn set_map(&mut self, mac: layer2::Address, ip_addr: layer3::ipv4::Address) -> Result<()>;
+    /// Insert entry of `ip_addr`, replace the old one.
+    ///
+    /// Storage of fixed capacity evicts the least recently used dynamic entry when full.
+    fn insert(&mut self, ip_addr: layer3::ipv4::Address, entry: ArpEntry) -> Result<()>;
+
+    /// Get entry of `ip_addr`.
+    fn get(&self, ip_addr: &layer3::ipv4::Address) -> Option<&ArpEntry>;
+
+    /// Get mutable entry of `ip_addr`.
+    fn get_mut(&mut self, ip_addr: &layer3::ipv4::Address) -> Option<&mut ArpEntry>;
+
+    /// Remove entry of `ip_addr`.
+    fn remove(&mut self, ip_addr: &layer3::ipv4::Address) -> Option<ArpEntry>;
+
+    /// Pin static map of `ip_addr` to `mac`.
+    fn set_static(&mut self, mac: layer2::Address, ip_addr: layer3::ipv4::Address) -> Result<()> {
+        self.insert(ip_addr, ArpEntry::new_static(mac))
+    }
+}
+
+/// Route to an IPv4 subnet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Route {
+    pub cidr: layer3::ipv4::Cidr,
+    /// Next hop to subnet, `None` if subnet is on link.
+    pub gateway: Option<layer3::ipv4::Address>,
+    /// Route of lower metric is preferred among routes of the same prefix length.
+    pub metric: u32,
+}
+
+impl Route {
+    /// Route to subnet on link.
+    pub fn on_link(cidr: layer3::ipv4::Cidr) -> Self {
+        Self {
+            cidr,
+            gateway: None,
+            metric: 0,
+        }
+    }
+
+    /// Route to `cidr` via `gateway`.
+    pub fn via(cidr: layer3::ipv4::Cidr, gateway: layer3::ipv4::Address, metric: u32) -> Self {
+        Self {
+            cidr,
+            gateway: Some(gateway),
+            metric,
+        }
+    }
+
+    /// Default route via `gateway`, matching any address.
+    pub fn default_via(gateway: layer3::ipv4::Address, metric: u32) -> Self {
+        Self::via(Default::default(), gateway, metric)
+    }
+
+    /// Query whether route is preferred to `other`, a longer prefix wins, then a lower
+    /// metric.
+    pub fn is_preferred_to(&self, other: &Route) -> bool {
+        (self.cidr.prefix_len(), other.metric) > (other.cidr.prefix_len(), self.metric)
+    }
+}
 
-    /// Get mac address according mac address
-    fn mac_addr(&self, ip_addr: &layer3::ipv4::Address) -> Result<Option<layer2::Address>>;
+/// Get the preferred route of `routes` matching `addr`.
+pub(crate) fn longest_prefix_match(
+    routes: impl IntoIterator<Item = Route>,
+    addr: &layer3::ipv4::Address,
+) -> Option<Route> {
+    routes
+        .into_iter()
+        .filter(|route| route.cidr.contains_addr(addr))
+        .fold(None, |best, route| match best {
+            Some(best) if !route.is_preferred_to(&best) => Some(best),
+            _ => Some(route),
+        })
 }
 
-/// Buffer to store ip fragment.
+/// Storage for routing table.
+pub trait RouteStorage {
+    /// Get all routes.
+    fn routes(&self) -> &[Route];
+
+    /// Insert `route`, replace the route to the same subnet via the same gateway.
+    fn insert(&mut self, route: Route) -> Result<()>;
+
+    /// Remove route to `cidr` via `gateway`.
+    fn remove(
+        &mut self,
+        cidr: &layer3::ipv4::Cidr,
+        gateway: Option<layer3::ipv4::Address>,
+    ) -> Option<Route>;
+
+    /// Get route of the longest prefix matching `addr`, the lower metric wins a tie.
+    fn lookup(&self, addr: &layer3::ipv4::Address) -> Option<Route> {
+        longest_prefix_match(self.routes().iter().copied(), addr)
+    }
+}
+
+/// Buffer to reassemble ip fragments.
+///
+/// Each slot holds state of a datagram being reassembled and a buffer of its payload,
+/// so the number of slots limits datagrams reassembled at the same time.
 pub trait IpFragmentBuffer {
-    /// Get ip fragment buffer, buffer length is 64k
-    fn get_buffer(&mut self, ident: u16) -> &mut [u8];
+    /// Get all slots, empty slot is `None`.
+    fn slots(&self) -> &[Option<Reassembly>];
+
+    /// Get all slots as mutable.
+    fn slots_mut(&mut self) -> &mut [Option<Reassembly>];
+
+    /// Get payload buffer of slot `index` with `len` octets, content of buffer is kept
+    /// while slot is used.
+    ///
+    /// Return `None` if `len` octets exceed memory limit of storage.
+    fn buffer(&mut self, index: usize, len: usize) -> Option<&mut [u8]>;
+
+    /// Release buffer of slot `index` after the slot is cleared.
+    fn release(&mut self, _index: usize) {}
+}
+
+/// Ring storage of packets for socket.
+///
+/// Each packet carry a metadata, such as remote endpoint of datagram.
+pub trait PacketStorage<M> {
+    /// Enqueue a packet of `len` octets, return buffer to fill payload.
+    fn enqueue(&mut self, meta: M, len: usize) -> Result<&mut [u8]>;
+
+    /// Get the oldest packet.
+    fn peek(&self) -> Option<(&M, &[u8])>;
+
+    /// Remove the oldest packet.
+    fn pop(&mut self);
+
+    /// Checking no packet in storage.
+    fn is_empty(&self) -> bool;
+
+    /// Checking no more packet can be enqueued.
+    fn is_full(&self) -> bool;
+}
+
+/// Storage for sockets polled by interface.
+pub trait SocketSet<'a> {
+    /// Get all slots of sockets, empty slot is `None`.
+    fn slots(&self) -> &[Option<Socket<'a>>];
+
+    /// Get all slots of sockets as mutable.
+    fn slots_mut(&mut self) -> &mut [Option<Socket<'a>>];
+
+    /// Insert socket into an empty slot, return index of slot.
+    fn insert(&mut self, socket: Socket<'a>) -> Result<usize>;
+
+    /// Add a socket, return typed handle of it.
+    fn add<T: AnySocket<'a>>(&mut self, socket: T) -> Result<SocketHandle<T>> {
+        let index = self.insert(socket.upcast())?;
+        Ok(SocketHandle::new(index))
+    }
+
+    /// Get socket by handle.
+    fn get<'s, T: AnySocket<'a>>(&'s self, handle: SocketHandle<T>) -> Option<&'s T>
+    where
+        'a: 's,
+    {
+        self.slots()
+            .get(handle.index())?
+            .as_ref()
+            .and_then(T::downcast)
+    }
+
+    /// Get mutable socket by handle.
+    fn get_mut<'s, T: AnySocket<'a>>(&'s mut self, handle: SocketHandle<T>) -> Option<&'s mut T>
+    where
+        'a: 's,
+    {
+        self.slots_mut()
+            .get_mut(handle.index())?
+            .as_mut()
+            .and_then(T::downcast_mut)
+    }
+
+    /// Remove socket by handle, slot is freed for later sockets.
+    fn remove<T: AnySocket<'a>>(&mut self, handle: SocketHandle<T>) -> Option<Socket<'a>> {
+        self.get(handle)?;
+        self.slots_mut()[handle.index()].take()
+    }
 }
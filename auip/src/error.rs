@@ -7,12 +7,34 @@ pub enum Error {
 
     NoSpaceForArpStorage,
 
+    NoSpaceForRouteStorage,
+
     UnexpectedType,
 
     IpAddrNotFound,
 
+    NoRouteToHost,
+
     NoVlanIdSet,
 
+    NoSpaceForSocketStorage,
+
+    NoSpaceForSocketSet,
+
+    SocketNotBound,
+
+    SocketNotConnected,
+
+    IllegalEndpoint,
+
+    IllegalSocketState,
+
+    IllegalMulticastGroup,
+
+    NoSpaceForPendingPackets,
+
+    PacketTooLarge,
+
     PacketError(auip_pkt::Error),
 }
 
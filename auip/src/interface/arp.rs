@@ -1,64 +1,127 @@
 use auip_pkt::{layer2, layer3, IntoInner};
 
-use crate::{bytes::ArpBytes, AddrsStorage, ArpStorage, Error, InterfaceConfig, Result};
-
-#[allow(clippy::too_many_arguments)]
+use crate::{
+    bytes::ArpBytes, emit_reply_header, reply_header_len, reply_protocol, time::Instant,
+    AddrsStorage, ArpEntry, ArpStorage, InterfaceConfig, Result,
+};
+
+/// Record sender of arp packet received at `now`, and build reply for request to this host.
+///
+/// Reply is SNAP encapsulated if `snap` is set, as the request was.
+///
+/// Existing entry is updated by any arp packet, new entry is only added for packet to
+/// this host (RFC 826). Static entry is never changed.
+///
+/// Return the address whose entry is recorded, if any, and the reply.
 pub(crate) fn build_and_record_arp(
+    now: Instant,
     sa: layer2::Address,
-    sha: layer3::arp::HardwareAddress,
-    spa: layer3::arp::ProtocolAddress,
-    tha: layer2::Address,
-    tpa: layer3::arp::ProtocolAddress,
+    arp: &layer3::arp::Repr,
+    snap: bool,
     config: &InterfaceConfig,
     addrs_storage: &impl AddrsStorage,
     arp_storage: &mut impl ArpStorage,
-) -> Result<Option<ArpBytes>> {
-    let mac_addr = sha.mac_addr().ok_or(Error::UnexpectedType)?;
-    let ip_addr = spa.ipv4_addr().ok_or(Error::UnexpectedType)?;
-
-    log::debug!("add arp map {} -> {}", &mac_addr, &ip_addr);
-
-    arp_storage.set_map(*mac_addr, *ip_addr)?;
-
-    let addr = tpa.ipv4_addr().ok_or(Error::UnexpectedType)?;
-
-    let ip_addr = layer3::Address::Ipv4(*addr);
-
-    if addrs_storage.has_ip_addr(&ip_addr) {
-        let arp_bytes = ArpBytes::default();
-
-        let mut layer2_pkt = layer2::ethernet::Packet::new_unchecked(arp_bytes);
-
-        layer2_pkt.set_src_addr(*addrs_storage.mac_addr());
-        layer2_pkt.set_dest_addr(sa);
-
-        let layer3_protocol = layer2::Layer3Protocol::ARP;
-
-        if config.vlan.tag_vlan0 {
-            let vlanid = config.vlan.vlanid0.ok_or(Error::NoVlanIdSet)?;
-            layer2_pkt.set_protocol(layer2::Protocol::IEEE8021Q(vlanid, layer3_protocol));
-        } else if config.vlan.tag_vlan0 && config.vlan.tag_vlan1 {
-            let vlanid0 = config.vlan.vlanid0.ok_or(Error::NoVlanIdSet)?;
-            let vlanid1 = config.vlan.vlanid0.ok_or(Error::NoVlanIdSet)?;
-            layer2_pkt.set_protocol(layer2::Protocol::QinQ(vlanid0, vlanid1, layer3_protocol));
-        } else {
-            layer2_pkt.set_protocol(layer2::Protocol::Layer3Protocol(layer3_protocol));
+) -> Result<(Option<layer3::ipv4::Address>, Option<ArpBytes>)> {
+    let mac_addr = arp.source_hardware_addr;
+    let ip_addr = arp.source_protocol_addr;
+
+    let target_addr = layer3::Address::Ipv4(arp.target_protocol_addr);
+    let to_host = addrs_storage.has_ip_addr(&target_addr);
+
+    let recorded = match arp_storage.get_mut(&ip_addr) {
+        Some(entry) if entry.is_static() => None,
+        Some(entry) => {
+            if entry.mac_addr != mac_addr {
+                log::debug!("update arp map {} -> {}", &mac_addr, &ip_addr);
+            }
+
+            *entry = ArpEntry {
+                used_at: entry.used_at,
+                ..ArpEntry::new(mac_addr, now)
+            };
+            Some(ip_addr)
         }
+        None if to_host && !ip_addr.is_unspecified() => {
+            log::debug!("add arp map {} -> {}", &mac_addr, &ip_addr);
+            arp_storage.insert(ip_addr, ArpEntry::new(mac_addr, now))?;
+            Some(ip_addr)
+        }
+        None => None,
+    };
 
-        let mut pkt = layer3::arp::Packet::new_unchecked(layer2_pkt.payload_mut());
-
-        pkt.set_operation(layer3::arp::Operation::Reply);
+    if arp.operation != layer3::arp::Operation::Request {
+        return Ok((recorded, None));
+    }
 
-        pkt.set_source_hardware_address(layer3::arp::HardwareAddress::Ethernet(tha))?;
-        pkt.set_source_protocol_address(tpa)?;
-        pkt.set_target_hardware_address(sha)?;
-        pkt.set_target_protocol_address(spa)?;
+    if to_host {
+        let protocol = reply_protocol(
+            config,
+            layer2::Layer3Protocol::ARP,
+            snap,
+            layer3::arp::field::PACKET_LEN,
+        )?;
+
+        let eth_repr = layer2::ethernet::Repr {
+            src_addr: *addrs_storage.mac_addr(),
+            dst_addr: sa,
+            protocol,
+        };
+
+        let arp_repr = layer3::arp::Repr {
+            operation: layer3::arp::Operation::Reply,
+            source_hardware_addr: *addrs_storage.mac_addr(),
+            source_protocol_addr: arp.target_protocol_addr,
+            target_hardware_addr: arp.source_hardware_addr,
+            target_protocol_addr: arp.source_protocol_addr,
+        };
+
+        let mut layer2_pkt = layer2::ethernet::Packet::new_unchecked(ArpBytes::default());
+        emit_reply_header(&eth_repr, layer2::Layer3Protocol::ARP, &mut layer2_pkt);
+
+        let header_len = reply_header_len(&eth_repr);
+        let mut pkt = layer3::arp::Packet::new_unchecked(&mut layer2_pkt.as_mut()[header_len..]);
+        arp_repr.emit(&mut pkt)?;
 
         log::debug!("Send packet: {}", layer2_pkt);
 
-        Ok(Some(layer2_pkt.into_inner()))
+        Ok((recorded, Some(layer2_pkt.into_inner())))
     } else {
         log::debug!("Ip address mismatch, Drop it.");
-        Ok(None)
+        Ok((recorded, None))
     }
 }
+
+/// Build ARP request for link layer address of `target_addr`, sent from `src_addr`.
+///
+/// Request is broadcast, or sent to `dst_addr` for refreshing a known entry.
+pub(crate) fn build_arp_request(
+    target_addr: layer3::ipv4::Address,
+    src_addr: layer3::ipv4::Address,
+    dst_addr: layer2::Address,
+    config: &InterfaceConfig,
+    addrs_storage: &impl AddrsStorage,
+) -> Result<ArpBytes> {
+    let eth_repr = layer2::ethernet::Repr {
+        src_addr: *addrs_storage.mac_addr(),
+        dst_addr,
+        protocol: config.vlan.protocol(layer2::Layer3Protocol::ARP)?,
+    };
+
+    let arp_repr = layer3::arp::Repr {
+        operation: layer3::arp::Operation::Request,
+        source_hardware_addr: *addrs_storage.mac_addr(),
+        source_protocol_addr: src_addr,
+        target_hardware_addr: layer2::Address::default(),
+        target_protocol_addr: target_addr,
+    };
+
+    let mut layer2_pkt = layer2::ethernet::Packet::new_unchecked(ArpBytes::default());
+    eth_repr.emit(&mut layer2_pkt);
+
+    let mut pkt = layer3::arp::Packet::new_unchecked(layer2_pkt.payload_mut());
+    arp_repr.emit(&mut pkt)?;
+
+    log::debug!("Send packet: {}", layer2_pkt);
+
+    Ok(layer2_pkt.into_inner())
+}
@@ -1,6 +1,6 @@
 use core::ops::Deref;
 
-use auip_pkt::{layer2, layer3, layer4};
+use auip_pkt::{layer2, layer3};
 
 use crate::consts;
 
@@ -42,7 +42,6 @@ define_bytes!(ArpBytes, MIN_ARP_PACKET_LEN);
 
 define_bytes!(NoFragIpBytes, consts::NO_FRAG_PACKET_LENGTH);
 
-const ICMPV4_PACKET_LENGTH: usize = layer3::ipv4::field::HEADER_LEN_WITHOUT_OPTION as usize + layer4::icmpv4::field::HEADER_END;
-define_bytes!(Icmpv4Bytes, ICMPV4_PACKET_LENGTH);
-
-
+const MAX_FRAME_LENGTH: usize =
+    layer2::ethernet::field::MAX_HEADER_LEN + consts::NO_FRAG_PACKET_LENGTH;
+define_bytes!(FrameBytes, MAX_FRAME_LENGTH);
@@ -1,9 +1,77 @@
-use auip_pkt::layer2::VlanId;
+use auip_pkt::layer2::{self, VlanId, VlanTag};
+
+use crate::{time::Duration, Error, Result};
 
 /// Config for interface
 #[derive(Debug, Default)]
 pub struct InterfaceConfig {
     pub vlan: VlanConfig,
+    pub arp: ArpConfig,
+    pub ipv4: Ipv4Config,
+    #[cfg(feature = "tcp")]
+    pub tcp: TcpConfig,
+}
+
+/// Config IPv4 for interface
+#[derive(Debug)]
+pub struct Ipv4Config {
+    /// Time to reassemble a fragmented datagram since its first fragment arrives.
+    pub reassembly_timeout: Duration,
+
+    /// Set don't fragment on packets sent by this host, packets exceed MTU are not
+    /// sent in fragments but return error.
+    pub dont_frag: bool,
+
+    /// Keep received packets not addressed to this host for `Router` to forward.
+    pub forwarding: bool,
+}
+
+impl Default for Ipv4Config {
+    fn default() -> Self {
+        Self {
+            reassembly_timeout: Duration::from_secs(30),
+            dont_frag: false,
+            forwarding: false,
+        }
+    }
+}
+
+/// Config TCP for interface
+#[cfg(feature = "tcp")]
+#[derive(Debug, Default)]
+pub struct TcpConfig {
+    /// Secret key of initial sequence numbers, RFC 6528 section 3.
+    ///
+    /// Sequence numbers are predictable with the default zero key, set it to a random
+    /// value on each boot, such as from a hardware random number generator.
+    pub isn_secret: u128,
+}
+
+/// Config arp cache for interface
+#[derive(Debug)]
+pub struct ArpConfig {
+    /// Time an entry is valid after it is confirmed.
+    pub timeout: Duration,
+
+    /// Time before expiry to send request for refreshing a used entry.
+    pub refresh: Duration,
+
+    /// Time to wait for reply before request is sent again.
+    pub retry_interval: Duration,
+
+    /// Requests sent for an address before packets waiting for it are dropped.
+    pub max_requests: usize,
+}
+
+impl Default for ArpConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(60),
+            refresh: Duration::from_secs(5),
+            retry_interval: Duration::from_secs(1),
+            max_requests: 3,
+        }
+    }
 }
 
 /// Config vlan for interface
@@ -14,4 +82,29 @@ pub struct VlanConfig {
 
     pub tag_vlan0: bool,
     pub tag_vlan1: bool,
+
+    /// TPID of outer tag on double tagged frame, use 802.1ad `0x88A8` if not set.
+    pub outer_tpid: Option<u16>,
+}
+
+impl VlanConfig {
+    /// Build the ethernet protocol for sending frames with configured tags.
+    pub fn protocol(&self, l3: layer2::Layer3Protocol) -> Result<layer2::Protocol> {
+        if self.tag_vlan0 && self.tag_vlan1 {
+            let vlanid0 = self.vlanid0.ok_or(Error::NoVlanIdSet)?;
+            let vlanid1 = self.vlanid1.ok_or(Error::NoVlanIdSet)?;
+
+            let mut outer = VlanTag::service(vlanid0);
+            if let Some(tpid) = self.outer_tpid {
+                outer.tpid = tpid;
+            }
+
+            Ok(layer2::Protocol::QinQ(outer, VlanTag::new(vlanid1), l3))
+        } else if self.tag_vlan0 {
+            let vlanid = self.vlanid0.ok_or(Error::NoVlanIdSet)?;
+            Ok(layer2::Protocol::IEEE8021Q(VlanTag::new(vlanid), l3))
+        } else {
+            Ok(layer2::Protocol::Layer3Protocol(l3))
+        }
+    }
 }
@@ -0,0 +1,639 @@
+use auip_pkt::{
+    layer3::{
+        ipv4::{self, field, Packet},
+        Protocol,
+    },
+    IntoInner,
+};
+
+use crate::{
+    time::{Duration, Instant},
+    IpFragmentBuffer,
+};
+
+/// Holes tracked for a datagram, datagram is dropped if it needs more.
+const MAX_HOLES: usize = 8;
+
+/// Header with the most options, RFC 791 section 3.1.
+pub(crate) const MAX_HEADER_LEN: usize = 60;
+
+/// Fragments of the same datagram share source, destination, protocol and ident,
+/// RFC 791 section 3.2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FragmentKey {
+    pub src_addr: ipv4::Address,
+    pub dst_addr: ipv4::Address,
+    pub protocol: Protocol,
+    pub ident: u16,
+}
+
+impl FragmentKey {
+    pub fn from_packet<T: AsRef<[u8]>>(pkt: &Packet<T>) -> Self {
+        Self {
+            src_addr: pkt.src_addr(),
+            dst_addr: pkt.dst_addr(),
+            protocol: pkt.protocol(),
+            ident: pkt.ident(),
+        }
+    }
+}
+
+/// State of a datagram being reassembled, with hole descriptors of RFC 815.
+#[derive(Debug, Clone, Copy)]
+pub struct Reassembly {
+    key: FragmentKey,
+    /// Ranges of payload not received yet, end of the last hole is unknown before
+    /// the last fragment arrives.
+    holes: [Option<(usize, usize)>; MAX_HOLES],
+    /// Payload length, known from the last fragment.
+    total_len: Option<usize>,
+    /// End of data received so far.
+    received_end: usize,
+    /// Header of the first fragment, empty before it arrives.
+    header: [u8; MAX_HEADER_LEN],
+    header_len: usize,
+    expires_at: Instant,
+}
+
+impl Reassembly {
+    fn new(key: FragmentKey, expires_at: Instant) -> Self {
+        let mut holes = [None; MAX_HOLES];
+        holes[0] = Some((0, usize::MAX));
+
+        Self {
+            key,
+            holes,
+            total_len: None,
+            received_end: 0,
+            header: [0; MAX_HEADER_LEN],
+            header_len: 0,
+            expires_at,
+        }
+    }
+
+    pub fn key(&self) -> &FragmentKey {
+        &self.key
+    }
+
+    /// Payload length of the datagram, known after the last fragment arrives.
+    pub fn payload_len(&self) -> usize {
+        self.total_len.unwrap_or_default()
+    }
+
+    /// Header of the datagram, taken from the first fragment.
+    pub fn header(&self) -> &[u8] {
+        &self.header[..self.header_len]
+    }
+
+    /// Time the datagram is dropped if it is not complete.
+    pub fn expires_at(&self) -> Instant {
+        self.expires_at
+    }
+
+    fn is_complete(&self) -> bool {
+        self.holes.iter().all(Option::is_none)
+    }
+
+    /// Fill holes with fragment of `data` at `offset`, data overlapping received data is
+    /// ignored, so the first copy wins.
+    ///
+    /// Return `false` if fragment is inconsistent with received ones, or too many holes.
+    fn add(&mut self, offset: usize, data: &[u8], more: bool, buffer: &mut [u8]) -> bool {
+        let first = offset;
+        let last = offset + data.len();
+
+        let consistent = match self.total_len {
+            Some(total_len) => last <= total_len && (more || last == total_len),
+            None => more || last >= self.received_end,
+        };
+
+        if !consistent {
+            return false;
+        }
+
+        let mut holes = [None; MAX_HOLES];
+        let mut count = 0;
+        let mut push = |hole: (usize, usize)| {
+            if count == MAX_HOLES {
+                return false;
+            }
+            holes[count] = Some(hole);
+            count += 1;
+            true
+        };
+
+        for (hole_first, hole_last) in self.holes.iter().flatten().copied() {
+            // The last fragment closes holes after it.
+            let hole_last = if more { hole_last } else { hole_last.min(last) };
+
+            if hole_first >= hole_last {
+                continue;
+            }
+
+            if first >= hole_last || last <= hole_first {
+                if !push((hole_first, hole_last)) {
+                    return false;
+                }
+                continue;
+            }
+
+            let (start, end) = (first.max(hole_first), last.min(hole_last));
+            buffer[start..end].copy_from_slice(&data[start - first..end - first]);
+
+            if first > hole_first && !push((hole_first, first)) {
+                return false;
+            }
+
+            if last < hole_last && !push((last, hole_last)) {
+                return false;
+            }
+        }
+
+        self.holes = holes;
+        self.received_end = self.received_end.max(last);
+
+        if !more {
+            self.total_len = Some(last);
+        }
+
+        true
+    }
+}
+
+/// Add fragment `pkt` received at `now` to reassembly in `buffer`.
+///
+/// Return slot index and the reassembly when the datagram is complete, payload is in
+/// buffer of the slot until the next call of [expire_fragments].
+pub(crate) fn reassemble_ipv4(
+    now: Instant,
+    timeout: Duration,
+    pkt: &Packet<&[u8]>,
+    buffer: &mut impl IpFragmentBuffer,
+) -> Option<(usize, Reassembly)> {
+    let key = FragmentKey::from_packet(pkt);
+    let offset = pkt.frag_offset() as usize;
+    let data = pkt.payload();
+    let more = pkt.more_frags();
+    let header_len = pkt.header_len() as usize;
+
+    // Every fragment except the last one carry a multiple of 8 octets, and datagram
+    // with its header fits in total length field, RFC 791 section 3.2.
+    if (more && !data.len().is_multiple_of(8))
+        || offset + data.len() > u16::MAX as usize - header_len
+    {
+        log::debug!("Invalid fragment of {:?}, Drop it.", key);
+        return None;
+    }
+
+    expire_fragments(now, buffer);
+
+    let index = buffer
+        .slots()
+        .iter()
+        .position(|slot| matches!(slot, Some(reassembly) if reassembly.key == key));
+
+    let index = match index {
+        Some(index) => index,
+        None => {
+            let index = buffer.slots().iter().position(Option::is_none);
+
+            let index = match index {
+                Some(index) => index,
+                None => {
+                    log::debug!("No space to reassemble {:?}, Drop it.", key);
+                    return None;
+                }
+            };
+
+            buffer.slots_mut()[index] = Some(Reassembly::new(key, now + timeout));
+            index
+        }
+    };
+
+    let mut reassembly = buffer.slots()[index]?;
+
+    let added = match buffer.buffer(index, offset + data.len()) {
+        Some(payload) => reassembly.add(offset, data, more, payload),
+        None => {
+            log::debug!("Memory limit of reassembly reached.");
+            false
+        }
+    };
+
+    if !added {
+        log::debug!("Drop datagram {:?} being reassembled.", key);
+        buffer.slots_mut()[index] = None;
+        buffer.release(index);
+        return None;
+    }
+
+    if offset == 0 {
+        let bytes = pkt.clone().into_inner();
+        reassembly.header[..header_len].copy_from_slice(&bytes[..header_len]);
+        reassembly.header_len = header_len;
+    }
+
+    if reassembly.is_complete() {
+        buffer.slots_mut()[index] = None;
+
+        // Header of the first fragment may be longer than of the others.
+        let total_len = reassembly.header_len + reassembly.payload_len();
+        if total_len > u16::MAX as usize {
+            log::debug!("Datagram {:?} too large, Drop it.", key);
+            buffer.release(index);
+            return None;
+        }
+
+        let mut header = Packet::new_unchecked(&mut reassembly.header[..]);
+        header.set_total_len(total_len as u16);
+        header.set_more_frags(false);
+        header.set_frag_offset(0);
+        header.fill_checksum();
+
+        return Some((index, reassembly));
+    }
+
+    buffer.slots_mut()[index] = Some(reassembly);
+    None
+}
+
+/// Drop datagrams not complete before `now`, and release buffers of empty slots.
+pub(crate) fn expire_fragments(now: Instant, buffer: &mut impl IpFragmentBuffer) {
+    for index in 0..buffer.slots().len() {
+        let slot = &mut buffer.slots_mut()[index];
+
+        if matches!(slot, Some(reassembly) if reassembly.expires_at <= now) {
+            log::debug!("Reassembly of {:?} timeout.", slot.map(|r| r.key));
+            *slot = None;
+        }
+
+        if slot.is_none() {
+            buffer.release(index);
+        }
+    }
+}
+
+/// Split `total_len` octets of payload into fragments carrying at most `size` octets,
+/// yield `(offset, len, more)` of each fragment.
+///
+/// Every fragment except the last one carry a multiple of 8 octets, RFC 791 section 3.2.
+pub(crate) fn fragments(
+    total_len: usize,
+    size: usize,
+) -> impl Iterator<Item = (usize, usize, bool)> {
+    let size = (size & !7).max(8);
+
+    (0..total_len).step_by(size).map(move |offset| {
+        let len = size.min(total_len - offset);
+        (offset, len, offset + len < total_len)
+    })
+}
+
+/// Emit fragment of `ip_repr` into `buffer`, carrying `len` octets at `offset` of
+/// payload made of `header` followed by `payload`.
+pub(crate) fn emit_fragment(
+    ip_repr: &ipv4::Repr,
+    (offset, len, more): (usize, usize, bool),
+    header: &[u8],
+    payload: &[u8],
+    buffer: &mut [u8],
+) {
+    let repr = ipv4::Repr {
+        payload_len: len,
+        ..ip_repr.clone()
+    };
+
+    let mut pkt = Packet::new_unchecked(buffer);
+    repr.emit(&mut pkt);
+    pkt.set_more_frags(more);
+    pkt.set_frag_offset(offset as u16);
+
+    let data = pkt.payload_mut();
+    let head = header.get(offset..).unwrap_or_default();
+    let split = head.len().min(len);
+    data[..split].copy_from_slice(&head[..split]);
+
+    let start = (offset + split).saturating_sub(header.len());
+    data[split..].copy_from_slice(&payload[start..start + len - split]);
+
+    pkt.fill_checksum();
+}
+
+/// Copy options of the first fragment in `options` into `buffer`, only options with the
+/// copied flag are kept if not `first`, RFC 791 section 3.1.
+///
+/// Return length of copied options padded to multiple of 4 octets.
+fn copy_options(options: &[u8], first: bool, buffer: &mut [u8]) -> usize {
+    let mut len = 0;
+    let mut pos = 0;
+
+    while pos < options.len() {
+        let (option, option_len) = match ipv4::Ipv4Option::parse(&options[pos..]) {
+            Ok(parsed) => parsed,
+            Err(_) => break,
+        };
+
+        if option == ipv4::Ipv4Option::EndOfList {
+            break;
+        }
+
+        if first || options[pos] & 0x80 != 0 {
+            buffer[len..len + option_len].copy_from_slice(&options[pos..pos + option_len]);
+            len += option_len;
+        }
+
+        pos += option_len;
+    }
+
+    let padded = (len + 3) & !3;
+    buffer[len..padded].fill(ipv4::consts::TYPE_END);
+    padded
+}
+
+/// Get length of fragment of received packet in `bytes` emitted by [emit_refragment],
+/// carrying `len` octets at `offset` of its payload.
+pub(crate) fn refragment_len(bytes: &[u8], (offset, len, _): (usize, usize, bool)) -> usize {
+    let pkt = Packet::new_unchecked(bytes);
+    let first = pkt.frag_offset() == 0 && offset == 0;
+
+    let mut options = [0u8; MAX_HEADER_LEN];
+    let options_len = copy_options(pkt.options(), first, &mut options);
+
+    field::HEADER_LEN_WITHOUT_OPTION as usize + options_len + len
+}
+
+/// Emit fragment of received packet in `bytes` into `buffer` with `ttl`, carrying `len`
+/// octets at `offset` of its payload.
+///
+/// Header is copied, with all options in the first fragment and options with the copied
+/// flag in others. Fragment of a fragment is placed relative to it.
+pub(crate) fn emit_refragment(
+    bytes: &[u8],
+    (offset, len, more): (usize, usize, bool),
+    ttl: u8,
+    buffer: &mut [u8],
+) {
+    let pkt = Packet::new_unchecked(bytes);
+    let first = pkt.frag_offset() == 0 && offset == 0;
+
+    let fixed_len = field::HEADER_LEN_WITHOUT_OPTION as usize;
+    buffer[..fixed_len].copy_from_slice(&bytes[..fixed_len]);
+
+    let header_len = fixed_len + copy_options(pkt.options(), first, &mut buffer[fixed_len..]);
+    buffer[header_len..header_len + len].copy_from_slice(&pkt.payload()[offset..offset + len]);
+
+    let mut frag = Packet::new_unchecked(buffer);
+    frag.set_header_len(header_len as u8);
+    frag.set_total_len((header_len + len) as u16);
+    frag.set_more_frags(more || pkt.more_frags());
+    frag.set_frag_offset(pkt.frag_offset() + offset as u16);
+    frag.set_ttl(ttl);
+    frag.fill_checksum();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::storage::fixed::IpFragment;
+
+    fn fragment(src: u8, ident: u16, offset: usize, data: &[u8], more: bool) -> Vec<u8> {
+        let repr = ipv4::Repr {
+            src_addr: ipv4::Address::new(10, 0, 0, src),
+            dst_addr: ipv4::Address::new(10, 0, 0, 100),
+            protocol: Protocol::Udp,
+            payload_len: data.len(),
+            ttl: 64,
+            ident,
+            dont_frag: false,
+        };
+
+        let mut buffer = std::vec![0u8; repr.buffer_len() + data.len()];
+        let mut pkt = Packet::new_unchecked(&mut buffer[..]);
+        repr.emit(&mut pkt);
+        pkt.set_more_frags(more);
+        pkt.set_frag_offset(offset as u16);
+        pkt.payload_mut().copy_from_slice(data);
+        pkt.fill_checksum();
+
+        buffer
+    }
+
+    fn add(now: Instant, buffer: &mut IpFragment<2>, bytes: &[u8]) -> Option<(usize, Reassembly)> {
+        let pkt = Packet::new_checked(bytes).unwrap();
+        assert_eq!(pkt.frag_offset() % 8, 0);
+        reassemble_ipv4(now, Duration::from_secs(30), &pkt, buffer)
+    }
+
+    #[test]
+    fn test_reassemble() {
+        let data: Vec<u8> = (0..40).collect();
+        let mut buffer = IpFragment::<2>::default();
+        let now = Instant::ZERO;
+
+        // Out of order, and overlapping fragment keeps data received first.
+        assert!(add(now, &mut buffer, &fragment(1, 7, 32, &data[32..], false)).is_none());
+        assert!(add(now, &mut buffer, &fragment(1, 7, 8, &[0xff; 16], true)).is_none());
+        // Same ident from another source is a different datagram.
+        assert!(add(now, &mut buffer, &fragment(2, 7, 0, &[0xee; 8], true)).is_none());
+        assert!(add(now, &mut buffer, &fragment(1, 7, 0, &data[..16], true)).is_none());
+
+        let (index, reassembly) =
+            add(now, &mut buffer, &fragment(1, 7, 16, &data[16..32], true)).unwrap();
+        let len = reassembly.payload_len();
+        assert_eq!(len, 40);
+
+        // Header is of the whole datagram.
+        let header = Packet::new_unchecked(reassembly.header());
+        assert!(header.verify_checksum());
+        assert_eq!(header.total_len(), 60);
+        assert!(!header.more_frags());
+        assert_eq!(header.frag_offset(), 0);
+
+        let payload = buffer.buffer(index, len).unwrap();
+        assert_eq!(&payload[..8], &data[..8]);
+        assert_eq!(&payload[8..24], &[0xff; 16]);
+        assert_eq!(&payload[24..], &data[24..]);
+
+        // Datagram from the other source is still being reassembled.
+        assert_eq!(buffer.slots().iter().flatten().count(), 1);
+    }
+
+    #[test]
+    fn test_invalid_fragment() {
+        let mut buffer = IpFragment::<2>::default();
+        let now = Instant::ZERO;
+
+        // Fragment with more fragments must be multiple of 8 octets.
+        assert!(add(now, &mut buffer, &fragment(1, 1, 0, &[0; 12], true)).is_none());
+        assert!(buffer.slots().iter().all(Option::is_none));
+
+        // Data after the last fragment drops the datagram.
+        assert!(add(now, &mut buffer, &fragment(1, 1, 16, &[0; 8], true)).is_none());
+        assert!(add(now, &mut buffer, &fragment(1, 1, 0, &[0; 8], false)).is_none());
+        assert!(buffer.slots().iter().all(Option::is_none));
+
+        // A different length of last fragment drops the datagram.
+        assert!(add(now, &mut buffer, &fragment(1, 2, 8, &[0; 8], false)).is_none());
+        assert!(add(now, &mut buffer, &fragment(1, 2, 8, &[0; 4], false)).is_none());
+        assert!(buffer.slots().iter().all(Option::is_none));
+
+        // Datagram with its header must fit in total length.
+        let offset = u16::MAX as usize - 20 - 3;
+        assert!(add(now, &mut buffer, &fragment(1, 2, offset, &[0; 4], false)).is_none());
+        assert!(buffer.slots().iter().all(Option::is_none));
+        assert!(add(now, &mut buffer, &fragment(1, 2, offset, &[0; 3], false)).is_none());
+        assert!(buffer.slots()[0].is_some());
+        buffer.slots_mut()[0] = None;
+
+        // No space for the third datagram.
+        assert!(add(now, &mut buffer, &fragment(1, 3, 0, &[0; 8], true)).is_none());
+        assert!(add(now, &mut buffer, &fragment(1, 4, 0, &[0; 8], true)).is_none());
+        assert!(add(now, &mut buffer, &fragment(1, 5, 0, &[0; 8], true)).is_none());
+        assert!(add(now, &mut buffer, &fragment(1, 5, 8, &[0; 8], false)).is_none());
+    }
+
+    #[test]
+    fn test_fragments() {
+        let header: Vec<u8> = (0..12).collect();
+        let payload: Vec<u8> = (12..60).collect();
+
+        let ip_repr = ipv4::Repr {
+            src_addr: ipv4::Address::new(10, 0, 0, 1),
+            dst_addr: ipv4::Address::new(10, 0, 0, 100),
+            protocol: Protocol::Udp,
+            payload_len: 60,
+            ttl: 64,
+            ident: 9,
+            dont_frag: false,
+        };
+
+        // Size is rounded down to multiple of 8 octets.
+        let frags: Vec<_> = fragments(60, 21).collect();
+        assert_eq!(
+            frags,
+            [
+                (0, 16, true),
+                (16, 16, true),
+                (32, 16, true),
+                (48, 12, false)
+            ]
+        );
+
+        let mut buffer = IpFragment::<2>::default();
+        let mut result = None;
+
+        for frag in frags.into_iter().rev() {
+            let mut bytes = std::vec![0u8; ip_repr.buffer_len() + frag.1];
+            emit_fragment(&ip_repr, frag, &header, &payload, &mut bytes);
+
+            let pkt = Packet::new_checked(&bytes[..]).unwrap();
+            assert!(pkt.verify_checksum());
+            assert_eq!(pkt.ident(), 9);
+            assert_eq!(pkt.more_frags(), frag.2);
+
+            result = reassemble_ipv4(Instant::ZERO, Duration::from_secs(30), &pkt, &mut buffer);
+        }
+
+        let (index, reassembly) = result.unwrap();
+        let len = reassembly.payload_len();
+        let expected: Vec<u8> = (0..60).collect();
+        assert_eq!(buffer.buffer(index, len).unwrap(), &expected[..]);
+    }
+
+    #[test]
+    fn test_refragment_options() {
+        // No operation, record route and router alert, only router alert is copied.
+        let options = [1, 7, 7, 4, 0, 0, 0, 0, 0x94, 4, 0, 0];
+        let payload: Vec<u8> = (0..24).collect();
+
+        let mut bytes = fragment(1, 3, 0, &[0; 36], false);
+        let mut pkt = Packet::new_unchecked(&mut bytes[..]);
+        pkt.set_header_len(32);
+        pkt.options_mut().copy_from_slice(&options);
+        pkt.payload_mut().copy_from_slice(&payload);
+        pkt.fill_checksum();
+
+        let frags: Vec<_> = fragments(24, 16).collect();
+        assert_eq!(frags, [(0, 16, true), (16, 8, false)]);
+
+        let mut emitted = Vec::new();
+
+        for frag in frags {
+            let len = refragment_len(&bytes, frag);
+            let mut buffer = std::vec![0u8; len];
+            emit_refragment(&bytes, frag, 63, &mut buffer);
+
+            let pkt = Packet::new_checked(&buffer[..]).unwrap();
+            assert!(pkt.verify_checksum());
+            assert_eq!(pkt.total_len() as usize, len);
+            assert_eq!(pkt.payload(), &payload[frag.0..frag.0 + frag.1]);
+            emitted.push(buffer);
+        }
+
+        let first = Packet::new_unchecked(&emitted[0][..]);
+        assert_eq!(first.options(), &options);
+
+        let last = Packet::new_unchecked(&emitted[1][..]);
+        assert_eq!(last.header_len(), 24);
+        assert_eq!(last.options(), &[0x94, 4, 0, 0]);
+        assert_eq!(last.frag_offset(), 16);
+        assert!(!last.more_frags());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_release_buffers() {
+        let mut buffer = crate::storage::dynamic::IpFragment::new(2);
+        buffer.set_memory_limit(64);
+
+        let add = |now, buffer: &mut crate::storage::dynamic::IpFragment, bytes: &[u8]| {
+            let pkt = Packet::new_checked(bytes).unwrap();
+            reassemble_ipv4(now, Duration::from_secs(30), &pkt, buffer)
+        };
+
+        // Buffer of completed datagram is released by the next call.
+        let now = Instant::ZERO;
+        assert!(add(now, &mut buffer, &fragment(1, 1, 0, &[0; 32], true)).is_none());
+        assert!(add(now, &mut buffer, &fragment(1, 1, 32, &[0; 8], false)).is_some());
+        assert!(buffer.memory_used() <= 64);
+        expire_fragments(now, &mut buffer);
+        assert_eq!(buffer.memory_used(), 0);
+
+        // Another datagram takes the whole limit.
+        assert!(add(now, &mut buffer, &fragment(1, 2, 56, &[0; 8], false)).is_none());
+        assert!(buffer.memory_used() <= 64);
+
+        // Buffer of expired datagram is released.
+        expire_fragments(Instant::from_secs(30), &mut buffer);
+        assert!(buffer.slots().iter().all(Option::is_none));
+        assert_eq!(buffer.memory_used(), 0);
+
+        // Buffer of dropped datagram is released.
+        assert!(add(now, &mut buffer, &fragment(1, 3, 56, &[0; 8], false)).is_none());
+        assert!(add(now, &mut buffer, &fragment(1, 3, 56, &[0; 4], false)).is_none());
+        assert_eq!(buffer.memory_used(), 0);
+    }
+
+    #[test]
+    fn test_reassembly_timeout() {
+        let mut buffer = IpFragment::<2>::default();
+
+        assert!(add(
+            Instant::ZERO,
+            &mut buffer,
+            &fragment(1, 1, 0, &[0; 8], true)
+        )
+        .is_none());
+        assert_eq!(
+            buffer.slots()[0].unwrap().expires_at(),
+            Instant::from_secs(30)
+        );
+
+        // The first fragment is dropped after timeout.
+        let now = Instant::from_secs(30);
+        assert!(add(now, &mut buffer, &fragment(1, 1, 8, &[0; 8], false)).is_none());
+        assert!(add(now, &mut buffer, &fragment(1, 1, 0, &[0; 8], true)).is_some());
+    }
+}
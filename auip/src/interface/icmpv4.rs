@@ -1,25 +1,132 @@
-use auip_pkt::layer4::icmpv4::{Message, Packet, TimeExceeded};
+use auip_pkt::{
+    layer3::{self, ipv4},
+    layer4::icmpv4::{Message, Packet, Repr},
+    utils::checksum,
+};
 
-use crate::Result;
+/// Length of type, code and checksum of ICMPv4 message.
+const HEADER_LEN: usize = 4;
 
-pub fn poll_icmpv4(bytes: &[u8]) -> Result<()> {
-    let pkt = Packet::new_checked(bytes)?;
+use crate::{
+    consts, AddrsStorage, ArpStorage, Device, Error, IcmpSocket, Interface, IpFragmentBuffer,
+    Result, RouteStorage, Socket,
+};
+
+/// Handle ICMPv4 packet addressed to this host.
+///
+/// Echo request is answered by stack, other messages are delivered to sockets,
+/// malformed message is dropped. Return the length of reply written into `tx`, if any.
+pub fn poll_icmpv4(
+    src_addr: layer3::Address,
+    bytes: &[u8],
+    sockets: &mut [Option<Socket<'_>>],
+    tx: &mut [u8],
+) -> Result<Option<usize>> {
+    let pkt = match Packet::new_checked(bytes) {
+        Ok(pkt) => pkt,
+        Err(e) => {
+            log::debug!("Malformed ICMP message: {:?}, Drop it.", e);
+            return Ok(None);
+        }
+    };
 
     log::debug!("Receive packet: {}", pkt);
-    Ok(())
-}
 
-fn build_time_exceeded(te: TimeExceeded, bytes: &mut [u8]) {
-    let mut pkt = Packet::new_unchecked(bytes);
+    let repr = match Repr::parse(&pkt) {
+        Ok(repr) => repr,
+        Err(e) => {
+            log::debug!("Malformed ICMP message: {:?}, Drop it.", e);
+            return Ok(None);
+        }
+    };
+
+    match repr.message {
+        Message::EchoRequest(echo) => {
+            let reply = Repr {
+                message: Message::EchoReply(echo),
+                payload: repr.payload,
+            };
+
+            let len = reply.buffer_len();
+            if tx.len() < len {
+                log::debug!("Echo reply too large for buffer, Drop it.");
+                return Ok(None);
+            }
+
+            let mut pkt = Packet::new_unchecked(&mut tx[..len]);
+            reply.emit(&mut pkt);
+
+            log::debug!("Send packet: {}", pkt);
 
-    let protocol = Message::TimeExceeded(te);
+            Ok(Some(len))
+        }
+        message => {
+            for socket in sockets.iter_mut().flatten() {
+                if let Socket::Icmp(socket) = socket {
+                    if socket.accepts(&message) {
+                        socket.process(src_addr, bytes);
+                    }
+                }
+            }
 
-    pkt.set_protocol(protocol);
-    pkt.fill_checksum();
+            Ok(None)
+        }
+    }
 }
 
-pub fn build_time_exceeded_ttl(bytes: &mut [u8]) {
-    let te = TimeExceeded::TtlExpired;
+impl<D, AS, ARPS, IFB, RS> Interface<D, AS, ARPS, IFB, RS>
+where
+    D: Device,
+    AS: AddrsStorage,
+    ARPS: ArpStorage,
+    IFB: IpFragmentBuffer,
+    RS: RouteStorage,
+{
+    pub(crate) fn dispatch_icmpv4(&mut self, socket: &mut IcmpSocket<'_>) -> Result<()> {
+        while let Some((remote, message)) = socket.peek_tx() {
+            let (src_addr, dst_addr) = match (self.ipv4_addr(), remote) {
+                (Some(src_addr), layer3::Address::Ipv4(dst_addr)) => (src_addr, dst_addr),
+                _ => {
+                    log::debug!("No route to {:?}, Drop it.", remote);
+                    socket.pop_tx();
+                    continue;
+                }
+            };
+
+            // Checksum covers the whole message, fill it in a copy of header.
+            let rest = &message[HEADER_LEN..];
+            let mut header = [0u8; HEADER_LEN];
+            header[..2].copy_from_slice(&message[..2]);
+            let sum = !checksum::combine(&[checksum::data(&header), checksum::data(rest)]);
+            header[2..4].copy_from_slice(&sum.to_be_bytes());
+
+            let ip_repr = ipv4::Repr {
+                src_addr,
+                dst_addr,
+                protocol: layer3::Protocol::Icmp,
+                payload_len: message.len(),
+                ttl: consts::DEFAULT_TTL,
+                ident: self.next_ipv4_ident(),
+                dont_frag: self.config().ipv4.dont_frag,
+            };
+
+            let sent = match self.dispatch_ipv4_payload(ip_repr, &header, rest) {
+                Ok(sent) => sent,
+                Err(e @ Error::PacketTooLarge) | Err(e @ Error::NoRouteToHost) => {
+                    log::debug!("Fail to send to {:?}: {:?}, Drop it.", remote, e);
+                    socket.pop_tx();
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            if !sent {
+                break;
+            }
+
+            socket.pop_tx();
+        }
 
-    build_time_exceeded(te, bytes)
+        Ok(())
+    }
 }
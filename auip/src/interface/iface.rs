@@ -1,15 +1,20 @@
 use auip_pkt::{
-    layer2::{self, ethernet},
-    layer3,
+    layer2::{self, ethernet, llc},
+    layer3::{self, ipv4},
 };
 
 use crate::{
-    build_and_record_arp, poll_ipv4, AddrsStorage, ArpStorage, Device, InterfaceConfig,
-    IpFragmentBuffer, Medium, Result,
+    build_and_record_arp, build_arp_request,
+    bytes::{FrameBytes, NoFragIpBytes},
+    consts, emit_fragment, expire_fragments, fragments, ipv4_addr, ipv4_src_addr,
+    is_subnet_broadcast, longest_prefix_match, multicast_link_addr, next_ident, poll_ipv4,
+    time::{Duration, Instant},
+    AddrsStorage, ArpStorage, Device, DropCounters, Error, InterfaceConfig, IpFragmentBuffer,
+    Medium, PendingQueue, Result, Route, RouteStorage, Socket, SocketSet,
 };
 
 /// Network interface
-pub struct Interface<D, AS, ARPS, IFB> {
+pub struct Interface<D, AS, ARPS, IFB, RS> {
     device: D,
     medium: Medium,
 
@@ -21,16 +26,39 @@ pub struct Interface<D, AS, ARPS, IFB> {
     arp_storage: ARPS,
 
     ip_fragment_buffer: IFB,
+
+    route_storage: RS,
+
+    /// Packets waiting for ARP reply.
+    pending: PendingQueue,
+
+    /// Packet received to be forwarded by router.
+    forward: Option<(usize, NoFragIpBytes)>,
+
+    /// Time of the latest poll.
+    now: Instant,
+
+    ipv4_ident: u16,
+
+    /// Received IPv4 packets dropped by header checks.
+    drops: DropCounters,
 }
 
-impl<D, AS, ARPS, IFB> Interface<D, AS, ARPS, IFB>
+impl<D, AS, ARPS, IFB, RS> Interface<D, AS, ARPS, IFB, RS>
 where
     D: Device,
     AS: AddrsStorage,
     ARPS: ArpStorage,
     IFB: IpFragmentBuffer,
+    RS: RouteStorage,
 {
-    pub fn new(device: D, addrs_storage: AS, arp_storage: ARPS, ip_fragment_buffer: IFB) -> Self {
+    pub fn new(
+        device: D,
+        addrs_storage: AS,
+        arp_storage: ARPS,
+        ip_fragment_buffer: IFB,
+        route_storage: RS,
+    ) -> Self {
         let medium = device.medium();
 
         Self {
@@ -40,6 +68,12 @@ where
             config: Default::default(),
             arp_storage,
             ip_fragment_buffer,
+            route_storage,
+            pending: PendingQueue::default(),
+            forward: None,
+            now: Instant::ZERO,
+            ipv4_ident: 0,
+            drops: DropCounters::default(),
         }
     }
 
@@ -67,6 +101,14 @@ where
         &mut self.arp_storage
     }
 
+    pub fn route_storage(&self) -> &RS {
+        &self.route_storage
+    }
+
+    pub fn route_storage_mut(&mut self) -> &mut RS {
+        &mut self.route_storage
+    }
+
     pub fn config(&self) -> &InterfaceConfig {
         &self.config
     }
@@ -75,7 +117,21 @@ where
         &mut self.config
     }
 
-    pub(crate) fn poll_ethernet(&mut self) -> Result<()> {
+    pub fn drop_counters(&self) -> &DropCounters {
+        &self.drops
+    }
+
+    pub fn drop_counters_mut(&mut self) -> &mut DropCounters {
+        &mut self.drops
+    }
+
+    pub(crate) fn poll_ethernet(
+        &mut self,
+        now: Instant,
+        sockets: &mut [Option<Socket<'_>>],
+    ) -> Result<()> {
+        let ip_mtu = self.ip_mtu();
+
         let device = &mut self.device;
 
         let this_mac_addr = *self.addrs_storage.mac_addr();
@@ -88,16 +144,35 @@ where
 
         let ip_fragment_buffer = &mut self.ip_fragment_buffer;
 
+        let forward = config
+            .ipv4
+            .forwarding
+            .then_some((ip_mtu, &mut self.forward));
+
         let rx_bytes = device.recv()?;
 
+        // Address recorded from ARP, packets waiting for it are sent after handling.
+        let mut resolved = None;
+
         if let Some(rx_bytes) = rx_bytes {
-            let rx_pkt = ethernet::Packet::new_checked(rx_bytes)?;
+            let rx_pkt = match ethernet::Packet::new_checked(rx_bytes) {
+                Ok(pkt) => pkt,
+                Err(e) => {
+                    log::debug!("Malformed ethernet frame: {:?}, Drop it.", e);
+                    return Ok(());
+                }
+            };
 
             log::debug!("Receive ethernet packet: {}", rx_pkt);
 
             let dest_addr = rx_pkt.dest_addr();
 
-            if dest_addr != this_mac_addr && dest_addr != layer2::Address::BROADCAST {
+            let accepted = dest_addr == this_mac_addr
+                || dest_addr == layer2::Address::BROADCAST
+                || dest_addr == multicast_link_addr(&ipv4::Address::MULTICAST_ALL_SYSTEMS)
+                || addrs_storage.has_multicast_link_addr(&dest_addr);
+
+            if !accepted {
                 log::debug!("Mac address {} mismatch, Drop it.", dest_addr);
 
                 return Ok(());
@@ -105,31 +180,49 @@ where
 
             let protocol = rx_pkt.protocol();
 
-            let l3 = match protocol {
-                layer2::Protocol::Layer3Protocol(l3) => l3,
-                layer2::Protocol::IEEE8021Q(vlanid, l3) => {
-                    if Some(vlanid) == self.config.vlan.vlanid0 {
-                        l3
+            // Replies to SNAP encapsulated frames are sent in SNAP encapsulation.
+            let snap = matches!(protocol, layer2::Protocol::Length(_));
+
+            let (l3, payload) = match protocol {
+                layer2::Protocol::Layer3Protocol(l3) => (l3, rx_pkt.payload()),
+                layer2::Protocol::IEEE8021Q(tag, l3) => {
+                    if Some(tag.vid) == self.config.vlan.vlanid0 {
+                        (l3, rx_pkt.payload())
                     } else {
                         log::debug!("VlanId mismatch, Drop it.");
                         return Ok(());
                     }
                 }
-                layer2::Protocol::QinQ(vlanid, vlanid1, l3) => {
-                    if Some(vlanid) == self.config.vlan.vlanid0
-                        && Some(vlanid1) == self.config.vlan.vlanid1
+                layer2::Protocol::QinQ(tag, tag1, l3) => {
+                    if Some(tag.vid) == self.config.vlan.vlanid0
+                        && Some(tag1.vid) == self.config.vlan.vlanid1
                     {
-                        l3
+                        (l3, rx_pkt.payload())
                     } else {
                         log::debug!("VlanId mismatch, Drop it.");
                         return Ok(());
                     }
                 }
 
-                // TODO: process IEEE802.3 packet.
                 layer2::Protocol::Length(_) => {
-                    log::debug!("Unsupport IEEE802.3. This format will support later, Drop it.");
-                    return Ok(());
+                    let payload = rx_pkt.payload();
+                    let llc = match layer2::llc::Packet::new_checked(payload) {
+                        Ok(llc) => llc,
+                        Err(e) => {
+                            log::debug!("Malformed LLC packet: {:?}, Drop it.", e);
+                            return Ok(());
+                        }
+                    };
+
+                    log::debug!("Receive packet: {}", llc);
+
+                    match llc.snap().and_then(|snap| snap.layer3_protocol()) {
+                        Some(l3) => (l3, &payload[llc.header_len()..]),
+                        None => {
+                            log::debug!("Unsupport LLC packet without SNAP ethertype, Drop it.");
+                            return Ok(());
+                        }
+                    }
                 }
 
                 // Skip
@@ -141,65 +234,1858 @@ where
 
             match l3 {
                 layer2::Layer3Protocol::ARP => {
-                    let pkt = layer3::arp::Packet::new_checked(rx_pkt.payload())?;
+                    let parsed = layer3::arp::Packet::new_checked(payload).and_then(|pkt| {
+                        log::debug!("Receive packet: {}", pkt);
+                        layer3::arp::Repr::parse(&pkt)
+                    });
 
-                    log::debug!("Receive packet: {}", pkt);
-
-                    let sha = pkt.source_hardware_address()?;
-                    let spa = pkt.source_protocol_address()?;
-                    let tpa = pkt.target_protocol_address()?;
-                    let sa = rx_pkt.src_addr();
-
-                    if let Some(arp) = build_and_record_arp(
-                        sa,
-                        sha,
-                        spa,
-                        this_mac_addr,
-                        tpa,
+                    let repr = match parsed {
+                        Ok(repr) => repr,
+                        Err(e) => {
+                            log::debug!("Malformed ARP packet: {:?}, Drop it.", e);
+                            return Ok(());
+                        }
+                    };
+                    let (recorded, reply) = build_and_record_arp(
+                        now,
+                        rx_pkt.src_addr(),
+                        &repr,
+                        snap,
                         config,
                         addrs_storage,
                         arp_storage,
-                    )? {
+                    )?;
+
+                    resolved = recorded;
+
+                    if let Some(arp) = reply {
                         device.send(&arp)?;
                     }
                 }
                 layer2::Layer3Protocol::IPv4 => {
-                    let pkt = layer3::ipv4::Packet::new_checked(rx_pkt.payload())?;
+                    let link_broadcast = dest_addr == layer2::Address::BROADCAST;
+
+                    let mut eth_repr = ethernet::Repr {
+                        src_addr: this_mac_addr,
+                        dst_addr: rx_pkt.src_addr(),
+                        protocol: reply_protocol(config, layer2::Layer3Protocol::IPv4, snap, 0)?,
+                    };
+
+                    let header_len = reply_header_len(&eth_repr);
+
+                    let mut tx_bytes = FrameBytes::default();
+
+                    if let Some(len) = poll_ipv4(
+                        now,
+                        config,
+                        payload,
+                        ip_fragment_buffer,
+                        addrs_storage,
+                        sockets,
+                        link_broadcast,
+                        forward,
+                        &mut self.ipv4_ident,
+                        &mut self.drops,
+                        &mut tx_bytes.as_mut()[header_len..],
+                    )? {
+                        eth_repr.protocol =
+                            reply_protocol(config, layer2::Layer3Protocol::IPv4, snap, len)?;
+
+                        let mut tx_pkt = ethernet::Packet::new_unchecked(tx_bytes.as_mut());
+                        emit_reply_header(&eth_repr, layer2::Layer3Protocol::IPv4, &mut tx_pkt);
 
-                    poll_ipv4(pkt, ip_fragment_buffer)?;
+                        log::debug!("Send packet: {}", tx_pkt);
+
+                        device.send(&tx_bytes[..header_len + len])?;
+                    }
+                }
+                layer2::Layer3Protocol::IPv6 => {
+                    log::debug!("Unsupport IPv6 packet, Drop it.");
+                }
+                layer2::Layer3Protocol::Unknown(ty) => {
+                    log::debug!("Unsupport protocol type: {}, Drop it.", ty);
                 }
-                layer2::Layer3Protocol::IPv6 => {}
-                layer2::Layer3Protocol::Unknown(_) => {}
             }
         }
 
+        if let Some(ip_addr) = resolved {
+            self.flush_pending(ip_addr)?;
+        }
+
         Ok(())
     }
 
-    pub(crate) fn poll_ip(&mut self) -> Result<()> {
+    pub(crate) fn poll_ip(
+        &mut self,
+        now: Instant,
+        sockets: &mut [Option<Socket<'_>>],
+    ) -> Result<()> {
+        let ip_mtu = self.ip_mtu();
+        let forward = self
+            .config
+            .ipv4
+            .forwarding
+            .then_some((ip_mtu, &mut self.forward));
+
         let ip_fragment_buffer = &mut self.ip_fragment_buffer;
+        let addrs_storage = &self.addrs_storage;
         let device = &mut self.device;
 
         if let Some(rx_bytes) = device.recv()? {
-            let ip_pkt = layer3::IpPacket::parse(rx_bytes)?;
+            match rx_bytes.first().map(|b| b >> 4) {
+                Some(4) => {
+                    let mut tx_bytes = FrameBytes::default();
 
-            match ip_pkt {
-                layer3::IpPacket::IPv4(pkt) => {
-                    poll_ipv4(pkt, ip_fragment_buffer)?;
+                    if let Some(len) = poll_ipv4(
+                        now,
+                        &self.config,
+                        rx_bytes,
+                        ip_fragment_buffer,
+                        addrs_storage,
+                        sockets,
+                        false,
+                        forward,
+                        &mut self.ipv4_ident,
+                        &mut self.drops,
+                        tx_bytes.as_mut(),
+                    )? {
+                        device.send(&tx_bytes[..len])?;
+                    }
                 }
-                layer3::IpPacket::Ipv6 => {}
+                Some(6) => {}
+                _ => log::debug!("Unknown IP version, Drop it."),
             }
         }
 
         Ok(())
     }
 
-    pub fn poll(&mut self) -> Result<()> {
-        match self.medium {
-            Medium::Ethernet => self.poll_ethernet()?,
-            Medium::Ip => self.poll_ip()?,
+    /// Get the first IPv4 address of interface.
+    pub(crate) fn ipv4_addr(&self) -> Option<ipv4::Address> {
+        ipv4_addr(&self.addrs_storage)
+    }
+
+    pub(crate) fn next_ipv4_ident(&mut self) -> u16 {
+        next_ident(&mut self.ipv4_ident)
+    }
+
+    /// Get IPv4 address of interface in the same subnet as `dst_addr`, or the first one.
+    pub(crate) fn ipv4_src_addr(&self, dst_addr: ipv4::Address) -> Option<ipv4::Address> {
+        ipv4_src_addr(dst_addr, &self.addrs_storage)
+    }
+
+    /// Send IPv4 packet carrying `payload` of `protocol` to `dst_addr`.
+    ///
+    /// Packet is parked until ARP reply is received if link layer address of `dst_addr`
+    /// is unknown, error is returned if no more packet can be parked.
+    pub fn send_ipv4(
+        &mut self,
+        dst_addr: ipv4::Address,
+        protocol: layer3::Protocol,
+        payload: &[u8],
+    ) -> Result<()> {
+        let src_addr = self.ipv4_src_addr(dst_addr).ok_or(Error::IpAddrNotFound)?;
+
+        let ip_repr = ipv4::Repr {
+            src_addr,
+            dst_addr,
+            protocol,
+            payload_len: payload.len(),
+            ttl: consts::DEFAULT_TTL,
+            ident: self.next_ipv4_ident(),
+            dont_frag: self.config.ipv4.dont_frag,
+        };
+
+        if self.dispatch_ipv4_payload(ip_repr, &[], payload)? {
+            Ok(())
+        } else {
+            Err(Error::NoSpaceForPendingPackets)
+        }
+    }
+
+    /// Maximum length of IPv4 packet sent to device or kept to be forwarded, MTU of device
+    /// limited by the packet buffer.
+    pub(crate) fn ip_mtu(&self) -> usize {
+        self.device.mtu().min(consts::NO_FRAG_PACKET_LENGTH)
+    }
+
+    /// Take packet received to be forwarded.
+    pub(crate) fn take_forward(&mut self) -> Option<(usize, NoFragIpBytes)> {
+        self.forward.take()
+    }
+
+    /// Send IPv4 packet of `ip_repr` carrying `header` followed by `payload`.
+    ///
+    /// Packet exceed MTU is sent in fragments, or error is returned if don't fragment
+    /// is set. Return `false` if packet can not be parked.
+    pub(crate) fn dispatch_ipv4_payload(
+        &mut self,
+        ip_repr: ipv4::Repr,
+        header: &[u8],
+        payload: &[u8],
+    ) -> Result<bool> {
+        let dst_addr = ip_repr.dst_addr;
+        let header_len = ip_repr.buffer_len();
+        let total_len = header.len() + payload.len();
+        let mtu = self.ip_mtu();
+
+        if header_len + total_len <= mtu {
+            return self.dispatch_ipv4(dst_addr, header_len + total_len, |buffer| {
+                emit_fragment(&ip_repr, (0, total_len, false), header, payload, buffer);
+
+                log::debug!("Send packet: {}", ipv4::Packet::new_unchecked(buffer));
+                Ok(())
+            });
+        }
+
+        if ip_repr.dont_frag || header_len + total_len > u16::MAX as usize || mtu < header_len + 8 {
+            log::debug!(
+                "Packet of {} octets to {} exceed mtu {}.",
+                total_len,
+                dst_addr,
+                mtu
+            );
+            return Err(Error::PacketTooLarge);
+        }
+
+        // Fragments are parked all or none, a partial datagram can not be reassembled.
+        let count = fragments(total_len, mtu - header_len).count();
+        if !self.can_dispatch_ipv4(dst_addr, count) {
+            log::debug!("No space for {} fragments to {}.", count, dst_addr);
+            return Ok(false);
+        }
+
+        for fragment in fragments(total_len, mtu - header_len) {
+            let sent = self.dispatch_ipv4(dst_addr, header_len + fragment.1, |buffer| {
+                emit_fragment(&ip_repr, fragment, header, payload, buffer);
+
+                log::debug!("Send fragment: {}", ipv4::Packet::new_unchecked(buffer));
+                Ok(())
+            })?;
+
+            if !sent {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Get the preferred route to `dst_addr`, subnets of interface are routes on link.
+    pub(crate) fn route(&self, dst_addr: ipv4::Address) -> Option<Route> {
+        let connected =
+            self.addrs_storage
+                .ip_addrs()
+                .iter()
+                .filter_map(|cidr| match cidr.address() {
+                    layer3::Address::Ipv4(addr) if !addr.is_unspecified() => Some(Route::on_link(
+                        ipv4::Cidr::new(*addr, cidr.prefix_len()).network(),
+                    )),
+                    _ => None,
+                });
+
+        longest_prefix_match(
+            connected.chain(self.route_storage.lookup(&dst_addr)),
+            &dst_addr,
+        )
+    }
+
+    /// Get next hop to `dst_addr`, the gateway of route or `dst_addr` itself if on link.
+    pub(crate) fn next_hop(&self, dst_addr: ipv4::Address) -> Option<ipv4::Address> {
+        let route = self.route(dst_addr)?;
+        Some(route.gateway.unwrap_or(dst_addr))
+    }
+
+    /// Get link layer address of broadcast or multicast `dst_addr`.
+    fn group_link_addr(&self, dst_addr: ipv4::Address) -> Option<layer2::Address> {
+        if dst_addr.is_broadcast() || is_subnet_broadcast(dst_addr, &self.addrs_storage) {
+            Some(layer2::Address::BROADCAST)
+        } else if dst_addr.is_multicast() {
+            Some(multicast_link_addr(&dst_addr))
+        } else {
+            None
+        }
+    }
+
+    /// Get link layer address of `ip_addr` in ARP table.
+    ///
+    /// Expired entry is removed, request is sent to refresh entry used near its expiry.
+    fn lookup_arp(&mut self, ip_addr: ipv4::Address) -> Result<Option<layer2::Address>> {
+        let now = self.now;
+        let timeout = self.config.arp.timeout;
+
+        let entry = match self.arp_storage.get_mut(&ip_addr) {
+            Some(entry) if entry.is_expired(now, timeout) => {
+                log::debug!("Arp entry of {} expired.", ip_addr);
+                self.arp_storage.remove(&ip_addr);
+                return Ok(None);
+            }
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        entry.used_at = now;
+
+        let refresh = !entry.refreshing && entry.is_expired(now + self.config.arp.refresh, timeout);
+        let mac_addr = entry.mac_addr;
+
+        if refresh {
+            entry.refreshing = true;
+            self.request_arp(ip_addr, mac_addr)?;
+        }
+
+        Ok(Some(mac_addr))
+    }
+
+    /// Send ARP request for `ip_addr` to `dst_mac`.
+    fn request_arp(&mut self, ip_addr: ipv4::Address, dst_mac: layer2::Address) -> Result<()> {
+        let src_addr = self.ipv4_src_addr(ip_addr).ok_or(Error::IpAddrNotFound)?;
+        let arp = build_arp_request(
+            ip_addr,
+            src_addr,
+            dst_mac,
+            &self.config,
+            &self.addrs_storage,
+        )?;
+        self.device.send(&arp)
+    }
+
+    /// Send IPv4 packet of `len` octets to device, `emit` write the packet into buffer.
+    ///
+    /// Packet is sent to gateway if `dst_addr` is not on link. If link layer address of
+    /// next hop is unknown, ARP request is broadcast and packet is parked until reply.
+    /// Return `false` if packet can not be parked, or error if packet exceed MTU.
+    pub(crate) fn dispatch_ipv4<F>(
+        &mut self,
+        dst_addr: ipv4::Address,
+        len: usize,
+        emit: F,
+    ) -> Result<bool>
+    where
+        F: FnOnce(&mut [u8]) -> Result<()>,
+    {
+        if len > self.ip_mtu() {
+            log::debug!("Packet length {} exceed mtu, Drop it.", len);
+            return Err(Error::PacketTooLarge);
+        }
+
+        let dst_mac = match self.medium {
+            Medium::Ethernet => match self.group_link_addr(dst_addr) {
+                Some(mac) => Some(mac),
+                None => {
+                    let next_hop = self.next_hop(dst_addr).ok_or_else(|| {
+                        log::debug!("No route to {}.", dst_addr);
+                        Error::NoRouteToHost
+                    })?;
+
+                    match self.lookup_arp(next_hop)? {
+                        Some(mac) => Some(mac),
+                        None => return self.park_ipv4(next_hop, len, emit),
+                    }
+                }
+            },
+            Medium::Ip => None,
+        };
+
+        self.send_ipv4_frame(dst_mac, len, emit)?;
+
+        Ok(true)
+    }
+
+    /// Query whether `count` packets to `dst_addr` can be sent or parked now, or dropped for
+    /// no route.
+    fn can_dispatch_ipv4(&self, dst_addr: ipv4::Address, count: usize) -> bool {
+        if matches!(self.medium, Medium::Ip) || self.group_link_addr(dst_addr).is_some() {
+            return true;
+        }
+
+        let next_hop = match self.next_hop(dst_addr) {
+            Some(next_hop) => next_hop,
+            None => return true,
+        };
+
+        let resolved = match self.arp_storage.get(&next_hop) {
+            Some(entry) => !entry.is_expired(self.now, self.config.arp.timeout),
+            None => false,
+        };
+
+        resolved || self.pending.vacant() >= count
+    }
+
+    /// Park packet until link layer address of `next_hop` is resolved.
+    ///
+    /// Request is sent for the first packet to `next_hop`, and again by [Interface::poll]
+    /// until reply is received or packets are dropped.
+    fn park_ipv4<F>(&mut self, next_hop: ipv4::Address, len: usize, emit: F) -> Result<bool>
+    where
+        F: FnOnce(&mut [u8]) -> Result<()>,
+    {
+        log::debug!("No arp entry for {}, Wait for reply.", next_hop);
+
+        // Earlier packet to `next_hop` has sent a request.
+        let requested = self.pending.contains(next_hop);
+        let retry_at = self.now + self.config.arp.retry_interval;
+
+        match self.pending.push(next_hop, len, retry_at) {
+            Some(buffer) => emit(buffer)?,
+            None => {
+                log::debug!("No space for pending packet to {}.", next_hop);
+                return Ok(false);
+            }
+        }
+
+        if !requested {
+            self.request_arp(next_hop, layer2::Address::BROADCAST)?;
+        }
+
+        Ok(true)
+    }
+
+    /// Send ARP requests for parked packets again, drop packets after the last request.
+    fn retry_pending(&mut self, now: Instant) -> Result<()> {
+        let hops = self.pending.retry(
+            now,
+            self.config.arp.retry_interval,
+            self.config.arp.max_requests,
+        );
+
+        for next_hop in hops.iter().flatten() {
+            log::debug!("No arp reply from {}, Request again.", next_hop);
+            self.request_arp(*next_hop, layer2::Address::BROADCAST)?;
+        }
+
+        Ok(())
+    }
+
+    /// Send packets parked for `ip_addr` after its link layer address is learned.
+    fn flush_pending(&mut self, ip_addr: ipv4::Address) -> Result<()> {
+        let mac_addr = match self.arp_storage.get(&ip_addr) {
+            Some(entry) => entry.mac_addr,
+            None => return Ok(()),
+        };
+
+        while let Some((len, bytes)) = self.pending.pop(ip_addr) {
+            log::debug!("Send packet parked for {}.", ip_addr);
+
+            self.send_ipv4_frame(Some(mac_addr), len, |buffer| {
+                buffer.copy_from_slice(&bytes[..len]);
+                Ok(())
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Send IPv4 packet in ethernet frame to `dst_mac` with configured VLAN tags,
+    /// or as is if `dst_mac` is `None`.
+    fn send_ipv4_frame<F>(
+        &mut self,
+        dst_mac: Option<layer2::Address>,
+        len: usize,
+        emit: F,
+    ) -> Result<()>
+    where
+        F: FnOnce(&mut [u8]) -> Result<()>,
+    {
+        let mut tx_bytes = FrameBytes::default();
+
+        let header_len = match dst_mac {
+            Some(dst_mac) => {
+                let eth_repr = ethernet::Repr {
+                    src_addr: *self.addrs_storage.mac_addr(),
+                    dst_addr: dst_mac,
+                    protocol: self.config.vlan.protocol(layer2::Layer3Protocol::IPv4)?,
+                };
+                eth_repr.emit(&mut ethernet::Packet::new_unchecked(tx_bytes.as_mut()));
+                eth_repr.buffer_len()
+            }
+            None => 0,
+        };
+
+        let total_len = header_len + len;
+
+        if total_len > tx_bytes.len() {
+            log::debug!("Frame length {} exceed buffer, Drop it.", total_len);
+            return Err(Error::PacketTooLarge);
+        }
+
+        emit(&mut tx_bytes.as_mut()[header_len..total_len])?;
+
+        self.device.send(&tx_bytes[..total_len])?;
+
+        Ok(())
+    }
+
+    /// Send queued packets of all sockets.
+    #[cfg_attr(not(feature = "tcp"), allow(unused_variables))]
+    pub(crate) fn dispatch_sockets(
+        &mut self,
+        now: Instant,
+        sockets: &mut [Option<Socket<'_>>],
+    ) -> Result<()> {
+        for socket in sockets.iter_mut().flatten() {
+            match socket {
+                #[cfg(feature = "udp")]
+                Socket::Udp(socket) => self.dispatch_udp(socket)?,
+                #[cfg(feature = "tcp")]
+                Socket::Tcp(socket) => self.dispatch_tcp(now, socket)?,
+                Socket::Icmp(socket) => self.dispatch_icmpv4(socket)?,
+                Socket::Raw(socket) => self.dispatch_raw(socket)?,
+            }
         }
 
         Ok(())
     }
+
+    /// Process received packets and send queued packets of sockets.
+    ///
+    /// `now` is the current time, used by timers of sockets. Malformed packets are dropped,
+    /// sockets are dispatched even if receiving fails, then the first error is returned.
+    pub fn poll<'a>(&mut self, now: Instant, sockets: &mut impl SocketSet<'a>) -> Result<()> {
+        self.poll_slots(now, sockets.slots_mut())
+    }
+
+    /// Process received packets and send queued packets of socket slots.
+    pub(crate) fn poll_slots(
+        &mut self,
+        now: Instant,
+        sockets: &mut [Option<Socket<'_>>],
+    ) -> Result<()> {
+        self.now = now;
+        expire_fragments(now, &mut self.ip_fragment_buffer);
+
+        let retried = self.retry_pending(now);
+
+        let received = match self.medium {
+            Medium::Ethernet => self.poll_ethernet(now, sockets),
+            Medium::Ip => self.poll_ip(now, sockets),
+        };
+
+        let dispatched = self.dispatch_sockets(now, sockets);
+
+        retried.and(received).and(dispatched)
+    }
+
+    /// Get the time [Interface::poll] should be called next, if no packet is received before.
+    ///
+    /// Return `None` if nothing is scheduled, [Instant::ZERO] if it should be called now.
+    /// Packet waiting for a free slot of parked packets is scheduled at the next ARP
+    /// request, when parked packets may be dropped.
+    pub fn poll_at<'a>(&self, sockets: &impl SocketSet<'a>) -> Option<Instant> {
+        self.poll_slots_at(sockets.slots())
+    }
+
+    /// Get the time [Interface::poll] should be called next with socket slots.
+    pub(crate) fn poll_slots_at(&self, sockets: &[Option<Socket<'_>>]) -> Option<Instant> {
+        let reassembly_at = self
+            .ip_fragment_buffer
+            .slots()
+            .iter()
+            .flatten()
+            .map(|reassembly| reassembly.expires_at())
+            .min();
+
+        let pending_at = self.pending.retry_at();
+
+        // Time a packet to `dst_addr` can be sent.
+        let send_at = |dst_addr: layer3::Address| match dst_addr {
+            layer3::Address::Ipv4(dst_addr) if !self.can_dispatch_ipv4(dst_addr, 1) => pending_at,
+            _ => Some(Instant::ZERO),
+        };
+
+        let socket_at = sockets
+            .iter()
+            .flatten()
+            .filter_map(|socket| match socket {
+                #[cfg(feature = "udp")]
+                Socket::Udp(socket) => socket
+                    .peek_tx()
+                    .and_then(|(_, remote, _)| send_at(remote.addr)),
+                #[cfg(feature = "tcp")]
+                Socket::Tcp(socket) => match (socket.poll_at(), socket.remote_endpoint()) {
+                    (Some(Instant::ZERO), Some(remote)) => send_at(remote.addr),
+                    (at, _) => at,
+                },
+                Socket::Icmp(socket) => socket.peek_tx().and_then(|(remote, _)| send_at(remote)),
+                Socket::Raw(socket) => socket.peek_tx().and_then(|packet| {
+                    let dst_addr = ipv4::Packet::new_unchecked(packet).dst_addr();
+                    send_at(layer3::Address::Ipv4(dst_addr))
+                }),
+            })
+            .min();
+
+        [reassembly_at, pending_at, socket_at]
+            .iter()
+            .flatten()
+            .min()
+            .copied()
+    }
+
+    /// Get the duration from `now` until [Interface::poll] should be called next.
+    ///
+    /// Return `None` if nothing is scheduled, a main loop may sleep until the next packet.
+    pub fn poll_delay<'a>(&self, now: Instant, sockets: &impl SocketSet<'a>) -> Option<Duration> {
+        self.poll_at(sockets).map(|at| at - now)
+    }
+}
+
+/// Build the ethernet protocol of a reply carrying `payload_len` octets of `l3`.
+///
+/// Reply to a SNAP encapsulated frame is an untagged IEEE 802.3 frame with SNAP
+/// header, otherwise it is framed with the configured tags.
+pub(crate) fn reply_protocol(
+    config: &InterfaceConfig,
+    l3: layer2::Layer3Protocol,
+    snap: bool,
+    payload_len: usize,
+) -> Result<layer2::Protocol> {
+    if snap {
+        let len = llc::field::SNAP_HEADER_LEN + payload_len;
+        Ok(layer2::Protocol::Length(len as u16))
+    } else {
+        config.vlan.protocol(l3)
+    }
+}
+
+/// Return the length of headers before layer3 payload of a reply.
+pub(crate) fn reply_header_len(eth_repr: &ethernet::Repr) -> usize {
+    match eth_repr.protocol {
+        layer2::Protocol::Length(_) => eth_repr.buffer_len() + llc::field::SNAP_HEADER_LEN,
+        _ => eth_repr.buffer_len(),
+    }
+}
+
+/// Emit ethernet header of a reply, and SNAP header for SNAP encapsulation.
+pub(crate) fn emit_reply_header<T: AsRef<[u8]> + AsMut<[u8]>>(
+    eth_repr: &ethernet::Repr,
+    l3: layer2::Layer3Protocol,
+    pkt: &mut ethernet::Packet<T>,
+) {
+    eth_repr.emit(pkt);
+
+    if let layer2::Protocol::Length(_) = eth_repr.protocol {
+        llc::Packet::new_unchecked(pkt.payload_mut()).set_snap(llc::Snap::ethertype(l3));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use auip_pkt::layer4::{
+        self,
+        icmpv4::{self, Echo, Message},
+    };
+
+    use super::*;
+    use crate::storage::fixed::{Addrs, Arp, IpFragment, PacketRing, Routes, Sockets};
+    use crate::{DropReason, IcmpSocket, RawSocket, SocketHandle};
+    #[cfg(feature = "udp")]
+    use crate::{Endpoint, UdpSocket};
+
+    /// Device hold one received packet and record all sent packets.
+    struct TestDevice {
+        medium: Medium,
+        rx: Option<Vec<u8>>,
+        tx: Vec<Vec<u8>>,
+    }
+
+    impl Device for TestDevice {
+        fn send(&mut self, buffer: &[u8]) -> Result<()> {
+            self.tx.push(buffer.into());
+            Ok(())
+        }
+
+        fn recv(&mut self) -> Result<Option<&[u8]>> {
+            Ok(self.rx.as_deref())
+        }
+
+        fn medium(&self) -> Medium {
+            self.medium.clone()
+        }
+    }
+
+    const HOST_MAC: layer2::Address = layer2::Address([0x02, 0, 0, 0, 0, 1]);
+    const PEER_MAC: layer2::Address = layer2::Address([0x02, 0, 0, 0, 0, 2]);
+
+    fn host_ip() -> layer3::ipv4::Address {
+        layer3::ipv4::Address::new(192, 168, 1, 1)
+    }
+
+    fn peer_ip() -> layer3::ipv4::Address {
+        layer3::ipv4::Address::new(192, 168, 1, 2)
+    }
+
+    fn build_ipv4(
+        dst: layer3::ipv4::Address,
+        protocol: layer3::Protocol,
+        payload: &[u8],
+        buffer: &mut [u8],
+    ) -> usize {
+        let ip_repr = layer3::ipv4::Repr {
+            src_addr: peer_ip(),
+            dst_addr: dst,
+            protocol,
+            payload_len: payload.len(),
+            ttl: 64,
+            ident: 0x4321,
+            dont_frag: false,
+        };
+
+        let len = ip_repr.buffer_len() + payload.len();
+        let mut pkt = layer3::ipv4::Packet::new_unchecked(&mut buffer[..len]);
+        ip_repr.emit(&mut pkt);
+        pkt.payload_mut().copy_from_slice(payload);
+        len
+    }
+
+    fn build_icmpv4(message: Message, payload: &[u8], buffer: &mut [u8]) -> usize {
+        let repr = icmpv4::Repr { message, payload };
+        let len = repr.buffer_len();
+        repr.emit(&mut icmpv4::Packet::new_unchecked(&mut buffer[..len]));
+        len
+    }
+
+    fn build_echo_request(dst: layer3::ipv4::Address, buffer: &mut [u8]) -> usize {
+        let mut icmp = [0u8; 16];
+        let message = Message::EchoRequest(Echo {
+            ident: 0x1234,
+            seq_no: 3,
+        });
+        let len = build_icmpv4(message, b"abcdefgh", &mut icmp);
+
+        build_ipv4(dst, layer3::Protocol::Icmp, &icmp[..len], buffer)
+    }
+
+    /// Build ARP packet from peer to host in ethernet frame.
+    fn build_arp(operation: layer3::arp::Operation, src_mac: layer2::Address) -> Vec<u8> {
+        let mut rx = [0u8; 42];
+        let eth_repr = ethernet::Repr {
+            src_addr: src_mac,
+            dst_addr: HOST_MAC,
+            protocol: layer2::Protocol::Layer3Protocol(layer2::Layer3Protocol::ARP),
+        };
+        eth_repr.emit(&mut ethernet::Packet::new_unchecked(&mut rx[..]));
+
+        let arp_repr = layer3::arp::Repr {
+            operation,
+            source_hardware_addr: src_mac,
+            source_protocol_addr: peer_ip(),
+            target_hardware_addr: HOST_MAC,
+            target_protocol_addr: host_ip(),
+        };
+        arp_repr
+            .emit(&mut layer3::arp::Packet::new_unchecked(&mut rx[14..]))
+            .unwrap();
+
+        rx.into()
+    }
+
+    type TestInterface = Interface<TestDevice, Addrs<1>, Arp<2>, IpFragment<1>, Routes<1>>;
+
+    fn interface(medium: Medium, rx: Vec<u8>) -> TestInterface {
+        let device = TestDevice {
+            medium,
+            rx: Some(rx),
+            tx: Vec::new(),
+        };
+
+        let mut addrs = Addrs::default();
+        addrs.set_mac_addr(HOST_MAC);
+        addrs
+            .add_ip_addr(layer3::Cidr::new(layer3::Address::Ipv4(host_ip()), 24))
+            .unwrap();
+
+        Interface::new(
+            device,
+            addrs,
+            Arp::default(),
+            IpFragment::default(),
+            Routes::default(),
+        )
+    }
+
+    fn check_echo_reply(bytes: &[u8]) {
+        let ip_pkt = layer3::ipv4::Packet::new_checked(bytes).unwrap();
+        let ip_repr = layer3::ipv4::Repr::parse(&ip_pkt).unwrap();
+        assert_eq!(ip_repr.src_addr, host_ip());
+        assert_eq!(ip_repr.dst_addr, peer_ip());
+        // Identification is taken from interface.
+        assert_eq!(ip_repr.ident, 1);
+
+        let icmp_pkt = icmpv4::Packet::new_checked(ip_pkt.payload()).unwrap();
+        let icmp_repr = icmpv4::Repr::parse(&icmp_pkt).unwrap();
+        assert_eq!(
+            icmp_repr.message,
+            Message::EchoReply(Echo {
+                ident: 0x1234,
+                seq_no: 3,
+            })
+        );
+        assert_eq!(icmp_repr.payload, b"abcdefgh");
+    }
+
+    #[test]
+    fn test_echo_reply_ethernet() {
+        let mut rx = [0u8; 64];
+        let eth_repr = ethernet::Repr {
+            src_addr: PEER_MAC,
+            dst_addr: HOST_MAC,
+            protocol: layer2::Protocol::Layer3Protocol(layer2::Layer3Protocol::IPv4),
+        };
+        eth_repr.emit(&mut ethernet::Packet::new_unchecked(&mut rx[..]));
+        let len = eth_repr.buffer_len() + build_echo_request(host_ip(), &mut rx[14..]);
+
+        let mut iface = interface(Medium::Ethernet, rx[..len].into());
+        iface
+            .poll(Instant::ZERO, &mut Sockets::<1>::default())
+            .unwrap();
+
+        let tx = &iface.device().tx;
+        assert_eq!(tx.len(), 1);
+
+        let eth_pkt = ethernet::Packet::new_checked(&tx[0][..]).unwrap();
+        assert_eq!(eth_pkt.dest_addr(), PEER_MAC);
+        assert_eq!(eth_pkt.src_addr(), HOST_MAC);
+        check_echo_reply(eth_pkt.payload());
+    }
+
+    /// Return the layer3 protocol and payload of SNAP encapsulated frame.
+    fn snap_payload(bytes: &[u8]) -> (layer2::Layer3Protocol, &[u8]) {
+        let eth_pkt = ethernet::Packet::new_checked(bytes).unwrap();
+        assert_eq!(eth_pkt.dest_addr(), PEER_MAC);
+        assert!(matches!(eth_pkt.protocol(), layer2::Protocol::Length(_)));
+
+        let llc_pkt = llc::Packet::new_checked(eth_pkt.payload()).unwrap();
+        let l3 = llc_pkt.snap().unwrap().layer3_protocol().unwrap();
+        (l3, &bytes[14 + llc_pkt.header_len()..])
+    }
+
+    #[test]
+    fn test_snap_replies() {
+        let snap_frame = |l3: layer2::Layer3Protocol, payload: &[u8]| {
+            let mut rx = std::vec![0u8; 22 + payload.len()];
+            let eth_repr = ethernet::Repr {
+                src_addr: PEER_MAC,
+                dst_addr: HOST_MAC,
+                protocol: layer2::Protocol::Length((8 + payload.len()) as u16),
+            };
+            let mut eth_pkt = ethernet::Packet::new_unchecked(&mut rx[..]);
+            emit_reply_header(&eth_repr, l3, &mut eth_pkt);
+            rx[22..].copy_from_slice(payload);
+            rx
+        };
+
+        let mut ip = [0u8; 64];
+        let len = build_echo_request(host_ip(), &mut ip);
+        let rx = snap_frame(layer2::Layer3Protocol::IPv4, &ip[..len]);
+
+        let mut iface = interface(Medium::Ethernet, rx);
+        iface
+            .poll(Instant::ZERO, &mut Sockets::<1>::default())
+            .unwrap();
+
+        let tx = &iface.device().tx;
+        assert_eq!(tx.len(), 1);
+        let (l3, payload) = snap_payload(&tx[0]);
+        assert_eq!(l3, layer2::Layer3Protocol::IPv4);
+        check_echo_reply(payload);
+
+        let arp = build_arp(layer3::arp::Operation::Request, PEER_MAC);
+        let rx = snap_frame(layer2::Layer3Protocol::ARP, &arp[14..]);
+
+        let mut iface = interface(Medium::Ethernet, rx);
+        iface
+            .poll(Instant::ZERO, &mut Sockets::<1>::default())
+            .unwrap();
+
+        let tx = &iface.device().tx;
+        assert_eq!(tx.len(), 1);
+        let (l3, payload) = snap_payload(&tx[0]);
+        assert_eq!(l3, layer2::Layer3Protocol::ARP);
+
+        let arp_pkt = layer3::arp::Packet::new_checked(payload).unwrap();
+        let arp_repr = layer3::arp::Repr::parse(&arp_pkt).unwrap();
+        assert_eq!(arp_repr.operation, layer3::arp::Operation::Reply);
+        assert_eq!(arp_repr.source_protocol_addr, host_ip());
+        assert_eq!(arp_repr.target_hardware_addr, PEER_MAC);
+    }
+
+    #[test]
+    fn test_echo_reply_ip() {
+        let mut rx = [0u8; 64];
+        let len = build_echo_request(host_ip(), &mut rx);
+
+        let mut iface = interface(Medium::Ip, rx[..len].into());
+        iface
+            .poll(Instant::ZERO, &mut Sockets::<1>::default())
+            .unwrap();
+
+        let tx = &iface.device().tx;
+        assert_eq!(tx.len(), 1);
+        check_echo_reply(&tx[0]);
+
+        // Not addressed to this host.
+        let len = build_echo_request(layer3::ipv4::Address::new(192, 168, 1, 255), &mut rx);
+        let mut iface = interface(Medium::Ip, rx[..len].into());
+        iface
+            .poll(Instant::ZERO, &mut Sockets::<1>::default())
+            .unwrap();
+        assert!(iface.device().tx.is_empty());
+    }
+
+    #[test]
+    fn test_port_unreachable() {
+        let mut udp = [0u8; 12];
+        let udp_repr = layer4::udp::Repr {
+            src_port: 5000,
+            dst_port: 53,
+            payload: b"test",
+        };
+        udp_repr
+            .emit(
+                &mut layer4::udp::Packet::new_unchecked(&mut udp[..]),
+                &layer3::Address::Ipv4(peer_ip()),
+                &layer3::Address::Ipv4(host_ip()),
+            )
+            .unwrap();
+
+        let mut rx = [0u8; 64];
+        let len = build_ipv4(host_ip(), layer3::Protocol::Udp, &udp, &mut rx);
+
+        let mut iface = interface(Medium::Ip, rx[..len].into());
+        iface
+            .poll(Instant::ZERO, &mut Sockets::<1>::default())
+            .unwrap();
+
+        let tx = &iface.device().tx;
+        assert_eq!(tx.len(), 1);
+
+        let ip_pkt = layer3::ipv4::Packet::new_checked(&tx[0][..]).unwrap();
+        assert_eq!(ip_pkt.dst_addr(), peer_ip());
+        assert_eq!(ip_pkt.total_len() as usize, 20 + 8 + 28);
+        assert_eq!(ip_pkt.ident(), 1);
+
+        let icmp_pkt = icmpv4::Packet::new_checked(ip_pkt.payload()).unwrap();
+        let icmp_repr = icmpv4::Repr::parse(&icmp_pkt).unwrap();
+        assert_eq!(
+            icmp_repr.message,
+            Message::DstUnreachable(icmpv4::DstUnreachable::PortUnreachable, 0)
+        );
+        assert_eq!(icmp_repr.payload, &rx[..28]);
+
+        // Error for fragmented datagram carry the reassembled header.
+        let mut udp = [0u8; 20];
+        let udp_repr = layer4::udp::Repr {
+            src_port: 5000,
+            dst_port: 53,
+            payload: b"fragmented",
+        };
+        udp_repr
+            .emit(
+                &mut layer4::udp::Packet::new_unchecked(&mut udp[..18]),
+                &layer3::Address::Ipv4(peer_ip()),
+                &layer3::Address::Ipv4(host_ip()),
+            )
+            .unwrap();
+
+        let mut datagram = [0u8; 64];
+        let len = build_ipv4(host_ip(), layer3::Protocol::Udp, &udp[..18], &mut datagram);
+
+        iface.device_mut().tx.clear();
+        for (offset, end, more) in [(0, 8, true), (8, len - 20, false)].iter() {
+            let mut frag = datagram[..20 + end - offset].to_vec();
+            frag[20..].copy_from_slice(&datagram[20 + offset..20 + end]);
+
+            let mut pkt = layer3::ipv4::Packet::new_unchecked(&mut frag[..]);
+            pkt.set_total_len((20 + end - offset) as u16);
+            pkt.set_more_frags(*more);
+            pkt.set_frag_offset(*offset as u16);
+            pkt.fill_checksum();
+
+            iface.device_mut().rx = Some(frag);
+            iface
+                .poll(Instant::ZERO, &mut Sockets::<1>::default())
+                .unwrap();
+        }
+
+        let tx = &iface.device().tx;
+        assert_eq!(tx.len(), 1);
+
+        let ip_pkt = layer3::ipv4::Packet::new_checked(&tx[0][..]).unwrap();
+        let icmp_pkt = icmpv4::Packet::new_checked(ip_pkt.payload()).unwrap();
+        let icmp_repr = icmpv4::Repr::parse(&icmp_pkt).unwrap();
+        assert_eq!(
+            icmp_repr.message,
+            Message::DstUnreachable(icmpv4::DstUnreachable::PortUnreachable, 0)
+        );
+        assert_eq!(icmp_repr.payload, &datagram[..28]);
+    }
+
+    #[test]
+    fn test_no_error_for_error() {
+        let mut original = [0u8; 28];
+        build_ipv4(peer_ip(), layer3::Protocol::Udp, &[0u8; 8], &mut original);
+
+        // Unknown type of ICMP error never get answered.
+        let mut icmp = [0u8; 64];
+        let message = Message::DstUnreachable(icmpv4::DstUnreachable::HostUnreachable, 0);
+        let icmp_len = build_icmpv4(message, &original, &mut icmp);
+
+        let mut rx = [0u8; 64];
+        let len = build_ipv4(
+            host_ip(),
+            layer3::Protocol::Icmp,
+            &icmp[..icmp_len],
+            &mut rx,
+        );
+        let mut iface = interface(Medium::Ip, rx[..len].into());
+        iface
+            .poll(Instant::ZERO, &mut Sockets::<1>::default())
+            .unwrap();
+        assert!(iface.device().tx.is_empty());
+
+        // Protocol unreachable is not sent for link layer broadcast.
+        let mut rx = [0u8; 64];
+        let eth_repr = ethernet::Repr {
+            src_addr: PEER_MAC,
+            dst_addr: layer2::Address::BROADCAST,
+            protocol: layer2::Protocol::Layer3Protocol(layer2::Layer3Protocol::IPv4),
+        };
+        eth_repr.emit(&mut ethernet::Packet::new_unchecked(&mut rx[..]));
+        let len = 14
+            + build_ipv4(
+                host_ip(),
+                layer3::Protocol::Unknown(253),
+                &[0u8; 8],
+                &mut rx[14..],
+            );
+
+        let mut iface = interface(Medium::Ethernet, rx[..len].into());
+        iface
+            .poll(Instant::ZERO, &mut Sockets::<1>::default())
+            .unwrap();
+        assert!(iface.device().tx.is_empty());
+    }
+
+    #[test]
+    fn test_malformed_transport() {
+        // Truncated ICMP message, and TCP or UDP with bad checksum are dropped silently.
+        let protocols: &[(layer3::Protocol, &[u8])] = &[
+            (layer3::Protocol::Icmp, b"\x08\x00"),
+            #[cfg(feature = "udp")]
+            (
+                layer3::Protocol::Udp,
+                b"\x13\x88\x00\x35\x00\x0c\xff\xfftest",
+            ),
+            #[cfg(feature = "tcp")]
+            (layer3::Protocol::Tcp, &[0x50; 20]),
+        ];
+
+        for &(protocol, payload) in protocols {
+            let mut rx = [0u8; 64];
+            let len = build_ipv4(host_ip(), protocol, payload, &mut rx);
+
+            let mut iface = interface(Medium::Ip, rx[..len].into());
+            iface
+                .poll(Instant::ZERO, &mut Sockets::<1>::default())
+                .unwrap();
+            assert!(iface.device().tx.is_empty(), "{:?}", protocol);
+        }
+    }
+
+    #[test]
+    fn test_icmp_socket() {
+        let mut rx_storage = PacketRing::<layer3::Address, 2, 32>::default();
+        let mut tx_storage = PacketRing::<layer3::Address, 2, 32>::default();
+        let mut socket = IcmpSocket::new(&mut rx_storage, &mut tx_storage);
+        socket.bind(0x1234).unwrap();
+
+        let mut sockets = Sockets::<1>::default();
+        let handle = sockets.add(socket).unwrap();
+
+        // Only echo reply of bound identifier is delivered.
+        let mut iface = interface(Medium::Ip, Vec::new());
+        for ident in [0x4321, 0x1234] {
+            let mut icmp = [0u8; 16];
+            let message = Message::EchoReply(Echo { ident, seq_no: 7 });
+            let icmp_len = build_icmpv4(message, b"pong", &mut icmp);
+
+            let mut rx = [0u8; 64];
+            let len = build_ipv4(
+                host_ip(),
+                layer3::Protocol::Icmp,
+                &icmp[..icmp_len],
+                &mut rx,
+            );
+            iface.device_mut().rx = Some(rx[..len].into());
+            iface.poll(Instant::ZERO, &mut sockets).unwrap();
+        }
+        assert!(iface.device().tx.is_empty());
+
+        let socket = sockets.get_mut(handle).unwrap();
+        let mut buffer = [0u8; 32];
+        let (len, remote) = socket.recv_from(&mut buffer).unwrap().unwrap();
+        assert_eq!(remote, layer3::Address::Ipv4(peer_ip()));
+
+        let icmp_pkt = icmpv4::Packet::new_checked(&buffer[..len]).unwrap();
+        let icmp_repr = icmpv4::Repr::parse(&icmp_pkt).unwrap();
+        assert_eq!(
+            icmp_repr.message,
+            Message::EchoReply(Echo {
+                ident: 0x1234,
+                seq_no: 7,
+            })
+        );
+        assert_eq!(icmp_repr.payload, b"pong");
+        assert!(socket.recv_from(&mut buffer).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_raw_socket() {
+        let mut rx_storage = PacketRing::<(), 2, 64>::default();
+        let mut tx_storage = PacketRing::<(), 2, 64>::default();
+        let socket = RawSocket::new(
+            layer3::Protocol::Unknown(253),
+            &mut rx_storage,
+            &mut tx_storage,
+        );
+
+        let mut other_rx_storage = PacketRing::<(), 1, 64>::default();
+        let mut other_tx_storage = PacketRing::<(), 1, 64>::default();
+        let other = RawSocket::new(
+            layer3::Protocol::Unknown(254),
+            &mut other_rx_storage,
+            &mut other_tx_storage,
+        );
+
+        let mut sockets = Sockets::<2>::default();
+        let handle = sockets.add(socket).unwrap();
+        let other_handle = sockets.add(other).unwrap();
+
+        let mut rx = [0u8; 64];
+        let len = build_ipv4(
+            host_ip(),
+            layer3::Protocol::Unknown(253),
+            b"payload",
+            &mut rx,
+        );
+
+        // Protocol is handled by raw socket, no protocol unreachable is sent.
+        let mut iface = interface(Medium::Ip, rx[..len].into());
+        iface.poll(Instant::ZERO, &mut sockets).unwrap();
+        assert!(iface.device().tx.is_empty());
+
+        let mut buffer = [0u8; 64];
+        let socket = sockets.get_mut(handle).unwrap();
+        assert_eq!(socket.recv(&mut buffer), Some(len));
+        assert_eq!(&buffer[..len], &rx[..len]);
+        assert_eq!(socket.recv(&mut buffer), None);
+
+        let other = sockets.get_mut(other_handle).unwrap();
+        assert_eq!(other.recv(&mut buffer), None);
+
+        // Fragments are delivered as the reassembled datagram.
+        let mut datagram = [0u8; 64];
+        let len = build_ipv4(
+            host_ip(),
+            layer3::Protocol::Unknown(253),
+            b"fragmented payload",
+            &mut datagram,
+        );
+
+        for (offset, end, more) in [(0, 8, true), (8, len - 20, false)].iter().rev() {
+            let mut frag = datagram[..20 + end - offset].to_vec();
+            frag[20..].copy_from_slice(&datagram[20 + offset..20 + end]);
+
+            let mut pkt = layer3::ipv4::Packet::new_unchecked(&mut frag[..]);
+            pkt.set_total_len((20 + end - offset) as u16);
+            pkt.set_more_frags(*more);
+            pkt.set_frag_offset(*offset as u16);
+            pkt.fill_checksum();
+
+            iface.device_mut().rx = Some(frag);
+            iface.poll(Instant::ZERO, &mut sockets).unwrap();
+        }
+
+        let socket = sockets.get_mut(handle).unwrap();
+        assert_eq!(socket.recv(&mut buffer), Some(len));
+        assert_eq!(&buffer[..len], &datagram[..len]);
+        assert_eq!(socket.recv(&mut buffer), None);
+
+        // Handle of other type get nothing.
+        assert!(sockets
+            .get::<IcmpSocket>(SocketHandle::new(handle.index()))
+            .is_none());
+    }
+
+    #[test]
+    fn test_malformed_frame() {
+        let eth_repr = |protocol| ethernet::Repr {
+            src_addr: PEER_MAC,
+            dst_addr: HOST_MAC,
+            protocol,
+        };
+
+        let mut arp = [0u8; 20];
+        eth_repr(layer2::Protocol::Layer3Protocol(
+            layer2::Layer3Protocol::ARP,
+        ))
+        .emit(&mut ethernet::Packet::new_unchecked(&mut arp[..]));
+
+        let mut llc = [0u8; 16];
+        eth_repr(layer2::Protocol::Length(2))
+            .emit(&mut ethernet::Packet::new_unchecked(&mut llc[..]));
+
+        let frames: &[&[u8]] = &[&[0; 10], &arp, &llc];
+
+        for frame in frames {
+            let mut rx_storage = PacketRing::<(), 1, 64>::default();
+            let mut tx_storage = PacketRing::<(), 1, 64>::default();
+            let mut socket = RawSocket::new(
+                layer3::Protocol::Unknown(253),
+                &mut rx_storage,
+                &mut tx_storage,
+            );
+
+            let mut packet = [0u8; 64];
+            let len = build_ipv4(
+                layer3::ipv4::Address::BROADCAST,
+                layer3::Protocol::Unknown(253),
+                b"still sent",
+                &mut packet,
+            );
+            socket.send(&packet[..len]).unwrap();
+
+            let mut sockets = Sockets::<1>::default();
+            sockets.add(socket).unwrap();
+
+            // Frame is dropped, and sockets are still dispatched.
+            let mut iface = interface(Medium::Ethernet, frame.to_vec());
+            iface.poll(Instant::ZERO, &mut sockets).unwrap();
+
+            let tx = &iface.device().tx;
+            assert_eq!(tx.len(), 1);
+            assert_eq!(&tx[0][14..], &packet[..len]);
+        }
+    }
+
+    #[test]
+    fn test_multicast_ethernet() {
+        let group = layer3::ipv4::Address::new(239, 1, 2, 3);
+
+        let mut rx_storage = PacketRing::<(), 2, 64>::default();
+        let mut tx_storage = PacketRing::<(), 1, 64>::default();
+        let socket = RawSocket::new(
+            layer3::Protocol::Unknown(253),
+            &mut rx_storage,
+            &mut tx_storage,
+        );
+
+        let mut sockets = Sockets::<1>::default();
+        let handle = sockets.add(socket).unwrap();
+
+        let mut iface = interface(Medium::Ethernet, Vec::new());
+        iface
+            .addrs_storage_mut()
+            .join_multicast_group(group)
+            .unwrap();
+
+        // Frame to link address of a group not joined is dropped.
+        for dst in [group, layer3::ipv4::Address::new(239, 1, 2, 4)].iter() {
+            let mut rx = [0u8; 64];
+            let eth_repr = ethernet::Repr {
+                src_addr: PEER_MAC,
+                dst_addr: multicast_link_addr(dst),
+                protocol: layer2::Protocol::Layer3Protocol(layer2::Layer3Protocol::IPv4),
+            };
+            eth_repr.emit(&mut ethernet::Packet::new_unchecked(&mut rx[..]));
+            let len = 14
+                + build_ipv4(
+                    *dst,
+                    layer3::Protocol::Unknown(253),
+                    b"group",
+                    &mut rx[14..],
+                );
+
+            iface.device_mut().rx = Some(rx[..len].into());
+            iface.poll(Instant::ZERO, &mut sockets).unwrap();
+        }
+
+        let mut buffer = [0u8; 64];
+        let socket = sockets.get_mut(handle).unwrap();
+        assert_eq!(socket.recv(&mut buffer), Some(25));
+        assert_eq!(&buffer[20..25], b"group");
+        assert_eq!(socket.recv(&mut buffer), None);
+        assert!(iface.device().tx.is_empty());
+    }
+
+    #[test]
+    fn test_drop_counters() {
+        let mut rx = [0u8; 64];
+        let len = build_echo_request(host_ip(), &mut rx);
+        rx[10] ^= 0xff;
+
+        let mut iface = interface(Medium::Ip, rx[..len].into());
+        let mut sockets = Sockets::<1>::default();
+        iface.poll(Instant::ZERO, &mut sockets).unwrap();
+
+        let len = build_echo_request(layer3::ipv4::Address::new(10, 0, 0, 1), &mut rx);
+        iface.device_mut().rx = Some(rx[..len].into());
+        iface.poll(Instant::ZERO, &mut sockets).unwrap();
+
+        assert!(iface.device().tx.is_empty());
+
+        let drops = iface.drop_counters();
+        assert_eq!(drops.get(DropReason::Checksum), 1);
+        assert_eq!(drops.get(DropReason::DstAddr), 1);
+        assert_eq!(drops.get(DropReason::Ttl), 0);
+        assert_eq!(drops.total(), 2);
+
+        iface.drop_counters_mut().reset();
+        assert_eq!(iface.drop_counters().total(), 0);
+    }
+
+    #[test]
+    fn test_packet_too_large() {
+        let mut iface = interface(Medium::Ip, Vec::new());
+        iface.device_mut().rx = None;
+
+        assert!(matches!(
+            iface.dispatch_ipv4(peer_ip(), 1501, |_| Ok(())),
+            Err(Error::PacketTooLarge)
+        ));
+        assert!(iface.device().tx.is_empty());
+
+        // Raw packet exceed MTU is dropped, and the next one is sent.
+        let mut rx_storage = PacketRing::<(), 1, 64>::default();
+        let mut tx_storage = PacketRing::<(), 2, 2048>::default();
+        let mut socket = RawSocket::new(
+            layer3::Protocol::Unknown(253),
+            &mut rx_storage,
+            &mut tx_storage,
+        );
+
+        let mut large = [0u8; 1600];
+        let len = build_ipv4(
+            peer_ip(),
+            layer3::Protocol::Unknown(253),
+            &[0; 1580],
+            &mut large,
+        );
+        socket.send(&large[..len]).unwrap();
+
+        let mut small = [0u8; 64];
+        let len = build_ipv4(
+            peer_ip(),
+            layer3::Protocol::Unknown(253),
+            b"small",
+            &mut small,
+        );
+        socket.send(&small[..len]).unwrap();
+
+        let mut sockets = Sockets::<1>::default();
+        sockets.add(socket).unwrap();
+
+        iface.poll(Instant::ZERO, &mut sockets).unwrap();
+        assert_eq!(iface.device().tx.len(), 1);
+        assert_eq!(iface.device().tx[0].len(), len);
+    }
+
+    #[test]
+    #[cfg(feature = "udp")]
+    fn test_udp_socket() {
+        let mut udp = [0u8; 12];
+        let udp_repr = layer4::udp::Repr {
+            src_port: 5000,
+            dst_port: 53,
+            payload: b"ping",
+        };
+        udp_repr
+            .emit(
+                &mut layer4::udp::Packet::new_unchecked(&mut udp[..]),
+                &layer3::Address::Ipv4(peer_ip()),
+                &layer3::Address::Ipv4(host_ip()),
+            )
+            .unwrap();
+
+        let mut rx = [0u8; 64];
+        let len = build_ipv4(host_ip(), layer3::Protocol::Udp, &udp, &mut rx);
+
+        let mut rx_storage = PacketRing::<Endpoint, 2, 16>::default();
+        let mut tx_storage = PacketRing::<Endpoint, 2, 16>::default();
+        let mut socket = UdpSocket::new(&mut rx_storage, &mut tx_storage);
+        socket.bind(53).unwrap();
+
+        let mut raw_rx_storage = PacketRing::<(), 1, 64>::default();
+        let mut raw_tx_storage = PacketRing::<(), 1, 64>::default();
+        let raw_socket = RawSocket::new(
+            layer3::Protocol::Udp,
+            &mut raw_rx_storage,
+            &mut raw_tx_storage,
+        );
+
+        let mut sockets = Sockets::<2>::default();
+        let handle = sockets.add(socket).unwrap();
+        let raw_handle = sockets.add(raw_socket).unwrap();
+        assert!(sockets.get::<RawSocket>(SocketHandle::new(0)).is_none());
+
+        let mut iface = interface(Medium::Ip, rx[..len].into());
+        iface.poll(Instant::ZERO, &mut sockets).unwrap();
+        assert!(iface.device().tx.is_empty());
+
+        let mut buffer = [0u8; 64];
+        let raw_len = sockets.get_mut(raw_handle).unwrap().recv(&mut buffer);
+        assert_eq!(raw_len, Some(len));
+
+        let socket = sockets.get_mut(handle).unwrap();
+
+        let (len, remote) = socket.recv_from(&mut buffer).unwrap().unwrap();
+        assert_eq!(&buffer[..len], b"ping");
+        assert_eq!(remote, Endpoint::from((peer_ip(), 5000)));
+        assert!(socket.recv_from(&mut buffer).unwrap().is_none());
+
+        socket.send_to(b"pong", remote).unwrap();
+        iface.device_mut().rx = None;
+        iface.poll(Instant::ZERO, &mut sockets).unwrap();
+
+        let tx = &iface.device().tx;
+        assert_eq!(tx.len(), 1);
+
+        let ip_pkt = layer3::ipv4::Packet::new_checked(&tx[0][..]).unwrap();
+        assert_eq!(ip_pkt.src_addr(), host_ip());
+        assert_eq!(ip_pkt.dst_addr(), peer_ip());
+
+        let udp_pkt = layer4::udp::Packet::new_checked(ip_pkt.payload()).unwrap();
+        let udp_repr = layer4::udp::Repr::parse(
+            &udp_pkt,
+            &layer3::Address::Ipv4(host_ip()),
+            &layer3::Address::Ipv4(peer_ip()),
+        )
+        .unwrap();
+        assert_eq!(udp_repr.src_port, 53);
+        assert_eq!(udp_repr.dst_port, 5000);
+        assert_eq!(udp_repr.payload, b"pong");
+    }
+
+    #[test]
+    #[cfg(feature = "udp")]
+    fn test_misrouted_udp() {
+        let mut udp = [0u8; 12];
+        let udp_repr = layer4::udp::Repr {
+            src_port: 5000,
+            dst_port: 53,
+            payload: b"ping",
+        };
+        let other_ip = layer3::ipv4::Address::new(192, 168, 1, 3);
+        udp_repr
+            .emit(
+                &mut layer4::udp::Packet::new_unchecked(&mut udp[..]),
+                &layer3::Address::Ipv4(peer_ip()),
+                &layer3::Address::Ipv4(other_ip),
+            )
+            .unwrap();
+
+        let mut rx = [0u8; 64];
+        let len = build_ipv4(other_ip, layer3::Protocol::Udp, &udp, &mut rx);
+
+        let mut rx_storage = PacketRing::<Endpoint, 2, 16>::default();
+        let mut tx_storage = PacketRing::<Endpoint, 2, 16>::default();
+        let mut socket = UdpSocket::new(&mut rx_storage, &mut tx_storage);
+        socket.bind(53).unwrap();
+
+        let mut sockets = Sockets::<1>::default();
+        let handle = sockets.add(socket).unwrap();
+
+        // Packet to other host is dropped without ICMP error.
+        let mut iface = interface(Medium::Ip, rx[..len].into());
+        iface.poll(Instant::ZERO, &mut sockets).unwrap();
+        assert!(iface.device().tx.is_empty());
+
+        let mut buffer = [0u8; 16];
+        let socket = sockets.get_mut(handle).unwrap();
+        assert!(socket.recv_from(&mut buffer).unwrap().is_none());
+
+        // Packet with bad header checksum is dropped before UDP.
+        let len = build_ipv4(host_ip(), layer3::Protocol::Udp, &udp, &mut rx);
+        rx[10] ^= 0xff;
+        iface.device_mut().rx = Some(rx[..len].into());
+        iface.poll(Instant::ZERO, &mut sockets).unwrap();
+        assert!(iface.device().tx.is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "udp")]
+    fn test_udp_fragments() {
+        let mut rx_storage = PacketRing::<Endpoint, 1, 2048>::default();
+        let mut tx_storage = PacketRing::<Endpoint, 1, 2048>::default();
+        let mut socket = UdpSocket::new(&mut rx_storage, &mut tx_storage);
+        socket.bind(53).unwrap();
+
+        let data: Vec<u8> = (0..2000).map(|i| i as u8).collect();
+        socket
+            .send_to(&data, Endpoint::from((host_ip(), 53)))
+            .unwrap();
+
+        let mut sockets = Sockets::<1>::default();
+        let handle = sockets.add(socket).unwrap();
+
+        let mut iface = interface(Medium::Ip, Vec::new());
+        iface.device_mut().rx = None;
+        iface.poll(Instant::ZERO, &mut sockets).unwrap();
+
+        let tx = core::mem::take(&mut iface.device_mut().tx);
+        assert_eq!(tx.len(), 2);
+        assert_eq!(tx[0].len(), 1500);
+        assert_eq!(tx[1].len(), 20 + 2008 - 1480);
+
+        let first = layer3::ipv4::Packet::new_checked(&tx[0][..]).unwrap();
+        let last = layer3::ipv4::Packet::new_checked(&tx[1][..]).unwrap();
+        assert!(first.more_frags() && !last.more_frags());
+        assert_eq!((first.frag_offset(), last.frag_offset()), (0, 1480));
+        assert_eq!(first.ident(), last.ident());
+
+        // Fragments are reassembled in any order.
+        for bytes in tx.into_iter().rev() {
+            iface.device_mut().rx = Some(bytes);
+            iface.poll(Instant::ZERO, &mut sockets).unwrap();
+        }
+
+        let mut buffer = [0u8; 2048];
+        let socket = sockets.get_mut(handle).unwrap();
+        let (len, remote) = socket.recv_from(&mut buffer).unwrap().unwrap();
+        assert_eq!(&buffer[..len], &data[..]);
+        assert_eq!(remote, Endpoint::from((host_ip(), 53)));
+
+        // Don't fragment is respected.
+        iface.device_mut().rx = None;
+        iface.config_mut().ipv4.dont_frag = true;
+        assert!(matches!(
+            iface.send_ipv4(peer_ip(), layer3::Protocol::Udp, &data),
+            Err(Error::PacketTooLarge)
+        ));
+        assert!(iface.device().tx.is_empty());
+    }
+
+    #[test]
+    fn test_send_ipv4_arp() {
+        let mut iface = interface(Medium::Ethernet, Vec::new());
+        iface.device_mut().rx = None;
+
+        let config = iface.config_mut();
+        config.vlan.vlanid0 = Some(layer2::VlanId(10));
+        config.vlan.tag_vlan0 = true;
+
+        iface
+            .send_ipv4(peer_ip(), layer3::Protocol::Udp, b"first")
+            .unwrap();
+        iface
+            .send_ipv4(peer_ip(), layer3::Protocol::Udp, b"second")
+            .unwrap();
+
+        // One request for both packets.
+        let tx = &iface.device().tx;
+        assert_eq!(tx.len(), 1);
+
+        let eth_pkt = ethernet::Packet::new_checked(&tx[0][..]).unwrap();
+        assert_eq!(eth_pkt.dest_addr(), layer2::Address::BROADCAST);
+        let arp_pkt = layer3::arp::Packet::new_checked(eth_pkt.payload()).unwrap();
+        let arp_repr = layer3::arp::Repr::parse(&arp_pkt).unwrap();
+        assert_eq!(arp_repr.operation, layer3::arp::Operation::Request);
+        assert_eq!(arp_repr.source_hardware_addr, HOST_MAC);
+        assert_eq!(arp_repr.source_protocol_addr, host_ip());
+        assert_eq!(arp_repr.target_protocol_addr, peer_ip());
+
+        // Queue is bounded.
+        for _ in 2..consts::MAX_PENDING_PACKETS {
+            iface
+                .send_ipv4(peer_ip(), layer3::Protocol::Udp, b"more")
+                .unwrap();
+        }
+        assert!(matches!(
+            iface.send_ipv4(peer_ip(), layer3::Protocol::Udp, b"dropped"),
+            Err(Error::NoSpaceForPendingPackets)
+        ));
+        // No more request is sent for later packets.
+        assert_eq!(iface.device().tx.len(), 1);
+        iface.device_mut().tx.clear();
+
+        // Reply to another host is not recorded, parked packets are kept.
+        let mut spoofed = build_arp(layer3::arp::Operation::Reply, [0x02, 0, 0, 0, 0, 9].into());
+        spoofed[38..42].copy_from_slice(&[192, 168, 1, 9]);
+        iface.device_mut().rx = Some(spoofed);
+        iface
+            .poll(Instant::ZERO, &mut Sockets::<1>::default())
+            .unwrap();
+        assert!(iface.device().tx.is_empty());
+        assert!(iface.arp_storage().get(&peer_ip()).is_none());
+
+        iface.device_mut().rx = Some(build_arp(layer3::arp::Operation::Reply, PEER_MAC));
+        iface
+            .poll(Instant::ZERO, &mut Sockets::<1>::default())
+            .unwrap();
+        iface.device_mut().rx = None;
+
+        // Reply is not answered, parked packets are sent in order with VLAN tag.
+        let tx = &iface.device().tx;
+        assert_eq!(tx.len(), consts::MAX_PENDING_PACKETS);
+
+        for (bytes, payload) in tx.iter().zip([&b"first"[..], b"second", b"more"]) {
+            let eth_pkt = ethernet::Packet::new_checked(&bytes[..]).unwrap();
+            assert_eq!(eth_pkt.dest_addr(), PEER_MAC);
+            assert!(matches!(
+                eth_pkt.protocol(),
+                layer2::Protocol::IEEE8021Q(tag, layer2::Layer3Protocol::IPv4) if tag.vid.0 == 10
+            ));
+
+            let ip_pkt = layer3::ipv4::Packet::new_checked(eth_pkt.payload()).unwrap();
+            assert_eq!(ip_pkt.src_addr(), host_ip());
+            assert_eq!(ip_pkt.dst_addr(), peer_ip());
+            assert_eq!(ip_pkt.payload(), payload);
+        }
+
+        // Address is resolved now.
+        iface
+            .send_ipv4(peer_ip(), layer3::Protocol::Udp, b"direct")
+            .unwrap();
+        assert_eq!(iface.device().tx.len(), consts::MAX_PENDING_PACKETS + 1);
+    }
+
+    #[test]
+    fn test_fragments_unresolved() {
+        let mut iface = interface(Medium::Ethernet, Vec::new());
+        iface.device_mut().rx = None;
+
+        iface
+            .send_ipv4(peer_ip(), layer3::Protocol::Udp, b"first")
+            .unwrap();
+
+        // Four fragments don't fit in the rest of queue, none is parked.
+        let data = [0u8; 5000];
+        assert!(matches!(
+            iface.send_ipv4(peer_ip(), layer3::Protocol::Udp, &data),
+            Err(Error::NoSpaceForPendingPackets)
+        ));
+
+        // Two fragments fit.
+        iface
+            .send_ipv4(peer_ip(), layer3::Protocol::Udp, &data[..2000])
+            .unwrap();
+        iface.device_mut().tx.clear();
+
+        iface.device_mut().rx = Some(build_arp(layer3::arp::Operation::Reply, PEER_MAC));
+        iface
+            .poll(Instant::ZERO, &mut Sockets::<1>::default())
+            .unwrap();
+
+        let lens: Vec<_> = iface.device().tx.iter().map(Vec::len).collect();
+        assert_eq!(lens, [14 + 20 + 5, 14 + 1500, 14 + 20 + 520]);
+    }
+
+    #[test]
+    fn test_arp_no_reply() {
+        let mut iface = interface(Medium::Ethernet, Vec::new());
+        let mut sockets = Sockets::<1>::default();
+        let mut poll = |iface: &mut TestInterface, now, rx: Option<Vec<u8>>| {
+            iface.device_mut().rx = rx;
+            iface.poll(now, &mut sockets).unwrap();
+            core::mem::take(&mut iface.device_mut().tx)
+        };
+
+        poll(&mut iface, Instant::ZERO, None);
+        iface
+            .send_ipv4(peer_ip(), layer3::Protocol::Udp, b"lost")
+            .unwrap();
+        assert_eq!(iface.device().tx.len(), 1);
+        iface.device_mut().tx.clear();
+
+        // Request is sent again once per interval.
+        let requests: Vec<usize> = [500, 1000, 1500, 2000, 2500]
+            .iter()
+            .map(|ms| poll(&mut iface, Instant::from_millis(*ms), None).len())
+            .collect();
+        assert_eq!(requests, [0, 1, 0, 1, 0]);
+
+        // Packet is dropped after the last request, late reply sends nothing.
+        assert_eq!(poll(&mut iface, Instant::from_secs(3), None).len(), 0);
+        assert!(!iface.pending.contains(peer_ip()));
+
+        let reply = build_arp(layer3::arp::Operation::Reply, PEER_MAC);
+        assert!(poll(&mut iface, Instant::from_secs(4), Some(reply)).is_empty());
+
+        // Queue has space for new packets again.
+        iface.arp_storage_mut().remove(&peer_ip());
+        for _ in 0..consts::MAX_PENDING_PACKETS {
+            iface
+                .send_ipv4(peer_ip(), layer3::Protocol::Udp, b"again")
+                .unwrap();
+        }
+        assert_eq!(iface.device().tx.len(), 1);
+    }
+
+    #[test]
+    fn test_poll_at_pending() {
+        let mut rx_storage = PacketRing::<(), 1, 64>::default();
+        let mut tx_storage = PacketRing::<(), 8, 64>::default();
+        let mut socket = RawSocket::new(layer3::Protocol::Udp, &mut rx_storage, &mut tx_storage);
+
+        let mut packet = [0u8; 64];
+        let len = build_ipv4(peer_ip(), layer3::Protocol::Udp, b"data", &mut packet);
+        let packet = &mut packet[..len];
+        packet[12..16].copy_from_slice(host_ip().as_bytes());
+        packet[16..20].copy_from_slice(peer_ip().as_bytes());
+        for _ in 0..consts::MAX_PENDING_PACKETS + 1 {
+            socket.send(packet).unwrap();
+        }
+
+        let mut sockets = Sockets::<1>::default();
+        sockets.add(socket).unwrap();
+
+        let mut iface = interface(Medium::Ethernet, Vec::new());
+        iface.device_mut().rx = None;
+        assert_eq!(iface.poll_at(&sockets), Some(Instant::ZERO));
+
+        // Queue of parked packets is full, wait for the next request.
+        iface.poll(Instant::ZERO, &mut sockets).unwrap();
+        assert_eq!(iface.device_mut().tx.len(), 1);
+        assert_eq!(iface.poll_at(&sockets), Some(Instant::from_secs(1)));
+
+        // Remaining packet is sent after reply.
+        iface.device_mut().tx.clear();
+        iface.device_mut().rx = Some(build_arp(layer3::arp::Operation::Reply, PEER_MAC));
+        iface.poll(Instant::from_millis(500), &mut sockets).unwrap();
+        assert_eq!(iface.device().tx.len(), consts::MAX_PENDING_PACKETS + 1);
+        assert_eq!(iface.poll_at(&sockets), None);
+    }
+
+    #[test]
+    fn test_send_via_gateway() {
+        let mut iface = interface(Medium::Ethernet, Vec::new());
+        iface.device_mut().rx = None;
+
+        let remote = layer3::ipv4::Address::new(8, 8, 8, 8);
+        assert!(matches!(
+            iface.send_ipv4(remote, layer3::Protocol::Udp, b"lost"),
+            Err(Error::NoRouteToHost)
+        ));
+        assert!(iface.device().tx.is_empty());
+
+        iface
+            .route_storage_mut()
+            .insert(Route::default_via(peer_ip(), 0))
+            .unwrap();
+        iface
+            .send_ipv4(remote, layer3::Protocol::Udp, b"hello")
+            .unwrap();
+
+        // Gateway is resolved instead of destination.
+        let tx = core::mem::take(&mut iface.device_mut().tx);
+        assert_eq!(tx.len(), 1);
+        let eth_pkt = ethernet::Packet::new_checked(&tx[0][..]).unwrap();
+        let arp_pkt = layer3::arp::Packet::new_checked(eth_pkt.payload()).unwrap();
+        let arp_repr = layer3::arp::Repr::parse(&arp_pkt).unwrap();
+        assert_eq!(arp_repr.target_protocol_addr, peer_ip());
+
+        iface.device_mut().rx = Some(build_arp(layer3::arp::Operation::Reply, PEER_MAC));
+        iface
+            .poll(Instant::ZERO, &mut Sockets::<1>::default())
+            .unwrap();
+
+        let tx = &iface.device().tx;
+        assert_eq!(tx.len(), 1);
+        let eth_pkt = ethernet::Packet::new_checked(&tx[0][..]).unwrap();
+        assert_eq!(eth_pkt.dest_addr(), PEER_MAC);
+        let ip_pkt = layer3::ipv4::Packet::new_checked(eth_pkt.payload()).unwrap();
+        assert_eq!(ip_pkt.dst_addr(), remote);
+        assert_eq!(ip_pkt.payload(), b"hello");
+
+        // Subnet of interface is on link, a longer prefix takes precedence.
+        let other = layer3::ipv4::Address::new(192, 168, 1, 3);
+        assert_eq!(iface.next_hop(other), Some(other));
+
+        let host_route = layer3::ipv4::Cidr::new(other, 32);
+        iface
+            .route_storage_mut()
+            .remove(&Default::default(), Some(peer_ip()));
+        iface
+            .route_storage_mut()
+            .insert(Route::via(host_route, peer_ip(), 10))
+            .unwrap();
+        assert_eq!(iface.next_hop(other), Some(peer_ip()));
+        assert_eq!(iface.next_hop(remote), None);
+    }
+
+    #[test]
+    fn test_arp_expiry() {
+        const NEW_MAC: layer2::Address = layer2::Address([0x02, 0, 0, 0, 0, 3]);
+
+        let mut iface = interface(Medium::Ethernet, Vec::new());
+        let mut sockets = Sockets::<1>::default();
+        let mut poll = |iface: &mut TestInterface, now, rx: Option<Vec<u8>>| {
+            iface.device_mut().rx = rx;
+            iface.poll(now, &mut sockets).unwrap();
+            iface.device_mut().tx.clear();
+        };
+
+        // Request from peer is answered and recorded.
+        let request = build_arp(layer3::arp::Operation::Request, PEER_MAC);
+        iface.device_mut().rx = Some(request);
+        iface
+            .poll(Instant::ZERO, &mut Sockets::<1>::default())
+            .unwrap();
+        assert_eq!(iface.device().tx.len(), 1);
+        assert_eq!(
+            iface.arp_storage().get(&peer_ip()).unwrap().mac_addr,
+            PEER_MAC
+        );
+
+        // Entry used near expiry is refreshed by unicast request once.
+        poll(&mut iface, Instant::from_secs(56), None);
+        iface
+            .send_ipv4(peer_ip(), layer3::Protocol::Udp, b"refresh")
+            .unwrap();
+        iface
+            .send_ipv4(peer_ip(), layer3::Protocol::Udp, b"again")
+            .unwrap();
+
+        let tx = &iface.device().tx;
+        assert_eq!(tx.len(), 3);
+        let eth_pkt = ethernet::Packet::new_checked(&tx[1][..]).unwrap();
+        assert_eq!(eth_pkt.dest_addr(), PEER_MAC);
+        assert!(layer3::arp::Packet::new_checked(eth_pkt.payload()).is_ok());
+
+        // Hardware is replaced, new address is learned from its reply.
+        let reply = build_arp(layer3::arp::Operation::Reply, NEW_MAC);
+        poll(&mut iface, Instant::from_secs(57), Some(reply));
+        let entry = iface.arp_storage().get(&peer_ip()).unwrap();
+        assert_eq!(entry.mac_addr, NEW_MAC);
+        assert_eq!(entry.confirmed_at, Some(Instant::from_secs(57)));
+        assert!(!entry.refreshing);
+
+        // Expired entry is not used.
+        poll(&mut iface, Instant::from_secs(117), None);
+        iface
+            .send_ipv4(peer_ip(), layer3::Protocol::Udp, b"expired")
+            .unwrap();
+        let tx = &iface.device().tx;
+        assert_eq!(tx.len(), 1);
+        let eth_pkt = ethernet::Packet::new_checked(&tx[0][..]).unwrap();
+        assert_eq!(eth_pkt.dest_addr(), layer2::Address::BROADCAST);
+        assert!(iface.arp_storage().get(&peer_ip()).is_none());
+
+        // Static entry never expires or changes.
+        iface
+            .arp_storage_mut()
+            .set_static(PEER_MAC, peer_ip())
+            .unwrap();
+        let reply = build_arp(layer3::arp::Operation::Reply, NEW_MAC);
+        poll(&mut iface, Instant::from_secs(1000), Some(reply));
+        iface
+            .send_ipv4(peer_ip(), layer3::Protocol::Udp, b"static")
+            .unwrap();
+        let tx = &iface.device().tx;
+        assert_eq!(tx.len(), 1);
+        let eth_pkt = ethernet::Packet::new_checked(&tx[0][..]).unwrap();
+        assert_eq!(eth_pkt.dest_addr(), PEER_MAC);
+    }
 }
@@ -1,85 +1,662 @@
-use auip_pkt::layer3::{
-    ipv4::{field, Packet},
-    Protocol,
+use auip_pkt::{
+    layer3::{
+        self,
+        ipv4::{self, field, Packet, Repr},
+        Protocol,
+    },
+    layer4::icmpv4::{self, DstUnreachable, Message},
+    IntoInner,
 };
 
-use crate::{build_time_exceeded_ttl, poll_icmpv4, poll_udp, IpFragmentBuffer, Result, bytes::Icmpv4Bytes};
+#[cfg(feature = "tcp")]
+use crate::poll_tcp;
+#[cfg(feature = "udp")]
+use crate::poll_udp;
+use crate::{
+    bytes::NoFragIpBytes, consts, poll_icmpv4, poll_raw, reassemble_ipv4, time::Instant,
+    AddrsStorage, InterfaceConfig, IpFragmentBuffer, Result, Socket, MAX_HEADER_LEN,
+};
 
-pub(crate) fn poll_ipv4(
-    pkt: Packet<&[u8]>,
-    ip_fragment_buffer: &mut impl IpFragmentBuffer,
-) -> Result<()> {
-    log::debug!("Receive packet: {}", pkt);
+/// Reason of dropping a received IPv4 packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropReason {
+    /// Packet is shorter than the fixed header.
+    Truncated,
+    /// Version is not 4.
+    Version,
+    /// Header length is less than 20 octets or exceed the packet.
+    HeaderLen,
+    /// Total length is less than header length or exceed the packet.
+    TotalLen,
+    /// Header checksum mismatch.
+    Checksum,
+    /// Options in header are malformed.
+    Options,
+    /// Time to live is zero.
+    Ttl,
+    /// Source is a broadcast or multicast address.
+    SrcAddr,
+    /// Destination is not an address of this host.
+    DstAddr,
+    /// Packet to forward exceeds MTU of the interface received it.
+    TooLarge,
+}
 
-    // Check is fragment
-    let payload = if pkt.dont_frag() {
-        // enter upper layer.
-        pkt.payload()
-    } else {
-        let ident = pkt.ident();
+/// Number of [DropReason] variants.
+const DROP_REASONS: usize = DropReason::TooLarge as usize + 1;
+
+/// Count of received IPv4 packets dropped for each [DropReason].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DropCounters {
+    counts: [u32; DROP_REASONS],
+}
+
+impl DropCounters {
+    /// Get count of packets dropped for `reason`, wraps on overflow.
+    pub fn get(&self, reason: DropReason) -> u32 {
+        self.counts[reason as usize]
+    }
+
+    /// Get count of packets dropped for any reason.
+    pub fn total(&self) -> u32 {
+        self.counts
+            .iter()
+            .fold(0, |total, count| total.wrapping_add(*count))
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub(crate) fn record(&mut self, reason: DropReason) {
+        let count = &mut self.counts[reason as usize];
+        *count = count.wrapping_add(1);
+    }
+}
+
+/// Kind of destination address of a packet accepted by this host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Destination {
+    Unicast,
+    Broadcast,
+    Multicast,
+}
+
+/// Check header of received IPv4 packet, RFC 1122 section 3.2.1.
+pub(crate) fn check_ipv4(bytes: &[u8]) -> core::result::Result<Packet<&[u8]>, DropReason> {
+    let min_header_len = field::HEADER_LEN_WITHOUT_OPTION as usize;
+
+    if bytes.len() < min_header_len {
+        return Err(DropReason::Truncated);
+    }
+
+    let pkt = Packet::new_unchecked(bytes);
+
+    if pkt.version() != 4 {
+        return Err(DropReason::Version);
+    }
+
+    let header_len = pkt.header_len() as usize;
+    if header_len < min_header_len || header_len > bytes.len() {
+        return Err(DropReason::HeaderLen);
+    }
+
+    // Link layer may pad the packet, but never truncate it.
+    let total_len = pkt.total_len() as usize;
+    if total_len < header_len || total_len > bytes.len() {
+        return Err(DropReason::TotalLen);
+    }
+
+    if !pkt.verify_checksum() {
+        return Err(DropReason::Checksum);
+    }
+
+    if pkt.check_options().is_err() {
+        return Err(DropReason::Options);
+    }
+
+    if pkt.ttl() == 0 {
+        return Err(DropReason::Ttl);
+    }
 
-        // Ip fragment support.
-        if pkt.more_frags() {
-            // save frag.
-            let payload = pkt.payload();
-            let payload_len = payload.len();
-            let offset = pkt.frag_offset() as usize;
+    let src_addr = pkt.src_addr();
+    if src_addr.is_broadcast() || src_addr.is_multicast() {
+        return Err(DropReason::SrcAddr);
+    }
 
-            let buffer = ip_fragment_buffer.get_buffer(ident);
-            let target_buf = &mut buffer[offset..payload_len];
-            target_buf.copy_from_slice(payload);
+    Ok(pkt)
+}
 
-            return Ok(());
+/// Check `dst_addr` is an unicast address of this host, the limited broadcast, a subnet
+/// broadcast of address of this host, or a multicast group joined.
+pub(crate) fn check_ipv4_dst(
+    dst_addr: ipv4::Address,
+    addrs_storage: &impl AddrsStorage,
+) -> core::result::Result<Destination, DropReason> {
+    if dst_addr.is_broadcast() || is_subnet_broadcast(dst_addr, addrs_storage) {
+        Ok(Destination::Broadcast)
+    } else if dst_addr.is_multicast() {
+        if dst_addr == ipv4::Address::MULTICAST_ALL_SYSTEMS
+            || addrs_storage.has_multicast_group(&dst_addr)
+        {
+            Ok(Destination::Multicast)
         } else {
-            let length = pkt.total_len() - pkt.header_len() as u16 + pkt.frag_offset();
-            let buffer = ip_fragment_buffer.get_buffer(ident);
-            &buffer[0..length as usize]
+            Err(DropReason::DstAddr)
+        }
+    } else if !dst_addr.is_unspecified()
+        && addrs_storage.has_ip_addr(&layer3::Address::Ipv4(dst_addr))
+    {
+        Ok(Destination::Unicast)
+    } else {
+        Err(DropReason::DstAddr)
+    }
+}
+
+/// Keep `pkt` addressed to an unicast address not of this host into `forward`, if it does
+/// not exceed `mtu` of interface.
+///
+/// Packet exceed `mtu` is dropped, an ICMP error is written into `tx` if it can not be
+/// fragmented. Return the length of the error, if any.
+fn keep_forward(
+    pkt: &Packet<&[u8]>,
+    mtu: usize,
+    forward: &mut Option<(usize, NoFragIpBytes)>,
+    addrs_storage: &impl AddrsStorage,
+    ipv4_ident: &mut u16,
+    drops: &mut DropCounters,
+    tx: &mut [u8],
+) -> Option<usize> {
+    let bytes = pkt.clone().into_inner();
+    let header_len = pkt.header_len() as usize;
+    let len = pkt.total_len() as usize;
+
+    if len <= mtu {
+        let mut buffer = NoFragIpBytes::default();
+        buffer.as_mut()[..len].copy_from_slice(&bytes[..len]);
+        *forward = Some((len, buffer));
+        return None;
+    }
+
+    log::debug!("Packet to {} exceed mtu {}, Drop it.", pkt.dst_addr(), mtu);
+    drops.record(DropReason::TooLarge);
+
+    if !pkt.dont_frag() {
+        return None;
+    }
+
+    let src_addr = ipv4_src_addr(pkt.src_addr(), addrs_storage)?;
+
+    build_icmpv4_error(
+        &bytes[..header_len],
+        pkt.payload(),
+        Message::DstUnreachable(DstUnreachable::FragRequired, mtu as u16),
+        src_addr,
+        false,
+        next_ident(ipv4_ident),
+        tx,
+    )
+}
+
+/// Get IPv4 address of this host in the same subnet as `dst_addr`, or the first one.
+pub(crate) fn ipv4_src_addr(
+    dst_addr: ipv4::Address,
+    addrs_storage: &impl AddrsStorage,
+) -> Option<ipv4::Address> {
+    let dst_addr = layer3::Address::Ipv4(dst_addr);
+
+    addrs_storage
+        .ip_addrs()
+        .iter()
+        .find_map(|cidr| match cidr.address() {
+            layer3::Address::Ipv4(addr) if cidr.contains_addr(&dst_addr) => Some(*addr),
+            _ => None,
+        })
+        .or_else(|| ipv4_addr(addrs_storage))
+}
+
+/// Get the first IPv4 address of this host.
+pub(crate) fn ipv4_addr(addrs_storage: &impl AddrsStorage) -> Option<ipv4::Address> {
+    addrs_storage
+        .ip_addrs()
+        .iter()
+        .find_map(|cidr| match cidr.address() {
+            layer3::Address::Ipv4(addr) if !addr.is_unspecified() => Some(*addr),
+            _ => None,
+        })
+}
+
+/// Check `addr` is the broadcast address of a subnet of this host.
+pub(crate) fn is_subnet_broadcast(addr: ipv4::Address, addrs_storage: &impl AddrsStorage) -> bool {
+    addrs_storage
+        .ip_addrs()
+        .iter()
+        .any(|cidr| match cidr.address() {
+            layer3::Address::Ipv4(ip_addr) => {
+                ipv4::Cidr::new(*ip_addr, cidr.prefix_len()).broadcast() == Some(addr)
+            }
+            _ => false,
+        })
+}
+
+/// Handle received IPv4 packet.
+///
+/// Packet to other host is kept in the slot of `forward`, if forwarding is enabled with
+/// MTU of interface. Return the length of reply packet written into `tx`, if any.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn poll_ipv4(
+    now: Instant,
+    config: &InterfaceConfig,
+    bytes: &[u8],
+    ip_fragment_buffer: &mut impl IpFragmentBuffer,
+    addrs_storage: &impl AddrsStorage,
+    sockets: &mut [Option<Socket<'_>>],
+    link_broadcast: bool,
+    forward: Option<(usize, &mut Option<(usize, NoFragIpBytes)>)>,
+    ipv4_ident: &mut u16,
+    drops: &mut DropCounters,
+    tx: &mut [u8],
+) -> Result<Option<usize>> {
+    let checked = check_ipv4(bytes).and_then(|pkt| {
+        check_ipv4_dst(pkt.dst_addr(), addrs_storage).map(|destination| (pkt, destination))
+    });
+
+    let (pkt, destination) = match checked {
+        Ok(checked) => checked,
+        Err(reason) => {
+            let pkt = Packet::new_unchecked(bytes);
+
+            match forward {
+                Some((mtu, forward))
+                    if reason == DropReason::DstAddr
+                        && !link_broadcast
+                        && pkt.dst_addr().is_unicast() =>
+                {
+                    return Ok(keep_forward(
+                        &pkt,
+                        mtu,
+                        forward,
+                        addrs_storage,
+                        ipv4_ident,
+                        drops,
+                        tx,
+                    ));
+                }
+                _ => {}
+            }
+
+            log::debug!("Drop IPv4 packet: {:?}.", reason);
+            drops.record(reason);
+            return Ok(None);
         }
     };
 
+    log::debug!("Receive packet: {}", pkt);
+
     let protocol = pkt.protocol();
 
-    match protocol {
+    let to_host = destination == Destination::Unicast;
+
+    // Datagram is delivered after all fragments arrive.
+    let reassembly;
+    let (header, payload) = if !pkt.more_frags() && pkt.frag_offset() == 0 {
+        (&bytes[..pkt.header_len() as usize], pkt.payload())
+    } else {
+        match reassemble_ipv4(
+            now,
+            config.ipv4.reassembly_timeout,
+            &pkt,
+            ip_fragment_buffer,
+        ) {
+            Some((index, complete)) => {
+                reassembly = complete;
+                match ip_fragment_buffer.buffer(index, reassembly.payload_len()) {
+                    Some(buffer) => (reassembly.header(), &*buffer),
+                    None => return Ok(None),
+                }
+            }
+            None => return Ok(None),
+        }
+    };
+
+    let raw_accepted = poll_raw(protocol, header, payload, sockets);
+
+    let error = match protocol {
         Protocol::Udp => {
-            poll_udp(payload)?;
+            #[cfg(feature = "udp")]
+            if poll_udp(
+                layer3::Address::Ipv4(pkt.src_addr()),
+                layer3::Address::Ipv4(pkt.dst_addr()),
+                payload,
+                sockets,
+            ) {
+                return Ok(None);
+            }
+
+            Message::DstUnreachable(DstUnreachable::PortUnreachable, 0)
         }
-        Protocol::Icmp => {
-            poll_icmpv4(payload)?;
+        #[cfg(feature = "tcp")]
+        Protocol::Tcp if to_host => {
+            let header_len = field::HEADER_LEN_WITHOUT_OPTION as usize;
+
+            let reply = poll_tcp(
+                now,
+                config.tcp.isn_secret,
+                layer3::Address::Ipv4(pkt.src_addr()),
+                layer3::Address::Ipv4(pkt.dst_addr()),
+                payload,
+                sockets,
+                &mut tx[header_len..],
+            )?;
+
+            return Ok(reply.map(|len| emit_reply(&pkt, len, next_ident(ipv4_ident), tx)));
+        }
+        Protocol::Icmp if to_host => {
+            let header_len = field::HEADER_LEN_WITHOUT_OPTION as usize;
+
+            let reply = poll_icmpv4(
+                layer3::Address::Ipv4(pkt.src_addr()),
+                payload,
+                sockets,
+                &mut tx[header_len..],
+            )?;
+
+            return Ok(reply.map(|len| emit_reply(&pkt, len, next_ident(ipv4_ident), tx)));
+        }
+        _ if to_host && !raw_accepted => {
+            Message::DstUnreachable(DstUnreachable::ProtoUnreachable, 0)
         }
-        _ => {}
+        _ => return Ok(None),
+    };
+
+    // No ICMP error for packet to broadcast or multicast address, RFC 1122 section 3.2.2.
+    if !to_host {
+        return Ok(None);
     }
 
-    Ok(())
+    Ok(build_icmpv4_error(
+        header,
+        payload,
+        error,
+        pkt.dst_addr(),
+        link_broadcast,
+        next_ident(ipv4_ident),
+        tx,
+    ))
 }
 
-pub(crate) fn build_response_packet<SendInner>(
-    recv: &Packet<&[u8]>,
-    send: &mut Packet<SendInner>,
-    protocol: Protocol,
-) where
-    SendInner: AsRef<[u8]> + AsMut<[u8]>,
-{
-    send.set_version(recv.version());
-    send.set_header_len(field::HEADER_LEN_WITHOUT_OPTION);
-    send.set_ident(recv.ident());
-    send.set_dont_frag(true);
-    send.set_ttl(64);
-    send.set_protocol(protocol);
-    send.set_src_addr(recv.dst_addr());
-    send.set_dst_addr(recv.src_addr());
+/// Advance IPv4 identification counter of interface, return the new value.
+pub(crate) fn next_ident(ipv4_ident: &mut u16) -> u16 {
+    *ipv4_ident = ipv4_ident.wrapping_add(1);
+    *ipv4_ident
 }
 
-pub(crate) fn build_icmp_time_expire_ttl_packet(recv: &Packet<&[u8]>) {
-    let bytes = Icmpv4Bytes::default();
+/// Emit IP header in reply to `recv`, for payload of `payload_len` already written in `tx`.
+///
+/// Return the length of IP packet.
+fn emit_reply(recv: &Packet<&[u8]>, payload_len: usize, ident: u16, tx: &mut [u8]) -> usize {
+    let repr = Repr {
+        src_addr: recv.dst_addr(),
+        dst_addr: recv.src_addr(),
+        protocol: recv.protocol(),
+        payload_len,
+        ttl: consts::DEFAULT_TTL,
+        ident,
+        dont_frag: true,
+    };
+
+    let len = repr.buffer_len() + payload_len;
+    repr.emit(&mut Packet::new_unchecked(&mut tx[..len]));
+
+    len
+}
+
+/// Build an ICMPv4 error message about datagram of `header` and `payload` into `tx`, sent
+/// from `src_addr` with identification `ident`.
+///
+/// The message carry the IP header and the first 8 octets of data of the datagram.
+/// Return the length of IP packet, or `None` if no error should be sent for the datagram
+/// (RFC 1122 section 3.2.2): it is an ICMP error, a non-initial fragment, or a
+/// datagram sent to or from a broadcast or multicast address.
+pub(crate) fn build_icmpv4_error(
+    header: &[u8],
+    payload: &[u8],
+    message: Message,
+    src_addr: ipv4::Address,
+    link_broadcast: bool,
+    ident: u16,
+    tx: &mut [u8],
+) -> Option<usize> {
+    let recv = Packet::new_unchecked(header);
+    let dst_addr = recv.src_addr();
+
+    if link_broadcast || !recv.dst_addr().is_unicast() || !dst_addr.is_unicast() {
+        log::debug!("Not send ICMP error for broadcast or multicast packet.");
+        return None;
+    }
+
+    if recv.frag_offset() != 0 {
+        log::debug!("Not send ICMP error for non-initial fragment.");
+        return None;
+    }
+
+    if recv.protocol() == Protocol::Icmp {
+        let is_error = icmpv4::Packet::new_checked(payload)
+            .map(|pkt| pkt.protocol().is_error())
+            .unwrap_or(true);
+
+        if is_error {
+            log::debug!("Not send ICMP error for ICMP error.");
+            return None;
+        }
+    }
+
+    let data_len = payload.len().min(icmpv4::field::ORIGINAL_DATA_LEN);
+    let original_len = header.len() + data_len;
 
-    let mut send = Packet::new_unchecked(bytes);
+    let mut original = [0u8; MAX_HEADER_LEN + icmpv4::field::ORIGINAL_DATA_LEN];
+    original[..header.len()].copy_from_slice(header);
+    original[header.len()..original_len].copy_from_slice(&payload[..data_len]);
 
-    build_response_packet(recv, &mut send, Protocol::Icmp);
-    let payload = send.payload_mut();
+    let icmp_repr = icmpv4::Repr {
+        message,
+        payload: &original[..original_len],
+    };
 
-    build_time_exceeded_ttl(payload);
+    let ip_repr = Repr {
+        src_addr,
+        dst_addr,
+        protocol: Protocol::Icmp,
+        payload_len: icmp_repr.buffer_len(),
+        ttl: consts::DEFAULT_TTL,
+        ident,
+        dont_frag: false,
+    };
 
-    send.fill_checksum();
+    let len = ip_repr.buffer_len() + icmp_repr.buffer_len();
+    if tx.len() < len {
+        return None;
+    }
+
+    let mut pkt = Packet::new_unchecked(&mut tx[..len]);
+    ip_repr.emit(&mut pkt);
+
+    let mut icmp_pkt = icmpv4::Packet::new_unchecked(pkt.payload_mut());
+    icmp_repr.emit(&mut icmp_pkt);
+
+    log::debug!("Send packet: {}", icmp_pkt);
+
+    Some(len)
 }
 
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::storage::fixed::Addrs;
+
+    fn packet(dst_addr: ipv4::Address, modify: impl FnOnce(&mut Packet<&mut [u8]>)) -> Vec<u8> {
+        let repr = Repr {
+            src_addr: ipv4::Address::new(192, 168, 1, 2),
+            dst_addr,
+            protocol: Protocol::Udp,
+            payload_len: 8,
+            ttl: 64,
+            ident: 1,
+            dont_frag: false,
+        };
+
+        let mut bytes = std::vec![0u8; repr.buffer_len() + 8];
+        let mut pkt = Packet::new_unchecked(&mut bytes[..]);
+        repr.emit(&mut pkt);
+        modify(&mut pkt);
+        if pkt.header_len() as usize <= repr.buffer_len() + 8 {
+            pkt.fill_checksum();
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn test_check_ipv4() {
+        let dst_addr = ipv4::Address::new(192, 168, 1, 1);
+        let check = |bytes: &[u8]| check_ipv4(bytes).err();
+
+        let bytes = packet(dst_addr, |_| {});
+        assert_eq!(check(&bytes), None);
+        assert_eq!(check(&bytes[..19]), Some(DropReason::Truncated));
+        // Total length exceed received bytes.
+        assert_eq!(check(&bytes[..27]), Some(DropReason::TotalLen));
+
+        // Link layer padding is allowed.
+        let mut padded = bytes.clone();
+        padded.extend_from_slice(&[0; 18]);
+        assert_eq!(check(&padded), None);
+
+        let mut corrupted = bytes.clone();
+        corrupted[8] = 1;
+        assert_eq!(check(&corrupted), Some(DropReason::Checksum));
+
+        let cases = [
+            (
+                packet(dst_addr, |pkt| pkt.set_version(6)),
+                DropReason::Version,
+            ),
+            (
+                packet(dst_addr, |pkt| pkt.set_header_len(16)),
+                DropReason::HeaderLen,
+            ),
+            (
+                packet(dst_addr, |pkt| pkt.set_header_len(60)),
+                DropReason::HeaderLen,
+            ),
+            (
+                packet(dst_addr, |pkt| pkt.set_total_len(16)),
+                DropReason::TotalLen,
+            ),
+            (
+                packet(dst_addr, |pkt| {
+                    pkt.set_header_len(24);
+                    pkt.options_mut().copy_from_slice(&[0x83, 0x08, 0x04, 0x00]);
+                }),
+                DropReason::Options,
+            ),
+            (packet(dst_addr, |pkt| pkt.set_ttl(0)), DropReason::Ttl),
+            (
+                packet(dst_addr, |pkt| pkt.set_src_addr(ipv4::Address::BROADCAST)),
+                DropReason::SrcAddr,
+            ),
+        ];
 
+        for (bytes, reason) in cases.iter() {
+            assert_eq!(check(bytes), Some(*reason));
+        }
+    }
+
+    #[test]
+    fn test_check_ipv4_dst() {
+        let mut addrs = Addrs::<1, 1>::default();
+        addrs
+            .add_ip_addr(layer3::Cidr::new(
+                layer3::Address::Ipv4(ipv4::Address::new(192, 168, 1, 1)),
+                24,
+            ))
+            .unwrap();
+
+        let group = ipv4::Address::new(239, 1, 2, 3);
+        assert!(addrs
+            .join_multicast_group(ipv4::Address::new(10, 0, 0, 1))
+            .is_err());
+        addrs.join_multicast_group(group).unwrap();
+        assert!(addrs
+            .join_multicast_group(ipv4::Address::new(239, 1, 2, 4))
+            .is_err());
+
+        let check = |addr| check_ipv4_dst(addr, &addrs);
+
+        assert_eq!(
+            check(ipv4::Address::new(192, 168, 1, 1)),
+            Ok(Destination::Unicast)
+        );
+        assert_eq!(check(ipv4::Address::BROADCAST), Ok(Destination::Broadcast));
+        assert_eq!(
+            check(ipv4::Address::new(192, 168, 1, 255)),
+            Ok(Destination::Broadcast)
+        );
+        assert_eq!(check(group), Ok(Destination::Multicast));
+        assert_eq!(
+            check(ipv4::Address::MULTICAST_ALL_SYSTEMS),
+            Ok(Destination::Multicast)
+        );
+
+        assert_eq!(
+            check(ipv4::Address::new(192, 168, 1, 3)),
+            Err(DropReason::DstAddr)
+        );
+        assert_eq!(
+            check(ipv4::Address::new(192, 168, 2, 255)),
+            Err(DropReason::DstAddr)
+        );
+        assert_eq!(
+            check(ipv4::Address::new(239, 1, 2, 4)),
+            Err(DropReason::DstAddr)
+        );
+        assert_eq!(check(ipv4::Address::UNSPECIFIED), Err(DropReason::DstAddr));
+
+        addrs.leave_multicast_group(&group).unwrap();
+        assert_eq!(check_ipv4_dst(group, &addrs), Err(DropReason::DstAddr));
+    }
+
+    #[test]
+    fn test_icmpv4_error_fragment() {
+        let src_addr = ipv4::Address::new(192, 168, 1, 1);
+        let build = |bytes: &[u8]| {
+            let pkt = Packet::new_unchecked(bytes);
+            let header = &bytes[..pkt.header_len() as usize];
+            build_icmpv4_error(
+                header,
+                pkt.payload(),
+                Message::DstUnreachable(DstUnreachable::PortUnreachable, 0),
+                src_addr,
+                false,
+                1,
+                &mut [0; 64],
+            )
+        };
+
+        assert_eq!(build(&packet(src_addr, |_| {})), Some(20 + 8 + 28));
+        assert_eq!(
+            build(&packet(src_addr, |pkt| pkt.set_more_frags(true))),
+            Some(20 + 8 + 28)
+        );
+
+        // No error for non-initial fragment, whatever Don't Fragment flag is.
+        for dont_frag in [false, true].iter() {
+            let bytes = packet(src_addr, |pkt| {
+                pkt.set_dont_frag(*dont_frag);
+                pkt.set_frag_offset(8);
+            });
+            assert_eq!(build(&bytes), None);
+        }
+    }
+}
@@ -10,12 +10,31 @@ mod arp;
 pub(crate) use arp::*;
 
 mod ipv4;
-pub(crate) use ipv4::*;
+pub use ipv4::*;
 
+mod fragment;
+pub use fragment::*;
+
+mod pending;
+pub(crate) use pending::*;
+
+mod router;
+pub use router::*;
+
+#[cfg(feature = "udp")]
 mod udp;
+#[cfg(feature = "udp")]
 pub(crate) use udp::*;
 
+#[cfg(feature = "tcp")]
+mod tcp;
+#[cfg(feature = "tcp")]
+pub(crate) use tcp::*;
+
 mod icmpv4;
 pub(crate) use icmpv4::*;
 
+mod raw;
+pub(crate) use raw::*;
+
 pub mod bytes;
@@ -0,0 +1,159 @@
+use auip_pkt::layer3::ipv4;
+
+use crate::{
+    bytes::NoFragIpBytes,
+    consts,
+    time::{Duration, Instant},
+};
+
+/// IPv4 packet waiting for link layer address of next hop.
+struct Pending {
+    next_hop: ipv4::Address,
+    len: usize,
+    bytes: NoFragIpBytes,
+    /// Time to send ARP request again, and count of requests sent.
+    retry_at: Instant,
+    requests: usize,
+}
+
+/// IPv4 packets waiting for link layer address of next hop.
+///
+/// Packets are kept in the order they are parked. Packets to the same next hop share
+/// the schedule of ARP requests.
+#[derive(Default)]
+pub(crate) struct PendingQueue {
+    packets: [Option<Pending>; consts::MAX_PENDING_PACKETS],
+}
+
+impl PendingQueue {
+    /// Park a packet of `len` octets to `next_hop`, return buffer to fill packet.
+    ///
+    /// Packet to a new next hop is scheduled to request again at `retry_at`, after the
+    /// first request. Return `None` if queue is full.
+    pub fn push(
+        &mut self,
+        next_hop: ipv4::Address,
+        len: usize,
+        retry_at: Instant,
+    ) -> Option<&mut [u8]> {
+        if len > consts::NO_FRAG_PACKET_LENGTH {
+            return None;
+        }
+
+        let (retry_at, requests) = self
+            .packets
+            .iter()
+            .flatten()
+            .find(|pending| pending.next_hop == next_hop)
+            .map_or((retry_at, 1), |pending| {
+                (pending.retry_at, pending.requests)
+            });
+
+        let slot = self.packets.iter_mut().find(|slot| slot.is_none())?;
+        let pending = slot.insert(Pending {
+            next_hop,
+            len,
+            bytes: NoFragIpBytes::default(),
+            retry_at,
+            requests,
+        });
+
+        Some(&mut pending.bytes.as_mut()[..len])
+    }
+
+    /// Take the oldest packet to `next_hop`.
+    pub fn pop(&mut self, next_hop: ipv4::Address) -> Option<(usize, NoFragIpBytes)> {
+        let index = self
+            .packets
+            .iter()
+            .position(|slot| matches!(slot, Some(pending) if pending.next_hop == next_hop))?;
+
+        let pending = self.packets[index].take()?;
+        self.packets[index..].rotate_left(1);
+
+        Some((pending.len, pending.bytes))
+    }
+
+    /// Query whether a packet to `next_hop` is parked.
+    pub fn contains(&self, next_hop: ipv4::Address) -> bool {
+        self.packets
+            .iter()
+            .flatten()
+            .any(|pending| pending.next_hop == next_hop)
+    }
+
+    /// Get the number of packets can be parked.
+    pub fn vacant(&self) -> usize {
+        self.packets.iter().filter(|slot| slot.is_none()).count()
+    }
+
+    /// Get the time to send the next ARP request.
+    pub fn retry_at(&self) -> Option<Instant> {
+        self.packets
+            .iter()
+            .flatten()
+            .map(|pending| pending.retry_at)
+            .min()
+    }
+
+    /// Schedule ARP requests due at `now` again after `interval`, return next hops to
+    /// request.
+    ///
+    /// Packets to next hop already requested `max_requests` times are dropped.
+    pub fn retry(
+        &mut self,
+        now: Instant,
+        interval: Duration,
+        max_requests: usize,
+    ) -> [Option<ipv4::Address>; consts::MAX_PENDING_PACKETS] {
+        let mut hops = [None; consts::MAX_PENDING_PACKETS];
+        let mut count = 0;
+
+        for index in 0..self.packets.len() {
+            let (next_hop, requests) = match &self.packets[index] {
+                Some(pending)
+                    if pending.retry_at <= now && !hops.contains(&Some(pending.next_hop)) =>
+                {
+                    (pending.next_hop, pending.requests)
+                }
+                _ => continue,
+            };
+
+            let timed_out = requests >= max_requests;
+            if timed_out {
+                log::debug!(
+                    "No arp reply from {}, Drop packets parked for it.",
+                    next_hop
+                );
+            } else {
+                hops[count] = Some(next_hop);
+                count += 1;
+            }
+
+            for slot in self.packets.iter_mut() {
+                match slot {
+                    Some(pending) if pending.next_hop == next_hop => {
+                        if timed_out {
+                            *slot = None;
+                        } else {
+                            pending.retry_at = now + interval;
+                            pending.requests += 1;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // Keep parked packets in order at front.
+        let mut front = 0;
+        for index in 0..self.packets.len() {
+            if self.packets[index].is_some() {
+                self.packets.swap(front, index);
+                front += 1;
+            }
+        }
+
+        hops
+    }
+}
@@ -0,0 +1,73 @@
+use auip_pkt::layer3::{ipv4, Protocol};
+
+use crate::{
+    AddrsStorage, ArpStorage, Device, Error, Interface, IpFragmentBuffer, RawSocket, Result,
+    RouteStorage, Socket,
+};
+
+/// Deliver copy of IPv4 datagram made of `header` and `payload` to raw sockets of
+/// `protocol`, fragmented datagram is delivered after reassembly.
+///
+/// Return whether any socket accept the datagram.
+pub(crate) fn poll_raw(
+    protocol: Protocol,
+    header: &[u8],
+    payload: &[u8],
+    sockets: &mut [Option<Socket<'_>>],
+) -> bool {
+    let mut accepted = false;
+
+    for socket in sockets.iter_mut().flatten() {
+        if let Socket::Raw(socket) = socket {
+            if socket.accepts(protocol) {
+                socket.process(header, payload);
+                accepted = true;
+            }
+        }
+    }
+
+    accepted
+}
+
+impl<D, AS, ARPS, IFB, RS> Interface<D, AS, ARPS, IFB, RS>
+where
+    D: Device,
+    AS: AddrsStorage,
+    ARPS: ArpStorage,
+    IFB: IpFragmentBuffer,
+    RS: RouteStorage,
+{
+    pub(crate) fn dispatch_raw(&mut self, socket: &mut RawSocket<'_>) -> Result<()> {
+        while let Some(packet) = socket.peek_tx() {
+            let dst_addr = ipv4::Packet::new_unchecked(packet).dst_addr();
+
+            let sent = self.dispatch_ipv4(dst_addr, packet.len(), |buffer| {
+                buffer.copy_from_slice(packet);
+
+                let mut pkt = ipv4::Packet::new_unchecked(buffer);
+                pkt.fill_checksum();
+
+                log::debug!("Send packet: {}", pkt);
+                Ok(())
+            });
+
+            let sent = match sent {
+                Ok(sent) => sent,
+                Err(e @ Error::NoRouteToHost) | Err(e @ Error::PacketTooLarge) => {
+                    log::debug!("Fail to send to {}: {:?}, Drop it.", dst_addr, e);
+                    socket.pop_tx();
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            if !sent {
+                break;
+            }
+
+            socket.pop_tx();
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,588 @@
+use auip_pkt::{
+    layer3::ipv4::{self, Packet},
+    layer4::icmpv4::{DstUnreachable, Message, TimeExceeded},
+    IntoInner,
+};
+
+use crate::{
+    build_icmpv4_error,
+    bytes::NoFragIpBytes,
+    emit_refragment, fragments, refragment_len,
+    time::{Duration, Instant},
+    AddrsStorage, ArpStorage, Device, Error, Interface, IpFragmentBuffer, Result, Route,
+    RouteStorage, Socket,
+};
+
+/// Interface connected by [Router], implemented by [Interface] of any device and storages.
+pub trait RouterInterface {
+    /// Process received packets and send queued packets of `sockets`, see [Interface::poll].
+    fn poll_sockets(&mut self, now: Instant, sockets: &mut [Option<Socket<'_>>]) -> Result<()>;
+
+    /// Get the time [RouterInterface::poll_sockets] should be called next, see
+    /// [Interface::poll_at].
+    fn poll_sockets_at(&self, sockets: &[Option<Socket<'_>>]) -> Option<Instant>;
+
+    /// Get the preferred route to `dst_addr`.
+    fn route(&self, dst_addr: ipv4::Address) -> Option<Route>;
+
+    /// Maximum length of IPv4 packet sent to device.
+    fn ip_mtu(&self) -> usize;
+
+    /// Take packet received to be forwarded.
+    fn take_forward(&mut self) -> Option<(usize, NoFragIpBytes)>;
+
+    /// Get IPv4 address of interface to send packet to `dst_addr` from.
+    fn ipv4_src_addr(&self, dst_addr: ipv4::Address) -> Option<ipv4::Address>;
+
+    /// Get identification for the next IPv4 packet sent.
+    fn next_ipv4_ident(&mut self) -> u16;
+
+    /// Send IPv4 packet of `len` octets to `dst_addr`, `emit` write the packet into buffer.
+    ///
+    /// Return `false` if packet can not be parked until link layer address is resolved.
+    fn dispatch_ipv4(
+        &mut self,
+        dst_addr: ipv4::Address,
+        len: usize,
+        emit: &mut dyn FnMut(&mut [u8]) -> Result<()>,
+    ) -> Result<bool>;
+}
+
+impl<D, AS, ARPS, IFB, RS> RouterInterface for Interface<D, AS, ARPS, IFB, RS>
+where
+    D: Device,
+    AS: AddrsStorage,
+    ARPS: ArpStorage,
+    IFB: IpFragmentBuffer,
+    RS: RouteStorage,
+{
+    fn poll_sockets(&mut self, now: Instant, sockets: &mut [Option<Socket<'_>>]) -> Result<()> {
+        Interface::poll_slots(self, now, sockets)
+    }
+
+    fn poll_sockets_at(&self, sockets: &[Option<Socket<'_>>]) -> Option<Instant> {
+        Interface::poll_slots_at(self, sockets)
+    }
+
+    fn route(&self, dst_addr: ipv4::Address) -> Option<Route> {
+        Interface::route(self, dst_addr)
+    }
+
+    fn ip_mtu(&self) -> usize {
+        Interface::ip_mtu(self)
+    }
+
+    fn take_forward(&mut self) -> Option<(usize, NoFragIpBytes)> {
+        Interface::take_forward(self)
+    }
+
+    fn ipv4_src_addr(&self, dst_addr: ipv4::Address) -> Option<ipv4::Address> {
+        Interface::ipv4_src_addr(self, dst_addr)
+    }
+
+    fn next_ipv4_ident(&mut self) -> u16 {
+        Interface::next_ipv4_ident(self)
+    }
+
+    fn dispatch_ipv4(
+        &mut self,
+        dst_addr: ipv4::Address,
+        len: usize,
+        emit: &mut dyn FnMut(&mut [u8]) -> Result<()>,
+    ) -> Result<bool> {
+        Interface::dispatch_ipv4(self, dst_addr, len, emit)
+    }
+}
+
+/// Router forwarding IPv4 packets between interfaces, RFC 1812.
+///
+/// Packets received by interface with `forwarding` enabled and not addressed to it are
+/// sent through the interface of the preferred route to destination. Interfaces are
+/// borrowed, so they may have different devices and storages.
+pub struct Router<'r, const N: usize> {
+    interfaces: [&'r mut dyn RouterInterface; N],
+}
+
+impl<'r, const N: usize> Router<'r, N> {
+    pub fn new(interfaces: [&'r mut dyn RouterInterface; N]) -> Self {
+        Self { interfaces }
+    }
+
+    pub fn interfaces(&self) -> &[&'r mut dyn RouterInterface; N] {
+        &self.interfaces
+    }
+
+    pub fn interfaces_mut(&mut self) -> &mut [&'r mut dyn RouterInterface; N] {
+        &mut self.interfaces
+    }
+
+    /// Poll each interface with sockets of the same index, and forward packets received.
+    ///
+    /// Error of an interface is logged and other interfaces are still polled, the first
+    /// error is returned.
+    pub fn poll(
+        &mut self,
+        now: Instant,
+        mut sockets: [&mut [Option<Socket<'_>>]; N],
+    ) -> Result<()> {
+        let mut result = Ok(());
+
+        for (index, sockets) in sockets.iter_mut().enumerate() {
+            let polled = self.interfaces[index]
+                .poll_sockets(now, sockets)
+                .and_then(|_| match self.interfaces[index].take_forward() {
+                    Some((len, bytes)) => self.forward(index, &bytes[..len]),
+                    None => Ok(()),
+                });
+
+            if let Err(e) = polled {
+                log::warn!("Poll interface {} failed: {:?}.", index, e);
+                result = result.and(Err(e));
+            }
+        }
+
+        result
+    }
+
+    /// Get the earliest time any interface should be polled.
+    pub fn poll_at(&self, sockets: [&[Option<Socket<'_>>]; N]) -> Option<Instant> {
+        self.interfaces
+            .iter()
+            .zip(sockets.iter())
+            .filter_map(|(iface, sockets)| iface.poll_sockets_at(sockets))
+            .min()
+    }
+
+    /// Get the duration from `now` to the time returned by `poll_at`.
+    pub fn poll_delay(
+        &self,
+        now: Instant,
+        sockets: [&[Option<Socket<'_>>]; N],
+    ) -> Option<Duration> {
+        self.poll_at(sockets).map(|at| at - now)
+    }
+
+    /// Get index of interface and the preferred route to `dst_addr` among interfaces.
+    fn route(&self, dst_addr: ipv4::Address) -> Option<(usize, Route)> {
+        self.interfaces
+            .iter()
+            .enumerate()
+            .filter_map(|(index, iface)| Some((index, iface.route(dst_addr)?)))
+            .fold(None, |best, (index, route)| match best {
+                Some((_, best_route)) if !route.is_preferred_to(&best_route) => best,
+                _ => Some((index, route)),
+            })
+    }
+
+    /// Forward packet in `bytes` received by interface `ingress`.
+    fn forward(&mut self, ingress: usize, bytes: &[u8]) -> Result<()> {
+        let pkt = Packet::new_unchecked(bytes);
+        let dst_addr = pkt.dst_addr();
+
+        // Packet can not live another hop, RFC 1812 section 5.3.1.
+        if pkt.ttl() <= 1 {
+            log::debug!("TTL of packet to {} exceeded, Drop it.", dst_addr);
+            let message = Message::TimeExceeded(TimeExceeded::TtlExpired);
+            return self.send_icmpv4_error(ingress, &pkt, message);
+        }
+
+        let egress = match self.route(dst_addr) {
+            Some((egress, _)) => egress,
+            None => {
+                log::debug!("No route to {}, Drop it.", dst_addr);
+                let message = Message::DstUnreachable(DstUnreachable::NetUnreachable, 0);
+                return self.send_icmpv4_error(ingress, &pkt, message);
+            }
+        };
+
+        let mtu = self.interfaces[egress].ip_mtu();
+
+        if bytes.len() <= mtu {
+            let sent =
+                self.interfaces[egress].dispatch_ipv4(dst_addr, bytes.len(), &mut |buffer| {
+                    buffer.copy_from_slice(bytes);
+
+                    let mut pkt = Packet::new_unchecked(buffer);
+                    pkt.decrement_ttl();
+
+                    log::debug!("Forward packet: {}", pkt);
+                    Ok(())
+                });
+
+            return check_sent(sent, dst_addr);
+        }
+
+        let header_len = pkt.header_len() as usize;
+
+        if pkt.dont_frag() || mtu < header_len + 8 {
+            log::debug!("Packet to {} exceed mtu {}, Drop it.", dst_addr, mtu);
+            let message = Message::DstUnreachable(DstUnreachable::FragRequired, mtu as u16);
+            return self.send_icmpv4_error(ingress, &pkt, message);
+        }
+
+        let ttl = pkt.ttl() - 1;
+
+        // Fragments after the first one may carry less options, sized by the first one.
+        for fragment in fragments(pkt.payload().len(), mtu - header_len) {
+            let len = refragment_len(bytes, fragment);
+
+            let sent = self.interfaces[egress].dispatch_ipv4(dst_addr, len, &mut |buffer| {
+                emit_refragment(bytes, fragment, ttl, buffer);
+
+                log::debug!("Forward fragment: {}", Packet::new_unchecked(buffer));
+                Ok(())
+            });
+
+            check_sent(sent, dst_addr)?;
+        }
+
+        Ok(())
+    }
+
+    /// Send ICMP error `message` about `pkt` received by interface `ingress`.
+    fn send_icmpv4_error(
+        &mut self,
+        ingress: usize,
+        pkt: &Packet<&[u8]>,
+        message: Message,
+    ) -> Result<()> {
+        let dst_addr = pkt.src_addr();
+
+        let src_addr = match self.interfaces[ingress].ipv4_src_addr(dst_addr) {
+            Some(addr) => addr,
+            None => {
+                log::debug!("No address to send ICMP error, Drop it.");
+                return Ok(());
+            }
+        };
+
+        let egress = self.route(dst_addr).map_or(ingress, |(egress, _)| egress);
+        let ident = self.interfaces[egress].next_ipv4_ident();
+
+        let mut tx = NoFragIpBytes::default();
+
+        let header = &pkt.clone().into_inner()[..pkt.header_len() as usize];
+
+        let len = match build_icmpv4_error(
+            header,
+            pkt.payload(),
+            message,
+            src_addr,
+            false,
+            ident,
+            tx.as_mut(),
+        ) {
+            Some(len) => len,
+            None => return Ok(()),
+        };
+
+        let sent = self.interfaces[egress].dispatch_ipv4(dst_addr, len, &mut |buffer| {
+            buffer.copy_from_slice(&tx[..len]);
+            Ok(())
+        });
+
+        check_sent(sent, dst_addr)
+    }
+}
+
+/// Log packet to `dst_addr` dropped by interface, other errors are returned.
+fn check_sent(sent: Result<bool>, dst_addr: ipv4::Address) -> Result<()> {
+    match sent {
+        Ok(true) => Ok(()),
+        Ok(false) => {
+            log::debug!("No space for pending packet to {}, Drop it.", dst_addr);
+            Ok(())
+        }
+        // Route is found by router, but its gateway is not on link.
+        Err(Error::NoRouteToHost) => {
+            log::warn!("No route to gateway towards {}, Drop it.", dst_addr);
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, vec::Vec};
+
+    use auip_pkt::{
+        layer3::{self, Protocol},
+        layer4::icmpv4,
+    };
+
+    use super::*;
+    use crate::{
+        reassemble_ipv4,
+        storage::fixed::{Addrs, Arp, IpFragment, Routes, Sockets},
+        DropReason, Medium, SocketSet,
+    };
+
+    /// Device of queued received packets, record all sent packets.
+    struct TestDevice {
+        mtu: usize,
+        /// Receiving fails, like a device gone down.
+        fail: bool,
+        rx_queue: VecDeque<Vec<u8>>,
+        rx: Option<Vec<u8>>,
+        tx: Vec<Vec<u8>>,
+    }
+
+    impl Device for TestDevice {
+        fn send(&mut self, buffer: &[u8]) -> Result<()> {
+            self.tx.push(buffer.into());
+            Ok(())
+        }
+
+        fn recv(&mut self) -> Result<Option<&[u8]>> {
+            if self.fail {
+                return Err(Error::PacketError(
+                    auip_pkt::Error::WrongLengthForIpv4Packet,
+                ));
+            }
+
+            self.rx = self.rx_queue.pop_front();
+            Ok(self.rx.as_deref())
+        }
+
+        fn medium(&self) -> Medium {
+            Medium::Ip
+        }
+
+        fn mtu(&self) -> usize {
+            self.mtu
+        }
+    }
+
+    type Sensor = Interface<TestDevice, Addrs<1>, Arp<1>, IpFragment<1>, Routes<1>>;
+
+    /// Uplink of other storages, router connects interfaces of different types.
+    type Uplink = Interface<TestDevice, Addrs<2>, Arp<2>, IpFragment<1>, Routes<2>>;
+
+    fn sensor_ip() -> ipv4::Address {
+        ipv4::Address::new(192, 168, 1, 2)
+    }
+
+    fn remote_ip() -> ipv4::Address {
+        ipv4::Address::new(8, 8, 8, 8)
+    }
+
+    fn interface<const A: usize, const P: usize, const R: usize>(
+        addr: ipv4::Address,
+        mtu: usize,
+    ) -> Interface<TestDevice, Addrs<A>, Arp<P>, IpFragment<1>, Routes<R>> {
+        let device = TestDevice {
+            mtu,
+            fail: false,
+            rx_queue: VecDeque::new(),
+            rx: None,
+            tx: Vec::new(),
+        };
+
+        let mut addrs = Addrs::default();
+        addrs
+            .add_ip_addr(layer3::Cidr::new(layer3::Address::Ipv4(addr), 24))
+            .unwrap();
+
+        Interface::new(
+            device,
+            addrs,
+            Arp::default(),
+            IpFragment::default(),
+            Routes::default(),
+        )
+    }
+
+    /// Sensor network and uplink of smaller MTU.
+    fn interfaces() -> (Sensor, Uplink) {
+        let mut sensor = interface(ipv4::Address::new(192, 168, 1, 1), 1500);
+        sensor.config_mut().ipv4.forwarding = true;
+
+        let uplink = interface(ipv4::Address::new(10, 0, 0, 1), 576);
+
+        (sensor, uplink)
+    }
+
+    /// Poll both interfaces by router, return result and packets sent by each interface.
+    fn poll(sensor: &mut Sensor, uplink: &mut Uplink) -> (Result<()>, [Vec<Vec<u8>>; 2]) {
+        let mut sensor_sockets = Sockets::<1>::default();
+        let mut uplink_sockets = Sockets::<1>::default();
+
+        let mut router = Router::new([&mut *sensor as &mut dyn RouterInterface, &mut *uplink]);
+        let result = router.poll(
+            Instant::ZERO,
+            [sensor_sockets.slots_mut(), uplink_sockets.slots_mut()],
+        );
+
+        let tx = [
+            core::mem::take(&mut sensor.device_mut().tx),
+            core::mem::take(&mut uplink.device_mut().tx),
+        ];
+        (result, tx)
+    }
+
+    /// Receive `bytes` on sensor interface, return packets sent by each interface.
+    fn forward(sensor: &mut Sensor, uplink: &mut Uplink, bytes: Vec<u8>) -> [Vec<Vec<u8>>; 2] {
+        sensor.device_mut().rx_queue.push_back(bytes);
+
+        let (result, tx) = poll(sensor, uplink);
+        result.unwrap();
+        tx
+    }
+
+    fn packet(ttl: u8, payload_len: usize, dont_frag: bool) -> Vec<u8> {
+        let repr = ipv4::Repr {
+            src_addr: sensor_ip(),
+            dst_addr: remote_ip(),
+            protocol: Protocol::Udp,
+            payload_len,
+            ttl,
+            ident: 7,
+            dont_frag,
+        };
+
+        let mut bytes = std::vec![0u8; repr.buffer_len() + payload_len];
+        let mut pkt = Packet::new_unchecked(&mut bytes[..]);
+        repr.emit(&mut pkt);
+        for (i, byte) in pkt.payload_mut().iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+
+        bytes
+    }
+
+    fn check_icmpv4_error(tx: &[Vec<u8>], message: Message) {
+        assert_eq!(tx.len(), 1);
+
+        let pkt = Packet::new_checked(&tx[0][..]).unwrap();
+        assert_eq!(pkt.src_addr(), ipv4::Address::new(192, 168, 1, 1));
+        assert_eq!(pkt.dst_addr(), sensor_ip());
+
+        let icmp_pkt = icmpv4::Packet::new_checked(pkt.payload()).unwrap();
+        assert_eq!(icmp_pkt.protocol(), message);
+    }
+
+    #[test]
+    fn test_forward() {
+        let (mut sensor, mut uplink) = interfaces();
+
+        let [sensor_tx, uplink_tx] = forward(&mut sensor, &mut uplink, packet(64, 100, false));
+        assert!(uplink_tx.is_empty());
+        check_icmpv4_error(
+            &sensor_tx,
+            Message::DstUnreachable(DstUnreachable::NetUnreachable, 0),
+        );
+
+        let gateway = ipv4::Address::new(10, 0, 0, 254);
+        uplink
+            .route_storage_mut()
+            .insert(Route::default_via(gateway, 0))
+            .unwrap();
+
+        // TTL is decremented, checksum is updated.
+        let [sensor_tx, uplink_tx] = forward(&mut sensor, &mut uplink, packet(64, 100, false));
+        assert!(sensor_tx.is_empty());
+        assert_eq!(uplink_tx.len(), 1);
+
+        let pkt = Packet::new_checked(&uplink_tx[0][..]).unwrap();
+        assert_eq!(pkt.ttl(), 63);
+        assert!(pkt.verify_checksum());
+        assert_eq!(pkt.dst_addr(), remote_ip());
+        assert_eq!(pkt.payload(), &packet(64, 100, false)[20..]);
+
+        let [sensor_tx, uplink_tx] = forward(&mut sensor, &mut uplink, packet(1, 100, false));
+        assert!(uplink_tx.is_empty());
+        check_icmpv4_error(&sensor_tx, Message::TimeExceeded(TimeExceeded::TtlExpired));
+
+        // Forwarding is disabled.
+        sensor.config_mut().ipv4.forwarding = false;
+        let [sensor_tx, uplink_tx] = forward(&mut sensor, &mut uplink, packet(64, 100, false));
+        assert!(sensor_tx.is_empty() && uplink_tx.is_empty());
+    }
+
+    #[test]
+    fn test_forward_too_large() {
+        let (mut sensor, mut uplink) = interfaces();
+        sensor.device_mut().mtu = 1000;
+
+        uplink
+            .route_storage_mut()
+            .insert(Route::default_via(ipv4::Address::new(10, 0, 0, 254), 0))
+            .unwrap();
+
+        // Packet exceed MTU of ingress interface is not kept to be forwarded.
+        let [sensor_tx, uplink_tx] = forward(&mut sensor, &mut uplink, packet(64, 1200, true));
+        assert!(uplink_tx.is_empty());
+        check_icmpv4_error(
+            &sensor_tx,
+            Message::DstUnreachable(DstUnreachable::FragRequired, 1000),
+        );
+
+        let [sensor_tx, uplink_tx] = forward(&mut sensor, &mut uplink, packet(64, 1200, false));
+        assert!(sensor_tx.is_empty() && uplink_tx.is_empty());
+
+        let drops = sensor.drop_counters();
+        assert_eq!(drops.get(DropReason::TooLarge), 2);
+        assert_eq!(drops.total(), 2);
+    }
+
+    #[test]
+    fn test_forward_fragments() {
+        let (mut sensor, mut uplink) = interfaces();
+        uplink
+            .route_storage_mut()
+            .insert(Route::on_link(ipv4::Cidr::new(remote_ip(), 32)))
+            .unwrap();
+
+        let [sensor_tx, uplink_tx] = forward(&mut sensor, &mut uplink, packet(64, 1000, true));
+        assert!(uplink_tx.is_empty());
+        check_icmpv4_error(
+            &sensor_tx,
+            Message::DstUnreachable(DstUnreachable::FragRequired, 576),
+        );
+
+        let [sensor_tx, uplink_tx] = forward(&mut sensor, &mut uplink, packet(64, 1000, false));
+        assert!(sensor_tx.is_empty());
+        assert_eq!(uplink_tx.len(), 2);
+
+        let mut buffer = IpFragment::<1>::default();
+        let mut result = None;
+
+        for bytes in uplink_tx.iter() {
+            assert!(bytes.len() <= 576);
+
+            let pkt = Packet::new_checked(&bytes[..]).unwrap();
+            assert!(pkt.verify_checksum());
+            assert_eq!(pkt.ttl(), 63);
+            assert_eq!(pkt.ident(), 7);
+
+            result = reassemble_ipv4(Instant::ZERO, Duration::from_secs(1), &pkt, &mut buffer);
+        }
+
+        let (index, reassembly) = result.unwrap();
+        let len = reassembly.payload_len();
+        assert_eq!(
+            buffer.buffer(index, len).unwrap(),
+            &packet(64, 1000, false)[20..]
+        );
+    }
+
+    #[test]
+    fn test_poll_error() {
+        let (mut sensor, mut uplink) = interfaces();
+        sensor.device_mut().fail = true;
+
+        // Uplink is still polled, and replies protocol unreachable to packet for it.
+        let mut bytes = packet(64, 8, false);
+        let mut pkt = Packet::new_unchecked(&mut bytes[..]);
+        pkt.set_protocol(Protocol::Unknown(253));
+        pkt.set_dst_addr(ipv4::Address::new(10, 0, 0, 1));
+        pkt.fill_checksum();
+        uplink.device_mut().rx_queue.push_back(bytes);
+
+        let (result, [sensor_tx, uplink_tx]) = poll(&mut sensor, &mut uplink);
+        assert!(matches!(result, Err(Error::PacketError(_))));
+        assert!(sensor_tx.is_empty());
+        assert_eq!(uplink_tx.len(), 1);
+    }
+}
@@ -0,0 +1,1034 @@
+use auip_pkt::{
+    layer3::{self, ipv4},
+    layer4::tcp::{Control, Packet, Repr},
+};
+
+use crate::{
+    consts, socket::tcp::rst_reply, time::Instant, AddrsStorage, ArpStorage, Device, Endpoint,
+    Error, Interface, IpFragmentBuffer, Result, RouteStorage, Socket, TcpSocket,
+};
+
+/// Handle TCP segment addressed to this host.
+///
+/// Segment not belonging to any socket is answered with RST, malformed segment is dropped.
+/// Return the length of reply written into `tx`, if any.
+pub(crate) fn poll_tcp(
+    now: Instant,
+    isn_secret: u128,
+    src_addr: layer3::Address,
+    dst_addr: layer3::Address,
+    bytes: &[u8],
+    sockets: &mut [Option<Socket<'_>>],
+    tx: &mut [u8],
+) -> Result<Option<usize>> {
+    let pkt = match Packet::new_checked(bytes) {
+        Ok(pkt) => pkt,
+        Err(e) => {
+            log::debug!("Malformed TCP segment: {:?}, Drop it.", e);
+            return Ok(None);
+        }
+    };
+
+    log::debug!("Receive packet: {}", pkt);
+
+    let repr = match Repr::parse(&pkt, &src_addr, &dst_addr) {
+        Ok(repr) => repr,
+        Err(e) => {
+            log::debug!("Malformed TCP segment: {:?}, Drop it.", e);
+            return Ok(None);
+        }
+    };
+
+    let remote = Endpoint::new(src_addr, repr.src_port);
+    let local = Endpoint::new(dst_addr, repr.dst_port);
+
+    // Connections take precedence over listening sockets.
+    let find = |sockets: &[Option<Socket<'_>>], listening: bool| {
+        sockets.iter().position(|socket| match socket {
+            Some(Socket::Tcp(socket)) => {
+                socket.is_listening() == listening && socket.accepts(&remote, &local)
+            }
+            _ => false,
+        })
+    };
+
+    let index = find(sockets, false).or_else(|| find(sockets, true));
+
+    let reply = match index.and_then(|index| sockets[index].as_mut()) {
+        Some(Socket::Tcp(socket)) => socket.process(now, isn_secret, remote, local, &repr),
+        _ => {
+            log::debug!("No connection on port {}.", repr.dst_port);
+
+            match repr.control {
+                Control::Rst => None,
+                _ => Some(rst_reply(&repr)),
+            }
+        }
+    };
+
+    match reply {
+        Some(reply) => {
+            let len = reply.buffer_len();
+            if tx.len() < len {
+                return Ok(None);
+            }
+
+            let mut pkt = Packet::new_unchecked(&mut tx[..len]);
+            reply.emit(&mut pkt, &dst_addr, &src_addr)?;
+
+            log::debug!("Send packet: {}", pkt);
+
+            Ok(Some(len))
+        }
+        None => Ok(None),
+    }
+}
+
+impl<D, AS, ARPS, IFB, RS> Interface<D, AS, ARPS, IFB, RS>
+where
+    D: Device,
+    AS: AddrsStorage,
+    ARPS: ArpStorage,
+    IFB: IpFragmentBuffer,
+    RS: RouteStorage,
+{
+    pub(crate) fn dispatch_tcp(&mut self, now: Instant, socket: &mut TcpSocket<'_>) -> Result<()> {
+        // Packet is never fragmented, so it must fit in buffer of interface.
+        let mtu = self.ip_mtu();
+        let isn_secret = self.config().tcp.isn_secret;
+
+        loop {
+            let sent = socket.dispatch(now, mtu, isn_secret, |local, remote, repr| {
+                let src_addr = match local.addr {
+                    layer3::Address::Ipv4(addr) => Some(addr),
+                    layer3::Address::Unspecified => self.ipv4_addr(),
+                    layer3::Address::Ipv6(_) => None,
+                };
+
+                let (src_addr, dst_addr) = match (src_addr, remote.addr) {
+                    (Some(src_addr), layer3::Address::Ipv4(dst_addr)) => (src_addr, dst_addr),
+                    _ => {
+                        log::debug!("No route from {:?} to {:?}.", local, remote);
+                        return Ok(false);
+                    }
+                };
+
+                let ip_repr = ipv4::Repr {
+                    src_addr,
+                    dst_addr,
+                    protocol: layer3::Protocol::Tcp,
+                    payload_len: repr.buffer_len(),
+                    ttl: consts::DEFAULT_TTL,
+                    ident: self.next_ipv4_ident(),
+                    dont_frag: true,
+                };
+
+                let len = ip_repr.buffer_len() + ip_repr.payload_len;
+
+                let sent = self.dispatch_ipv4(dst_addr, len, |buffer| {
+                    let mut pkt = ipv4::Packet::new_unchecked(buffer);
+                    ip_repr.emit(&mut pkt);
+
+                    let mut tcp_pkt = Packet::new_unchecked(pkt.payload_mut());
+                    repr.emit(
+                        &mut tcp_pkt,
+                        &layer3::Address::Ipv4(src_addr),
+                        &layer3::Address::Ipv4(dst_addr),
+                    )?;
+
+                    log::debug!("Send packet: {}", tcp_pkt);
+                    Ok(())
+                });
+
+                match sent {
+                    Err(Error::NoRouteToHost) => Ok(false),
+                    sent => sent,
+                }
+            })?;
+
+            if !sent {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, collections::VecDeque, rc::Rc, vec, vec::Vec};
+
+    use super::*;
+    use crate::{
+        storage::fixed::{Addrs, Arp, IpFragment, Routes, Sockets},
+        tcp::{
+            congestion::{CongestionControl, Controller},
+            State,
+        },
+        time::Duration,
+        Medium, SocketSet,
+    };
+
+    type Queue = Rc<RefCell<VecDeque<Vec<u8>>>>;
+
+    /// One end of an in-memory link, sent packets of indexes in `lost` are dropped.
+    struct LinkDevice {
+        rx_queue: Queue,
+        tx_queue: Queue,
+        rx: Option<Vec<u8>>,
+        sent: usize,
+        lost: Vec<usize>,
+    }
+
+    impl Device for LinkDevice {
+        fn send(&mut self, buffer: &[u8]) -> Result<()> {
+            if !self.lost.contains(&self.sent) {
+                self.tx_queue.borrow_mut().push_back(buffer.into());
+            }
+            self.sent += 1;
+            Ok(())
+        }
+
+        fn recv(&mut self) -> Result<Option<&[u8]>> {
+            self.rx = self.rx_queue.borrow_mut().pop_front();
+            Ok(self.rx.as_deref())
+        }
+
+        fn medium(&self) -> Medium {
+            Medium::Ip
+        }
+    }
+
+    type TestInterface = Interface<LinkDevice, Addrs<1>, Arp<1>, IpFragment<1>, Routes<1>>;
+
+    fn client_ip() -> ipv4::Address {
+        ipv4::Address::new(10, 0, 0, 1)
+    }
+
+    fn server_ip() -> ipv4::Address {
+        ipv4::Address::new(10, 0, 0, 2)
+    }
+
+    fn interface(
+        addr: ipv4::Address,
+        rx_queue: &Queue,
+        tx_queue: &Queue,
+        lost: &[usize],
+    ) -> TestInterface {
+        let device = LinkDevice {
+            rx_queue: rx_queue.clone(),
+            tx_queue: tx_queue.clone(),
+            rx: None,
+            sent: 0,
+            lost: lost.into(),
+        };
+
+        let mut addrs = Addrs::default();
+        addrs
+            .add_ip_addr(layer3::Cidr::new(layer3::Address::Ipv4(addr), 24))
+            .unwrap();
+
+        Interface::new(
+            device,
+            addrs,
+            Arp::default(),
+            IpFragment::default(),
+            Routes::default(),
+        )
+    }
+
+    /// Build client and server interface linked to each other.
+    fn link(client_lost: &[usize], server_lost: &[usize]) -> (TestInterface, TestInterface) {
+        let to_server = Queue::default();
+        let to_client = Queue::default();
+
+        (
+            interface(client_ip(), &to_client, &to_server, client_lost),
+            interface(server_ip(), &to_server, &to_client, server_lost),
+        )
+    }
+
+    fn run<'a>(
+        now: Instant,
+        client: &mut TestInterface,
+        client_sockets: &mut impl SocketSet<'a>,
+        server: &mut TestInterface,
+        server_sockets: &mut impl SocketSet<'a>,
+    ) {
+        for _ in 0..16 {
+            client.poll(now, client_sockets).unwrap();
+            server.poll(now, server_sockets).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_connection() {
+        let (mut client, mut server) = link(&[], &[]);
+
+        let mut buffers = [[0u8; 64]; 4];
+        let [a, b, c, d] = &mut buffers;
+
+        let mut socket = TcpSocket::new(a, b);
+        socket.listen(80).unwrap();
+        let mut server_sockets = Sockets::<1>::default();
+        let server_handle = server_sockets.add(socket).unwrap();
+
+        let mut socket = TcpSocket::new(c, d);
+        socket
+            .connect(Endpoint::from((server_ip(), 80)), 49152)
+            .unwrap();
+        let mut client_sockets = Sockets::<1>::default();
+        let client_handle = client_sockets.add(socket).unwrap();
+
+        let now = Instant::ZERO;
+        run(
+            now,
+            &mut client,
+            &mut client_sockets,
+            &mut server,
+            &mut server_sockets,
+        );
+
+        let socket = server_sockets.get_mut(server_handle).unwrap();
+        assert_eq!(socket.state(), State::Established);
+        assert_eq!(
+            socket.remote_endpoint(),
+            Some(Endpoint::from((client_ip(), 49152)))
+        );
+
+        let socket = client_sockets.get_mut(client_handle).unwrap();
+        assert_eq!(socket.state(), State::Established);
+        assert_eq!(socket.send_slice(b"hello").unwrap(), 5);
+
+        run(
+            now,
+            &mut client,
+            &mut client_sockets,
+            &mut server,
+            &mut server_sockets,
+        );
+
+        let mut buffer = [0u8; 16];
+        let socket = server_sockets.get_mut(server_handle).unwrap();
+        assert_eq!(socket.recv_slice(&mut buffer).unwrap(), 5);
+        assert_eq!(&buffer[..5], b"hello");
+        socket.send_slice(b"world").unwrap();
+
+        run(
+            now,
+            &mut client,
+            &mut client_sockets,
+            &mut server,
+            &mut server_sockets,
+        );
+
+        let socket = client_sockets.get_mut(client_handle).unwrap();
+        assert_eq!(socket.recv_slice(&mut buffer).unwrap(), 5);
+        assert_eq!(&buffer[..5], b"world");
+        assert_eq!(socket.send_queue(), 0);
+        socket.close();
+
+        run(
+            now,
+            &mut client,
+            &mut client_sockets,
+            &mut server,
+            &mut server_sockets,
+        );
+
+        assert_eq!(
+            client_sockets.get(client_handle).unwrap().state(),
+            State::FinWait2
+        );
+        let socket = server_sockets.get_mut(server_handle).unwrap();
+        assert_eq!(socket.state(), State::CloseWait);
+        assert!(socket.recv_slice(&mut buffer).is_err());
+        socket.close();
+
+        run(
+            now,
+            &mut client,
+            &mut client_sockets,
+            &mut server,
+            &mut server_sockets,
+        );
+
+        assert_eq!(
+            server_sockets.get(server_handle).unwrap().state(),
+            State::Closed
+        );
+        assert_eq!(
+            client_sockets.get(client_handle).unwrap().state(),
+            State::TimeWait
+        );
+
+        let now = now + Duration::from_secs(10);
+        run(
+            now,
+            &mut client,
+            &mut client_sockets,
+            &mut server,
+            &mut server_sockets,
+        );
+
+        assert_eq!(
+            client_sockets.get(client_handle).unwrap().state(),
+            State::Closed
+        );
+    }
+
+    #[test]
+    fn test_refused() {
+        let (mut client, mut server) = link(&[], &[]);
+
+        let mut buffers = [[0u8; 64]; 2];
+        let [a, b] = &mut buffers;
+
+        let mut socket = TcpSocket::new(a, b);
+        socket
+            .connect(Endpoint::from((server_ip(), 80)), 49152)
+            .unwrap();
+        let mut client_sockets = Sockets::<1>::default();
+        let handle = client_sockets.add(socket).unwrap();
+
+        let mut server_sockets = Sockets::<1>::default();
+        run(
+            Instant::ZERO,
+            &mut client,
+            &mut client_sockets,
+            &mut server,
+            &mut server_sockets,
+        );
+
+        assert_eq!(client_sockets.get(handle).unwrap().state(), State::Closed);
+    }
+
+    #[test]
+    fn test_retransmit() {
+        // Lose the first data segment: SYN and ACK are sent before it.
+        let (mut client, mut server) = link(&[2], &[]);
+
+        let mut buffers = [[0u8; 64]; 4];
+        let [a, b, c, d] = &mut buffers;
+
+        let mut socket = TcpSocket::new(a, b);
+        socket.listen(80).unwrap();
+        let mut server_sockets = Sockets::<1>::default();
+        let server_handle = server_sockets.add(socket).unwrap();
+
+        let mut socket = TcpSocket::new(c, d);
+        socket
+            .connect(Endpoint::from((server_ip(), 80)), 49152)
+            .unwrap();
+        let mut client_sockets = Sockets::<1>::default();
+        let client_handle = client_sockets.add(socket).unwrap();
+
+        let now = Instant::ZERO;
+        run(
+            now,
+            &mut client,
+            &mut client_sockets,
+            &mut server,
+            &mut server_sockets,
+        );
+
+        let socket = client_sockets.get_mut(client_handle).unwrap();
+        socket.send_slice(b"hello").unwrap();
+
+        run(
+            now,
+            &mut client,
+            &mut client_sockets,
+            &mut server,
+            &mut server_sockets,
+        );
+        assert_eq!(server_sockets.get(server_handle).unwrap().recv_queue(), 0);
+
+        let now = now + Duration::from_secs(1);
+        run(
+            now,
+            &mut client,
+            &mut client_sockets,
+            &mut server,
+            &mut server_sockets,
+        );
+
+        let mut buffer = [0u8; 16];
+        let socket = server_sockets.get_mut(server_handle).unwrap();
+        assert_eq!(socket.recv_slice(&mut buffer).unwrap(), 5);
+        assert_eq!(&buffer[..5], b"hello");
+        assert_eq!(client_sockets.get(client_handle).unwrap().send_queue(), 0);
+    }
+
+    #[test]
+    fn test_retransmit_limit() {
+        // Every segment after handshake is lost.
+        let lost: Vec<usize> = (2..64).collect();
+        let (mut client, mut server) = link(&lost, &[]);
+
+        let mut buffers = [[0u8; 64]; 4];
+        let [a, b, c, d] = &mut buffers;
+
+        let mut socket = TcpSocket::new(a, b);
+        socket.listen(80).unwrap();
+        let mut server_sockets = Sockets::<1>::default();
+        server_sockets.add(socket).unwrap();
+
+        let mut socket = TcpSocket::new(c, d);
+        socket.set_max_retransmits(3);
+        socket
+            .connect(Endpoint::from((server_ip(), 80)), 49152)
+            .unwrap();
+        let mut client_sockets = Sockets::<1>::default();
+        let client_handle = client_sockets.add(socket).unwrap();
+
+        let mut now = Instant::ZERO;
+        run(
+            now,
+            &mut client,
+            &mut client_sockets,
+            &mut server,
+            &mut server_sockets,
+        );
+
+        let socket = client_sockets.get_mut(client_handle).unwrap();
+        socket.send_slice(b"hello").unwrap();
+
+        // Timeout backs off 1, 2 and 4 seconds for 3 retransmissions.
+        for delay in [0, 1, 2, 4] {
+            now += Duration::from_secs(delay);
+            run(
+                now,
+                &mut client,
+                &mut client_sockets,
+                &mut server,
+                &mut server_sockets,
+            );
+            let socket = client_sockets.get(client_handle).unwrap();
+            assert_eq!(socket.state(), State::Established);
+        }
+
+        now += Duration::from_secs(8);
+        run(
+            now,
+            &mut client,
+            &mut client_sockets,
+            &mut server,
+            &mut server_sockets,
+        );
+        assert_eq!(
+            client_sockets.get(client_handle).unwrap().state(),
+            State::Closed
+        );
+
+        // User timeout aborts connection before the count is reached.
+        let (mut client, mut server) = link(&lost, &[]);
+
+        let mut socket = TcpSocket::new(a, b);
+        socket.listen(80).unwrap();
+        let mut server_sockets = Sockets::<1>::default();
+        server_sockets.add(socket).unwrap();
+
+        let mut socket = TcpSocket::new(c, d);
+        socket.set_timeout(Some(Duration::from_secs(5)));
+        socket
+            .connect(Endpoint::from((server_ip(), 80)), 49152)
+            .unwrap();
+        let mut client_sockets = Sockets::<1>::default();
+        let client_handle = client_sockets.add(socket).unwrap();
+
+        let mut now = Instant::ZERO;
+        run(
+            now,
+            &mut client,
+            &mut client_sockets,
+            &mut server,
+            &mut server_sockets,
+        );
+
+        let socket = client_sockets.get_mut(client_handle).unwrap();
+        socket.send_slice(b"hello").unwrap();
+
+        for delay in [0, 1, 2] {
+            now += Duration::from_secs(delay);
+            run(
+                now,
+                &mut client,
+                &mut client_sockets,
+                &mut server,
+                &mut server_sockets,
+            );
+            let socket = client_sockets.get(client_handle).unwrap();
+            assert_eq!(socket.state(), State::Established);
+        }
+
+        now += Duration::from_secs(4);
+        run(
+            now,
+            &mut client,
+            &mut client_sockets,
+            &mut server,
+            &mut server_sockets,
+        );
+        assert_eq!(
+            client_sockets.get(client_handle).unwrap().state(),
+            State::Closed
+        );
+    }
+
+    #[test]
+    fn test_syn_timeout() {
+        // ACK of client is lost, SYN-ACK of server is never acknowledged.
+        let lost: Vec<usize> = (1..64).collect();
+        let (mut client, mut server) = link(&lost, &[]);
+
+        let mut buffers = [[0u8; 64]; 4];
+        let [a, b, c, d] = &mut buffers;
+
+        let mut socket = TcpSocket::new(a, b);
+        socket.set_max_retransmits(1);
+        socket.listen(80).unwrap();
+        let mut server_sockets = Sockets::<1>::default();
+        let server_handle = server_sockets.add(socket).unwrap();
+
+        let mut socket = TcpSocket::new(c, d);
+        socket
+            .connect(Endpoint::from((server_ip(), 80)), 49152)
+            .unwrap();
+        let mut client_sockets = Sockets::<1>::default();
+        client_sockets.add(socket).unwrap();
+
+        let mut now = Instant::ZERO;
+        for _ in 0..3 {
+            run(
+                now,
+                &mut client,
+                &mut client_sockets,
+                &mut server,
+                &mut server_sockets,
+            );
+            now += Duration::from_secs(4);
+        }
+
+        // Passive open returns to listen.
+        assert_eq!(
+            server_sockets.get(server_handle).unwrap().state(),
+            State::Listen
+        );
+
+        // SYN of client is lost, active open is closed.
+        let lost: Vec<usize> = (0..64).collect();
+        let (mut client, mut server) = link(&lost, &[]);
+
+        let mut socket = TcpSocket::new(c, d);
+        socket.set_max_retransmits(1);
+        socket
+            .connect(Endpoint::from((server_ip(), 80)), 49152)
+            .unwrap();
+        let mut client_sockets = Sockets::<1>::default();
+        let client_handle = client_sockets.add(socket).unwrap();
+        let mut server_sockets = Sockets::<1>::default();
+
+        let mut now = Instant::ZERO;
+        for _ in 0..3 {
+            run(
+                now,
+                &mut client,
+                &mut client_sockets,
+                &mut server,
+                &mut server_sockets,
+            );
+            now += Duration::from_secs(4);
+        }
+
+        assert_eq!(
+            client_sockets.get(client_handle).unwrap().state(),
+            State::Closed
+        );
+    }
+
+    #[test]
+    fn test_poll_at() {
+        // Lose the first data segment: SYN and ACK are sent before it.
+        let (mut client, mut server) = link(&[2], &[]);
+
+        let mut buffers = [[0u8; 64]; 4];
+        let [a, b, c, d] = &mut buffers;
+
+        let mut socket = TcpSocket::new(a, b);
+        socket.listen(80).unwrap();
+        let mut server_sockets = Sockets::<1>::default();
+        let server_handle = server_sockets.add(socket).unwrap();
+        assert_eq!(server.poll_at(&server_sockets), None);
+
+        let mut socket = TcpSocket::new(c, d);
+        socket
+            .connect(Endpoint::from((server_ip(), 80)), 49152)
+            .unwrap();
+        let mut client_sockets = Sockets::<1>::default();
+        let client_handle = client_sockets.add(socket).unwrap();
+        assert_eq!(client.poll_at(&client_sockets), Some(Instant::ZERO));
+
+        let now = Instant::from_secs(5);
+        run(
+            now,
+            &mut client,
+            &mut client_sockets,
+            &mut server,
+            &mut server_sockets,
+        );
+        assert_eq!(client.poll_at(&client_sockets), None);
+        assert_eq!(server.poll_at(&server_sockets), None);
+
+        let socket = client_sockets.get_mut(client_handle).unwrap();
+        socket.send_slice(b"hello").unwrap();
+        assert_eq!(
+            client.poll_delay(now, &client_sockets),
+            Some(Duration::ZERO)
+        );
+
+        // Data is lost, socket waits for retransmission timeout.
+        run(
+            now,
+            &mut client,
+            &mut client_sockets,
+            &mut server,
+            &mut server_sockets,
+        );
+        assert_eq!(
+            client.poll_at(&client_sockets),
+            Some(now + Duration::from_secs(1))
+        );
+        assert_eq!(
+            client.poll_delay(now + Duration::from_millis(400), &client_sockets),
+            Some(Duration::from_millis(600))
+        );
+
+        let now = now + Duration::from_secs(1);
+        run(
+            now,
+            &mut client,
+            &mut client_sockets,
+            &mut server,
+            &mut server_sockets,
+        );
+        assert_eq!(client.poll_at(&client_sockets), None);
+        assert_eq!(server_sockets.get(server_handle).unwrap().recv_queue(), 5);
+    }
+
+    #[test]
+    fn test_zero_window_probe() {
+        // Lose the window update after server read data: SYN-ACK and ACK are sent before it.
+        let (mut client, mut server) = link(&[], &[2]);
+
+        let mut rx_buffer = [0u8; 8];
+        let mut buffers = [[0u8; 64]; 3];
+        let [b, c, d] = &mut buffers;
+
+        let mut socket = TcpSocket::new(&mut rx_buffer, b);
+        socket.listen(80).unwrap();
+        let mut server_sockets = Sockets::<1>::default();
+        let server_handle = server_sockets.add(socket).unwrap();
+
+        let mut socket = TcpSocket::new(c, d);
+        socket
+            .connect(Endpoint::from((server_ip(), 80)), 49152)
+            .unwrap();
+        let mut client_sockets = Sockets::<1>::default();
+        let client_handle = client_sockets.add(socket).unwrap();
+
+        let data: Vec<u8> = (0..20).collect();
+
+        let mut now = Instant::ZERO;
+        run(
+            now,
+            &mut client,
+            &mut client_sockets,
+            &mut server,
+            &mut server_sockets,
+        );
+
+        let socket = client_sockets.get_mut(client_handle).unwrap();
+        assert_eq!(socket.send_slice(&data).unwrap(), 20);
+
+        let mut received = Vec::new();
+        let mut buffer = [0u8; 8];
+
+        for _ in 0..8 {
+            run(
+                now,
+                &mut client,
+                &mut client_sockets,
+                &mut server,
+                &mut server_sockets,
+            );
+
+            let socket = server_sockets.get_mut(server_handle).unwrap();
+            let len = socket.recv_slice(&mut buffer).unwrap();
+            received.extend_from_slice(&buffer[..len]);
+
+            now += Duration::from_secs(1);
+        }
+
+        assert_eq!(received, data);
+        assert_eq!(client_sockets.get(client_handle).unwrap().send_queue(), 0);
+    }
+
+    /// Send `len` octets from client to server and check received data,
+    /// time advances `step` each round.
+    fn transfer(algorithm: CongestionControl, client_lost: &[usize], len: usize, step: Duration) {
+        transfer_with(
+            16384,
+            |socket| socket.set_congestion_control(algorithm),
+            client_lost,
+            len,
+            step,
+        );
+    }
+
+    /// Same as `transfer`, with buffers of `buffer_len` and sockets set up by `configure`.
+    fn transfer_with(
+        buffer_len: usize,
+        configure: impl Fn(&mut TcpSocket<'_>),
+        client_lost: &[usize],
+        len: usize,
+        step: Duration,
+    ) {
+        let (mut client, mut server) = link(client_lost, &[]);
+
+        let mut buffers = vec![vec![0u8; buffer_len]; 4];
+        let [a, b, c, d] = &mut buffers[..] else {
+            unreachable!()
+        };
+
+        let mut socket = TcpSocket::new(a, b);
+        configure(&mut socket);
+        socket.listen(80).unwrap();
+        let mut server_sockets = Sockets::<1>::default();
+        let server_handle = server_sockets.add(socket).unwrap();
+
+        let mut socket = TcpSocket::new(c, d);
+        configure(&mut socket);
+        let algorithm = socket.congestion_control();
+        socket
+            .connect(Endpoint::from((server_ip(), 80)), 49152)
+            .unwrap();
+        let mut client_sockets = Sockets::<1>::default();
+        let client_handle = client_sockets.add(socket).unwrap();
+
+        let data: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        let mut sent = 0;
+        let mut received = Vec::new();
+        let mut buffer = [0u8; 1024];
+        let mut now = Instant::ZERO;
+
+        for _ in 0..1000 {
+            let socket = client_sockets.get_mut(client_handle).unwrap();
+            assert_eq!(socket.congestion_control(), algorithm);
+            if socket.may_send() {
+                sent += socket.send_slice(&data[sent..]).unwrap();
+            }
+
+            run(
+                now,
+                &mut client,
+                &mut client_sockets,
+                &mut server,
+                &mut server_sockets,
+            );
+
+            let socket = server_sockets.get_mut(server_handle).unwrap();
+            while socket.can_recv() {
+                let len = socket.recv_slice(&mut buffer).unwrap();
+                received.extend_from_slice(&buffer[..len]);
+            }
+
+            if received.len() == len {
+                break;
+            }
+
+            now += step;
+        }
+
+        assert_eq!(received.len(), len, "{:?} {:?}", algorithm, client_lost);
+        assert!(received == data, "{:?} {:?}", algorithm, client_lost);
+    }
+
+    /// Controller of fixed window in segments, counting events.
+    #[derive(Default)]
+    struct FixedWindow {
+        mss: usize,
+        acked: usize,
+        timeouts: usize,
+    }
+
+    impl Controller for FixedWindow {
+        fn window(&self) -> usize {
+            2 * self.mss
+        }
+
+        fn reset(&mut self, mss: usize) {
+            self.mss = mss;
+        }
+
+        fn set_mss(&mut self, mss: usize) {
+            self.mss = mss;
+        }
+
+        fn on_ack(&mut self, _now: Instant, acked: usize, _rtt: Duration) {
+            self.acked += acked;
+        }
+
+        fn on_fast_retransmit(&mut self, _now: Instant, _in_flight: usize) {}
+
+        fn on_dup_ack(&mut self) {}
+
+        fn on_partial_ack(&mut self, acked: usize) {
+            self.acked += acked;
+        }
+
+        fn on_recovery_exit(&mut self) {}
+
+        fn on_timeout(&mut self, _now: Instant, _in_flight: usize) {
+            self.timeouts += 1;
+        }
+    }
+
+    #[test]
+    fn test_custom_controller() {
+        // Lose the first data segment, a window of 2 segments recovers it by timeout.
+        let (mut client, mut server) = link(&[2], &[]);
+
+        let mut buffers = vec![vec![0u8; 4096]; 4];
+        let [a, b, c, d] = &mut buffers[..] else {
+            unreachable!()
+        };
+
+        let mut socket = TcpSocket::new(a, b);
+        socket.listen(80).unwrap();
+        let mut server_sockets = Sockets::<1>::default();
+        let server_handle = server_sockets.add(socket).unwrap();
+
+        let mut controller = FixedWindow::default();
+        let mut socket = TcpSocket::new(c, d);
+        socket.set_congestion_controller(&mut controller);
+        assert_eq!(socket.congestion_control(), None);
+        socket
+            .connect(Endpoint::from((server_ip(), 80)), 49152)
+            .unwrap();
+        let mut client_sockets = Sockets::<1>::default();
+        let client_handle = client_sockets.add(socket).unwrap();
+
+        let data: Vec<u8> = (0..4096).map(|i| i as u8).collect();
+        let mut sent = 0;
+        let mut received = Vec::new();
+        let mut buffer = [0u8; 1024];
+        let mut now = Instant::ZERO;
+
+        for _ in 0..100 {
+            let socket = client_sockets.get_mut(client_handle).unwrap();
+            if socket.may_send() {
+                sent += socket.send_slice(&data[sent..]).unwrap();
+            }
+
+            run(
+                now,
+                &mut client,
+                &mut client_sockets,
+                &mut server,
+                &mut server_sockets,
+            );
+
+            let socket = server_sockets.get_mut(server_handle).unwrap();
+            while socket.can_recv() {
+                let len = socket.recv_slice(&mut buffer).unwrap();
+                received.extend_from_slice(&buffer[..len]);
+            }
+
+            if received.len() == data.len() {
+                break;
+            }
+
+            now += Duration::from_secs(1);
+        }
+
+        assert!(received == data);
+        assert_eq!(controller.acked, data.len());
+        assert_eq!(controller.timeouts, 1);
+    }
+
+    #[test]
+    fn test_options() {
+        // Window scale, timestamps and SACK.
+        let options = [
+            (false, false, false),
+            (true, false, false),
+            (false, true, false),
+            (false, false, true),
+            (true, true, true),
+        ];
+
+        for (window_scaling, timestamps, sack) in options {
+            let configure = |socket: &mut TcpSocket<'_>| {
+                socket.set_window_scaling(window_scaling);
+                socket.set_timestamps(timestamps);
+                #[cfg(feature = "tcp-sack")]
+                socket.set_sack(sack);
+                #[cfg(not(feature = "tcp-sack"))]
+                let _ = sack;
+            };
+
+            // Receive window is larger than 65535 octets with window scale.
+            transfer_with(262144, configure, &[], 1 << 20, Duration::ZERO);
+            // Several losses in one window, SACK recovers them in one round trip.
+            transfer_with(
+                262144,
+                configure,
+                &[20, 22, 24, 26],
+                1 << 20,
+                Duration::ZERO,
+            );
+            transfer_with(
+                262144,
+                configure,
+                &[10, 11, 30],
+                1 << 20,
+                Duration::from_millis(10),
+            );
+        }
+    }
+
+    #[test]
+    fn test_fast_retransmit() {
+        let algorithms = [
+            CongestionControl::None,
+            CongestionControl::NewReno,
+            CongestionControl::Cubic,
+        ];
+
+        // Time is frozen, losses are recovered without timeout.
+        for algorithm in algorithms {
+            transfer(algorithm, &[], 65536, Duration::ZERO);
+            transfer(algorithm, &[4], 65536, Duration::ZERO);
+            // The second loss is recovered by partial acknowledgment.
+            transfer(algorithm, &[5, 7], 65536, Duration::ZERO);
+        }
+    }
+
+    #[test]
+    fn test_loss_pattern() {
+        let algorithms = [
+            CongestionControl::None,
+            CongestionControl::NewReno,
+            CongestionControl::Cubic,
+        ];
+
+        // Burst loss and retransmission loss need timeout, which backs off.
+        let patterns: [&[usize]; 3] = [&[2, 3, 4], &[3, 5, 7, 9, 11, 13], &[8, 9, 10, 11, 12]];
+
+        for algorithm in algorithms {
+            for pattern in patterns {
+                transfer(algorithm, pattern, 65536, Duration::from_millis(100));
+            }
+        }
+    }
+}
@@ -1,10 +1,122 @@
-use auip_pkt::layer4::udp::Packet;
+use auip_pkt::{
+    layer3::{self, ipv4},
+    layer4::udp::{Packet, Repr, HEADER_LEN},
+};
 
-use crate::Result;
+use crate::{
+    consts, AddrsStorage, ArpStorage, Device, Endpoint, Error, Interface, IpFragmentBuffer, Result,
+    RouteStorage, Socket, UdpSocket,
+};
 
-pub(crate) fn poll_udp(bytes: &[u8]) -> Result<()> {
-    let pkt = Packet::new_checked(bytes)?;
+/// Handle UDP datagram addressed to this host.
+///
+/// Return `false` if no socket accepts the datagram, malformed datagram is dropped
+/// and counted as handled.
+pub(crate) fn poll_udp(
+    src_addr: layer3::Address,
+    dst_addr: layer3::Address,
+    bytes: &[u8],
+    sockets: &mut [Option<Socket<'_>>],
+) -> bool {
+    let pkt = match Packet::new_checked(bytes) {
+        Ok(pkt) => pkt,
+        Err(e) => {
+            log::debug!("Malformed UDP datagram: {:?}, Drop it.", e);
+            return true;
+        }
+    };
 
     log::debug!("Receive packet: {}", pkt);
-    Ok(())
+
+    let repr = match Repr::parse(&pkt, &src_addr, &dst_addr) {
+        Ok(repr) => repr,
+        Err(e) => {
+            log::debug!("Malformed UDP datagram: {:?}, Drop it.", e);
+            return true;
+        }
+    };
+
+    let src = Endpoint::new(src_addr, repr.src_port);
+    let dst = Endpoint::new(dst_addr, repr.dst_port);
+
+    for socket in sockets.iter_mut().flatten() {
+        if let Socket::Udp(socket) = socket {
+            if socket.accepts(&src, &dst) {
+                socket.process(src, repr.payload);
+                return true;
+            }
+        }
+    }
+
+    log::debug!("No listener on port {}.", repr.dst_port);
+    false
+}
+
+impl<D, AS, ARPS, IFB, RS> Interface<D, AS, ARPS, IFB, RS>
+where
+    D: Device,
+    AS: AddrsStorage,
+    ARPS: ArpStorage,
+    IFB: IpFragmentBuffer,
+    RS: RouteStorage,
+{
+    pub(crate) fn dispatch_udp(&mut self, socket: &mut UdpSocket<'_>) -> Result<()> {
+        while let Some((local, remote, payload)) = socket.peek_tx() {
+            let src_addr = match local.addr {
+                layer3::Address::Ipv4(addr) => Some(addr),
+                layer3::Address::Unspecified => self.ipv4_addr(),
+                layer3::Address::Ipv6(_) => None,
+            };
+
+            let (src_addr, dst_addr) = match (src_addr, remote.addr) {
+                (Some(src_addr), layer3::Address::Ipv4(dst_addr)) => (src_addr, dst_addr),
+                _ => {
+                    log::debug!("No route from {:?} to {:?}, Drop it.", local, remote);
+                    socket.pop_tx();
+                    continue;
+                }
+            };
+
+            let udp_repr = Repr {
+                src_port: local.port,
+                dst_port: remote.port,
+                payload,
+            };
+
+            let mut header = [0u8; HEADER_LEN];
+            udp_repr.emit_header(
+                &mut header,
+                &layer3::Address::Ipv4(src_addr),
+                &layer3::Address::Ipv4(dst_addr),
+            )?;
+
+            let ip_repr = ipv4::Repr {
+                src_addr,
+                dst_addr,
+                protocol: layer3::Protocol::Udp,
+                payload_len: udp_repr.buffer_len(),
+                ttl: consts::DEFAULT_TTL,
+                ident: self.next_ipv4_ident(),
+                dont_frag: self.config().ipv4.dont_frag,
+            };
+
+            let sent = match self.dispatch_ipv4_payload(ip_repr, &header, payload) {
+                Ok(sent) => sent,
+                Err(e @ Error::PacketTooLarge) | Err(e @ Error::NoRouteToHost) => {
+                    log::debug!("Fail to send to {:?}: {:?}, Drop it.", remote, e);
+                    socket.pop_tx();
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            if !sent {
+                break;
+            }
+
+            socket.pop_tx();
+        }
+
+        Ok(())
+    }
 }
@@ -15,8 +15,13 @@ pub use device::*;
 mod interface;
 pub use interface::*;
 
+mod socket;
+pub use socket::*;
+
 pub mod storage;
 
 pub mod utils;
 
+pub mod time;
+
 pub mod consts;
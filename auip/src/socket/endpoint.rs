@@ -0,0 +1,34 @@
+use auip_pkt::layer3;
+
+/// Address and port of transport layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Endpoint {
+    pub addr: layer3::Address,
+    pub port: u16,
+}
+
+impl Endpoint {
+    pub fn new(addr: layer3::Address, port: u16) -> Self {
+        Self { addr, port }
+    }
+
+    /// Query whether both address and port are specified.
+    pub fn is_specified(&self) -> bool {
+        self.addr != layer3::Address::Unspecified && self.port != 0
+    }
+}
+
+impl From<(layer3::Address, u16)> for Endpoint {
+    fn from((addr, port): (layer3::Address, u16)) -> Self {
+        Self { addr, port }
+    }
+}
+
+impl From<(layer3::ipv4::Address, u16)> for Endpoint {
+    fn from((addr, port): (layer3::ipv4::Address, u16)) -> Self {
+        Self {
+            addr: layer3::Address::Ipv4(addr),
+            port,
+        }
+    }
+}
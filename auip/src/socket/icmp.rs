@@ -0,0 +1,129 @@
+use auip_pkt::{
+    layer3,
+    layer4::icmpv4::{Message, Packet},
+};
+
+use crate::{Error, PacketStorage, Result};
+
+/// ICMPv4 socket.
+///
+/// Socket is bound to an echo identifier, and receive echo replies carrying it.
+/// Datagrams are whole ICMP messages, checksum is filled when sending.
+pub struct IcmpSocket<'a> {
+    ident: Option<u16>,
+    rx_storage: &'a mut dyn PacketStorage<layer3::Address>,
+    tx_storage: &'a mut dyn PacketStorage<layer3::Address>,
+}
+
+impl<'a> IcmpSocket<'a> {
+    pub fn new(
+        rx_storage: &'a mut dyn PacketStorage<layer3::Address>,
+        tx_storage: &'a mut dyn PacketStorage<layer3::Address>,
+    ) -> Self {
+        Self {
+            ident: None,
+            rx_storage,
+            tx_storage,
+        }
+    }
+
+    /// Bind to echo identifier.
+    pub fn bind(&mut self, ident: u16) -> Result<()> {
+        self.ident = Some(ident);
+        Ok(())
+    }
+
+    /// Unbind socket and drop all queued messages.
+    pub fn close(&mut self) {
+        self.ident = None;
+
+        while !self.rx_storage.is_empty() {
+            self.rx_storage.pop();
+        }
+
+        while !self.tx_storage.is_empty() {
+            self.tx_storage.pop();
+        }
+    }
+
+    pub fn ident(&self) -> Option<u16> {
+        self.ident
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.ident.is_some()
+    }
+
+    pub fn can_send(&self) -> bool {
+        !self.tx_storage.is_full()
+    }
+
+    pub fn can_recv(&self) -> bool {
+        !self.rx_storage.is_empty()
+    }
+
+    /// Enqueue an ICMP message to `remote`.
+    pub fn send_to(&mut self, message: &[u8], remote: layer3::Address) -> Result<()> {
+        if self.ident.is_none() {
+            return Err(Error::SocketNotBound);
+        }
+
+        if remote == layer3::Address::Unspecified {
+            return Err(Error::IllegalEndpoint);
+        }
+
+        Packet::new_checked(message)?;
+
+        let buffer = self.tx_storage.enqueue(remote, message.len())?;
+        buffer.copy_from_slice(message);
+
+        Ok(())
+    }
+
+    /// Dequeue an ICMP message into `buffer`, return its length and remote address.
+    ///
+    /// Message longer than `buffer` is truncated.
+    pub fn recv_from(&mut self, buffer: &mut [u8]) -> Result<Option<(usize, layer3::Address)>> {
+        if self.ident.is_none() {
+            return Err(Error::SocketNotBound);
+        }
+
+        let result = self.rx_storage.peek().map(|(remote, message)| {
+            let len = message.len().min(buffer.len());
+            buffer[..len].copy_from_slice(&message[..len]);
+            (len, *remote)
+        });
+
+        self.rx_storage.pop();
+
+        Ok(result)
+    }
+
+    /// Checking message belongs to this socket.
+    pub(crate) fn accepts(&self, message: &Message) -> bool {
+        match (self.ident, message) {
+            (Some(ident), Message::EchoReply(echo)) => echo.ident == ident,
+            _ => false,
+        }
+    }
+
+    /// Enqueue received message, drop it if storage is full.
+    pub(crate) fn process(&mut self, src: layer3::Address, message: &[u8]) {
+        match self.rx_storage.enqueue(src, message.len()) {
+            Ok(buffer) => buffer.copy_from_slice(message),
+            Err(_) => log::debug!("No space for message from {:?}, Drop it.", src),
+        }
+    }
+
+    /// Get the oldest message to send, with remote address.
+    pub(crate) fn peek_tx(&self) -> Option<(layer3::Address, &[u8])> {
+        self.ident?;
+        let (remote, message) = self.tx_storage.peek()?;
+        Some((*remote, message))
+    }
+
+    /// Remove the oldest message to send.
+    pub(crate) fn pop_tx(&mut self) {
+        self.tx_storage.pop();
+    }
+}
@@ -0,0 +1,23 @@
+//! Socket module.
+
+mod endpoint;
+pub use endpoint::*;
+
+mod set;
+pub use set::*;
+
+#[cfg(feature = "udp")]
+mod udp;
+#[cfg(feature = "udp")]
+pub use udp::*;
+
+#[cfg(feature = "tcp")]
+pub mod tcp;
+#[cfg(feature = "tcp")]
+pub use tcp::TcpSocket;
+
+mod icmp;
+pub use icmp::*;
+
+mod raw;
+pub use raw::*;
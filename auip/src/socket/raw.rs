@@ -0,0 +1,90 @@
+use auip_pkt::layer3::{ipv4::Packet, Protocol};
+
+use crate::{PacketStorage, Result};
+
+/// Raw IPv4 socket.
+///
+/// Socket receive copies of whole IPv4 packets carrying its protocol,
+/// and send whole IPv4 packets, header checksum is filled when sending.
+pub struct RawSocket<'a> {
+    protocol: Protocol,
+    rx_storage: &'a mut dyn PacketStorage<()>,
+    tx_storage: &'a mut dyn PacketStorage<()>,
+}
+
+impl<'a> RawSocket<'a> {
+    pub fn new(
+        protocol: Protocol,
+        rx_storage: &'a mut dyn PacketStorage<()>,
+        tx_storage: &'a mut dyn PacketStorage<()>,
+    ) -> Self {
+        Self {
+            protocol,
+            rx_storage,
+            tx_storage,
+        }
+    }
+
+    pub fn protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    pub fn can_send(&self) -> bool {
+        !self.tx_storage.is_full()
+    }
+
+    pub fn can_recv(&self) -> bool {
+        !self.rx_storage.is_empty()
+    }
+
+    /// Enqueue an IPv4 packet.
+    pub fn send(&mut self, packet: &[u8]) -> Result<()> {
+        Packet::new_checked(packet)?;
+
+        let buffer = self.tx_storage.enqueue((), packet.len())?;
+        buffer.copy_from_slice(packet);
+
+        Ok(())
+    }
+
+    /// Dequeue an IPv4 packet into `buffer`, return its length.
+    ///
+    /// Packet longer than `buffer` is truncated.
+    pub fn recv(&mut self, buffer: &mut [u8]) -> Option<usize> {
+        let result = self.rx_storage.peek().map(|(_, packet)| {
+            let len = packet.len().min(buffer.len());
+            buffer[..len].copy_from_slice(&packet[..len]);
+            len
+        });
+
+        self.rx_storage.pop();
+
+        result
+    }
+
+    /// Checking packet of `protocol` belongs to this socket.
+    pub(crate) fn accepts(&self, protocol: Protocol) -> bool {
+        self.protocol == protocol
+    }
+
+    /// Enqueue received packet of `header` and `payload`, drop it if storage is full.
+    pub(crate) fn process(&mut self, header: &[u8], payload: &[u8]) {
+        match self.rx_storage.enqueue((), header.len() + payload.len()) {
+            Ok(buffer) => {
+                buffer[..header.len()].copy_from_slice(header);
+                buffer[header.len()..].copy_from_slice(payload);
+            }
+            Err(_) => log::debug!("No space for raw packet, Drop it."),
+        }
+    }
+
+    /// Get the oldest packet to send.
+    pub(crate) fn peek_tx(&self) -> Option<&[u8]> {
+        self.tx_storage.peek().map(|(_, packet)| packet)
+    }
+
+    /// Remove the oldest packet to send.
+    pub(crate) fn pop_tx(&mut self) {
+        self.tx_storage.pop();
+    }
+}
@@ -0,0 +1,107 @@
+use core::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+};
+
+#[cfg(feature = "tcp")]
+use crate::TcpSocket;
+#[cfg(feature = "udp")]
+use crate::UdpSocket;
+use crate::{IcmpSocket, RawSocket};
+
+/// Socket of any type, stored in socket set.
+///
+/// Sockets are stored inline so fixed storage works without allocator.
+#[allow(clippy::large_enum_variant)]
+pub enum Socket<'a> {
+    #[cfg(feature = "udp")]
+    Udp(UdpSocket<'a>),
+    #[cfg(feature = "tcp")]
+    Tcp(TcpSocket<'a>),
+    Icmp(IcmpSocket<'a>),
+    Raw(RawSocket<'a>),
+}
+
+/// Socket type can be stored in [Socket].
+pub trait AnySocket<'a>: Sized {
+    fn upcast(self) -> Socket<'a>;
+
+    fn downcast<'s>(socket: &'s Socket<'a>) -> Option<&'s Self>;
+
+    fn downcast_mut<'s>(socket: &'s mut Socket<'a>) -> Option<&'s mut Self>;
+}
+
+macro_rules! impl_any_socket {
+    ($ty:ident, $variant:ident) => {
+        impl<'a> AnySocket<'a> for $ty<'a> {
+            fn upcast(self) -> Socket<'a> {
+                Socket::$variant(self)
+            }
+
+            #[allow(unreachable_patterns)]
+            fn downcast<'s>(socket: &'s Socket<'a>) -> Option<&'s Self> {
+                match socket {
+                    Socket::$variant(socket) => Some(socket),
+                    _ => None,
+                }
+            }
+
+            #[allow(unreachable_patterns)]
+            fn downcast_mut<'s>(socket: &'s mut Socket<'a>) -> Option<&'s mut Self> {
+                match socket {
+                    Socket::$variant(socket) => Some(socket),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+#[cfg(feature = "udp")]
+impl_any_socket!(UdpSocket, Udp);
+#[cfg(feature = "tcp")]
+impl_any_socket!(TcpSocket, Tcp);
+impl_any_socket!(IcmpSocket, Icmp);
+impl_any_socket!(RawSocket, Raw);
+
+/// Handle of socket in socket set, typed by the socket.
+pub struct SocketHandle<T> {
+    index: usize,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> SocketHandle<T> {
+    pub(crate) fn new(index: usize) -> Self {
+        Self {
+            index,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Index of slot in socket set.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl<T> Clone for SocketHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for SocketHandle<T> {}
+
+impl<T> PartialEq for SocketHandle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<T> Eq for SocketHandle<T> {}
+
+impl<T> Debug for SocketHandle<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "SocketHandle({})", self.index)
+    }
+}
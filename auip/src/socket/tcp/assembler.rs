@@ -0,0 +1,135 @@
+/// Maximum number of out of order ranges tracked by [Assembler].
+pub const ASSEMBLER_RANGES: usize = 4;
+
+/// Tracker of out of order octets received by TCP socket.
+///
+/// Ranges are offsets relative to the next expected octet, sorted and never overlap or adjoin.
+#[derive(Debug, Clone, Default)]
+pub struct Assembler {
+    ranges: [Option<(usize, usize)>; ASSEMBLER_RANGES],
+}
+
+impl Assembler {
+    pub fn is_empty(&self) -> bool {
+        self.ranges[0].is_none()
+    }
+
+    pub fn clear(&mut self) {
+        self.ranges = Default::default();
+    }
+
+    /// Iterate ranges as `(start, end)`.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.ranges.iter().map_while(|range| *range)
+    }
+
+    /// Record `size` octets received at `offset`.
+    ///
+    /// Return `false` without change if ranges exceed [ASSEMBLER_RANGES].
+    pub fn add(&mut self, offset: usize, size: usize) -> bool {
+        if size == 0 {
+            return true;
+        }
+
+        let (mut start, mut end) = (offset, offset + size);
+
+        let mut ranges = [None; ASSEMBLER_RANGES + 1];
+        let mut len = 0;
+        let mut inserted = false;
+
+        for (s, e) in self.iter() {
+            if e < start {
+                ranges[len] = Some((s, e));
+                len += 1;
+            } else if s > end {
+                if !inserted {
+                    ranges[len] = Some((start, end));
+                    len += 1;
+                    inserted = true;
+                }
+                ranges[len] = Some((s, e));
+                len += 1;
+            } else {
+                start = start.min(s);
+                end = end.max(e);
+            }
+        }
+
+        if !inserted {
+            ranges[len] = Some((start, end));
+            len += 1;
+        }
+
+        if len > ASSEMBLER_RANGES {
+            return false;
+        }
+
+        self.ranges.copy_from_slice(&ranges[..ASSEMBLER_RANGES]);
+        true
+    }
+
+    /// Remove the range starting at offset zero, return its length.
+    ///
+    /// Offsets of remaining ranges are shifted by the length.
+    pub fn remove_front(&mut self) -> usize {
+        let len = match self.ranges[0] {
+            Some((0, end)) => end,
+            _ => return 0,
+        };
+
+        self.ranges.rotate_left(1);
+        self.ranges[ASSEMBLER_RANGES - 1] = None;
+
+        for (start, end) in self.ranges.iter_mut().flatten() {
+            *start -= len;
+            *end -= len;
+        }
+
+        len
+    }
+}
+
+impl Assembler {
+    /// Drop octets before `count` and shift offsets of ranges by it.
+    pub fn shift(&mut self, count: usize) {
+        let mut ranges = [None; ASSEMBLER_RANGES];
+
+        for (slot, (start, end)) in ranges
+            .iter_mut()
+            .zip(self.iter().filter(|(_, end)| *end > count))
+        {
+            *slot = Some((start.max(count) - count, end - count));
+        }
+
+        self.ranges = ranges;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    fn test_add_and_remove() {
+        let mut assembler = Assembler::default();
+
+        assert!(assembler.add(4, 2));
+        assert!(assembler.add(10, 2));
+        assert!(assembler.add(6, 2));
+        assert_eq!(assembler.iter().collect::<Vec<_>>(), [(4, 8), (10, 12)]);
+        assert_eq!(assembler.remove_front(), 0);
+
+        assert!(assembler.add(20, 1));
+        assert!(assembler.add(30, 1));
+        assert!(!assembler.add(25, 1));
+
+        assert!(assembler.add(0, 10));
+        assert_eq!(assembler.remove_front(), 12);
+        assert_eq!(assembler.iter().collect::<Vec<_>>(), [(8, 9), (18, 19)]);
+
+        assembler.shift(9);
+        assert_eq!(assembler.iter().collect::<Vec<_>>(), [(9, 10)]);
+    }
+}
@@ -0,0 +1,135 @@
+use crate::time::{Duration, Instant};
+
+use super::{Cubic, NewReno, NoControl};
+
+/// Congestion controller of TCP sender.
+///
+/// Windows are counted in octets. Loss detection and retransmission are done by socket,
+/// controller only reacts to events and limits octets in flight. Algorithms not in
+/// [CongestionControl] are set by [crate::TcpSocket::set_congestion_controller].
+pub trait Controller {
+    /// Get congestion window.
+    fn window(&self) -> usize;
+
+    /// Start over for a new connection with maximum segment size `mss`.
+    fn reset(&mut self, mss: usize);
+
+    /// Set maximum segment size of connection.
+    fn set_mss(&mut self, mss: usize);
+
+    /// New data of `acked` octets is acknowledged, `rtt` is the smoothed round trip time.
+    fn on_ack(&mut self, now: Instant, acked: usize, rtt: Duration);
+
+    /// Third duplicate acknowledgment is received with `in_flight` octets outstanding,
+    /// fast retransmit is done and fast recovery begins.
+    fn on_fast_retransmit(&mut self, now: Instant, in_flight: usize);
+
+    /// Another duplicate acknowledgment is received in fast recovery.
+    fn on_dup_ack(&mut self);
+
+    /// Partial acknowledgment of `acked` octets is received in fast recovery.
+    fn on_partial_ack(&mut self, acked: usize);
+
+    /// All data outstanding at the beginning of fast recovery is acknowledged.
+    fn on_recovery_exit(&mut self);
+
+    /// Retransmission timer expired with `in_flight` octets outstanding.
+    fn on_timeout(&mut self, now: Instant, in_flight: usize);
+}
+
+/// Congestion control algorithm used by socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CongestionControl {
+    /// Only limited by window of remote, for tiny devices.
+    None,
+    #[default]
+    NewReno,
+    Cubic,
+}
+
+/// Controller of any algorithm, selected by [CongestionControl] or provided by caller.
+pub enum AnyController<'a> {
+    None(NoControl),
+    NewReno(NewReno),
+    Cubic(Cubic),
+    Custom(&'a mut dyn Controller),
+}
+
+impl<'a> AnyController<'a> {
+    pub fn new(algorithm: CongestionControl, mss: usize) -> Self {
+        let mut controller = match algorithm {
+            CongestionControl::None => AnyController::None(NoControl),
+            CongestionControl::NewReno => AnyController::NewReno(NewReno::default()),
+            CongestionControl::Cubic => AnyController::Cubic(Cubic::default()),
+        };
+
+        controller.set_mss(mss);
+        controller
+    }
+
+    /// Algorithm of controller, `None` for controller provided by caller.
+    pub fn algorithm(&self) -> Option<CongestionControl> {
+        match self {
+            AnyController::None(_) => Some(CongestionControl::None),
+            AnyController::NewReno(_) => Some(CongestionControl::NewReno),
+            AnyController::Cubic(_) => Some(CongestionControl::Cubic),
+            AnyController::Custom(_) => None,
+        }
+    }
+
+    fn inner(&self) -> &dyn Controller {
+        match self {
+            AnyController::None(c) => c,
+            AnyController::NewReno(c) => c,
+            AnyController::Cubic(c) => c,
+            AnyController::Custom(c) => &**c,
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut dyn Controller {
+        match self {
+            AnyController::None(c) => c,
+            AnyController::NewReno(c) => c,
+            AnyController::Cubic(c) => c,
+            AnyController::Custom(c) => &mut **c,
+        }
+    }
+}
+
+impl<'a> Controller for AnyController<'a> {
+    fn window(&self) -> usize {
+        self.inner().window()
+    }
+
+    fn reset(&mut self, mss: usize) {
+        self.inner_mut().reset(mss)
+    }
+
+    fn set_mss(&mut self, mss: usize) {
+        self.inner_mut().set_mss(mss)
+    }
+
+    fn on_ack(&mut self, now: Instant, acked: usize, rtt: Duration) {
+        self.inner_mut().on_ack(now, acked, rtt)
+    }
+
+    fn on_fast_retransmit(&mut self, now: Instant, in_flight: usize) {
+        self.inner_mut().on_fast_retransmit(now, in_flight)
+    }
+
+    fn on_dup_ack(&mut self) {
+        self.inner_mut().on_dup_ack()
+    }
+
+    fn on_partial_ack(&mut self, acked: usize) {
+        self.inner_mut().on_partial_ack(acked)
+    }
+
+    fn on_recovery_exit(&mut self) {
+        self.inner_mut().on_recovery_exit()
+    }
+
+    fn on_timeout(&mut self, now: Instant, in_flight: usize) {
+        self.inner_mut().on_timeout(now, in_flight)
+    }
+}
@@ -0,0 +1,202 @@
+use crate::time::{Duration, Instant};
+
+use super::{new_reno::initial_window, Controller};
+
+/// Scaling constant of cubic function, RFC 9438 section 5.
+const C: f64 = 0.4;
+
+/// Multiplicative window decrease factor, RFC 9438 section 5.
+const BETA: f64 = 0.7;
+
+/// Cube root by Newton's method, `core` has no `cbrt`.
+fn cbrt(x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+
+    let mut y = if x > 1.0 { x / 3.0 } else { 1.0 };
+    for _ in 0..64 {
+        y = (2.0 * y + x / (y * y)) / 3.0;
+    }
+    y
+}
+
+/// CUBIC congestion control, RFC 9438.
+#[derive(Debug, Clone)]
+pub struct Cubic {
+    mss: usize,
+    cwnd: usize,
+    ssthresh: usize,
+    /// Window before last reduction.
+    w_max: usize,
+    /// Start of current congestion avoidance epoch.
+    epoch: Option<Instant>,
+    /// Time to reach `origin` from start of epoch, in seconds.
+    k: f64,
+    /// Window at the plateau of cubic function.
+    origin: usize,
+    /// Estimated window of Reno in the same conditions.
+    w_est: f64,
+}
+
+impl Default for Cubic {
+    fn default() -> Self {
+        let mss = 536;
+
+        Self {
+            mss,
+            cwnd: initial_window(mss),
+            ssthresh: usize::MAX,
+            w_max: 0,
+            epoch: None,
+            k: 0.0,
+            origin: 0,
+            w_est: 0.0,
+        }
+    }
+}
+
+impl Cubic {
+    pub fn slow_start_threshold(&self) -> usize {
+        self.ssthresh
+    }
+
+    /// Reduce window after congestion, RFC 9438 section 4.6 and 4.7.
+    fn reduce(&mut self, in_flight: usize) {
+        self.epoch = None;
+
+        // Fast convergence.
+        self.w_max = if self.cwnd < self.w_max {
+            (self.cwnd as f64 * (1.0 + BETA) / 2.0) as usize
+        } else {
+            self.cwnd
+        };
+
+        let reduced = (in_flight.max(self.cwnd) as f64 * BETA) as usize;
+        self.ssthresh = reduced.max(2 * self.mss);
+    }
+}
+
+impl Controller for Cubic {
+    fn window(&self) -> usize {
+        self.cwnd
+    }
+
+    fn reset(&mut self, mss: usize) {
+        *self = Self::default();
+        self.set_mss(mss);
+    }
+
+    fn set_mss(&mut self, mss: usize) {
+        self.mss = mss;
+        self.cwnd = self.cwnd.max(initial_window(mss));
+    }
+
+    fn on_ack(&mut self, now: Instant, acked: usize, rtt: Duration) {
+        if self.cwnd < self.ssthresh {
+            self.cwnd = self.cwnd.saturating_add(acked.min(self.mss));
+            return;
+        }
+
+        let mss = self.mss as f64;
+        let cwnd = self.cwnd as f64;
+
+        let epoch = match self.epoch {
+            Some(epoch) => epoch,
+            None => {
+                self.k = if self.cwnd < self.w_max {
+                    cbrt((self.w_max - self.cwnd) as f64 / mss / C)
+                } else {
+                    0.0
+                };
+                self.origin = self.w_max.max(self.cwnd);
+                self.w_est = cwnd;
+                self.epoch = Some(now);
+                now
+            }
+        };
+
+        let t = (now - epoch + rtt).total_millis() as f64 / 1000.0;
+        let d = t - self.k;
+        let target = (self.origin as f64 + C * d * d * d * mss).clamp(cwnd, 1.5 * cwnd);
+
+        // Reno friendly region, RFC 9438 section 4.3.
+        let alpha = 3.0 * (1.0 - BETA) / (1.0 + BETA);
+        self.w_est += alpha * mss * acked as f64 / cwnd;
+
+        self.cwnd = if self.w_est > target {
+            self.w_est as usize
+        } else {
+            self.cwnd + ((target - cwnd) * acked as f64 / cwnd) as usize
+        };
+    }
+
+    fn on_fast_retransmit(&mut self, _now: Instant, in_flight: usize) {
+        self.reduce(in_flight);
+        self.cwnd = self.ssthresh + 3 * self.mss;
+    }
+
+    fn on_dup_ack(&mut self) {
+        self.cwnd += self.mss;
+    }
+
+    fn on_partial_ack(&mut self, acked: usize) {
+        self.cwnd = self.cwnd.saturating_sub(acked) + self.mss;
+    }
+
+    fn on_recovery_exit(&mut self) {
+        self.cwnd = self.ssthresh;
+    }
+
+    fn on_timeout(&mut self, _now: Instant, in_flight: usize) {
+        self.reduce(in_flight);
+        self.cwnd = self.mss;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cubic() {
+        assert!((cbrt(27.0) - 3.0).abs() < 1e-9);
+
+        let rtt = Duration::from_millis(100);
+        let mut now = Instant::ZERO;
+
+        let mut cubic = Cubic::default();
+        cubic.set_mss(1000);
+
+        for _ in 0..196 {
+            cubic.on_ack(now, 1000, rtt);
+        }
+        assert_eq!(cubic.window(), 200000);
+
+        cubic.on_fast_retransmit(now, 200000);
+        assert_eq!(cubic.slow_start_threshold(), 140000);
+        cubic.on_recovery_exit();
+        assert_eq!(cubic.window(), 140000);
+
+        // Concave growth back to window before loss, then convex beyond it.
+        let mut last = cubic.window();
+        let mut reached_at = None;
+        for i in 0..100 {
+            now += rtt;
+            for _ in 0..cubic.window() / 1000 {
+                cubic.on_ack(now, 1000, rtt);
+            }
+            assert!(cubic.window() >= last);
+            last = cubic.window();
+
+            if reached_at.is_none() && last >= 199000 {
+                reached_at = Some(i);
+            }
+        }
+
+        // K is about 5.3 seconds for reduction of 60 segments.
+        let reached_at = reached_at.unwrap();
+        assert!((35..60).contains(&reached_at), "{}", reached_at);
+        assert!(last > 230000, "{}", last);
+    }
+}
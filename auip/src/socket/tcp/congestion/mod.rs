@@ -0,0 +1,13 @@
+//! Congestion control of TCP sender.
+
+mod controller;
+pub use controller::*;
+
+mod no_control;
+pub use no_control::*;
+
+mod new_reno;
+pub use new_reno::*;
+
+mod cubic;
+pub use cubic::*;
@@ -0,0 +1,132 @@
+use crate::time::{Duration, Instant};
+
+use super::Controller;
+
+/// Initial window, RFC 5681 section 3.1.
+pub(crate) fn initial_window(mss: usize) -> usize {
+    if mss > 2190 {
+        2 * mss
+    } else if mss > 1095 {
+        3 * mss
+    } else {
+        4 * mss
+    }
+}
+
+/// Slow start threshold after loss, RFC 5681 equation (4).
+pub(crate) fn loss_threshold(in_flight: usize, mss: usize) -> usize {
+    (in_flight / 2).max(2 * mss)
+}
+
+/// NewReno congestion control, RFC 5681 and RFC 6582.
+#[derive(Debug, Clone)]
+pub struct NewReno {
+    mss: usize,
+    cwnd: usize,
+    ssthresh: usize,
+}
+
+impl Default for NewReno {
+    fn default() -> Self {
+        let mss = 536;
+
+        Self {
+            mss,
+            cwnd: initial_window(mss),
+            ssthresh: usize::MAX,
+        }
+    }
+}
+
+impl NewReno {
+    pub fn slow_start_threshold(&self) -> usize {
+        self.ssthresh
+    }
+}
+
+impl Controller for NewReno {
+    fn window(&self) -> usize {
+        self.cwnd
+    }
+
+    fn reset(&mut self, mss: usize) {
+        *self = Self::default();
+        self.set_mss(mss);
+    }
+
+    fn set_mss(&mut self, mss: usize) {
+        self.mss = mss;
+        self.cwnd = self.cwnd.max(initial_window(mss));
+    }
+
+    fn on_ack(&mut self, _now: Instant, acked: usize, _rtt: Duration) {
+        let increase = if self.cwnd < self.ssthresh {
+            acked.min(self.mss)
+        } else {
+            (self.mss * self.mss / self.cwnd).max(1)
+        };
+
+        self.cwnd = self.cwnd.saturating_add(increase);
+    }
+
+    fn on_fast_retransmit(&mut self, _now: Instant, in_flight: usize) {
+        self.ssthresh = loss_threshold(in_flight, self.mss);
+        self.cwnd = self.ssthresh + 3 * self.mss;
+    }
+
+    fn on_dup_ack(&mut self) {
+        self.cwnd += self.mss;
+    }
+
+    fn on_partial_ack(&mut self, acked: usize) {
+        self.cwnd = self.cwnd.saturating_sub(acked) + self.mss;
+    }
+
+    fn on_recovery_exit(&mut self) {
+        self.cwnd = self.ssthresh;
+    }
+
+    fn on_timeout(&mut self, _now: Instant, in_flight: usize) {
+        self.ssthresh = loss_threshold(in_flight, self.mss);
+        self.cwnd = self.mss;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_reno() {
+        let now = Instant::ZERO;
+        let rtt = Duration::from_millis(10);
+
+        let mut reno = NewReno::default();
+        reno.set_mss(1000);
+        assert_eq!(reno.window(), 4000);
+
+        // Slow start
+        for _ in 0..4 {
+            reno.on_ack(now, 1000, rtt);
+        }
+        assert_eq!(reno.window(), 8000);
+
+        reno.on_fast_retransmit(now, 8000);
+        assert_eq!(reno.slow_start_threshold(), 4000);
+        assert_eq!(reno.window(), 7000);
+
+        reno.on_dup_ack();
+        assert_eq!(reno.window(), 8000);
+
+        reno.on_recovery_exit();
+        assert_eq!(reno.window(), 4000);
+
+        // Congestion avoidance
+        reno.on_ack(now, 1000, rtt);
+        assert_eq!(reno.window(), 4250);
+
+        reno.on_timeout(now, 4000);
+        assert_eq!(reno.slow_start_threshold(), 2000);
+        assert_eq!(reno.window(), 1000);
+    }
+}
@@ -0,0 +1,29 @@
+use crate::time::{Duration, Instant};
+
+use super::Controller;
+
+/// Controller without congestion window.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoControl;
+
+impl Controller for NoControl {
+    fn window(&self) -> usize {
+        usize::MAX
+    }
+
+    fn reset(&mut self, _mss: usize) {}
+
+    fn set_mss(&mut self, _mss: usize) {}
+
+    fn on_ack(&mut self, _now: Instant, _acked: usize, _rtt: Duration) {}
+
+    fn on_fast_retransmit(&mut self, _now: Instant, _in_flight: usize) {}
+
+    fn on_dup_ack(&mut self) {}
+
+    fn on_partial_ack(&mut self, _acked: usize) {}
+
+    fn on_recovery_exit(&mut self) {}
+
+    fn on_timeout(&mut self, _now: Instant, _in_flight: usize) {}
+}
@@ -0,0 +1,18 @@
+//! TCP socket.
+
+mod seq;
+pub use seq::*;
+
+mod ring;
+pub use ring::*;
+
+mod assembler;
+pub use assembler::*;
+
+pub mod congestion;
+
+mod timer;
+pub(crate) use timer::*;
+
+mod socket;
+pub use socket::*;
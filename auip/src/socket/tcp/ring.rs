@@ -0,0 +1,166 @@
+/// Ring buffer of octets for TCP stream.
+///
+/// The storage is provided by caller, it can be a fixed array or a `Vec`.
+#[derive(Debug)]
+pub struct RingBuffer<'a> {
+    storage: &'a mut [u8],
+    read_at: usize,
+    length: usize,
+}
+
+impl<'a> RingBuffer<'a> {
+    pub fn new(storage: &'a mut [u8]) -> Self {
+        Self {
+            storage,
+            read_at: 0,
+            length: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.storage.len()
+    }
+
+    /// Length of allocated octets.
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    /// Length of free space.
+    pub fn window(&self) -> usize {
+        self.capacity() - self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.window() == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.read_at = 0;
+        self.length = 0;
+    }
+
+    fn index(&self, offset: usize) -> usize {
+        (self.read_at + offset) % self.capacity()
+    }
+
+    /// Append octets, return the number of octets enqueued.
+    pub fn enqueue_slice(&mut self, data: &[u8]) -> usize {
+        let len = self.write_unallocated(0, data);
+        self.enqueue_unallocated(len);
+        len
+    }
+
+    /// Remove octets from the front into `data`, return the number of octets dequeued.
+    pub fn dequeue_slice(&mut self, data: &mut [u8]) -> usize {
+        let len = self.read_allocated(0, data);
+        self.dequeue_allocated(len);
+        len
+    }
+
+    /// Write octets into free space at `offset` past the allocated octets,
+    /// return the number of octets written.
+    pub fn write_unallocated(&mut self, offset: usize, data: &[u8]) -> usize {
+        if offset >= self.window() {
+            return 0;
+        }
+
+        let len = data.len().min(self.window() - offset);
+        let start = self.index(self.length + offset);
+        let first = len.min(self.capacity() - start);
+
+        self.storage[start..start + first].copy_from_slice(&data[..first]);
+        self.storage[..len - first].copy_from_slice(&data[first..len]);
+
+        len
+    }
+
+    /// Mark `count` octets of free space as allocated.
+    pub fn enqueue_unallocated(&mut self, count: usize) {
+        assert!(count <= self.window());
+        self.length += count;
+    }
+
+    /// Copy allocated octets starting at `offset` into `data`, return the number of octets copied.
+    pub fn read_allocated(&self, offset: usize, data: &mut [u8]) -> usize {
+        if offset >= self.length {
+            return 0;
+        }
+
+        let len = data.len().min(self.length - offset);
+        let start = self.index(offset);
+        let first = len.min(self.capacity() - start);
+
+        data[..first].copy_from_slice(&self.storage[start..start + first]);
+        data[first..len].copy_from_slice(&self.storage[..len - first]);
+
+        len
+    }
+
+    /// Get contiguous allocated octets starting at `offset`, at most `size` octets.
+    pub fn get_allocated(&self, offset: usize, size: usize) -> &[u8] {
+        if offset >= self.length {
+            return &[];
+        }
+
+        let start = self.index(offset);
+        let len = size.min(self.length - offset).min(self.capacity() - start);
+
+        &self.storage[start..start + len]
+    }
+
+    /// Release `count` octets from the front.
+    pub fn dequeue_allocated(&mut self, count: usize) {
+        assert!(count <= self.length);
+
+        if count == 0 {
+            return;
+        }
+
+        // Free space keeps its position, out of order data may be stored in it.
+        self.read_at = self.index(count);
+        self.length -= count;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RingBuffer;
+
+    #[test]
+    fn test_wrap_around() {
+        let mut storage = [0u8; 8];
+        let mut ring = RingBuffer::new(&mut storage);
+
+        assert_eq!(ring.enqueue_slice(b"abcdef"), 6);
+        let mut buffer = [0u8; 4];
+        assert_eq!(ring.dequeue_slice(&mut buffer), 4);
+        assert_eq!(&buffer, b"abcd");
+
+        assert_eq!(ring.enqueue_slice(b"ghijklmn"), 6);
+        assert!(ring.is_full());
+        assert_eq!(ring.get_allocated(0, 8), b"efgh");
+        assert_eq!(ring.get_allocated(4, 8), b"ijkl");
+
+        let mut buffer = [0u8; 8];
+        assert_eq!(ring.read_allocated(1, &mut buffer), 7);
+        assert_eq!(&buffer[..7], b"fghijkl");
+
+        ring.dequeue_allocated(3);
+        assert_eq!(ring.write_unallocated(1, b"xyz"), 2);
+        ring.enqueue_unallocated(3);
+        assert_eq!(ring.dequeue_slice(&mut buffer), 8);
+        assert_eq!(&buffer[..5], b"hijkl");
+        assert_eq!(&buffer[6..], b"xy");
+
+        // Free space is kept after buffer becomes empty.
+        assert_eq!(ring.write_unallocated(2, b"uv"), 2);
+        ring.enqueue_unallocated(4);
+        assert_eq!(ring.read_allocated(2, &mut buffer), 2);
+        assert_eq!(&buffer[..2], b"uv");
+    }
+}
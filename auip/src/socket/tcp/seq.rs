@@ -0,0 +1,86 @@
+use core::{
+    cmp::Ordering,
+    fmt::{self, Display, Formatter},
+    ops::{Add, AddAssign, Sub},
+};
+
+/// TCP sequence number, compared in modulo 2^32 arithmetic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct SeqNumber(pub u32);
+
+impl SeqNumber {
+    pub fn max(self, rhs: Self) -> Self {
+        if self > rhs {
+            self
+        } else {
+            rhs
+        }
+    }
+
+    pub fn min(self, rhs: Self) -> Self {
+        if self < rhs {
+            self
+        } else {
+            rhs
+        }
+    }
+}
+
+impl Display for SeqNumber {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Add<usize> for SeqNumber {
+    type Output = SeqNumber;
+
+    fn add(self, rhs: usize) -> SeqNumber {
+        if rhs > i32::MAX as usize {
+            panic!("attempt to add to sequence number with unsigned overflow")
+        }
+
+        SeqNumber(self.0.wrapping_add(rhs as u32))
+    }
+}
+
+impl AddAssign<usize> for SeqNumber {
+    fn add_assign(&mut self, rhs: usize) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub<usize> for SeqNumber {
+    type Output = SeqNumber;
+
+    fn sub(self, rhs: usize) -> SeqNumber {
+        if rhs > i32::MAX as usize {
+            panic!("attempt to subtract from sequence number with unsigned overflow")
+        }
+
+        SeqNumber(self.0.wrapping_sub(rhs as u32))
+    }
+}
+
+impl Sub<SeqNumber> for SeqNumber {
+    type Output = usize;
+
+    /// Return distance from `rhs` to `self`.
+    ///
+    /// # Panics
+    /// This function panics if `rhs` is after `self`.
+    fn sub(self, rhs: SeqNumber) -> usize {
+        let diff = self.0.wrapping_sub(rhs.0) as i32;
+        if diff < 0 {
+            panic!("attempt to subtract sequence numbers with underflow")
+        }
+
+        diff as usize
+    }
+}
+
+impl PartialOrd for SeqNumber {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (self.0.wrapping_sub(other.0) as i32).partial_cmp(&0)
+    }
+}
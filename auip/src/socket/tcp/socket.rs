@@ -0,0 +1,1716 @@
+use core::{
+    fmt::{self, Display, Formatter},
+    hash::{Hash, Hasher},
+};
+
+use auip_pkt::{
+    layer3,
+    layer4::tcp::{consts::MAX_SACK_BLOCKS, Control, Repr},
+};
+
+use crate::{
+    time::{Duration, Instant},
+    Endpoint, Error, Result,
+};
+
+use super::{
+    congestion::{AnyController, CongestionControl, Controller},
+    Assembler, RingBuffer, RttEstimator, SeqNumber, Timer,
+};
+
+/// Default MSS of remote when no MSS option is received, RFC 9293 section 3.7.1.
+const DEFAULT_MSS: usize = 536;
+
+/// Length of IPv4 and TCP header without option.
+const HEADER_LEN: usize = 40;
+
+/// Maximum shift of window scale option, RFC 7323 section 2.3.
+const MAX_WND_SHIFT: u8 = 14;
+
+/// Duplicate acknowledgments to trigger fast retransmit, RFC 5681 section 3.2.
+const DUP_ACK_THRESHOLD: usize = 3;
+
+/// Time to stay in TIME-WAIT state.
+const CLOSE_DELAY: Duration = Duration::from_secs(10);
+
+/// Retransmissions by timeout before connection is aborted.
+const DEFAULT_MAX_RETRANSMITS: usize = 12;
+
+/// State of TCP connection, RFC 9293 section 3.3.2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    Closed,
+    Listen,
+    SynSent,
+    SynReceived,
+    Established,
+    FinWait1,
+    FinWait2,
+    CloseWait,
+    Closing,
+    LastAck,
+    TimeWait,
+}
+
+impl Display for State {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            State::Closed => write!(f, "CLOSED"),
+            State::Listen => write!(f, "LISTEN"),
+            State::SynSent => write!(f, "SYN-SENT"),
+            State::SynReceived => write!(f, "SYN-RECEIVED"),
+            State::Established => write!(f, "ESTABLISHED"),
+            State::FinWait1 => write!(f, "FIN-WAIT-1"),
+            State::FinWait2 => write!(f, "FIN-WAIT-2"),
+            State::CloseWait => write!(f, "CLOSE-WAIT"),
+            State::Closing => write!(f, "CLOSING"),
+            State::LastAck => write!(f, "LAST-ACK"),
+            State::TimeWait => write!(f, "TIME-WAIT"),
+        }
+    }
+}
+
+/// TCP socket.
+///
+/// Stream is buffered in ring buffers over storages provided by caller. A listening
+/// socket turns into the accepted connection, add more listening sockets to accept
+/// concurrent connections.
+pub struct TcpSocket<'a> {
+    state: State,
+    timer: Timer,
+    rtte: RttEstimator,
+    congestion: AnyController<'a>,
+
+    /// Endpoint to return to when a passive open connection is reset.
+    listen: Option<Endpoint>,
+    local: Option<Endpoint>,
+    remote: Option<Endpoint>,
+
+    /// Initial send sequence number.
+    iss: SeqNumber,
+    /// Oldest unacknowledged sequence number.
+    snd_una: SeqNumber,
+    /// Next sequence number to send.
+    snd_nxt: SeqNumber,
+    /// Highest sequence number ever sent.
+    snd_max: SeqNumber,
+    /// Send window of remote.
+    snd_wnd: usize,
+    /// Sequence and acknowledgment number of segment used for last window update.
+    snd_wl1: SeqNumber,
+    snd_wl2: SeqNumber,
+    remote_mss: usize,
+    /// Count of duplicate acknowledgments received.
+    dup_acks: usize,
+    /// Highest sequence number sent when fast recovery began, RFC 6582.
+    recover: Option<SeqNumber>,
+    /// The first unacknowledged segment must be sent again.
+    fast_retransmit: bool,
+    /// Retransmissions by timeout since the last acknowledgment of new data.
+    retransmits: usize,
+    /// Time since data is sent and not acknowledged, acknowledgment of new data restarts it.
+    unacked_since: Option<Instant>,
+
+    /// Next sequence number expected from remote.
+    rcv_nxt: SeqNumber,
+    /// Last acknowledgment number and window sent.
+    ack_sent: Option<SeqNumber>,
+    wnd_sent: usize,
+    /// An acknowledgment must be sent even if nothing changed.
+    ack_needed: bool,
+    /// A reset must be sent for aborted connection.
+    rst_needed: bool,
+
+    rx_buffer: RingBuffer<'a>,
+    tx_buffer: RingBuffer<'a>,
+    assembler: Assembler,
+
+    /// Limits of retransmission before connection is aborted.
+    max_retransmits: usize,
+    timeout: Option<Duration>,
+
+    /// Options requested for new connections.
+    window_scaling: bool,
+    timestamps: bool,
+    #[cfg(feature = "tcp-sack")]
+    sack: bool,
+
+    /// Window scale is negotiated, RFC 7323 section 2.
+    ws_enabled: bool,
+    snd_wnd_shift: u8,
+    rcv_wnd_shift: u8,
+    /// Timestamps are negotiated, and the timestamp to echo, RFC 7323 section 3.
+    ts_enabled: bool,
+    ts_recent: u32,
+    /// Selective acknowledgment is negotiated, RFC 2018.
+    #[cfg(feature = "tcp-sack")]
+    sack_enabled: bool,
+    /// Sequence number of the latest out of order segment received.
+    #[cfg(feature = "tcp-sack")]
+    sack_latest: Option<SeqNumber>,
+    /// Ranges reported by SACK blocks of remote, offset from `snd_una`.
+    #[cfg(feature = "tcp-sack")]
+    scoreboard: Assembler,
+    /// Highest sequence number retransmitted in fast recovery, RFC 6675.
+    #[cfg(feature = "tcp-sack")]
+    high_rxt: SeqNumber,
+}
+
+impl<'a> TcpSocket<'a> {
+    pub fn new(rx_buffer: &'a mut [u8], tx_buffer: &'a mut [u8]) -> Self {
+        Self {
+            state: State::Closed,
+            timer: Timer::Idle,
+            rtte: RttEstimator::default(),
+            congestion: AnyController::new(CongestionControl::default(), DEFAULT_MSS),
+            listen: None,
+            local: None,
+            remote: None,
+            iss: SeqNumber::default(),
+            snd_una: SeqNumber::default(),
+            snd_nxt: SeqNumber::default(),
+            snd_max: SeqNumber::default(),
+            snd_wnd: 0,
+            snd_wl1: SeqNumber::default(),
+            snd_wl2: SeqNumber::default(),
+            remote_mss: DEFAULT_MSS,
+            dup_acks: 0,
+            recover: None,
+            fast_retransmit: false,
+            retransmits: 0,
+            unacked_since: None,
+            rcv_nxt: SeqNumber::default(),
+            ack_sent: None,
+            wnd_sent: 0,
+            ack_needed: false,
+            rst_needed: false,
+            rx_buffer: RingBuffer::new(rx_buffer),
+            tx_buffer: RingBuffer::new(tx_buffer),
+            assembler: Assembler::default(),
+            max_retransmits: DEFAULT_MAX_RETRANSMITS,
+            timeout: None,
+            window_scaling: true,
+            timestamps: true,
+            #[cfg(feature = "tcp-sack")]
+            sack: true,
+            ws_enabled: false,
+            snd_wnd_shift: 0,
+            rcv_wnd_shift: 0,
+            ts_enabled: false,
+            ts_recent: 0,
+            #[cfg(feature = "tcp-sack")]
+            sack_enabled: false,
+            #[cfg(feature = "tcp-sack")]
+            sack_latest: None,
+            #[cfg(feature = "tcp-sack")]
+            scoreboard: Assembler::default(),
+            #[cfg(feature = "tcp-sack")]
+            high_rxt: SeqNumber::default(),
+        }
+    }
+
+    /// Listen on `port` of all addresses of interface.
+    pub fn listen(&mut self, port: u16) -> Result<()> {
+        self.listen_endpoint(Endpoint::new(layer3::Address::Unspecified, port))
+    }
+
+    /// Listen on local endpoint, unspecified address means all addresses of interface.
+    ///
+    /// Socket turns into [State::SynReceived] when a connection request is received.
+    pub fn listen_endpoint(&mut self, endpoint: Endpoint) -> Result<()> {
+        if endpoint.port == 0 {
+            return Err(Error::IllegalEndpoint);
+        }
+
+        if self.state != State::Closed {
+            return Err(Error::IllegalSocketState);
+        }
+
+        self.reset();
+        self.listen = Some(endpoint);
+        self.local = Some(endpoint);
+        self.state = State::Listen;
+        Ok(())
+    }
+
+    /// Open connection to `remote` from `local_port`.
+    ///
+    /// SYN is sent on next poll of interface.
+    pub fn connect(&mut self, remote: Endpoint, local_port: u16) -> Result<()> {
+        if !remote.is_specified() || local_port == 0 {
+            return Err(Error::IllegalEndpoint);
+        }
+
+        if self.state != State::Closed {
+            return Err(Error::IllegalSocketState);
+        }
+
+        self.reset();
+        self.local = Some(Endpoint::new(layer3::Address::Unspecified, local_port));
+        self.remote = Some(remote);
+        self.request_options();
+        self.state = State::SynSent;
+        Ok(())
+    }
+
+    /// Close the sending half of connection, FIN is sent after all queued data.
+    pub fn close(&mut self) {
+        match self.state {
+            State::Listen | State::SynSent => self.reset(),
+            State::SynReceived | State::Established => self.set_state(State::FinWait1),
+            State::CloseWait => self.set_state(State::LastAck),
+            _ => {}
+        }
+    }
+
+    /// Close connection immediately, RST is sent if connection is synchronized.
+    pub fn abort(&mut self) {
+        let synchronized = !matches!(self.state, State::Closed | State::Listen | State::SynSent);
+
+        let (local, remote) = (self.local, self.remote);
+        self.reset();
+
+        if synchronized {
+            self.local = local;
+            self.remote = remote;
+            self.rst_needed = true;
+        }
+    }
+
+    /// Set congestion control algorithm, window of controller starts over.
+    pub fn set_congestion_control(&mut self, algorithm: CongestionControl) {
+        self.congestion = AnyController::new(algorithm, self.remote_mss);
+    }
+
+    /// Set congestion controller provided by caller, window of controller starts over.
+    ///
+    /// Controller is reset for each new connection.
+    pub fn set_congestion_controller(&mut self, controller: &'a mut dyn Controller) {
+        controller.reset(self.remote_mss);
+        self.congestion = AnyController::Custom(controller);
+    }
+
+    /// Congestion control algorithm, `None` if controller is provided by caller.
+    pub fn congestion_control(&self) -> Option<CongestionControl> {
+        self.congestion.algorithm()
+    }
+
+    /// Set retransmissions by timeout before connection is aborted, 12 by default.
+    ///
+    /// Timeout backs off on each retransmission, count starts over when new data is
+    /// acknowledged. Aborted connection turns into [State::Closed], or back into
+    /// [State::Listen] if it is not established from a listening socket.
+    pub fn set_max_retransmits(&mut self, count: usize) {
+        self.max_retransmits = count;
+    }
+
+    pub fn max_retransmits(&self) -> usize {
+        self.max_retransmits
+    }
+
+    /// Set user timeout, connection is aborted if sent data is not acknowledged in
+    /// `timeout`, RFC 9293 section 3.10.8. No user timeout by default.
+    ///
+    /// Timeout is checked when retransmission timer expires.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Enable window scale option for new connections, enabled by default.
+    ///
+    /// Receive window larger than 65535 octets needs window scale.
+    pub fn set_window_scaling(&mut self, enabled: bool) {
+        self.window_scaling = enabled;
+    }
+
+    pub fn window_scaling(&self) -> bool {
+        self.window_scaling
+    }
+
+    /// Enable timestamps option for new connections, enabled by default.
+    ///
+    /// Timestamps measure round trip time of every acknowledgment, and protect
+    /// against wrapped sequence numbers (PAWS).
+    pub fn set_timestamps(&mut self, enabled: bool) {
+        self.timestamps = enabled;
+    }
+
+    pub fn timestamps(&self) -> bool {
+        self.timestamps
+    }
+
+    /// Enable selective acknowledgment for new connections, enabled by default.
+    #[cfg(feature = "tcp-sack")]
+    pub fn set_sack(&mut self, enabled: bool) {
+        self.sack = enabled;
+    }
+
+    #[cfg(feature = "tcp-sack")]
+    pub fn sack(&self) -> bool {
+        self.sack
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn local_endpoint(&self) -> Option<Endpoint> {
+        self.local
+    }
+
+    pub fn remote_endpoint(&self) -> Option<Endpoint> {
+        self.remote
+    }
+
+    pub fn is_open(&self) -> bool {
+        !matches!(self.state, State::Closed | State::TimeWait)
+    }
+
+    pub fn is_listening(&self) -> bool {
+        self.state == State::Listen
+    }
+
+    /// Query whether connection is being established or established.
+    pub fn is_active(&self) -> bool {
+        !matches!(self.state, State::Closed | State::Listen | State::TimeWait)
+    }
+
+    /// Query whether data can be queued for sending in current state.
+    pub fn may_send(&self) -> bool {
+        matches!(self.state, State::Established | State::CloseWait)
+    }
+
+    /// Query whether data can be received in current state.
+    pub fn may_recv(&self) -> bool {
+        matches!(
+            self.state,
+            State::Established | State::FinWait1 | State::FinWait2
+        ) || !self.rx_buffer.is_empty()
+    }
+
+    pub fn can_send(&self) -> bool {
+        self.may_send() && !self.tx_buffer.is_full()
+    }
+
+    pub fn can_recv(&self) -> bool {
+        !self.rx_buffer.is_empty()
+    }
+
+    /// Number of octets queued for sending, including unacknowledged.
+    pub fn send_queue(&self) -> usize {
+        self.tx_buffer.len()
+    }
+
+    /// Number of octets received and not read.
+    pub fn recv_queue(&self) -> usize {
+        self.rx_buffer.len()
+    }
+
+    /// Queue data for sending, return the number of octets queued.
+    pub fn send_slice(&mut self, data: &[u8]) -> Result<usize> {
+        if !self.may_send() {
+            return Err(Error::IllegalSocketState);
+        }
+
+        Ok(self.tx_buffer.enqueue_slice(data))
+    }
+
+    /// Read received data, return the number of octets read.
+    ///
+    /// Return error if no more data can be received, such as remote closed
+    /// connection and all data is read.
+    pub fn recv_slice(&mut self, data: &mut [u8]) -> Result<usize> {
+        if !self.may_recv() {
+            return Err(Error::IllegalSocketState);
+        }
+
+        Ok(self.rx_buffer.dequeue_slice(data))
+    }
+
+    fn set_state(&mut self, state: State) {
+        if self.state != state {
+            log::debug!("TCP state {} -> {}.", self.state, state);
+        }
+
+        self.state = state;
+    }
+
+    fn reset(&mut self) {
+        self.rx_buffer.clear();
+        self.tx_buffer.clear();
+        self.set_state(State::Closed);
+        self.timer = Timer::Idle;
+        self.rtte = RttEstimator::default();
+        self.congestion.reset(DEFAULT_MSS);
+        self.listen = None;
+        self.local = None;
+        self.remote = None;
+        self.remote_mss = DEFAULT_MSS;
+        self.dup_acks = 0;
+        self.recover = None;
+        self.fast_retransmit = false;
+        self.retransmits = 0;
+        self.unacked_since = None;
+        self.ack_sent = None;
+        self.wnd_sent = 0;
+        self.ack_needed = false;
+        self.rst_needed = false;
+        self.assembler.clear();
+        self.ws_enabled = false;
+        self.snd_wnd_shift = 0;
+        self.rcv_wnd_shift = 0;
+        self.ts_enabled = false;
+        self.ts_recent = 0;
+
+        #[cfg(feature = "tcp-sack")]
+        {
+            self.sack_enabled = false;
+            self.sack_latest = None;
+            self.scoreboard.clear();
+        }
+    }
+
+    /// Request options enabled for new connection, they are kept if remote agrees.
+    fn request_options(&mut self) {
+        self.ws_enabled = self.window_scaling;
+        self.ts_enabled = self.timestamps;
+
+        #[cfg(feature = "tcp-sack")]
+        {
+            self.sack_enabled = self.sack;
+        }
+    }
+
+    /// Negotiate options with SYN of remote.
+    fn negotiate(&mut self, repr: &Repr<'_>) {
+        match repr.window_scale {
+            Some(shift) if self.ws_enabled => {
+                self.snd_wnd_shift = shift.min(MAX_WND_SHIFT);
+                self.rcv_wnd_shift = self.rx_shift();
+            }
+            _ => {
+                self.ws_enabled = false;
+                self.snd_wnd_shift = 0;
+                self.rcv_wnd_shift = 0;
+            }
+        }
+
+        match repr.timestamp {
+            Some((tsval, _)) if self.ts_enabled => self.ts_recent = tsval,
+            _ => self.ts_enabled = false,
+        }
+
+        #[cfg(feature = "tcp-sack")]
+        {
+            self.sack_enabled &= repr.sack_permitted;
+        }
+
+        self.remote_mss = remote_mss(repr);
+        self.congestion.set_mss(self.remote_mss);
+        // Window of SYN is never scaled.
+        self.snd_wnd = repr.window_len as usize;
+    }
+
+    /// Return to listen after a passive open connection is reset, or close.
+    fn reset_connection(&mut self) {
+        match self.listen {
+            Some(listen) => {
+                self.reset();
+                self.listen = Some(listen);
+                self.local = Some(listen);
+                self.set_state(State::Listen);
+            }
+            None => self.reset(),
+        }
+    }
+
+    /// Length of SYN in sequence space if it is not acknowledged.
+    fn syn_len(&self) -> usize {
+        match self.state {
+            State::SynSent | State::SynReceived => 1,
+            State::FinWait1 if self.snd_una == self.iss => 1,
+            _ => 0,
+        }
+    }
+
+    /// Query whether FIN should be sent after queued data.
+    fn fin_queued(&self) -> bool {
+        matches!(
+            self.state,
+            State::FinWait1 | State::Closing | State::LastAck
+        )
+    }
+
+    /// Window advertised to remote, rounded down to unit of window scale.
+    fn window(&self) -> usize {
+        let shift = self.rcv_wnd_shift;
+        let window = self.rx_buffer.window().min((u16::MAX as usize) << shift);
+        (window >> shift) << shift
+    }
+
+    /// Window of a segment from remote.
+    fn snd_window(&self, repr: &Repr<'_>) -> usize {
+        (repr.window_len as usize) << self.snd_wnd_shift
+    }
+
+    fn in_window(&self, seq: SeqNumber, window: usize) -> bool {
+        self.rcv_nxt <= seq && seq < self.rcv_nxt + window
+    }
+
+    fn enter_time_wait(&mut self, now: Instant) {
+        self.set_state(State::TimeWait);
+        self.timer = Timer::Close {
+            expires_at: now + CLOSE_DELAY,
+        };
+    }
+
+    /// Query whether a segment from `remote` to `local` belongs to this socket.
+    pub(crate) fn accepts(&self, remote: &Endpoint, local: &Endpoint) -> bool {
+        let local_matched = match self.local {
+            Some(endpoint) => {
+                endpoint.port == local.port
+                    && (endpoint.addr == layer3::Address::Unspecified
+                        || endpoint.addr == local.addr)
+            }
+            None => false,
+        };
+
+        match self.state {
+            State::Closed => false,
+            State::Listen => local_matched,
+            _ => local_matched && self.remote == Some(*remote),
+        }
+    }
+
+    /// Process a segment from `remote` to `local`, return a segment to reply immediately.
+    ///
+    /// `isn_secret` is the key of initial sequence number of accepted connection.
+    pub(crate) fn process(
+        &mut self,
+        now: Instant,
+        isn_secret: u128,
+        remote: Endpoint,
+        local: Endpoint,
+        repr: &Repr<'_>,
+    ) -> Option<Repr<'static>> {
+        match self.state {
+            State::Closed => return None,
+            State::Listen => return self.process_listen(now, isn_secret, remote, local, repr),
+            State::SynSent => return self.process_syn_sent(now, local, repr),
+            _ => {}
+        }
+
+        let seq = SeqNumber(repr.seq_number);
+        let seg_len = repr.segment_len();
+        let window = self.rx_buffer.window();
+
+        // Protection against wrapped sequence numbers, RFC 7323 section 5.3.
+        if let (true, Some((tsval, _))) = (self.ts_enabled, repr.timestamp) {
+            if repr.control != Control::Rst && (tsval.wrapping_sub(self.ts_recent) as i32) < 0 {
+                log::debug!("Segment {} with old timestamp, Drop it.", seq);
+                self.ack_needed = true;
+                return None;
+            }
+        }
+
+        // Check sequence number, RFC 9293 section 3.10.7.4.
+        let acceptable = match (seg_len, window) {
+            (0, 0) => seq == self.rcv_nxt,
+            (0, _) => self.in_window(seq, window),
+            (_, 0) => false,
+            (_, _) => self.in_window(seq, window) || self.in_window(seq + (seg_len - 1), window),
+        };
+
+        // Only ACK and RST are processed for a closed window.
+        let mut accept_data = true;
+
+        if !acceptable {
+            if repr.control == Control::Rst {
+                return None;
+            }
+
+            if window == 0 && seq == self.rcv_nxt {
+                accept_data = false;
+            } else {
+                log::debug!("Unacceptable segment {}, Drop it.", seq);
+
+                match self.state {
+                    State::TimeWait => self.enter_time_wait(now),
+                    // Remote may miss SYN-ACK, send it again.
+                    State::SynReceived => self.snd_nxt = self.snd_una,
+                    _ => {}
+                }
+
+                self.ack_needed = true;
+                return None;
+            }
+        }
+
+        if repr.control == Control::Rst {
+            // Only exact sequence number reset connection, RFC 5961 section 3.2.
+            if seq != self.rcv_nxt {
+                self.ack_needed = true;
+                return None;
+            }
+
+            log::debug!("Connection reset by {:?}.", remote);
+            self.reset_connection();
+            return None;
+        }
+
+        if repr.control == Control::Syn {
+            // Challenge ACK, RFC 5961 section 4.2.
+            self.ack_needed = true;
+            return None;
+        }
+
+        // Timestamp to echo is of segment filling the last acknowledgment, RFC 7323 section 4.3.
+        if let (true, Some((tsval, _))) = (self.ts_enabled, repr.timestamp) {
+            if seq <= self.ack_sent.unwrap_or(self.rcv_nxt) {
+                self.ts_recent = tsval;
+            }
+        }
+
+        let ack = SeqNumber(repr.ack_number?);
+
+        if self.syn_len() == 1 && !(self.snd_una < ack && ack <= self.snd_max) {
+            return Some(rst_reply(repr));
+        }
+
+        if ack > self.snd_max {
+            log::debug!("Acknowledgment {} of unsent data, Drop it.", ack);
+            self.ack_needed = true;
+            return None;
+        }
+
+        // Duplicate acknowledgment, RFC 5681 section 2.
+        let dup_ack = ack == self.snd_una
+            && self.snd_una != self.snd_max
+            && seg_len == 0
+            && self.snd_window(repr) == self.snd_wnd;
+
+        if ack > self.snd_una {
+            let mut acked = ack - self.snd_una;
+
+            #[cfg(feature = "tcp-sack")]
+            self.scoreboard.shift(acked);
+
+            if self.syn_len() == 1 {
+                acked -= 1;
+
+                if self.state == State::SynReceived {
+                    self.set_state(State::Established);
+                }
+            }
+
+            let fin_acked = self.fin_queued() && acked > self.tx_buffer.len();
+            self.tx_buffer
+                .dequeue_allocated(acked.min(self.tx_buffer.len()));
+
+            self.snd_una = ack;
+            self.snd_nxt = self.snd_nxt.max(ack);
+            self.dup_acks = 0;
+            self.retransmits = 0;
+            self.unacked_since = if ack == self.snd_max { None } else { Some(now) };
+
+            match repr.timestamp {
+                Some((_, tsecr)) if self.ts_enabled && tsecr != 0 => {
+                    let rtt = ts_clock(now).wrapping_sub(tsecr);
+                    self.rtte.on_sample(Duration::from_millis(rtt as u64));
+                }
+                _ => self.rtte.on_ack(now, ack),
+            }
+
+            match self.recover {
+                Some(recover) if ack >= recover => {
+                    self.recover = None;
+                    self.congestion.on_recovery_exit();
+                }
+                Some(_) => {
+                    // Partial acknowledgment, send the next hole, RFC 6582 section 3.2.
+                    self.fast_retransmit = true;
+                    self.congestion.on_partial_ack(acked);
+
+                    #[cfg(feature = "tcp-sack")]
+                    {
+                        self.high_rxt = self.high_rxt.max(self.snd_una);
+                    }
+                }
+                None => self.congestion.on_ack(now, acked, self.rtte.rtt()),
+            }
+
+            self.timer = if self.snd_una == self.snd_max {
+                Timer::Idle
+            } else {
+                Timer::Retransmit {
+                    expires_at: now + self.rtte.rto(),
+                }
+            };
+
+            if fin_acked {
+                match self.state {
+                    State::FinWait1 => self.set_state(State::FinWait2),
+                    State::Closing => self.enter_time_wait(now),
+                    State::LastAck => {
+                        self.reset();
+                        return None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        #[cfg(feature = "tcp-sack")]
+        if self.sack_enabled {
+            for (left, right) in repr.sack_ranges.iter().flatten() {
+                let (left, right) = (SeqNumber(*left), SeqNumber(*right));
+
+                if self.snd_una < left && left < right && right <= self.snd_max {
+                    // Scoreboard is only a hint, a lost block is reported again.
+                    self.scoreboard.add(left - self.snd_una, right - left);
+                }
+            }
+        }
+
+        if dup_ack {
+            self.dup_acks += 1;
+
+            if self.recover.is_some() {
+                self.congestion.on_dup_ack();
+
+                // More holes reported by SACK are sent in fast recovery, RFC 6675 section 5.
+                #[cfg(feature = "tcp-sack")]
+                if self.next_hole().is_some() {
+                    self.fast_retransmit = true;
+                }
+            } else if self.dup_acks == DUP_ACK_THRESHOLD {
+                log::debug!("Fast retransmit from {}.", self.snd_una);
+                self.recover = Some(self.snd_max);
+                self.fast_retransmit = true;
+                self.congestion
+                    .on_fast_retransmit(now, self.snd_max - self.snd_una);
+
+                #[cfg(feature = "tcp-sack")]
+                {
+                    self.high_rxt = self.snd_una;
+                }
+            }
+        }
+
+        if self.snd_wl1 < seq || (self.snd_wl1 == seq && self.snd_wl2 <= ack) {
+            self.snd_wnd = self.snd_window(repr);
+            self.snd_wl1 = seq;
+            self.snd_wl2 = ack;
+
+            if self.snd_wnd != 0 && matches!(self.timer, Timer::ZeroWindowProbe { .. }) {
+                self.timer = Timer::Idle;
+            }
+        }
+
+        let receiving = matches!(
+            self.state,
+            State::Established | State::FinWait1 | State::FinWait2
+        );
+
+        if !accept_data || !receiving {
+            if seg_len != 0 {
+                self.ack_needed = true;
+            }
+            return None;
+        }
+
+        if !repr.payload.is_empty() {
+            let (offset, payload) = if seq < self.rcv_nxt {
+                let skip = (self.rcv_nxt - seq).min(repr.payload.len());
+                (0, &repr.payload[skip..])
+            } else {
+                (seq - self.rcv_nxt, repr.payload)
+            };
+
+            let written = self.rx_buffer.write_unallocated(offset, payload);
+
+            if !self.assembler.add(offset, written) {
+                log::debug!("Too many out of order segments, Drop it.");
+            }
+
+            #[cfg(feature = "tcp-sack")]
+            if offset != 0 && written != 0 {
+                self.sack_latest = Some(self.rcv_nxt + offset);
+            }
+
+            let len = self.assembler.remove_front();
+            self.rx_buffer.enqueue_unallocated(len);
+            self.rcv_nxt += len;
+
+            if offset != 0 || written != payload.len() {
+                // Duplicate acknowledgment for out of order segment.
+                self.ack_needed = true;
+            }
+        }
+
+        if repr.control == Control::Fin && seq + repr.payload.len() == self.rcv_nxt {
+            self.rcv_nxt += 1;
+
+            match self.state {
+                State::Established => self.set_state(State::CloseWait),
+                State::FinWait1 => self.set_state(State::Closing),
+                State::FinWait2 => self.enter_time_wait(now),
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    fn process_listen(
+        &mut self,
+        now: Instant,
+        isn_secret: u128,
+        remote: Endpoint,
+        local: Endpoint,
+        repr: &Repr<'_>,
+    ) -> Option<Repr<'static>> {
+        if repr.control == Control::Rst {
+            return None;
+        }
+
+        if repr.ack_number.is_some() {
+            return Some(rst_reply(repr));
+        }
+
+        if repr.control != Control::Syn {
+            return None;
+        }
+
+        let seq = SeqNumber(repr.seq_number);
+        let iss = initial_seq_number(now, isn_secret, &local, &remote);
+
+        self.local = Some(local);
+        self.remote = Some(remote);
+        self.iss = iss;
+        self.snd_una = iss;
+        self.snd_nxt = iss;
+        self.snd_max = iss;
+        self.snd_wl1 = seq;
+        self.snd_wl2 = iss;
+        self.request_options();
+        self.negotiate(repr);
+        self.rcv_nxt = seq + 1;
+        self.set_state(State::SynReceived);
+
+        None
+    }
+
+    fn process_syn_sent(
+        &mut self,
+        now: Instant,
+        local: Endpoint,
+        repr: &Repr<'_>,
+    ) -> Option<Repr<'static>> {
+        let ack = repr.ack_number.map(SeqNumber);
+
+        if let Some(ack) = ack {
+            if !(self.snd_una < ack && ack <= self.snd_max) {
+                return match repr.control {
+                    Control::Rst => None,
+                    _ => Some(rst_reply(repr)),
+                };
+            }
+        }
+
+        if repr.control == Control::Rst {
+            if ack.is_some() {
+                log::debug!("Connection refused by {:?}.", self.remote);
+                self.reset();
+            }
+            return None;
+        }
+
+        if repr.control != Control::Syn {
+            return None;
+        }
+
+        let seq = SeqNumber(repr.seq_number);
+
+        self.local = Some(local);
+        self.rcv_nxt = seq + 1;
+        self.negotiate(repr);
+        self.snd_wl1 = seq;
+
+        match ack {
+            Some(ack) => {
+                self.snd_una = ack;
+                self.snd_nxt = ack;
+                self.snd_wl2 = ack;
+                self.rtte.on_ack(now, ack);
+                self.timer = Timer::Idle;
+                self.retransmits = 0;
+                self.unacked_since = None;
+                self.set_state(State::Established);
+            }
+            None => {
+                // Simultaneous open, SYN is sent again with ACK.
+                self.snd_nxt = self.snd_una;
+                self.snd_wl2 = self.snd_una;
+                self.timer = Timer::Idle;
+                self.set_state(State::SynReceived);
+            }
+        }
+
+        None
+    }
+
+    /// Time the socket needs to be dispatched, [Instant::ZERO] if a segment can be sent now.
+    pub(crate) fn poll_at(&self) -> Option<Instant> {
+        if self.local.is_none() || self.remote.is_none() {
+            return None;
+        }
+
+        let syn_len = self.syn_len();
+        let data_start = self.snd_una + syn_len;
+        let synchronized = !matches!(self.state, State::Closed | State::Listen | State::SynSent);
+
+        let send_syn = syn_len == 1 && self.snd_nxt == self.snd_una;
+        let (send_data, send_fin) = if self.snd_nxt >= data_start {
+            let sent = self.snd_nxt - data_start;
+            let window_end = self.snd_una + self.snd_wnd.min(self.congestion.window());
+
+            (
+                self.tx_buffer.len() > sent && window_end > self.snd_nxt,
+                self.fin_queued() && sent <= self.tx_buffer.len(),
+            )
+        } else {
+            (false, false)
+        };
+        let send_ack = synchronized && self.ack_sent != Some(self.rcv_nxt);
+
+        if self.rst_needed
+            || self.ack_needed
+            || self.fast_retransmit
+            || send_syn
+            || send_data
+            || send_fin
+            || send_ack
+        {
+            return Some(Instant::ZERO);
+        }
+
+        self.timer.expires_at()
+    }
+
+    /// Build next segment to send and pass it to `emit` with local and remote endpoint.
+    ///
+    /// `mtu` is the maximum length of IP packet, MSS is derived from it.
+    /// `isn_secret` is the key of initial sequence number of active open connection.
+    /// `emit` return `false` if segment can not be sent now, then nothing is changed.
+    /// Return whether a segment is sent.
+    pub(crate) fn dispatch<F>(
+        &mut self,
+        now: Instant,
+        mtu: usize,
+        isn_secret: u128,
+        emit: F,
+    ) -> Result<bool>
+    where
+        F: FnOnce(&Endpoint, &Endpoint, &Repr<'_>) -> Result<bool>,
+    {
+        let (local, remote) = match (self.local, self.remote) {
+            (Some(local), Some(remote)) => (local, remote),
+            _ => return Ok(false),
+        };
+
+        if self.timer.expired(now)
+            && matches!(self.timer, Timer::Retransmit { .. })
+            && self.timed_out(now)
+        {
+            log::debug!("Connection to {:?} timed out, Abort it.", remote);
+
+            match self.state {
+                // Remote never acknowledged SYN, return to listen without RST.
+                State::SynReceived if self.listen.is_some() => {
+                    self.reset_connection();
+                    return Ok(false);
+                }
+                _ => self.abort(),
+            }
+        }
+
+        if self.rst_needed {
+            let mut repr = segment(local.port, remote.port, self.snd_nxt);
+            repr.control = Control::Rst;
+
+            if emit(&local, &remote, &repr)? {
+                self.reset();
+                return Ok(true);
+            }
+            return Ok(false);
+        }
+
+        match self.state {
+            State::Closed | State::Listen => return Ok(false),
+            State::SynSent if self.snd_max == self.iss => {
+                let iss = initial_seq_number(now, isn_secret, &local, &remote);
+                self.iss = iss;
+                self.snd_una = iss;
+                self.snd_nxt = iss;
+                self.snd_max = iss;
+            }
+            _ => {}
+        }
+
+        match self.timer {
+            Timer::Close { .. } if self.timer.expired(now) => {
+                self.reset();
+                return Ok(false);
+            }
+            Timer::Retransmit { .. } if self.timer.expired(now) => {
+                log::debug!("Retransmit from {} after timeout.", self.snd_una);
+                self.retransmits += 1;
+                self.congestion.on_timeout(now, self.snd_max - self.snd_una);
+                self.snd_nxt = self.snd_una;
+                self.rtte.on_retransmit();
+                self.timer = Timer::Idle;
+                self.dup_acks = 0;
+                self.recover = None;
+                self.fast_retransmit = false;
+
+                // Remote may renege SACK blocks, RFC 2018 section 8.
+                #[cfg(feature = "tcp-sack")]
+                self.scoreboard.clear();
+            }
+            _ => {}
+        }
+
+        let syn_len = self.syn_len();
+
+        // Fast retransmit send a lost segment, `snd_nxt` is kept.
+        let retransmit = match (self.fast_retransmit, syn_len) {
+            (true, 0) => self.retransmit_range(),
+            _ => None,
+        };
+
+        if retransmit.is_none() {
+            self.fast_retransmit = false;
+        }
+
+        let seq = match retransmit {
+            Some((seq, _)) => seq,
+            None => self.snd_nxt,
+        };
+
+        let mut repr = segment(local.port, remote.port, seq);
+
+        if self.state != State::SynSent {
+            repr.ack_number = Some(self.rcv_nxt.0);
+        }
+
+        if self.ts_enabled {
+            repr.timestamp = Some((ts_clock(now), self.ts_recent));
+        }
+
+        let local_mss = mtu.saturating_sub(HEADER_LEN);
+        let window = self.window();
+
+        if syn_len == 1 && self.snd_nxt == self.snd_una {
+            repr.control = Control::Syn;
+            repr.window_len = window.min(u16::MAX as usize) as u16;
+            repr.max_seg_size = Some(local_mss.min(u16::MAX as usize) as u16);
+
+            if self.ws_enabled {
+                repr.window_scale = Some(match self.state {
+                    // Shift is decided when SYN of remote is received.
+                    State::SynSent => self.rx_shift(),
+                    _ => self.rcv_wnd_shift,
+                });
+            }
+
+            #[cfg(feature = "tcp-sack")]
+            {
+                repr.sack_permitted = self.sack_enabled;
+            }
+        } else {
+            repr.window_len = (window >> self.rcv_wnd_shift) as u16;
+
+            #[cfg(feature = "tcp-sack")]
+            if self.sack_enabled && repr.ack_number.is_some() {
+                repr.sack_ranges = self.sack_ranges();
+            }
+
+            // Options take room of payload, RFC 6691.
+            let mss = self
+                .remote_mss
+                .min(local_mss)
+                .saturating_sub(repr.header_len() - (HEADER_LEN - 20))
+                .max(1);
+
+            let data_start = self.snd_una + syn_len;
+            let offset = seq - data_start;
+            let unsent = self.tx_buffer.len().saturating_sub(offset);
+
+            let window_end = self.snd_una + self.snd_wnd.min(self.congestion.window());
+            let window_left = if window_end > seq {
+                window_end - seq
+            } else {
+                0
+            };
+
+            let mut size = match retransmit {
+                Some((_, len)) => unsent.min(len).min(mss),
+                None => unsent.min(window_left).min(mss),
+            };
+
+            if size == 0 && unsent != 0 && self.snd_wnd == 0 && self.snd_nxt == self.snd_una {
+                match self.timer {
+                    Timer::Idle => {
+                        self.timer = Timer::ZeroWindowProbe {
+                            expires_at: now + self.rtte.rto(),
+                        };
+                    }
+                    Timer::ZeroWindowProbe { .. } if self.timer.expired(now) => {
+                        log::debug!("Send zero window probe.");
+                        self.timer = Timer::Idle;
+                        size = 1;
+                    }
+                    _ => {}
+                }
+            }
+
+            let payload = self.tx_buffer.get_allocated(offset, size);
+
+            if self.fin_queued() && offset <= self.tx_buffer.len() && payload.len() == unsent {
+                repr.control = Control::Fin;
+            }
+
+            repr.payload = payload;
+        }
+
+        let seg_len = repr.segment_len();
+
+        let ack_changed = repr.ack_number.map(SeqNumber) != self.ack_sent;
+        let window_opened = window > self.wnd_sent
+            && window - self.wnd_sent >= local_mss.min(self.rx_buffer.capacity() / 2);
+
+        if seg_len == 0 && !self.ack_needed && !ack_changed && !window_opened {
+            return Ok(false);
+        }
+
+        if !emit(&local, &remote, &repr)? {
+            return Ok(false);
+        }
+
+        if retransmit.is_some() {
+            self.fast_retransmit = false;
+            self.rtte.discard_sample();
+
+            #[cfg(feature = "tcp-sack")]
+            {
+                self.high_rxt = self.high_rxt.max(seq + seg_len);
+            }
+        }
+
+        if seg_len != 0 {
+            let end = seq + seg_len;
+
+            if end > self.snd_max {
+                self.snd_max = end;
+                self.rtte.on_send(now, end);
+            }
+
+            self.snd_nxt = self.snd_nxt.max(end);
+            self.unacked_since.get_or_insert(now);
+
+            if !matches!(self.timer, Timer::Retransmit { .. }) {
+                self.timer = Timer::Retransmit {
+                    expires_at: now + self.rtte.rto(),
+                };
+            }
+        }
+
+        self.ack_sent = repr.ack_number.map(SeqNumber);
+        self.wnd_sent = window;
+        self.ack_needed = false;
+
+        Ok(true)
+    }
+
+    /// Query whether retransmission limits are reached when retransmission timer expires.
+    fn timed_out(&self, now: Instant) -> bool {
+        let user_timed_out = match (self.timeout, self.unacked_since) {
+            (Some(timeout), Some(since)) => now >= since + timeout,
+            _ => false,
+        };
+
+        self.retransmits >= self.max_retransmits || user_timed_out
+    }
+
+    /// Smallest window scale to advertise the whole receive buffer.
+    fn rx_shift(&self) -> u8 {
+        (0..MAX_WND_SHIFT)
+            .find(|shift| self.rx_buffer.capacity() >> shift <= u16::MAX as usize)
+            .unwrap_or(MAX_WND_SHIFT)
+    }
+
+    /// Sequence number and length limit of segment to retransmit in fast recovery.
+    fn retransmit_range(&self) -> Option<(SeqNumber, usize)> {
+        #[cfg(feature = "tcp-sack")]
+        if self.sack_enabled && !self.scoreboard.is_empty() {
+            return self.next_hole();
+        }
+
+        Some((self.snd_una, usize::MAX))
+    }
+
+    /// The first hole below SACK blocks not retransmitted yet, RFC 6675 section 4.
+    #[cfg(feature = "tcp-sack")]
+    fn next_hole(&self) -> Option<(SeqNumber, usize)> {
+        let mut start = 0;
+
+        for (left, right) in self.scoreboard.iter() {
+            let hole_start = (self.snd_una + start).max(self.high_rxt);
+            let hole_end = self.snd_una + left;
+
+            if hole_start < hole_end {
+                return Some((hole_start, hole_end - hole_start));
+            }
+
+            start = right;
+        }
+
+        None
+    }
+
+    /// SACK blocks of out of order data, block of the latest segment is the first, RFC 2018 section 4.
+    #[cfg(feature = "tcp-sack")]
+    fn sack_ranges(&self) -> [Option<(u32, u32)>; MAX_SACK_BLOCKS] {
+        let to_range =
+            |(start, end): (usize, usize)| ((self.rcv_nxt + start).0, (self.rcv_nxt + end).0);
+
+        let latest = self.sack_latest.and_then(|seq| {
+            if seq < self.rcv_nxt {
+                return None;
+            }
+
+            let offset = seq - self.rcv_nxt;
+            self.assembler
+                .iter()
+                .find(|(start, end)| *start <= offset && offset < *end)
+        });
+
+        let others = self.assembler.iter().filter(|range| Some(*range) != latest);
+
+        let mut ranges = [None; MAX_SACK_BLOCKS];
+        for (slot, range) in ranges.iter_mut().zip(latest.into_iter().chain(others)) {
+            *slot = Some(to_range(range));
+        }
+
+        ranges
+    }
+}
+
+/// Clock of timestamps option in milliseconds, RFC 7323 section 5.4.
+fn ts_clock(now: Instant) -> u32 {
+    now.total_millis() as u32
+}
+
+/// Segment without control, acknowledgment and payload.
+fn segment(src_port: u16, dst_port: u16, seq: SeqNumber) -> Repr<'static> {
+    Repr {
+        src_port,
+        dst_port,
+        control: Control::None,
+        seq_number: seq.0,
+        ack_number: None,
+        window_len: 0,
+        window_scale: None,
+        max_seg_size: None,
+        sack_permitted: false,
+        sack_ranges: [None; MAX_SACK_BLOCKS],
+        timestamp: None,
+        payload: &[],
+    }
+}
+
+/// Build RST in reply to a segment not belonging to any connection, RFC 9293 section 3.10.7.1.
+pub(crate) fn rst_reply(repr: &Repr<'_>) -> Repr<'static> {
+    let mut reply = segment(repr.dst_port, repr.src_port, SeqNumber::default());
+    reply.control = Control::Rst;
+
+    match repr.ack_number {
+        Some(ack) => reply.seq_number = ack,
+        None => {
+            let seq = SeqNumber(repr.seq_number) + repr.segment_len();
+            reply.ack_number = Some(seq.0);
+        }
+    }
+
+    reply
+}
+
+fn remote_mss(repr: &Repr<'_>) -> usize {
+    match repr.max_seg_size {
+        Some(mss) if mss != 0 => mss as usize,
+        _ => DEFAULT_MSS,
+    }
+}
+
+/// SipHash-2-4 keyed hasher for initial sequence number.
+struct SipHasher {
+    state: [u64; 4],
+    /// Octets not compressed yet, and count of all octets written.
+    tail: u64,
+    len: usize,
+}
+
+impl SipHasher {
+    fn new(key: u128) -> Self {
+        let (k0, k1) = (key as u64, (key >> 64) as u64);
+
+        Self {
+            state: [
+                k0 ^ 0x736f6d6570736575,
+                k1 ^ 0x646f72616e646f6d,
+                k0 ^ 0x6c7967656e657261,
+                k1 ^ 0x7465646279746573,
+            ],
+            tail: 0,
+            len: 0,
+        }
+    }
+
+    fn rounds(state: &mut [u64; 4], count: usize) {
+        let [v0, v1, v2, v3] = state;
+
+        for _ in 0..count {
+            *v0 = v0.wrapping_add(*v1);
+            *v1 = v1.rotate_left(13) ^ *v0;
+            *v0 = v0.rotate_left(32);
+            *v2 = v2.wrapping_add(*v3);
+            *v3 = v3.rotate_left(16) ^ *v2;
+            *v0 = v0.wrapping_add(*v3);
+            *v3 = v3.rotate_left(21) ^ *v0;
+            *v2 = v2.wrapping_add(*v1);
+            *v1 = v1.rotate_left(17) ^ *v2;
+            *v2 = v2.rotate_left(32);
+        }
+    }
+
+    fn compress(state: &mut [u64; 4], word: u64) {
+        state[3] ^= word;
+        Self::rounds(state, 2);
+        state[0] ^= word;
+    }
+}
+
+impl Hasher for SipHasher {
+    fn finish(&self) -> u64 {
+        let mut state = self.state;
+        Self::compress(&mut state, self.tail | (self.len as u64) << 56);
+
+        state[2] ^= 0xff;
+        Self::rounds(&mut state, 4);
+
+        state.iter().fold(0, |hash, v| hash ^ v)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.tail |= (*byte as u64) << (8 * (self.len % 8));
+            self.len += 1;
+
+            if self.len.is_multiple_of(8) {
+                Self::compress(&mut self.state, self.tail);
+                self.tail = 0;
+            }
+        }
+    }
+}
+
+/// Generate initial sequence number, RFC 6528 section 3.
+///
+/// A 4 microseconds clock plus a keyed hash of connection, so sequence number of a
+/// reused connection is unlikely to fall in the window of an old one, and can not
+/// be guessed by others without `secret`.
+fn initial_seq_number(
+    now: Instant,
+    secret: u128,
+    local: &Endpoint,
+    remote: &Endpoint,
+) -> SeqNumber {
+    let mut hasher = SipHasher::new(secret);
+    local.hash(&mut hasher);
+    remote.hash(&mut hasher);
+
+    let clock = now.total_millis().wrapping_mul(250);
+    SeqNumber(clock.wrapping_add(hasher.finish()) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{vec, vec::Vec};
+
+    use super::*;
+
+    const REMOTE_ISS: SeqNumber = SeqNumber(1000);
+
+    fn local() -> Endpoint {
+        Endpoint::new(layer3::Address::Ipv4([10, 0, 0, 1].into()), 80)
+    }
+
+    fn remote() -> Endpoint {
+        Endpoint::new(layer3::Address::Ipv4([10, 0, 0, 2].into()), 49152)
+    }
+
+    /// Segment from remote with acknowledgment and large window.
+    fn remote_segment(seq: SeqNumber, ack: SeqNumber) -> Repr<'static> {
+        let mut repr = segment(remote().port, local().port, seq);
+        repr.ack_number = Some(ack.0);
+        repr.window_len = u16::MAX;
+        repr
+    }
+
+    /// Dispatch socket with interface of `mtu`, return the segment sent and its payload.
+    fn send(
+        socket: &mut TcpSocket<'_>,
+        now: Instant,
+        mtu: usize,
+    ) -> Option<(Repr<'static>, Vec<u8>)> {
+        let mut sent = None;
+
+        socket
+            .dispatch(now, mtu, 0, |_, _, repr| {
+                let payload = repr.payload.to_vec();
+                sent = Some((
+                    Repr {
+                        payload: &[],
+                        ..*repr
+                    },
+                    payload,
+                ));
+                Ok(true)
+            })
+            .unwrap();
+
+        sent
+    }
+
+    /// Accept connection with SYN of remote on listening `socket`, return SYN-ACK.
+    fn accept(socket: &mut TcpSocket<'_>, syn: &Repr<'_>, mtu: usize) -> Repr<'static> {
+        socket.listen(local().port).unwrap();
+        assert!(socket
+            .process(Instant::ZERO, 0, remote(), local(), syn)
+            .is_none());
+
+        let (syn_ack, _) = send(socket, Instant::ZERO, mtu).unwrap();
+        assert_eq!(syn_ack.control, Control::Syn);
+
+        let mut ack = remote_segment(REMOTE_ISS + 1, SeqNumber(syn_ack.seq_number) + 1);
+        ack.timestamp = syn.timestamp.map(|(tsval, _)| (tsval, 0));
+        assert!(socket
+            .process(Instant::ZERO, 0, remote(), local(), &ack)
+            .is_none());
+        assert_eq!(socket.state(), State::Established);
+
+        syn_ack
+    }
+
+    fn remote_syn() -> Repr<'static> {
+        let mut syn = segment(remote().port, local().port, REMOTE_ISS);
+        syn.control = Control::Syn;
+        syn.window_len = u16::MAX;
+        syn
+    }
+
+    #[test]
+    fn test_window_scale_and_mss() {
+        let mut rx_buffer = vec![0u8; 262144];
+        let mut tx_buffer = vec![0u8; 4096];
+        let mut socket = TcpSocket::new(&mut rx_buffer, &mut tx_buffer);
+
+        let mut syn = remote_syn();
+        syn.window_scale = Some(7);
+        syn.max_seg_size = Some(1000);
+        syn.timestamp = Some((100, 0));
+
+        // MSS of SYN-ACK follows MTU of interface, shift covers the receive buffer.
+        let syn_ack = accept(&mut socket, &syn, 576);
+        assert_eq!(syn_ack.max_seg_size, Some(536));
+        assert_eq!(syn_ack.window_scale, Some(3));
+        assert_eq!((socket.snd_wnd_shift, socket.rcv_wnd_shift), (7, 3));
+
+        // Window of remote is scaled, advertised window is scaled down.
+        let iss = SeqNumber(syn_ack.seq_number);
+        let mut ack = remote_segment(REMOTE_ISS + 1, iss + 1);
+        ack.window_len = 100;
+        ack.timestamp = Some((101, 0));
+        socket.process(Instant::ZERO, 0, remote(), local(), &ack);
+        assert_eq!(socket.snd_wnd, 100 << 7);
+
+        // Segment is limited by MSS of the smaller MTU, less timestamps option.
+        socket.send_slice(&[0u8; 2000]).unwrap();
+        let (repr, payload) = send(&mut socket, Instant::ZERO, 576).unwrap();
+        assert_eq!(repr.window_len as usize, 262144 >> 3);
+        assert!(repr.timestamp.is_some());
+        assert_eq!(payload.len(), 536 - 12);
+
+        // MSS of remote is the limit on a larger MTU.
+        let (_, payload) = send(&mut socket, Instant::ZERO, 1500).unwrap();
+        assert_eq!(payload.len(), 1000 - 12);
+
+        // Options not requested by remote are not used.
+        let mut rx_buffer = vec![0u8; 262144];
+        let mut socket = TcpSocket::new(&mut rx_buffer, &mut tx_buffer);
+        let syn_ack = accept(&mut socket, &remote_syn(), 1500);
+        assert_eq!(syn_ack.max_seg_size, Some(1460));
+        assert_eq!(syn_ack.window_scale, None);
+        assert_eq!(syn_ack.timestamp, None);
+        assert_eq!((socket.snd_wnd_shift, socket.rcv_wnd_shift), (0, 0));
+        assert_eq!(socket.window(), u16::MAX as usize);
+
+        socket.send_slice(&[0u8; 1000]).unwrap();
+        let (_, payload) = send(&mut socket, Instant::ZERO, 1500).unwrap();
+        assert_eq!(payload.len(), DEFAULT_MSS);
+    }
+
+    #[test]
+    fn test_paws() {
+        let mut rx_buffer = [0u8; 1024];
+        let mut tx_buffer = [0u8; 1024];
+        let mut socket = TcpSocket::new(&mut rx_buffer, &mut tx_buffer);
+
+        let mut syn = remote_syn();
+        syn.timestamp = Some((100, 0));
+        let syn_ack = accept(&mut socket, &syn, 1500);
+        let iss = SeqNumber(syn_ack.seq_number);
+        assert_eq!(syn_ack.timestamp, Some((0, 100)));
+
+        // Segment with timestamp older than the recent one is dropped and acknowledged.
+        let mut old = remote_segment(REMOTE_ISS + 1, iss + 1);
+        old.timestamp = Some((99, 0));
+        old.payload = b"old";
+        socket.process(Instant::ZERO, 0, remote(), local(), &old);
+        assert_eq!(socket.recv_queue(), 0);
+
+        let (ack, payload) = send(&mut socket, Instant::ZERO, 1500).unwrap();
+        assert!(payload.is_empty());
+        assert_eq!(ack.ack_number, Some((REMOTE_ISS + 1).0));
+
+        // Newer timestamp is accepted and echoed.
+        let mut new = remote_segment(REMOTE_ISS + 1, iss + 1);
+        new.timestamp = Some((200, 0));
+        new.payload = b"new";
+        socket.process(Instant::ZERO, 0, remote(), local(), &new);
+        assert_eq!(socket.recv_queue(), 3);
+
+        let (ack, _) = send(&mut socket, Instant::ZERO, 1500).unwrap();
+        assert_eq!(ack.ack_number, Some((REMOTE_ISS + 4).0));
+        assert_eq!(ack.timestamp, Some((0, 200)));
+    }
+
+    #[cfg(feature = "tcp-sack")]
+    #[test]
+    fn test_sack() {
+        let mut rx_buffer = [0u8; 4096];
+        let mut tx_buffer = [0u8; 4096];
+        let mut socket = TcpSocket::new(&mut rx_buffer, &mut tx_buffer);
+
+        let mut syn = remote_syn();
+        syn.sack_permitted = true;
+        syn.max_seg_size = Some(500);
+        let syn_ack = accept(&mut socket, &syn, 1500);
+        assert!(syn_ack.sack_permitted);
+        let iss = SeqNumber(syn_ack.seq_number);
+
+        // Out of order segment is reported by SACK block.
+        let mut data = remote_segment(REMOTE_ISS + 101, iss + 1);
+        data.payload = &[1u8; 100];
+        socket.process(Instant::ZERO, 0, remote(), local(), &data);
+
+        let (ack, _) = send(&mut socket, Instant::ZERO, 1500).unwrap();
+        assert_eq!(ack.ack_number, Some((REMOTE_ISS + 1).0));
+        assert_eq!(
+            ack.sack_ranges,
+            [
+                Some(((REMOTE_ISS + 101).0, (REMOTE_ISS + 201).0)),
+                None,
+                None
+            ]
+        );
+
+        // Hole is filled, no more SACK block.
+        let mut data = remote_segment(REMOTE_ISS + 1, iss + 1);
+        data.payload = &[1u8; 100];
+        socket.process(Instant::ZERO, 0, remote(), local(), &data);
+
+        let (ack, _) = send(&mut socket, Instant::ZERO, 1500).unwrap();
+        assert_eq!(ack.ack_number, Some((REMOTE_ISS + 201).0));
+        assert_eq!(ack.sack_ranges, [None; MAX_SACK_BLOCKS]);
+
+        // Send 4 segments of 500 octets, the second and the fourth arrive.
+        socket.send_slice(&[0u8; 2000]).unwrap();
+        let mut seqs = Vec::new();
+        while let Some((repr, payload)) = send(&mut socket, Instant::ZERO, 1500) {
+            assert_eq!(payload.len(), 500);
+            seqs.push(SeqNumber(repr.seq_number));
+        }
+        assert_eq!(seqs.len(), 4);
+
+        let start = seqs[0];
+        let mut dup_ack = remote_segment(REMOTE_ISS + 201, start);
+        dup_ack.sack_ranges = [
+            Some(((start + 1500).0, (start + 2000).0)),
+            Some(((start + 500).0, (start + 1000).0)),
+            None,
+        ];
+
+        for _ in 0..DUP_ACK_THRESHOLD {
+            socket.process(Instant::ZERO, 0, remote(), local(), &dup_ack);
+        }
+
+        // Holes are retransmitted in order, SACKed segments are skipped.
+        let (repr, payload) = send(&mut socket, Instant::ZERO, 1500).unwrap();
+        assert_eq!((SeqNumber(repr.seq_number), payload.len()), (start, 500));
+
+        socket.process(Instant::ZERO, 0, remote(), local(), &dup_ack);
+        let (repr, payload) = send(&mut socket, Instant::ZERO, 1500).unwrap();
+        assert_eq!(
+            (SeqNumber(repr.seq_number), payload.len()),
+            (start + 1000, 500)
+        );
+
+        socket.process(Instant::ZERO, 0, remote(), local(), &dup_ack);
+        assert!(send(&mut socket, Instant::ZERO, 1500).is_none());
+    }
+
+    #[test]
+    fn test_sip_hasher() {
+        // Test vectors of SipHash-2-4 with key 00..0f and message 00..(len - 1).
+        let key = u128::from_le_bytes(core::array::from_fn(|i| i as u8));
+        let message: [u8; 15] = core::array::from_fn(|i| i as u8);
+
+        for (len, hash) in [(0, 0x726fdb47dd0e0e31), (15, 0xa129ca6149be45e5)] {
+            let mut hasher = SipHasher::new(key);
+            hasher.write(&message[..len]);
+            assert_eq!(hasher.finish(), hash);
+        }
+    }
+
+    #[test]
+    fn test_initial_seq_number() {
+        let local = Endpoint::new(layer3::Address::Ipv4([10, 0, 0, 1].into()), 80);
+        let remote = Endpoint::new(layer3::Address::Ipv4([10, 0, 0, 2].into()), 49152);
+        let now = Instant::from_secs(1);
+
+        let iss = initial_seq_number(now, 1, &local, &remote);
+        assert_eq!(iss, initial_seq_number(now, 1, &local, &remote));
+        assert_ne!(iss, initial_seq_number(now, 2, &local, &remote));
+        assert_ne!(iss, initial_seq_number(now, 1, &remote, &local));
+
+        // Clock advances 250 per millisecond.
+        let later = initial_seq_number(now + Duration::from_millis(1), 1, &local, &remote);
+        assert_eq!(later - iss, 250);
+    }
+}
@@ -0,0 +1,121 @@
+use crate::time::{Duration, Instant};
+
+use super::SeqNumber;
+
+/// Lower bound of retransmission timeout, RFC 6298 section 2.4.
+const RTO_MIN: Duration = Duration::from_millis(1000);
+
+/// Upper bound of retransmission timeout.
+const RTO_MAX: Duration = Duration::from_secs(60);
+
+/// Timer of TCP socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Timer {
+    Idle,
+    Retransmit { expires_at: Instant },
+    ZeroWindowProbe { expires_at: Instant },
+    Close { expires_at: Instant },
+}
+
+impl Timer {
+    pub(crate) fn expired(&self, now: Instant) -> bool {
+        match self {
+            Timer::Idle => false,
+            Timer::Retransmit { expires_at }
+            | Timer::ZeroWindowProbe { expires_at }
+            | Timer::Close { expires_at } => now >= *expires_at,
+        }
+    }
+
+    pub(crate) fn expires_at(&self) -> Option<Instant> {
+        match self {
+            Timer::Idle => None,
+            Timer::Retransmit { expires_at }
+            | Timer::ZeroWindowProbe { expires_at }
+            | Timer::Close { expires_at } => Some(*expires_at),
+        }
+    }
+}
+
+/// Round trip time estimator, RFC 6298.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RttEstimator {
+    /// Smoothed round trip time and its variation, in milliseconds.
+    srtt: Option<(u64, u64)>,
+    rto: Duration,
+    /// Time and end of segment being timed.
+    sample: Option<(Instant, SeqNumber)>,
+}
+
+impl Default for RttEstimator {
+    fn default() -> Self {
+        Self {
+            srtt: None,
+            rto: RTO_MIN,
+            sample: None,
+        }
+    }
+}
+
+impl RttEstimator {
+    pub(crate) fn rto(&self) -> Duration {
+        self.rto
+    }
+
+    /// Smoothed round trip time, initial timeout before any sample.
+    pub(crate) fn rtt(&self) -> Duration {
+        match self.srtt {
+            Some((srtt, _)) => Duration::from_millis(srtt),
+            None => self.rto,
+        }
+    }
+
+    /// Start timing a segment ending at `seq` if none is being timed.
+    pub(crate) fn on_send(&mut self, now: Instant, seq: SeqNumber) {
+        if self.sample.is_none() {
+            self.sample = Some((now, seq));
+        }
+    }
+
+    pub(crate) fn on_ack(&mut self, now: Instant, ack: SeqNumber) {
+        if let Some((sent_at, seq)) = self.sample {
+            if ack >= seq {
+                self.sample = None;
+                self.update((now - sent_at).total_millis());
+            }
+        }
+    }
+
+    /// Take a sample measured by timestamp option, RFC 7323 section 4.1.
+    pub(crate) fn on_sample(&mut self, rtt: Duration) {
+        self.sample = None;
+        self.update(rtt.total_millis());
+    }
+
+    /// Discard segment being timed as it is retransmitted, Karn's algorithm.
+    pub(crate) fn discard_sample(&mut self) {
+        self.sample = None;
+    }
+
+    /// Back off timer, segment being timed is discarded by Karn's algorithm.
+    pub(crate) fn on_retransmit(&mut self) {
+        self.sample = None;
+        self.rto = (self.rto * 2).min(RTO_MAX);
+    }
+
+    fn update(&mut self, rtt: u64) {
+        let (srtt, rttvar) = match self.srtt {
+            None => (rtt, rtt / 2),
+            Some((srtt, rttvar)) => {
+                let rttvar = (3 * rttvar + srtt.abs_diff(rtt)) / 4;
+                let srtt = (7 * srtt + rtt) / 8;
+                (srtt, rttvar)
+            }
+        };
+
+        self.srtt = Some((srtt, rttvar));
+
+        let rto = Duration::from_millis(srtt + (4 * rttvar).max(1));
+        self.rto = rto.max(RTO_MIN).min(RTO_MAX);
+    }
+}
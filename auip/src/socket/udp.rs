@@ -0,0 +1,173 @@
+use auip_pkt::layer3;
+
+use crate::{Endpoint, Error, PacketStorage, Result};
+
+/// UDP socket.
+///
+/// Received and sending datagrams are queued in storages provided by caller,
+/// metadata of each datagram is the remote endpoint.
+pub struct UdpSocket<'a> {
+    local: Option<Endpoint>,
+    remote: Option<Endpoint>,
+    rx_storage: &'a mut dyn PacketStorage<Endpoint>,
+    tx_storage: &'a mut dyn PacketStorage<Endpoint>,
+}
+
+impl<'a> UdpSocket<'a> {
+    pub fn new(
+        rx_storage: &'a mut dyn PacketStorage<Endpoint>,
+        tx_storage: &'a mut dyn PacketStorage<Endpoint>,
+    ) -> Self {
+        Self {
+            local: None,
+            remote: None,
+            rx_storage,
+            tx_storage,
+        }
+    }
+
+    /// Bind to `port` on all addresses of interface.
+    pub fn bind(&mut self, port: u16) -> Result<()> {
+        self.bind_endpoint(Endpoint::new(layer3::Address::Unspecified, port))
+    }
+
+    /// Bind to local endpoint, unspecified address means all addresses of interface.
+    pub fn bind_endpoint(&mut self, endpoint: Endpoint) -> Result<()> {
+        if endpoint.port == 0 {
+            return Err(Error::IllegalEndpoint);
+        }
+
+        self.local = Some(endpoint);
+        Ok(())
+    }
+
+    /// Set default remote endpoint, then only datagrams from it are received.
+    pub fn connect(&mut self, remote: Endpoint) -> Result<()> {
+        if self.local.is_none() {
+            return Err(Error::SocketNotBound);
+        }
+
+        if !remote.is_specified() {
+            return Err(Error::IllegalEndpoint);
+        }
+
+        self.remote = Some(remote);
+        Ok(())
+    }
+
+    /// Unbind socket and drop all queued datagrams.
+    pub fn close(&mut self) {
+        self.local = None;
+        self.remote = None;
+
+        while !self.rx_storage.is_empty() {
+            self.rx_storage.pop();
+        }
+
+        while !self.tx_storage.is_empty() {
+            self.tx_storage.pop();
+        }
+    }
+
+    pub fn local_endpoint(&self) -> Option<Endpoint> {
+        self.local
+    }
+
+    pub fn remote_endpoint(&self) -> Option<Endpoint> {
+        self.remote
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.local.is_some()
+    }
+
+    pub fn can_send(&self) -> bool {
+        !self.tx_storage.is_full()
+    }
+
+    pub fn can_recv(&self) -> bool {
+        !self.rx_storage.is_empty()
+    }
+
+    /// Enqueue a datagram to `remote`.
+    pub fn send_to(&mut self, payload: &[u8], remote: Endpoint) -> Result<()> {
+        if self.local.is_none() {
+            return Err(Error::SocketNotBound);
+        }
+
+        if !remote.is_specified() {
+            return Err(Error::IllegalEndpoint);
+        }
+
+        let buffer = self.tx_storage.enqueue(remote, payload.len())?;
+        buffer.copy_from_slice(payload);
+
+        Ok(())
+    }
+
+    /// Enqueue a datagram to connected remote endpoint.
+    pub fn send(&mut self, payload: &[u8]) -> Result<()> {
+        let remote = self.remote.ok_or(Error::SocketNotConnected)?;
+        self.send_to(payload, remote)
+    }
+
+    /// Dequeue a datagram into `buffer`, return its length and remote endpoint.
+    ///
+    /// Datagram longer than `buffer` is truncated.
+    pub fn recv_from(&mut self, buffer: &mut [u8]) -> Result<Option<(usize, Endpoint)>> {
+        if self.local.is_none() {
+            return Err(Error::SocketNotBound);
+        }
+
+        let result = self.rx_storage.peek().map(|(remote, payload)| {
+            let len = payload.len().min(buffer.len());
+            buffer[..len].copy_from_slice(&payload[..len]);
+            (len, *remote)
+        });
+
+        self.rx_storage.pop();
+
+        Ok(result)
+    }
+
+    /// Checking datagram from `src` to `dst` belongs to this socket.
+    pub(crate) fn accepts(&self, src: &Endpoint, dst: &Endpoint) -> bool {
+        let local = match &self.local {
+            Some(local) => local,
+            None => return false,
+        };
+
+        if local.port != dst.port {
+            return false;
+        }
+
+        if local.addr != layer3::Address::Unspecified && local.addr != dst.addr {
+            return false;
+        }
+
+        match &self.remote {
+            Some(remote) => remote == src,
+            None => true,
+        }
+    }
+
+    /// Enqueue received datagram, drop it if storage is full.
+    pub(crate) fn process(&mut self, src: Endpoint, payload: &[u8]) {
+        match self.rx_storage.enqueue(src, payload.len()) {
+            Ok(buffer) => buffer.copy_from_slice(payload),
+            Err(_) => log::debug!("No space for datagram from {:?}, Drop it.", src),
+        }
+    }
+
+    /// Get the oldest datagram to send, with local and remote endpoint.
+    pub(crate) fn peek_tx(&self) -> Option<(Endpoint, Endpoint, &[u8])> {
+        let local = self.local?;
+        let (remote, payload) = self.tx_storage.peek()?;
+        Some((local, *remote, payload))
+    }
+
+    /// Remove the oldest datagram to send.
+    pub(crate) fn pop_tx(&mut self) {
+        self.tx_storage.pop();
+    }
+}
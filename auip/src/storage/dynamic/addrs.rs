@@ -1,15 +1,16 @@
 use alloc::vec::Vec;
 use auip_pkt::{
     layer2,
-    layer3::{self, Cidr},
+    layer3::{self, ipv4, Cidr},
 };
 
-use crate::{AddrsStorage, Error, Result};
+use crate::{multicast_link_addr, AddrsStorage, Error, Result};
 
 #[derive(Default, Debug)]
 pub struct Addrs {
     pub mac_addr: layer2::Address,
     pub ip_addrs: Vec<Cidr>,
+    pub multicast_groups: Vec<ipv4::Address>,
 }
 
 impl AddrsStorage for Addrs {
@@ -24,6 +25,20 @@ impl AddrsStorage for Addrs {
             .binary_search_by_key(ip_addr, |a| *a.address())
             .is_ok()
     }
+
+    fn ip_addrs(&self) -> &[layer3::Cidr] {
+        &self.ip_addrs
+    }
+
+    fn has_multicast_group(&self, group: &ipv4::Address) -> bool {
+        self.multicast_groups.contains(group)
+    }
+
+    fn has_multicast_link_addr(&self, addr: &layer2::Address) -> bool {
+        self.multicast_groups
+            .iter()
+            .any(|group| multicast_link_addr(group) == *addr)
+    }
 }
 
 impl Addrs {
@@ -46,7 +61,25 @@ impl Addrs {
         }
     }
 
-    pub fn ip_addrs(&self) -> &[layer3::Cidr] {
-        &self.ip_addrs
+    pub fn join_multicast_group(&mut self, group: ipv4::Address) -> Result<()> {
+        if !group.is_multicast() {
+            return Err(Error::IllegalMulticastGroup);
+        }
+
+        if !self.has_multicast_group(&group) {
+            self.multicast_groups.push(group);
+        }
+
+        Ok(())
+    }
+
+    pub fn leave_multicast_group(&mut self, group: &ipv4::Address) -> Result<()> {
+        match self.multicast_groups.iter().position(|g| g == group) {
+            Some(pos) => {
+                self.multicast_groups.remove(pos);
+                Ok(())
+            }
+            None => Err(Error::IpAddrNotFound),
+        }
     }
 }
@@ -1,20 +1,29 @@
 use alloc::collections::BTreeMap;
-use auip_pkt::{layer2, layer3};
+use auip_pkt::layer3;
 
-use crate::{ArpStorage, Result};
+use crate::{ArpEntry, ArpStorage, Result};
 
+/// Arp table without limit of entries.
 #[derive(Debug, Default)]
 pub struct Arp {
-    pub map: BTreeMap<layer3::ipv4::Address, layer2::Address>,
+    pub map: BTreeMap<layer3::ipv4::Address, ArpEntry>,
 }
 
 impl ArpStorage for Arp {
-    fn set_map(&mut self, mac: layer2::Address, ip_addr: layer3::ipv4::Address) -> Result<()> {
-        self.map.insert(ip_addr, mac);
+    fn insert(&mut self, ip_addr: layer3::ipv4::Address, entry: ArpEntry) -> Result<()> {
+        self.map.insert(ip_addr, entry);
         Ok(())
     }
 
-    fn mac_addr(&self, ip_addr: &layer3::ipv4::Address) -> Result<Option<layer2::Address>> {
-        Ok(self.map.get(ip_addr).copied())
+    fn get(&self, ip_addr: &layer3::ipv4::Address) -> Option<&ArpEntry> {
+        self.map.get(ip_addr)
+    }
+
+    fn get_mut(&mut self, ip_addr: &layer3::ipv4::Address) -> Option<&mut ArpEntry> {
+        self.map.get_mut(ip_addr)
+    }
+
+    fn remove(&mut self, ip_addr: &layer3::ipv4::Address) -> Option<ArpEntry> {
+        self.map.remove(ip_addr)
     }
 }
@@ -1,46 +1,72 @@
-use alloc::{collections::BTreeMap, vec::Vec};
+use alloc::{vec, vec::Vec};
 
-use crate::{consts::MAX_IP_FRAGMENT_PACKET_LENGTH, IpFragmentBuffer};
+use crate::{consts::MAX_IP_FRAGMENT_PACKET_LENGTH, IpFragmentBuffer, Reassembly};
 
+/// Reassemble at most `max_length` datagrams at the same time.
+///
+/// Buffers grow with received fragments, octets allocated for all buffers are limited.
 pub struct IpFragment {
-    max_length: usize,
-    buffers: Vec<u8>,
-    mapping: BTreeMap<u16, usize>,
-    curser: usize,
+    slots: Vec<Option<Reassembly>>,
+    buffers: Vec<Vec<u8>>,
+    memory_limit: usize,
 }
 
 impl IpFragment {
     pub fn new(max_length: usize) -> Self {
         Self {
-            max_length,
-            buffers: Vec::new(),
-            mapping: BTreeMap::new(),
-            curser: 0,
+            slots: vec![None; max_length],
+            buffers: vec![Vec::new(); max_length],
+            memory_limit: max_length * MAX_IP_FRAGMENT_PACKET_LENGTH,
         }
     }
+
+    /// Set limit of octets allocated for all buffers, default is 64k for each datagram.
+    pub fn set_memory_limit(&mut self, memory_limit: usize) {
+        self.memory_limit = memory_limit;
+    }
+
+    pub fn memory_limit(&self) -> usize {
+        self.memory_limit
+    }
+
+    /// Octets allocated for all buffers, including buffer of a completed datagram not
+    /// released yet.
+    pub fn memory_used(&self) -> usize {
+        self.buffers.iter().map(Vec::capacity).sum()
+    }
 }
 
 impl IpFragmentBuffer for IpFragment {
-    fn get_buffer(&mut self, ident: u16) -> &mut [u8] {
-        let curser = if self.max_length == 1 {
-            0
-        } else if let Some(curser) = self.mapping.get(&ident) {
-            *curser
-        } else {
-            self.curser += 1;
-            if self.curser == self.max_length {
-                self.curser = 0;
+    fn slots(&self) -> &[Option<Reassembly>] {
+        &self.slots
+    }
+
+    fn slots_mut(&mut self) -> &mut [Option<Reassembly>] {
+        &mut self.slots
+    }
+
+    fn buffer(&mut self, index: usize, len: usize) -> Option<&mut [u8]> {
+        let current = self.buffers.get(index)?.capacity();
+
+        if len > current {
+            if self.memory_used() + len - current > self.memory_limit {
+                return None;
             }
-            log::debug!("Current curser is {}, map to {}", self.curser, ident);
-            self.mapping.insert(ident, self.curser);
-            self.curser
-        };
 
-        let begin = curser * MAX_IP_FRAGMENT_PACKET_LENGTH;
-        let end = (curser + 1) * MAX_IP_FRAGMENT_PACKET_LENGTH;
+            let buffer = &mut self.buffers[index];
+            buffer.reserve_exact(len - buffer.len());
+        }
+
+        if len > self.buffers[index].len() {
+            self.buffers[index].resize(len, 0);
+        }
 
-        self.buffers.resize(end, 0);
+        self.buffers[index].get_mut(..len)
+    }
 
-        &mut self.buffers[begin..end]
+    fn release(&mut self, index: usize) {
+        if let Some(buffer) = self.buffers.get_mut(index) {
+            *buffer = Vec::new();
+        }
     }
 }
@@ -6,3 +6,12 @@ pub use arp::*;
 
 mod ip_fragment;
 pub use ip_fragment::*;
+
+mod packet;
+pub use packet::*;
+
+mod route;
+pub use route::*;
+
+mod sockets;
+pub use sockets::*;
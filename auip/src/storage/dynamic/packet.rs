@@ -0,0 +1,52 @@
+use alloc::{collections::VecDeque, vec, vec::Vec};
+
+use crate::{Error, PacketStorage, Result};
+
+/// Ring of at most `capacity` packets.
+pub struct PacketRing<M> {
+    capacity: usize,
+    packets: VecDeque<(M, Vec<u8>)>,
+}
+
+impl<M> PacketRing<M> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            packets: VecDeque::new(),
+        }
+    }
+}
+
+impl<M> PacketStorage<M> for PacketRing<M> {
+    fn enqueue(&mut self, meta: M, len: usize) -> Result<&mut [u8]> {
+        if self.is_full() {
+            return Err(Error::NoSpaceForSocketStorage);
+        }
+
+        self.packets.push_back((meta, vec![0u8; len]));
+
+        let (_, buffer) = self
+            .packets
+            .back_mut()
+            .ok_or(Error::NoSpaceForSocketStorage)?;
+        Ok(buffer)
+    }
+
+    fn peek(&self) -> Option<(&M, &[u8])> {
+        self.packets
+            .front()
+            .map(|(meta, buffer)| (meta, &buffer[..]))
+    }
+
+    fn pop(&mut self) {
+        self.packets.pop_front();
+    }
+
+    fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
+
+    fn is_full(&self) -> bool {
+        self.packets.len() >= self.capacity
+    }
+}
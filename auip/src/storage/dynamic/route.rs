@@ -0,0 +1,38 @@
+use alloc::vec::Vec;
+use auip_pkt::layer3::ipv4;
+
+use crate::{Result, Route, RouteStorage};
+
+/// Routing table grow on demand.
+#[derive(Default, Debug)]
+pub struct Routes {
+    routes: Vec<Route>,
+}
+
+impl RouteStorage for Routes {
+    fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+
+    fn insert(&mut self, route: Route) -> Result<()> {
+        match self
+            .routes
+            .iter_mut()
+            .find(|r| r.cidr == route.cidr && r.gateway == route.gateway)
+        {
+            Some(r) => *r = route,
+            None => self.routes.push(route),
+        }
+
+        Ok(())
+    }
+
+    fn remove(&mut self, cidr: &ipv4::Cidr, gateway: Option<ipv4::Address>) -> Option<Route> {
+        let pos = self
+            .routes
+            .iter()
+            .position(|r| r.cidr == *cidr && r.gateway == gateway)?;
+
+        Some(self.routes.remove(pos))
+    }
+}
@@ -0,0 +1,29 @@
+use alloc::vec::Vec;
+
+use crate::{Result, Socket, SocketSet};
+
+/// Socket set grow on demand.
+#[derive(Default)]
+pub struct Sockets<'a> {
+    slots: Vec<Option<Socket<'a>>>,
+}
+
+impl<'a> SocketSet<'a> for Sockets<'a> {
+    fn slots(&self) -> &[Option<Socket<'a>>] {
+        &self.slots
+    }
+
+    fn slots_mut(&mut self) -> &mut [Option<Socket<'a>>] {
+        &mut self.slots
+    }
+
+    fn insert(&mut self, socket: Socket<'a>) -> Result<usize> {
+        if let Some(pos) = self.slots.iter().position(|s| s.is_none()) {
+            self.slots[pos] = Some(socket);
+            Ok(pos)
+        } else {
+            self.slots.push(Some(socket));
+            Ok(self.slots.len() - 1)
+        }
+    }
+}
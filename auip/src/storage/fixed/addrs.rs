@@ -1,24 +1,31 @@
-use auip_pkt::{layer2, layer3};
+use auip_pkt::{
+    layer2,
+    layer3::{self, ipv4},
+};
 
-use crate::{AddrsStorage, Error, Result};
+use crate::{multicast_link_addr, AddrsStorage, Error, Result};
 
-pub struct Addrs<const IP_ADDR_NUM: usize> {
+pub struct Addrs<const IP_ADDR_NUM: usize, const GROUP_NUM: usize = 4> {
     pub mac_addr: layer2::Address,
     pub ip_addrs: [layer3::Cidr; IP_ADDR_NUM],
+    pub multicast_groups: [Option<ipv4::Address>; GROUP_NUM],
 }
 
-impl<const IP_ADDR_NUM: usize> Default for Addrs<IP_ADDR_NUM> {
+impl<const IP_ADDR_NUM: usize, const GROUP_NUM: usize> Default for Addrs<IP_ADDR_NUM, GROUP_NUM> {
     fn default() -> Self {
         let ip_addrs = [Default::default(); IP_ADDR_NUM];
 
         Self {
             mac_addr: Default::default(),
             ip_addrs,
+            multicast_groups: [None; GROUP_NUM],
         }
     }
 }
 
-impl<const IP_ADDR_NUM: usize> AddrsStorage for Addrs<IP_ADDR_NUM> {
+impl<const IP_ADDR_NUM: usize, const GROUP_NUM: usize> AddrsStorage
+    for Addrs<IP_ADDR_NUM, GROUP_NUM>
+{
     fn mac_addr(&self) -> &layer2::Address {
         &self.mac_addr
     }
@@ -30,9 +37,24 @@ impl<const IP_ADDR_NUM: usize> AddrsStorage for Addrs<IP_ADDR_NUM> {
             .binary_search_by_key(ip_addr, |a| *a.address())
             .is_ok()
     }
+
+    fn ip_addrs(&self) -> &[layer3::Cidr] {
+        &self.ip_addrs
+    }
+
+    fn has_multicast_group(&self, group: &ipv4::Address) -> bool {
+        self.multicast_groups.contains(&Some(*group))
+    }
+
+    fn has_multicast_link_addr(&self, addr: &layer2::Address) -> bool {
+        self.multicast_groups
+            .iter()
+            .flatten()
+            .any(|group| multicast_link_addr(group) == *addr)
+    }
 }
 
-impl<const IP_ADDR_NUM: usize> Addrs<IP_ADDR_NUM> {
+impl<const IP_ADDR_NUM: usize, const GROUP_NUM: usize> Addrs<IP_ADDR_NUM, GROUP_NUM> {
     pub fn set_mac_addr(&mut self, addr: layer2::Address) {
         self.mac_addr = addr;
     }
@@ -57,7 +79,35 @@ impl<const IP_ADDR_NUM: usize> Addrs<IP_ADDR_NUM> {
         }
     }
 
-    pub fn ip_addrs(&self) -> &[layer3::Cidr] {
-        &self.ip_addrs
+    pub fn join_multicast_group(&mut self, group: ipv4::Address) -> Result<()> {
+        if !group.is_multicast() {
+            return Err(Error::IllegalMulticastGroup);
+        }
+
+        if self.has_multicast_group(&group) {
+            return Ok(());
+        }
+
+        match self.multicast_groups.iter_mut().find(|g| g.is_none()) {
+            Some(slot) => {
+                *slot = Some(group);
+                Ok(())
+            }
+            None => Err(Error::NoSpaceForAddrsStorage),
+        }
+    }
+
+    pub fn leave_multicast_group(&mut self, group: &ipv4::Address) -> Result<()> {
+        match self
+            .multicast_groups
+            .iter_mut()
+            .find(|g| **g == Some(*group))
+        {
+            Some(slot) => {
+                *slot = None;
+                Ok(())
+            }
+            None => Err(Error::IpAddrNotFound),
+        }
     }
 }
@@ -1,9 +1,10 @@
-use auip_pkt::{layer2, layer3};
+use auip_pkt::layer3;
 
-use crate::{ArpStorage, Error, Result};
+use crate::{ArpEntry, ArpStorage, Error, Result};
 
+/// Arp table of at most `NUM` entries.
 pub struct Arp<const NUM: usize> {
-    pub map: [Option<(layer3::ipv4::Address, layer2::Address)>; NUM],
+    pub map: [Option<(layer3::ipv4::Address, ArpEntry)>; NUM],
 }
 
 impl<const NUM: usize> Default for Arp<NUM> {
@@ -12,28 +13,89 @@ impl<const NUM: usize> Default for Arp<NUM> {
     }
 }
 
+impl<const NUM: usize> Arp<NUM> {
+    fn position(&self, ip_addr: &layer3::ipv4::Address) -> Option<usize> {
+        self.map
+            .iter()
+            .position(|slot| matches!(slot, Some((addr, _)) if addr == ip_addr))
+    }
+}
+
 impl<const NUM: usize> ArpStorage for Arp<NUM> {
-    fn set_map(&mut self, mac: layer2::Address, ip_addr: layer3::ipv4::Address) -> Result<()> {
-        if let Ok(pos) = self.map.binary_search(&None) {
-            self.map[pos] = Some((ip_addr, mac));
-            Ok(())
-        } else {
-            Err(Error::NoSpaceForArpStorage)
-        }
+    fn insert(&mut self, ip_addr: layer3::ipv4::Address, entry: ArpEntry) -> Result<()> {
+        let pos = self
+            .position(&ip_addr)
+            .or_else(|| self.map.iter().position(|slot| slot.is_none()))
+            .or_else(|| {
+                // Evict the least recently used dynamic entry.
+                self.map
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(pos, slot)| slot.map(|(_, entry)| (pos, entry)))
+                    .filter(|(_, entry)| !entry.is_static())
+                    .min_by_key(|(_, entry)| entry.used_at)
+                    .map(|(pos, _)| pos)
+            })
+            .ok_or(Error::NoSpaceForArpStorage)?;
+
+        self.map[pos] = Some((ip_addr, entry));
+        Ok(())
+    }
+
+    fn get(&self, ip_addr: &layer3::ipv4::Address) -> Option<&ArpEntry> {
+        let pos = self.position(ip_addr)?;
+        self.map[pos].as_ref().map(|(_, entry)| entry)
+    }
+
+    fn get_mut(&mut self, ip_addr: &layer3::ipv4::Address) -> Option<&mut ArpEntry> {
+        let pos = self.position(ip_addr)?;
+        self.map[pos].as_mut().map(|(_, entry)| entry)
+    }
+
+    fn remove(&mut self, ip_addr: &layer3::ipv4::Address) -> Option<ArpEntry> {
+        let pos = self.position(ip_addr)?;
+        self.map[pos].take().map(|(_, entry)| entry)
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use auip_pkt::layer2;
+
+    use super::*;
+    use crate::time::Instant;
+
+    #[test]
+    fn test_lru_eviction() {
+        let ip = |n| layer3::ipv4::Address::new(10, 0, 0, n);
+        let mac = |n| layer2::Address::new(2, 0, 0, 0, 0, n);
+
+        let mut arp = Arp::<3>::default();
+        arp.set_static(mac(1), ip(1)).unwrap();
+        arp.insert(ip(2), ArpEntry::new(mac(2), Instant::from_secs(1)))
+            .unwrap();
+        arp.insert(ip(3), ArpEntry::new(mac(3), Instant::from_secs(2)))
+            .unwrap();
+
+        arp.get_mut(&ip(2)).unwrap().used_at = Instant::from_secs(3);
+
+        // The least recently used dynamic entry is evicted, static entry is kept.
+        arp.insert(ip(4), ArpEntry::new(mac(4), Instant::from_secs(4)))
+            .unwrap();
+        assert!(arp.get(&ip(3)).is_none());
+        assert_eq!(arp.get(&ip(1)).unwrap().mac_addr, mac(1));
+        assert_eq!(arp.get(&ip(2)).unwrap().mac_addr, mac(2));
+        assert_eq!(arp.get(&ip(4)).unwrap().mac_addr, mac(4));
+
+        // Replace entry in place.
+        arp.insert(ip(2), ArpEntry::new(mac(5), Instant::from_secs(5)))
+            .unwrap();
+        assert_eq!(arp.get(&ip(2)).unwrap().mac_addr, mac(5));
 
-    fn mac_addr(&self, ip_addr: &layer3::ipv4::Address) -> Result<Option<layer2::Address>> {
-        if let Ok(pos) = self
-            .map
-            .binary_search_by_key(&Some(*ip_addr), |a| a.map(|(a, _)| a))
-        {
-            if let Some((_, b)) = self.map[pos] {
-                Ok(Some(b))
-            } else {
-                Ok(None)
-            }
-        } else {
-            Ok(None)
-        }
+        let mut arp = Arp::<1>::default();
+        arp.set_static(mac(1), ip(1)).unwrap();
+        assert!(arp.set_static(mac(2), ip(2)).is_err());
+        assert!(arp.remove(&ip(1)).is_some());
+        assert!(arp.get(&ip(1)).is_none());
     }
 }
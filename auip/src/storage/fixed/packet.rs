@@ -0,0 +1,61 @@
+use crate::{utils::FixedBytes, Error, PacketStorage, Result};
+
+/// Ring of `N` packets, each packet at most `LEN` octets.
+pub struct PacketRing<M, const N: usize, const LEN: usize> {
+    metas: [Option<(M, usize)>; N],
+    buffers: [FixedBytes<LEN>; N],
+    begin: usize,
+    len: usize,
+}
+
+impl<M: Copy, const N: usize, const LEN: usize> Default for PacketRing<M, N, LEN> {
+    fn default() -> Self {
+        assert!(N > 0, "Packet ring must at least 1");
+
+        Self {
+            metas: [None; N],
+            buffers: [Default::default(); N],
+            begin: 0,
+            len: 0,
+        }
+    }
+}
+
+impl<M, const N: usize, const LEN: usize> PacketStorage<M> for PacketRing<M, N, LEN> {
+    fn enqueue(&mut self, meta: M, len: usize) -> Result<&mut [u8]> {
+        if self.len == N || len > LEN {
+            return Err(Error::NoSpaceForSocketStorage);
+        }
+
+        let pos = (self.begin + self.len) % N;
+        self.metas[pos] = Some((meta, len));
+        self.len += 1;
+
+        Ok(&mut self.buffers[pos].0[..len])
+    }
+
+    fn peek(&self) -> Option<(&M, &[u8])> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let (meta, len) = self.metas[self.begin].as_ref()?;
+        Some((meta, &self.buffers[self.begin].0[..*len]))
+    }
+
+    fn pop(&mut self) {
+        if self.len != 0 {
+            self.metas[self.begin] = None;
+            self.begin = (self.begin + 1) % N;
+            self.len -= 1;
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn is_full(&self) -> bool {
+        self.len == N
+    }
+}
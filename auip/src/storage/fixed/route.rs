@@ -0,0 +1,93 @@
+use auip_pkt::layer3::ipv4;
+
+use crate::{Error, Result, Route, RouteStorage};
+
+/// Routing table holding at most `NUM` routes.
+pub struct Routes<const NUM: usize> {
+    routes: [Route; NUM],
+    len: usize,
+}
+
+impl<const NUM: usize> Default for Routes<NUM> {
+    fn default() -> Self {
+        Self {
+            routes: [Route::default(); NUM],
+            len: 0,
+        }
+    }
+}
+
+impl<const NUM: usize> Routes<NUM> {
+    fn position(&self, cidr: &ipv4::Cidr, gateway: Option<ipv4::Address>) -> Option<usize> {
+        self.routes()
+            .iter()
+            .position(|route| route.cidr == *cidr && route.gateway == gateway)
+    }
+}
+
+impl<const NUM: usize> RouteStorage for Routes<NUM> {
+    fn routes(&self) -> &[Route] {
+        &self.routes[..self.len]
+    }
+
+    fn insert(&mut self, route: Route) -> Result<()> {
+        if let Some(pos) = self.position(&route.cidr, route.gateway) {
+            self.routes[pos] = route;
+        } else if self.len < NUM {
+            self.routes[self.len] = route;
+            self.len += 1;
+        } else {
+            return Err(Error::NoSpaceForRouteStorage);
+        }
+
+        Ok(())
+    }
+
+    fn remove(&mut self, cidr: &ipv4::Cidr, gateway: Option<ipv4::Address>) -> Option<Route> {
+        let pos = self.position(cidr, gateway)?;
+        let route = self.routes[pos];
+
+        self.routes[pos..self.len].rotate_left(1);
+        self.len -= 1;
+
+        Some(route)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        let gateway0 = ipv4::Address::new(10, 0, 0, 1);
+        let gateway1 = ipv4::Address::new(10, 0, 0, 2);
+        let subnet = ipv4::Cidr::new(ipv4::Address::new(172, 16, 0, 0), 16);
+        let host = ipv4::Address::new(172, 16, 1, 1);
+
+        let mut routes = Routes::<3>::default();
+        assert_eq!(routes.lookup(&host), None);
+
+        routes.insert(Route::default_via(gateway0, 0)).unwrap();
+        routes.insert(Route::via(subnet, gateway0, 10)).unwrap();
+        routes.insert(Route::via(subnet, gateway1, 5)).unwrap();
+        assert!(routes.insert(Route::default_via(gateway1, 0)).is_err());
+
+        // Longest prefix, then lower metric.
+        assert_eq!(routes.lookup(&host), Some(Route::via(subnet, gateway1, 5)));
+        assert_eq!(
+            routes.lookup(&ipv4::Address::new(8, 8, 8, 8)),
+            Some(Route::default_via(gateway0, 0))
+        );
+
+        // Replace route via the same gateway.
+        routes.insert(Route::via(subnet, gateway0, 1)).unwrap();
+        assert_eq!(routes.routes().len(), 3);
+        assert_eq!(routes.lookup(&host), Some(Route::via(subnet, gateway0, 1)));
+
+        assert!(routes.remove(&subnet, Some(gateway0)).is_some());
+        assert!(routes.remove(&subnet, Some(gateway0)).is_none());
+        assert_eq!(routes.lookup(&host), Some(Route::via(subnet, gateway1, 5)));
+        assert_eq!(routes.routes().len(), 2);
+    }
+}
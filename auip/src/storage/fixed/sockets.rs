@@ -0,0 +1,91 @@
+use crate::{Error, Result, Socket, SocketSet};
+
+/// Socket set of at most `N` sockets.
+pub struct Sockets<'a, const N: usize> {
+    slots: [Option<Socket<'a>>; N],
+}
+
+impl<'a, const N: usize> Default for Sockets<'a, N> {
+    fn default() -> Self {
+        Self {
+            slots: core::array::from_fn(|_| None),
+        }
+    }
+}
+
+impl<'a, const N: usize> SocketSet<'a> for Sockets<'a, N> {
+    fn slots(&self) -> &[Option<Socket<'a>>] {
+        &self.slots
+    }
+
+    fn slots_mut(&mut self) -> &mut [Option<Socket<'a>>] {
+        &mut self.slots
+    }
+
+    fn insert(&mut self, socket: Socket<'a>) -> Result<usize> {
+        let pos = self
+            .slots
+            .iter()
+            .position(|s| s.is_none())
+            .ok_or(Error::NoSpaceForSocketSet)?;
+
+        self.slots[pos] = Some(socket);
+        Ok(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use auip_pkt::layer3::{self, Protocol};
+
+    use super::*;
+    use crate::{storage::fixed::PacketRing, AnySocket, IcmpSocket, RawSocket, SocketHandle};
+
+    #[test]
+    fn test_sockets() {
+        let mut raw_rx = PacketRing::<(), 1, 16>::default();
+        let mut raw_tx = PacketRing::<(), 1, 16>::default();
+        let mut icmp_rx = PacketRing::<layer3::Address, 1, 16>::default();
+        let mut icmp_tx = PacketRing::<layer3::Address, 1, 16>::default();
+        let mut other_rx = PacketRing::<(), 1, 16>::default();
+        let mut other_tx = PacketRing::<(), 1, 16>::default();
+        let mut reuse_rx = PacketRing::<(), 1, 16>::default();
+        let mut reuse_tx = PacketRing::<(), 1, 16>::default();
+
+        let mut sockets = Sockets::<2>::default();
+        let raw = sockets
+            .add(RawSocket::new(Protocol::Udp, &mut raw_rx, &mut raw_tx))
+            .unwrap();
+        let icmp = sockets
+            .add(IcmpSocket::new(&mut icmp_rx, &mut icmp_tx))
+            .unwrap();
+        assert_eq!((raw.index(), icmp.index()), (0, 1));
+
+        let other = RawSocket::new(Protocol::Tcp, &mut other_rx, &mut other_tx);
+        assert!(matches!(
+            sockets.insert(other.upcast()),
+            Err(Error::NoSpaceForSocketSet)
+        ));
+
+        assert_eq!(sockets.get(raw).unwrap().protocol(), Protocol::Udp);
+        sockets.get_mut(icmp).unwrap().bind(1).unwrap();
+        assert_eq!(sockets.get(icmp).unwrap().ident(), Some(1));
+
+        // Handle of other socket type get nothing and remove nothing.
+        let mismatch = SocketHandle::<IcmpSocket>::new(raw.index());
+        assert!(sockets.get(mismatch).is_none());
+        assert!(sockets.get_mut(mismatch).is_none());
+        assert!(sockets.remove(mismatch).is_none());
+        assert!(sockets.get(raw).is_some());
+
+        // Slot is freed by remove and reused.
+        assert!(matches!(sockets.remove(raw), Some(Socket::Raw(_))));
+        assert!(sockets.get(raw).is_none());
+        assert!(sockets.remove(raw).is_none());
+
+        let other = RawSocket::new(Protocol::Tcp, &mut reuse_rx, &mut reuse_tx);
+        let other = sockets.add(other).unwrap();
+        assert_eq!(other.index(), 0);
+        assert_eq!(sockets.get(other).unwrap().protocol(), Protocol::Tcp);
+    }
+}
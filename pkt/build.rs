@@ -0,0 +1,4 @@
+fn main() {
+    // Set by cargo-fuzz, skips checksum verification.
+    println!("cargo:rustc-check-cfg=cfg(fuzzing)");
+}
@@ -3,16 +3,36 @@
 pub enum Error {
     WrongLengthForEthernetAddress,
     WrongLengthForIpv4Address,
+    WrongLengthForIpv6Address,
     WrongLengthForArpPacket,
     WrongLengthForIpv4Packet,
+    WrongLengthForIpv4Option,
+    WrongLengthForIpv6Packet,
+    WrongLengthForIpv6ExtHeader,
+    WrongLengthForIpv6Option,
+    WrongLengthForNdpOption,
+    WrongLengthForMldRecord,
+    WrongLengthForTcpPacket,
+    WrongLengthForTcpOption,
     WrongLengthForEthernetPacket,
+    WrongLengthForLlcPacket,
     WrongLengthForBufferLength,
+    WrongLengthForPcap,
+    UnknownPcapFormat,
+    PcapSinkFull,
     UnknownIpVersionNumber,
     IllegalNetmask,
     ParseMacAddressFailed,
     ParseIpv4CidrFailed,
     ParseIpv4AddressFailed,
+    ParseIpv6AddressFailed,
     SrcAndDstMustSame,
+    WrongChecksum,
+    IllegalTcpFlags,
+    UnsupportedArpType,
+    Ipv6HopByHopNotFirst,
+    Ipv6ExtHeaderRepeated,
+    TooManyIpv6ExtHeaders,
     ParseIntError(core::num::ParseIntError),
 }
 
@@ -2,7 +2,7 @@
 
 use crate::{prelude::IntoInner, Error, Result};
 
-use super::{consts, Address, Layer3Protocol, Protocol, VlanId};
+use super::{is_tpid, Address, Layer3Protocol, Protocol, VlanTag};
 use byteorder::{ByteOrder, NetworkEndian};
 use core::fmt::{self, Display};
 
@@ -27,6 +27,15 @@ pub mod field {
         pub const PAYLOAD: Rest = 14..;
     }
 
+    pub mod ieee8023 {
+        use crate::utils::field::Field;
+
+        /// Payload of IEEE802.3 frame, padding after length is excluded.
+        pub fn payload(length: u16) -> Field {
+            super::ethernetii::PAYLOAD.start..super::ethernetii::PAYLOAD.start + length as usize
+        }
+    }
+
     pub mod ieee8021q {
         use crate::utils::field::{Field, Rest};
 
@@ -91,15 +100,33 @@ impl<T: AsRef<[u8]>> Packet<T> {
     }
 
     fn check_len(&self) -> Result<()> {
-        let len = self.buffer.as_ref().len();
+        let data = self.buffer.as_ref();
+        let len = data.len();
 
-        let header_len = self.header_len();
+        if len < field::ethernetii::PAYLOAD.start {
+            return Err(Error::WrongLengthForEthernetPacket);
+        }
 
-        if len < header_len {
-            Err(Error::WrongLengthForEthernetPacket)
-        } else {
-            Ok(())
+        if is_tpid(NetworkEndian::read_u16(&data[field::ETHERTYPE])) {
+            if len < field::ieee8021q::PAYLOAD.start {
+                return Err(Error::WrongLengthForEthernetPacket);
+            }
+
+            if is_tpid(NetworkEndian::read_u16(&data[field::ieee8021q::ETHERTYPE]))
+                && len < field::qinq::PAYLOAD.start
+            {
+                return Err(Error::WrongLengthForEthernetPacket);
+            }
         }
+
+        // Length field of IEEE802.3 frame must not exceed the buffer.
+        if let Protocol::Length(length) = self.protocol() {
+            if len < field::ethernetii::PAYLOAD.start + length as usize {
+                return Err(Error::WrongLengthForEthernetPacket);
+            }
+        }
+
+        Ok(())
     }
 
     pub fn protocol(&self) -> Protocol {
@@ -107,19 +134,19 @@ impl<T: AsRef<[u8]>> Packet<T> {
 
         let ty = NetworkEndian::read_u16(&data[field::ETHERTYPE]);
 
-        if ty == consts::IEEE802_1Q {
-            let ty = NetworkEndian::read_u16(&data[field::ieee8021q::ETHERTYPE]);
+        if is_tpid(ty) {
+            let tag = VlanTag::from_bytes_unchecked(ty, &data[field::ieee8021q::PRI_CFI_VID]);
 
-            let vlanid = VlanId::from_bytes_unchecked(&data[field::ieee8021q::PRI_CFI_VID]);
+            let ty = NetworkEndian::read_u16(&data[field::ieee8021q::ETHERTYPE]);
 
-            if ty == consts::IEEE802_1Q {
-                let vlanid1 = VlanId::from_bytes_unchecked(&data[field::qinq::PRI_CFI_VID]);
+            if is_tpid(ty) {
+                let tag1 = VlanTag::from_bytes_unchecked(ty, &data[field::qinq::PRI_CFI_VID]);
 
                 let layer3_raw = NetworkEndian::read_u16(&data[field::qinq::ETHERTYPE]);
 
-                Protocol::QinQ(vlanid, vlanid1, Layer3Protocol::from(layer3_raw))
+                Protocol::QinQ(tag, tag1, Layer3Protocol::from(layer3_raw))
             } else {
-                Protocol::IEEE8021Q(vlanid, Layer3Protocol::from(ty))
+                Protocol::IEEE8021Q(tag, Layer3Protocol::from(ty))
             }
         } else {
             Protocol::from(ty)
@@ -137,9 +164,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     }
 
     pub fn payload_len(&self) -> usize {
-        let len = self.header_len();
-
-        self.buffer.as_ref().len() - len
+        self.payload().len()
     }
 
     #[inline]
@@ -164,6 +189,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
         match protocol {
             Protocol::IEEE8021Q(_, _) => &inner[field::ieee8021q::PAYLOAD],
             Protocol::QinQ(_, _, _) => &inner[field::qinq::PAYLOAD],
+            Protocol::Length(length) => &inner[field::ieee8023::payload(length)],
             _ => &inner[field::ethernetii::PAYLOAD],
         }
     }
@@ -175,17 +201,14 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
         let data = self.buffer.as_mut();
 
         match &protocol {
-            Protocol::IEEE8021Q(vlanid, l3) => {
-                NetworkEndian::write_u16(&mut data[field::ieee8021q::PRI_CFI_VID], vlanid.0);
+            Protocol::IEEE8021Q(tag, l3) => {
+                NetworkEndian::write_u16(&mut data[field::ieee8021q::PRI_CFI_VID], tag.tci());
                 NetworkEndian::write_u16(&mut data[field::ieee8021q::ETHERTYPE], l3.into());
             }
-            Protocol::QinQ(vlanid, vlanid1, l3) => {
-                NetworkEndian::write_u16(&mut data[field::ieee8021q::PRI_CFI_VID], vlanid.0);
-                NetworkEndian::write_u16(
-                    &mut data[field::ieee8021q::ETHERTYPE],
-                    (&protocol).into(),
-                );
-                NetworkEndian::write_u16(&mut data[field::qinq::PRI_CFI_VID], vlanid1.0);
+            Protocol::QinQ(tag, tag1, l3) => {
+                NetworkEndian::write_u16(&mut data[field::ieee8021q::PRI_CFI_VID], tag.tci());
+                NetworkEndian::write_u16(&mut data[field::ieee8021q::ETHERTYPE], tag1.tpid);
+                NetworkEndian::write_u16(&mut data[field::qinq::PRI_CFI_VID], tag1.tci());
                 NetworkEndian::write_u16(&mut data[field::qinq::ETHERTYPE], l3.into());
             }
             _ => {}
@@ -194,6 +217,52 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
         NetworkEndian::write_u16(&mut data[field::ETHERTYPE], protocol.into())
     }
 
+    /// Insert a tag as the outermost tag of frame, shift the rest of frame.
+    ///
+    /// `frame_len` is the length of frame in buffer, return the new length of frame.
+    pub fn insert_tag(&mut self, tag: VlanTag, frame_len: usize) -> Result<usize> {
+        let data = self.buffer.as_mut();
+        let new_len = frame_len + VlanTag::LEN;
+
+        if frame_len < field::ETHERTYPE.end || data.len() < new_len {
+            return Err(Error::WrongLengthForEthernetPacket);
+        }
+
+        data.copy_within(
+            field::ETHERTYPE.start..frame_len,
+            field::ETHERTYPE.start + VlanTag::LEN,
+        );
+        NetworkEndian::write_u16(&mut data[field::ETHERTYPE], tag.tpid);
+        NetworkEndian::write_u16(&mut data[field::ieee8021q::PRI_CFI_VID], tag.tci());
+
+        Ok(new_len)
+    }
+
+    /// Remove the outermost tag of frame, shift the rest of frame.
+    ///
+    /// `frame_len` is the length of frame in buffer, return the tag and the new length
+    /// of frame, or `None` if frame is untagged.
+    pub fn strip_tag(&mut self, frame_len: usize) -> Option<(VlanTag, usize)> {
+        let data = self.buffer.as_mut();
+
+        if frame_len < field::ieee8021q::ETHERTYPE.end || data.len() < frame_len {
+            return None;
+        }
+
+        let tpid = NetworkEndian::read_u16(&data[field::ETHERTYPE]);
+        if !is_tpid(tpid) {
+            return None;
+        }
+
+        let tag = VlanTag::from_bytes_unchecked(tpid, &data[field::ieee8021q::PRI_CFI_VID]);
+        data.copy_within(
+            field::ieee8021q::ETHERTYPE.start..frame_len,
+            field::ETHERTYPE.start,
+        );
+
+        Some((tag, frame_len - VlanTag::LEN))
+    }
+
     #[inline]
     pub fn set_dest_addr(&mut self, addr: Address) {
         let data = self.buffer.as_mut();
@@ -215,7 +284,132 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
         match protocol {
             Protocol::IEEE8021Q(_, _) => &mut inner[field::ieee8021q::PAYLOAD],
             Protocol::QinQ(_, _, _) => &mut inner[field::qinq::PAYLOAD],
+            Protocol::Length(length) => &mut inner[field::ieee8023::payload(length)],
             _ => &mut inner[field::ethernetii::PAYLOAD],
         }
     }
 }
+
+/// High-level representation of ethernet header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repr {
+    pub src_addr: Address,
+    pub dst_addr: Address,
+    pub protocol: Protocol,
+}
+
+impl Repr {
+    /// Parse an ethernet packet and return a high-level representation.
+    pub fn parse<T: AsRef<[u8]>>(packet: &Packet<T>) -> Result<Repr> {
+        packet.check_len()?;
+
+        Ok(Repr {
+            src_addr: packet.src_addr(),
+            dst_addr: packet.dest_addr(),
+            protocol: packet.protocol(),
+        })
+    }
+
+    /// Return the length of header that will be emitted from this representation.
+    pub fn buffer_len(&self) -> usize {
+        match self.protocol {
+            Protocol::IEEE8021Q(_, _) => field::ieee8021q::PAYLOAD.start,
+            Protocol::QinQ(_, _, _) => field::qinq::PAYLOAD.start,
+            _ => field::ethernetii::PAYLOAD.start,
+        }
+    }
+
+    /// Emit this representation into an ethernet packet.
+    pub fn emit<T: AsRef<[u8]> + AsMut<[u8]>>(&self, packet: &mut Packet<T>) {
+        packet.set_src_addr(self.src_addr);
+        packet.set_dest_addr(self.dst_addr);
+        packet.set_protocol(self.protocol.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layer2::{consts, VlanId};
+
+    #[test]
+    fn test_repr() {
+        let src_addr = Address::new(0x02, 0, 0, 0, 0, 1);
+        let dst_addr = Address::new(0x02, 0, 0, 0, 0, 2);
+        let ctag = VlanTag::new(VlanId(100));
+        let stag = VlanTag::service(VlanId(200));
+
+        let protocols = [
+            (Protocol::Layer3Protocol(Layer3Protocol::ARP), 14),
+            (Protocol::IEEE8021Q(ctag, Layer3Protocol::IPv4), 18),
+            (Protocol::QinQ(stag, ctag, Layer3Protocol::IPv6), 22),
+        ];
+
+        for (protocol, header_len) in protocols.iter() {
+            let repr = Repr {
+                src_addr,
+                dst_addr,
+                protocol: protocol.clone(),
+            };
+            assert_eq!(repr.buffer_len(), *header_len);
+
+            let mut bytes = [0u8; 64];
+            let mut pkt = Packet::new_unchecked(&mut bytes[..]);
+            repr.emit(&mut pkt);
+
+            let pkt = Packet::new_checked(&bytes[..]).unwrap();
+            assert_eq!(Repr::parse(&pkt).unwrap(), repr);
+            assert_eq!(pkt.payload().len(), 64 - header_len);
+        }
+    }
+
+    #[test]
+    fn test_service_tag_and_strip() {
+        let mut bytes = [0u8; 64];
+        let mut pkt = Packet::new_unchecked(&mut bytes[..]);
+        pkt.set_protocol(Protocol::Layer3Protocol(Layer3Protocol::IPv4));
+        pkt.as_mut()[14..18].copy_from_slice(&[0x45, 0, 0, 20]);
+        let len = 18;
+
+        let ctag = VlanTag {
+            tpid: consts::IEEE802_1Q,
+            pcp: 5,
+            dei: true,
+            vid: VlanId(100),
+        };
+        let stag = VlanTag::service(VlanId(200));
+
+        let len = pkt.insert_tag(ctag, len).unwrap();
+        let len = pkt.insert_tag(stag, len).unwrap();
+        assert_eq!(len, 26);
+        assert_eq!(
+            pkt.protocol(),
+            Protocol::QinQ(stag, ctag, Layer3Protocol::IPv4)
+        );
+        assert_eq!(&pkt.payload()[..4], &[0x45, 0, 0, 20]);
+
+        let (tag, len) = pkt.strip_tag(len).unwrap();
+        assert_eq!(tag, stag);
+        assert_eq!(
+            pkt.protocol(),
+            Protocol::IEEE8021Q(ctag, Layer3Protocol::IPv4)
+        );
+
+        let (tag, len) = pkt.strip_tag(len).unwrap();
+        assert_eq!(tag, ctag);
+        assert_eq!(len, 18);
+        assert_eq!(
+            pkt.protocol(),
+            Protocol::Layer3Protocol(Layer3Protocol::IPv4)
+        );
+        assert!(pkt.strip_tag(len).is_none());
+
+        // Legacy 0x9100 outer tag is recognised.
+        bytes[12..14].copy_from_slice(&[0x91, 0x00]);
+        let pkt = Packet::new_unchecked(&bytes[..]);
+        match pkt.protocol() {
+            Protocol::IEEE8021Q(tag, _) => assert_eq!(tag.tpid, consts::QINQ_LEGACY),
+            p => panic!("unexpected protocol {:?}", p),
+        }
+    }
+}
@@ -0,0 +1,231 @@
+//! IEEE 802.2 LLC and SNAP header.
+
+use core::fmt::{self, Display};
+
+use byteorder::{ByteOrder, NetworkEndian};
+
+use crate::{prelude::IntoInner, Error, Result};
+
+use super::Layer3Protocol;
+
+pub mod consts {
+    /// DSAP and SSAP of SNAP.
+    pub const SAP_SNAP: u8 = 0xAA;
+
+    /// Unnumbered information control field.
+    pub const CONTROL_UI: u8 = 0x03;
+
+    /// OUI of RFC 1042 encapsulation, protocol id is an ethertype.
+    pub const OUI_ETHERTYPE: [u8; 3] = [0, 0, 0];
+}
+
+pub mod field {
+    use crate::utils::field::Field;
+
+    pub const DSAP: usize = 0;
+    pub const SSAP: usize = 1;
+    pub const CONTROL: usize = 2;
+
+    /// Header length with unnumbered format control field.
+    pub const HEADER_LEN_U: usize = 3;
+    /// Header length with information or supervisory format control field.
+    pub const HEADER_LEN_IS: usize = 4;
+
+    pub const SNAP_OUI: Field = 3..6;
+    pub const SNAP_PID: Field = 6..8;
+    pub const SNAP_HEADER_LEN: usize = SNAP_PID.end;
+}
+
+/// SNAP header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Snap {
+    pub oui: [u8; 3],
+    pub protocol_id: u16,
+}
+
+impl Snap {
+    /// Create SNAP header for RFC 1042 encapsulated ethertype.
+    pub fn ethertype(protocol: Layer3Protocol) -> Self {
+        Self {
+            oui: consts::OUI_ETHERTYPE,
+            protocol_id: protocol.into(),
+        }
+    }
+
+    /// Return the layer3 protocol if protocol id is an ethertype.
+    pub fn layer3_protocol(&self) -> Option<Layer3Protocol> {
+        if self.oui == consts::OUI_ETHERTYPE {
+            Some(Layer3Protocol::from(self.protocol_id))
+        } else {
+            None
+        }
+    }
+}
+
+/// LLC packet, payload of IEEE802.3 frame.
+#[derive(Debug, Clone)]
+pub struct Packet<T> {
+    buffer: T,
+}
+
+impl<T: AsRef<[u8]>> Display for Packet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("LLC Packet:")?;
+        f.write_fmt(format_args!(
+            "DSAP: {:#04x}, SSAP: {:#04x}, Control: {:#04x}, SNAP: {:?}",
+            self.dsap(),
+            self.ssap(),
+            self.control(),
+            self.snap(),
+        ))
+    }
+}
+
+impl<T> IntoInner for Packet<T> {
+    type Inner = T;
+
+    fn into_inner(self) -> Self::Inner {
+        self.buffer
+    }
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    /// new unchecked packet.
+    pub fn new_unchecked(buffer: T) -> Packet<T> {
+        Packet { buffer }
+    }
+
+    /// new checked packet.
+    pub fn new_checked(buffer: T) -> Result<Packet<T>> {
+        let packet = Self::new_unchecked(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+
+        if len < field::HEADER_LEN_U || len < self.header_len() {
+            Err(Error::WrongLengthForLlcPacket)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[inline]
+    pub fn dsap(&self) -> u8 {
+        self.buffer.as_ref()[field::DSAP]
+    }
+
+    #[inline]
+    pub fn ssap(&self) -> u8 {
+        self.buffer.as_ref()[field::SSAP]
+    }
+
+    /// Return the first octet of control field.
+    #[inline]
+    pub fn control(&self) -> u8 {
+        self.buffer.as_ref()[field::CONTROL]
+    }
+
+    /// Query whether this packet carry SNAP header.
+    pub fn is_snap(&self) -> bool {
+        self.dsap() == consts::SAP_SNAP
+            && self.ssap() == consts::SAP_SNAP
+            && self.control() == consts::CONTROL_UI
+    }
+
+    /// Return the header length, including SNAP header.
+    pub fn header_len(&self) -> usize {
+        if self.is_snap() {
+            field::SNAP_HEADER_LEN
+        } else if self.control() & 0x03 == 0x03 {
+            field::HEADER_LEN_U
+        } else {
+            field::HEADER_LEN_IS
+        }
+    }
+
+    /// Return the SNAP header.
+    pub fn snap(&self) -> Option<Snap> {
+        if self.is_snap() {
+            let data = self.buffer.as_ref();
+            let mut oui = [0u8; 3];
+            oui.copy_from_slice(&data[field::SNAP_OUI]);
+
+            Some(Snap {
+                oui,
+                protocol_id: NetworkEndian::read_u16(&data[field::SNAP_PID]),
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        let data = self.buffer.as_ref();
+        &data[self.header_len()..]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    #[inline]
+    pub fn set_dsap(&mut self, value: u8) {
+        self.buffer.as_mut()[field::DSAP] = value
+    }
+
+    #[inline]
+    pub fn set_ssap(&mut self, value: u8) {
+        self.buffer.as_mut()[field::SSAP] = value
+    }
+
+    /// Set the first octet of control field.
+    #[inline]
+    pub fn set_control(&mut self, value: u8) {
+        self.buffer.as_mut()[field::CONTROL] = value
+    }
+
+    /// Set SAP and control field for SNAP, and write SNAP header.
+    pub fn set_snap(&mut self, snap: Snap) {
+        self.set_dsap(consts::SAP_SNAP);
+        self.set_ssap(consts::SAP_SNAP);
+        self.set_control(consts::CONTROL_UI);
+
+        let data = self.buffer.as_mut();
+        data[field::SNAP_OUI].copy_from_slice(&snap.oui);
+        NetworkEndian::write_u16(&mut data[field::SNAP_PID], snap.protocol_id);
+    }
+
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let header_len = self.header_len();
+        let data = self.buffer.as_mut();
+        &mut data[header_len..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layer2::{ethernet, Protocol};
+
+    #[test]
+    fn test_snap_ipv4() {
+        let mut bytes = [0u8; 64];
+        bytes[12..14].copy_from_slice(&[0x00, 0x0c]);
+        bytes[14..22].copy_from_slice(&[0xaa, 0xaa, 0x03, 0x00, 0x00, 0x00, 0x08, 0x00]);
+        bytes[22..26].copy_from_slice(&[0x45, 0x00, 0x00, 0x04]);
+
+        let eth = ethernet::Packet::new_checked(&bytes[..]).unwrap();
+        assert_eq!(eth.protocol(), Protocol::Length(12));
+        assert_eq!(eth.payload().len(), 12);
+
+        let llc = Packet::new_checked(eth.payload()).unwrap();
+        let snap = llc.snap().unwrap();
+        assert_eq!(snap.layer3_protocol(), Some(Layer3Protocol::IPv4));
+        assert_eq!(llc.payload(), &[0x45, 0x00, 0x00, 0x04]);
+
+        // Length larger than buffer.
+        bytes[12..14].copy_from_slice(&[0x05, 0xdc]);
+        assert!(ethernet::Packet::new_checked(&bytes[..]).is_err());
+    }
+}
@@ -5,5 +5,10 @@ pub use address::*;
 
 pub mod ethernet;
 
+pub mod llc;
+
 mod protocol;
 pub use protocol::*;
+
+mod vlan;
+pub use vlan::*;
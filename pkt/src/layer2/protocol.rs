@@ -1,16 +1,21 @@
-use super::VlanId;
+use super::VlanTag;
 
 pub mod consts {
     pub const IPV4: u16 = 0x0800;
     pub const IPV6: u16 = 0x86DD;
     pub const ARP: u16 = 0x0806;
     pub const IEEE802_1Q: u16 = 0x8100;
+    /// TPID of both tags in double tagged frames of older switches, use
+    /// [IEEE802_1AD] for service tags.
     pub const Q_IN_Q: u16 = IEEE802_1Q;
+    pub const IEEE802_1AD: u16 = 0x88A8;
+    /// Pre-standard TPID of service tag.
+    pub const QINQ_LEGACY: u16 = 0x9100;
     pub const IEEE802_3: u16 = 0x05DC;
 }
 
 /// Ethernet payload type for layer3
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Layer3Protocol {
     IPv4,
 
@@ -55,13 +60,15 @@ impl From<&Layer3Protocol> for u16 {
 }
 
 /// Ethernet payload type.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Protocol {
     Layer3Protocol(Layer3Protocol),
 
-    IEEE8021Q(VlanId, Layer3Protocol),
+    /// Single tagged frame.
+    IEEE8021Q(VlanTag, Layer3Protocol),
 
-    QinQ(VlanId, VlanId, Layer3Protocol),
+    /// Double tagged frame, outer tag first.
+    QinQ(VlanTag, VlanTag, Layer3Protocol),
 
     /// IEEE802.3
     Length(u16),
@@ -76,7 +83,7 @@ impl From<u16> for Protocol {
         if let Layer3Protocol::Unknown(ty) = &layer3 {
             let ty = *ty;
 
-            if ty < consts::IEEE802_3 {
+            if ty <= consts::IEEE802_3 {
                 Self::Length(ty)
             } else {
                 Self::Unknown(ty)
@@ -91,8 +98,8 @@ impl From<Protocol> for u16 {
     fn from(e: Protocol) -> u16 {
         match e {
             Protocol::Layer3Protocol(p) => p.into(),
-            Protocol::IEEE8021Q(_, _) => consts::IEEE802_1Q,
-            Protocol::QinQ(_, _, _) => consts::Q_IN_Q,
+            Protocol::IEEE8021Q(tag, _) => tag.tpid,
+            Protocol::QinQ(tag, _, _) => tag.tpid,
             Protocol::Length(v) => v,
             Protocol::Unknown(v) => v,
         }
@@ -103,8 +110,8 @@ impl From<&Protocol> for u16 {
     fn from(e: &Protocol) -> u16 {
         match e {
             Protocol::Layer3Protocol(p) => p.into(),
-            Protocol::IEEE8021Q(_, _) => consts::IEEE802_1Q,
-            Protocol::QinQ(_, _, _) => consts::Q_IN_Q,
+            Protocol::IEEE8021Q(tag, _) => tag.tpid,
+            Protocol::QinQ(tag, _, _) => tag.tpid,
             Protocol::Length(v) => *v,
             Protocol::Unknown(v) => *v,
         }
@@ -0,0 +1,73 @@
+use byteorder::{ByteOrder, NetworkEndian};
+
+use super::{consts, VlanId};
+
+/// Query whether an ethertype is a TPID of VLAN tag.
+pub fn is_tpid(ty: u16) -> bool {
+    matches!(
+        ty,
+        consts::IEEE802_1Q | consts::IEEE802_1AD | consts::QINQ_LEGACY
+    )
+}
+
+/// VLAN tag, include TPID and tag control information.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub struct VlanTag {
+    /// Tag protocol identifier.
+    pub tpid: u16,
+    /// Priority code point.
+    pub pcp: u8,
+    /// Drop eligible indicator.
+    pub dei: bool,
+    /// VLAN identifier.
+    pub vid: VlanId,
+}
+
+impl VlanTag {
+    /// Length of tag in frame, TPID and TCI.
+    pub const LEN: usize = 4;
+
+    /// Create an 802.1Q customer tag.
+    pub fn new(vid: VlanId) -> Self {
+        Self {
+            tpid: consts::IEEE802_1Q,
+            pcp: 0,
+            dei: false,
+            vid,
+        }
+    }
+
+    /// Create an 802.1ad service tag.
+    pub fn service(vid: VlanId) -> Self {
+        Self {
+            tpid: consts::IEEE802_1AD,
+            ..Self::new(vid)
+        }
+    }
+
+    /// Create tag from TPID and tag control information field.
+    pub fn from_tci(tpid: u16, tci: u16) -> Self {
+        Self {
+            tpid,
+            pcp: (tci >> 13) as u8,
+            dei: tci & 0x1000 != 0,
+            vid: VlanId(tci & 0x0fff),
+        }
+    }
+
+    /// Parse tag from TPID and the 2 octets tag control information.
+    pub fn from_bytes_unchecked(tpid: u16, buf: &[u8]) -> Self {
+        Self::from_tci(tpid, NetworkEndian::read_u16(buf))
+    }
+
+    /// Return the tag control information field.
+    pub fn tci(&self) -> u16 {
+        ((self.pcp as u16 & 0x07) << 13) | ((self.dei as u16) << 12) | (self.vid.0 & 0x0fff)
+    }
+}
+
+impl From<VlanId> for VlanTag {
+    fn from(vid: VlanId) -> Self {
+        Self::new(vid)
+    }
+}
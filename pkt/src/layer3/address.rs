@@ -1,4 +1,4 @@
-use super::ipv4;
+use super::{ipv4, ipv6};
 
 // #[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 // pub enum Version {
@@ -16,6 +16,6 @@ pub enum Address {
     Unspecified,
     /// An IPv4 address.
     Ipv4(ipv4::Address),
-    // An IPv6 address.
-    // Ipv6(Ipv6Address),
+    /// An IPv6 address.
+    Ipv6(ipv6::Address),
 }
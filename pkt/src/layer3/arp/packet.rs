@@ -315,3 +315,83 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
         data[field::target_protocol_address(hardware_len, protocol_len)].copy_from_slice(value)
     }
 }
+
+/// High-level representation of an Ethernet/IPv4 ARP packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repr {
+    pub operation: Operation,
+    pub source_hardware_addr: layer2::Address,
+    pub source_protocol_addr: Address,
+    pub target_hardware_addr: layer2::Address,
+    pub target_protocol_addr: Address,
+}
+
+impl Repr {
+    /// Parse an ARP packet and return a high-level representation.
+    /// Only Ethernet hardware and IPv4 protocol are supported.
+    pub fn parse<T: AsRef<[u8]>>(packet: &Packet<T>) -> Result<Repr> {
+        packet.check_len()?;
+
+        let sha = packet.source_hardware_address()?;
+        let spa = packet.source_protocol_address()?;
+        let tha = packet.target_hardware_address()?;
+        let tpa = packet.target_protocol_address()?;
+
+        match (sha, spa, tha, tpa) {
+            (
+                HardwareAddress::Ethernet(sha),
+                ProtocolAddress::IPv4(spa),
+                HardwareAddress::Ethernet(tha),
+                ProtocolAddress::IPv4(tpa),
+            ) => Ok(Repr {
+                operation: packet.operation(),
+                source_hardware_addr: sha,
+                source_protocol_addr: spa,
+                target_hardware_addr: tha,
+                target_protocol_addr: tpa,
+            }),
+            _ => Err(Error::UnsupportedArpType),
+        }
+    }
+
+    /// Return the length of packet that will be emitted from this representation.
+    pub fn buffer_len(&self) -> usize {
+        field::PACKET_LEN
+    }
+
+    /// Emit this representation into an ARP packet.
+    pub fn emit<T: AsRef<[u8]> + AsMut<[u8]>>(&self, packet: &mut Packet<T>) -> Result<()> {
+        packet.set_operation(self.operation.clone());
+        packet.set_source_hardware_address(HardwareAddress::Ethernet(self.source_hardware_addr))?;
+        packet.set_source_protocol_address(ProtocolAddress::IPv4(self.source_protocol_addr))?;
+        packet.set_target_hardware_address(HardwareAddress::Ethernet(self.target_hardware_addr))?;
+        packet.set_target_protocol_address(ProtocolAddress::IPv4(self.target_protocol_addr))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repr() {
+        let repr = Repr {
+            operation: Operation::Reply,
+            source_hardware_addr: layer2::Address::new(0x02, 0, 0, 0, 0, 1),
+            source_protocol_addr: Address::new(192, 168, 1, 1),
+            target_hardware_addr: layer2::Address::new(0x02, 0, 0, 0, 0, 2),
+            target_protocol_addr: Address::new(192, 168, 1, 2),
+        };
+
+        let mut bytes = [0u8; field::PACKET_LEN];
+        assert_eq!(repr.buffer_len(), bytes.len());
+        let mut pkt = Packet::new_unchecked(&mut bytes[..]);
+        repr.emit(&mut pkt).unwrap();
+
+        let pkt = Packet::new_checked(&bytes[..]).unwrap();
+        assert_eq!(Repr::parse(&pkt).unwrap(), repr);
+        assert!(Packet::new_checked(&bytes[..field::PACKET_LEN - 1]).is_err());
+    }
+}
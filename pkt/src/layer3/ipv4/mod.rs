@@ -8,3 +8,6 @@ pub use cidr::*;
 
 mod packet;
 pub use packet::*;
+
+mod option;
+pub use option::*;
@@ -0,0 +1,353 @@
+use byteorder::{ByteOrder, NetworkEndian};
+
+use super::Address;
+use crate::{Error, Result};
+
+pub mod consts {
+    pub const TYPE_END: u8 = 0x00;
+    pub const TYPE_NOP: u8 = 0x01;
+    pub const TYPE_RECORD_ROUTE: u8 = 0x07;
+    pub const TYPE_TIMESTAMP: u8 = 0x44;
+    pub const TYPE_LOOSE_SOURCE_ROUTE: u8 = 0x83;
+    pub const TYPE_STRICT_SOURCE_ROUTE: u8 = 0x89;
+    pub const TYPE_ROUTER_ALERT: u8 = 0x94;
+
+    pub const LEN_ROUTER_ALERT: usize = 4;
+
+    /// Route data start after type, length and pointer.
+    pub const ROUTE_DATA_START: usize = 3;
+
+    /// Timestamp data start after type, length, pointer and overflow/flags.
+    pub const TIMESTAMP_DATA_START: usize = 4;
+
+    /// Minimum value of pointer field in route options.
+    pub const ROUTE_MIN_POINTER: u8 = 4;
+
+    /// Minimum value of pointer field in timestamp option.
+    pub const TIMESTAMP_MIN_POINTER: u8 = 5;
+
+    /// Maximum length of options area, in octets.
+    pub const MAX_OPTIONS_LEN: usize = 40;
+}
+
+/// Route data of record route, loose source route and strict source route options.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Route<'a> {
+    /// Pointer field, point to the octet begins the next address, counted from option type.
+    pub pointer: u8,
+    /// Route data, multiple of 4 octets.
+    pub data: &'a [u8],
+}
+
+impl<'a> Route<'a> {
+    /// Iterate addresses in route data.
+    pub fn addresses(&self) -> impl Iterator<Item = Address> + 'a {
+        self.data.chunks_exact(4).map(Address::from_bytes)
+    }
+}
+
+/// Timestamp option data.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Timestamp<'a> {
+    /// Pointer field, counted from option type.
+    pub pointer: u8,
+    /// Number of modules that cannot register timestamps.
+    pub overflow: u8,
+    /// Timestamp flag: 0 timestamps only, 1 address and timestamp, 3 prespecified addresses.
+    pub flags: u8,
+    pub data: &'a [u8],
+}
+
+/// IPv4 header option.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Ipv4Option<'a> {
+    EndOfList,
+    NoOperation,
+    RecordRoute(Route<'a>),
+    Timestamp(Timestamp<'a>),
+    LooseSourceRoute(Route<'a>),
+    StrictSourceRoute(Route<'a>),
+    /// Router alert (RFC 2113), carry the value field.
+    RouterAlert(u16),
+    Unknown(u8, &'a [u8]),
+}
+
+impl<'a> Ipv4Option<'a> {
+    /// Parse one option from the start of buffer. Return option and its length in octets.
+    pub fn parse(data: &'a [u8]) -> Result<(Self, usize)> {
+        let ty = *data.first().ok_or(Error::WrongLengthForIpv4Option)?;
+
+        match ty {
+            consts::TYPE_END => return Ok((Self::EndOfList, 1)),
+            consts::TYPE_NOP => return Ok((Self::NoOperation, 1)),
+            _ => {}
+        }
+
+        if data.len() < 2 {
+            return Err(Error::WrongLengthForIpv4Option);
+        }
+
+        let len = data[1] as usize;
+        if len < 2 || data.len() < len {
+            return Err(Error::WrongLengthForIpv4Option);
+        }
+
+        let data = &data[..len];
+
+        let option = match ty {
+            consts::TYPE_RECORD_ROUTE
+            | consts::TYPE_LOOSE_SOURCE_ROUTE
+            | consts::TYPE_STRICT_SOURCE_ROUTE => {
+                if len < consts::ROUTE_DATA_START
+                    || !(len - consts::ROUTE_DATA_START).is_multiple_of(4)
+                    || data[2] < consts::ROUTE_MIN_POINTER
+                {
+                    return Err(Error::WrongLengthForIpv4Option);
+                }
+
+                let route = Route {
+                    pointer: data[2],
+                    data: &data[consts::ROUTE_DATA_START..],
+                };
+
+                match ty {
+                    consts::TYPE_RECORD_ROUTE => Self::RecordRoute(route),
+                    consts::TYPE_LOOSE_SOURCE_ROUTE => Self::LooseSourceRoute(route),
+                    _ => Self::StrictSourceRoute(route),
+                }
+            }
+            consts::TYPE_TIMESTAMP => {
+                if len < consts::TIMESTAMP_DATA_START || data[2] < consts::TIMESTAMP_MIN_POINTER {
+                    return Err(Error::WrongLengthForIpv4Option);
+                }
+
+                Self::Timestamp(Timestamp {
+                    pointer: data[2],
+                    overflow: data[3] >> 4,
+                    flags: data[3] & 0x0f,
+                    data: &data[consts::TIMESTAMP_DATA_START..],
+                })
+            }
+            consts::TYPE_ROUTER_ALERT => {
+                if len != consts::LEN_ROUTER_ALERT {
+                    return Err(Error::WrongLengthForIpv4Option);
+                }
+
+                Self::RouterAlert(NetworkEndian::read_u16(&data[2..4]))
+            }
+            _ => Self::Unknown(ty, &data[2..]),
+        };
+
+        Ok((option, len))
+    }
+
+    /// Return the length of this option when emitted, in octets.
+    pub fn buffer_len(&self) -> usize {
+        match self {
+            Self::EndOfList | Self::NoOperation => 1,
+            Self::RecordRoute(r) | Self::LooseSourceRoute(r) | Self::StrictSourceRoute(r) => {
+                consts::ROUTE_DATA_START + r.data.len()
+            }
+            Self::Timestamp(t) => consts::TIMESTAMP_DATA_START + t.data.len(),
+            Self::RouterAlert(_) => consts::LEN_ROUTER_ALERT,
+            Self::Unknown(_, data) => 2 + data.len(),
+        }
+    }
+
+    /// Emit this option into buffer. Return length written.
+    ///
+    /// # Panics
+    /// This function panics if buffer is shorter than [buffer_len].
+    ///
+    /// [buffer_len]: #method.buffer_len
+    pub fn emit(&self, data: &mut [u8]) -> usize {
+        let len = self.buffer_len();
+
+        match self {
+            Self::EndOfList => data[0] = consts::TYPE_END,
+            Self::NoOperation => data[0] = consts::TYPE_NOP,
+            Self::RecordRoute(r) | Self::LooseSourceRoute(r) | Self::StrictSourceRoute(r) => {
+                data[0] = match self {
+                    Self::RecordRoute(_) => consts::TYPE_RECORD_ROUTE,
+                    Self::LooseSourceRoute(_) => consts::TYPE_LOOSE_SOURCE_ROUTE,
+                    _ => consts::TYPE_STRICT_SOURCE_ROUTE,
+                };
+                data[1] = len as u8;
+                data[2] = r.pointer;
+                data[consts::ROUTE_DATA_START..len].copy_from_slice(r.data);
+            }
+            Self::Timestamp(t) => {
+                data[0] = consts::TYPE_TIMESTAMP;
+                data[1] = len as u8;
+                data[2] = t.pointer;
+                data[3] = (t.overflow << 4) | (t.flags & 0x0f);
+                data[consts::TIMESTAMP_DATA_START..len].copy_from_slice(t.data);
+            }
+            Self::RouterAlert(value) => {
+                data[0] = consts::TYPE_ROUTER_ALERT;
+                data[1] = len as u8;
+                NetworkEndian::write_u16(&mut data[2..4], *value);
+            }
+            Self::Unknown(ty, value) => {
+                data[0] = *ty;
+                data[1] = len as u8;
+                data[2..len].copy_from_slice(value);
+            }
+        }
+
+        len
+    }
+}
+
+/// Iterator over IPv4 options, stop at end of option list.
+pub struct OptionIter<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> OptionIter<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+}
+
+impl<'a> Iterator for OptionIter<'a> {
+    type Item = Result<Ipv4Option<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+
+        match Ipv4Option::parse(self.data) {
+            Ok((Ipv4Option::EndOfList, _)) => {
+                self.data = &[];
+                None
+            }
+            Ok((option, len)) => {
+                self.data = &self.data[len..];
+                Some(Ok(option))
+            }
+            Err(e) => {
+                self.data = &[];
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Emit options into buffer, padding with end of list to a multiple of 4 octets.
+/// Return length written.
+///
+/// Returns `Err(Error::WrongLengthForIpv4Option)` if options exceed the 40 octets
+/// available in header.
+pub fn emit_options(options: &[Ipv4Option<'_>], data: &mut [u8]) -> Result<usize> {
+    if options_len(options) > consts::MAX_OPTIONS_LEN {
+        return Err(Error::WrongLengthForIpv4Option);
+    }
+
+    let mut len = 0;
+
+    for option in options {
+        len += option.emit(&mut data[len..]);
+    }
+
+    while !len.is_multiple_of(4) {
+        data[len] = consts::TYPE_END;
+        len += 1;
+    }
+
+    Ok(len)
+}
+
+/// Return the length of options emitted by [emit_options].
+pub fn options_len(options: &[Ipv4Option<'_>]) -> usize {
+    let len: usize = options.iter().map(|o| o.buffer_len()).sum();
+    len.div_ceil(4) * 4
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layer3::{ipv4::Packet, Protocol};
+
+    #[test]
+    fn test_router_alert_round_trip() {
+        let options = [Ipv4Option::RouterAlert(0)];
+        let header_len = 20 + options_len(&options);
+        assert_eq!(header_len, 24);
+
+        // IGMPv2 membership report is 8 octets.
+        let mut bytes = [0u8; 32];
+        let mut packet = Packet::new_unchecked(&mut bytes[..]);
+        packet.set_version(4);
+        packet.set_header_len(header_len as u8);
+        packet.set_total_len(32);
+        packet.set_ttl(1);
+        packet.set_protocol(Protocol::Igmp);
+        assert_eq!(emit_options(&options, packet.options_mut()).unwrap(), 4);
+        packet.fill_checksum();
+
+        let packet = Packet::new_checked(&bytes[..]).unwrap();
+        assert!(packet.verify_checksum());
+        assert_eq!(packet.options(), &[0x94, 0x04, 0x00, 0x00]);
+        assert_eq!(packet.payload().len(), 8);
+
+        let mut iter = packet.option_iter();
+        assert_eq!(iter.next().unwrap().unwrap(), Ipv4Option::RouterAlert(0));
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_record_route() {
+        let raw = [0x01, 0x07, 0x07, 0x08, 10, 0, 0, 1, 0x00];
+        let mut iter = OptionIter::new(&raw);
+        assert_eq!(iter.next().unwrap().unwrap(), Ipv4Option::NoOperation);
+
+        match iter.next().unwrap().unwrap() {
+            Ipv4Option::RecordRoute(route) => {
+                assert_eq!(route.pointer, 8);
+                let mut addrs = route.addresses();
+                assert_eq!(addrs.next(), Some(Address::new(10, 0, 0, 1)));
+                assert!(addrs.next().is_none());
+            }
+            _ => panic!("expect record route"),
+        }
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_length_exceed_header() {
+        // Option claims 8 octets but only 4 in header.
+        let mut bytes = [0u8; 24];
+        let mut packet = Packet::new_unchecked(&mut bytes[..]);
+        packet.set_version(4);
+        packet.set_header_len(24);
+        packet.set_total_len(24);
+        packet
+            .options_mut()
+            .copy_from_slice(&[0x83, 0x08, 0x04, 0x00]);
+
+        // Options are not checked with the packet length.
+        let packet = Packet::new_checked(&bytes[..]).unwrap();
+        assert!(packet.check_options().is_err());
+        assert!(Ipv4Option::parse(&[0x94, 0x03, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_options_limit() {
+        let mut buffer = [0u8; 44];
+
+        // 36 octets of route data, 39 octets option padded to 40.
+        let route = Ipv4Option::RecordRoute(Route {
+            pointer: 4,
+            data: &[0u8; 36],
+        });
+        assert_eq!(
+            emit_options(&[route], &mut buffer).unwrap(),
+            consts::MAX_OPTIONS_LEN
+        );
+
+        let options = [route, Ipv4Option::NoOperation, Ipv4Option::NoOperation];
+        assert!(emit_options(&options, &mut buffer).is_err());
+    }
+}
@@ -2,7 +2,7 @@ use core::fmt::{self, Display, Formatter};
 
 use crate::{layer3::Protocol, prelude::IntoInner, utils::checksum, Error, Result};
 
-use super::Address;
+use super::{Address, OptionIter};
 use byteorder::{ByteOrder, NetworkEndian};
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -79,6 +79,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     fn check_len(&self) -> Result<()> {
         let len = self.buffer.as_ref().len();
         if len < field::DST_ADDR.end
+            || self.header_len() < field::HEADER_LEN_WITHOUT_OPTION
             || len < self.header_len() as usize
             || self.header_len() as u16 > self.total_len()
             || len < self.total_len() as usize
@@ -145,10 +146,12 @@ impl<T: AsRef<[u8]>> Packet<T> {
     }
 
     /// Return the fragment offset, in octets.
+    ///
+    /// Offset field is in units of 8 octets, below flags.
     #[inline]
     pub fn frag_offset(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::FLG_OFF]) << 3
+        (NetworkEndian::read_u16(&data[field::FLG_OFF]) & 0x1fff) << 3
     }
 
     /// Return the time to live field.
@@ -192,6 +195,27 @@ impl<T: AsRef<[u8]>> Packet<T> {
         checksum::data(&data[..self.header_len() as usize]) == !0
     }
 
+    /// Return the options area of header.
+    #[inline]
+    pub fn options(&self) -> &[u8] {
+        let range = field::HEADER_LEN_WITHOUT_OPTION as usize..self.header_len() as usize;
+        let data = self.buffer.as_ref();
+        &data[range]
+    }
+
+    /// Iterate options in header.
+    pub fn option_iter(&self) -> OptionIter<'_> {
+        OptionIter::new(self.options())
+    }
+
+    /// Check every option fits in the header length.
+    pub fn check_options(&self) -> Result<()> {
+        for option in self.option_iter() {
+            option?;
+        }
+        Ok(())
+    }
+
     #[inline]
     pub fn payload(&self) -> &[u8] {
         let range = self.header_len() as usize..self.total_len() as usize;
@@ -324,6 +348,29 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
         self.set_checksum(checksum)
     }
 
+    /// Decrement the time to live field, update header checksum incrementally as
+    /// RFC 1624 equation 3.
+    pub fn decrement_ttl(&mut self) {
+        let data = self.buffer.as_mut();
+        let word = field::TTL..field::PROTOCOL + 1;
+
+        let old = NetworkEndian::read_u16(&data[word.clone()]);
+        data[field::TTL] = data[field::TTL].saturating_sub(1);
+        let new = NetworkEndian::read_u16(&data[word]);
+
+        let checksum = NetworkEndian::read_u16(&data[field::CHECKSUM]);
+        let checksum = !checksum::combine(&[!checksum, !old, new]);
+        NetworkEndian::write_u16(&mut data[field::CHECKSUM], checksum)
+    }
+
+    /// Return a mutable pointer to the options area of header.
+    #[inline]
+    pub fn options_mut(&mut self) -> &mut [u8] {
+        let range = field::HEADER_LEN_WITHOUT_OPTION as usize..self.header_len() as usize;
+        let data = self.buffer.as_mut();
+        &mut data[range]
+    }
+
     /// Return a mutable pointer to the payload.
     #[inline]
     pub fn payload_mut(&mut self) -> &mut [u8] {
@@ -332,3 +379,98 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
         &mut data[range]
     }
 }
+
+/// High-level representation of IPv4 header, options are not represented.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repr {
+    pub src_addr: Address,
+    pub dst_addr: Address,
+    pub protocol: Protocol,
+    pub payload_len: usize,
+    pub ttl: u8,
+    pub ident: u16,
+    pub dont_frag: bool,
+}
+
+impl Repr {
+    /// Parse an IPv4 packet and return a high-level representation.
+    ///
+    /// Header checksum is verified, fragment fields other than ident and DF are ignored.
+    pub fn parse<T: AsRef<[u8]>>(packet: &Packet<T>) -> Result<Repr> {
+        packet.check_len()?;
+
+        if packet.version() != 4 {
+            return Err(Error::UnknownIpVersionNumber);
+        }
+
+        if !packet.verify_checksum() {
+            return Err(Error::WrongChecksum);
+        }
+
+        Ok(Repr {
+            src_addr: packet.src_addr(),
+            dst_addr: packet.dst_addr(),
+            protocol: packet.protocol(),
+            payload_len: packet.payload().len(),
+            ttl: packet.ttl(),
+            ident: packet.ident(),
+            dont_frag: packet.dont_frag(),
+        })
+    }
+
+    /// Return the length of header that will be emitted from this representation.
+    pub fn buffer_len(&self) -> usize {
+        field::HEADER_LEN_WITHOUT_OPTION as usize
+    }
+
+    /// Emit this representation into an IPv4 packet, fill total length and checksum.
+    pub fn emit<T: AsRef<[u8]> + AsMut<[u8]>>(&self, packet: &mut Packet<T>) {
+        packet.set_version(4);
+        packet.set_header_len(field::HEADER_LEN_WITHOUT_OPTION);
+        packet.set_dscp(0);
+        packet.set_ecn(0);
+        packet.set_total_len((self.buffer_len() + self.payload_len) as u16);
+        packet.set_ident(self.ident);
+        packet.clear_flags();
+        packet.set_dont_frag(self.dont_frag);
+        packet.set_frag_offset(0);
+        packet.set_ttl(self.ttl);
+        packet.set_protocol(self.protocol);
+        packet.set_src_addr(self.src_addr);
+        packet.set_dst_addr(self.dst_addr);
+        packet.fill_checksum();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layer3::Protocol;
+
+    #[test]
+    fn test_decrement_ttl() {
+        let repr = Repr {
+            src_addr: Address::new(10, 0, 0, 1),
+            dst_addr: Address::new(192, 168, 1, 1),
+            protocol: Protocol::Udp,
+            payload_len: 0,
+            ttl: 64,
+            ident: 0x1234,
+            dont_frag: true,
+        };
+
+        let mut bytes = [0u8; 20];
+        let mut pkt = Packet::new_unchecked(&mut bytes[..]);
+        repr.emit(&mut pkt);
+
+        for ttl in (0..64).rev() {
+            pkt.decrement_ttl();
+            assert_eq!(pkt.ttl(), ttl);
+            assert!(pkt.verify_checksum());
+
+            let checksum = pkt.checksum();
+            pkt.fill_checksum();
+            assert_eq!(pkt.checksum(), checksum);
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use core::fmt::{self, Display};
+use core::format_args;
+
+use byteorder::{ByteOrder, NetworkEndian};
+
+use crate::{layer3::ipv4, Error, Result};
+
+#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+pub struct Address(pub [u8; 16]);
+
+impl Display for Address {
+    /// Format address as RFC 5952 recommended text representation.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(v4) = self.as_ipv4_mapped() {
+            return f.write_fmt(format_args!("::ffff:{}", v4));
+        }
+
+        let parts = self.parts();
+
+        // Find the longest run of zero groups, first one wins on tie.
+        let mut best = (0usize, 0usize);
+        let mut current = (0usize, 0usize);
+        for (i, part) in parts.iter().enumerate() {
+            if *part == 0 {
+                if current.1 == 0 {
+                    current.0 = i;
+                }
+                current.1 += 1;
+                if current.1 > best.1 {
+                    best = current;
+                }
+            } else {
+                current = (0, 0);
+            }
+        }
+
+        // A single zero group must not be shortened.
+        if best.1 < 2 {
+            best = (parts.len(), 0);
+        }
+
+        let mut i = 0;
+        while i < parts.len() {
+            if i == best.0 {
+                f.write_str("::")?;
+                i += best.1;
+                continue;
+            }
+
+            if i != 0 && i != best.0 + best.1 {
+                f.write_str(":")?;
+            }
+
+            f.write_fmt(format_args!("{:x}", parts[i]))?;
+            i += 1;
+        }
+
+        Ok(())
+    }
+}
+
+impl Address {
+    /// An unspecified address.
+    pub const UNSPECIFIED: Address = Address([0x00; 16]);
+
+    /// The loopback address.
+    pub const LOOPBACK: Address = Address([
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x01,
+    ]);
+
+    /// All nodes on link.
+    pub const LINK_LOCAL_ALL_NODES: Address = Address([
+        0xff, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x01,
+    ]);
+
+    /// All routers on link.
+    pub const LINK_LOCAL_ALL_ROUTERS: Address = Address([
+        0xff, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x02,
+    ]);
+
+    /// Construct an IPv6 address from 16-bit groups.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(a0: u16, a1: u16, a2: u16, a3: u16, a4: u16, a5: u16, a6: u16, a7: u16) -> Address {
+        Self::from_parts(&[a0, a1, a2, a3, a4, a5, a6, a7])
+    }
+
+    /// Parse IPv6 address from text representation (RFC 4291 section 2.2).
+    ///
+    /// Support `::` compression and trailing embedded IPv4 address.
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut parts = [0u16; 8];
+
+        let mut halves = s.splitn(2, "::");
+        let head = halves.next().ok_or(Error::ParseIpv6AddressFailed)?;
+
+        if let Some(tail) = halves.next() {
+            if tail.contains("::") {
+                return Err(Error::ParseIpv6AddressFailed);
+            }
+
+            let mut head_parts = [0u16; 8];
+            let mut tail_parts = [0u16; 8];
+
+            let head_len = parse_groups(head, false, &mut head_parts)?;
+            let tail_len = parse_groups(tail, true, &mut tail_parts)?;
+
+            if head_len + tail_len > 7 {
+                return Err(Error::ParseIpv6AddressFailed);
+            }
+
+            parts[..head_len].copy_from_slice(&head_parts[..head_len]);
+            parts[8 - tail_len..].copy_from_slice(&tail_parts[..tail_len]);
+        } else if parse_groups(head, true, &mut parts)? != 8 {
+            return Err(Error::ParseIpv6AddressFailed);
+        }
+
+        Ok(Self::from_parts(&parts))
+    }
+
+    /// Construct an IPv6 address from a sequence of octets, in big-endian.
+    ///
+    /// # Panics
+    /// The function panics if `data` is not sixteen octets long.
+    pub fn from_bytes(data: &[u8]) -> Address {
+        let mut bytes = [0; 16];
+        bytes.copy_from_slice(data);
+        Address(bytes)
+    }
+
+    /// Construct an IPv6 address from a sequence of 16-bit groups.
+    ///
+    /// # Panics
+    /// The function panics if `data` is not eight groups long.
+    pub fn from_parts(data: &[u16]) -> Address {
+        assert!(data.len() == 8);
+        let mut bytes = [0; 16];
+        for (i, part) in data.iter().enumerate() {
+            NetworkEndian::write_u16(&mut bytes[i * 2..i * 2 + 2], *part);
+        }
+        Address(bytes)
+    }
+
+    /// Return an IPv6 address as 16-bit groups.
+    pub fn parts(&self) -> [u16; 8] {
+        let mut parts = [0u16; 8];
+        for (i, part) in parts.iter_mut().enumerate() {
+            *part = NetworkEndian::read_u16(&self.0[i * 2..i * 2 + 2]);
+        }
+        parts
+    }
+
+    /// Return an IPv6 address as a sequence of octets, in big-endian.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Query whether the address is an unicast address.
+    pub fn is_unicast(&self) -> bool {
+        !(self.is_multicast() || self.is_unspecified())
+    }
+
+    /// Query whether the address is a multicast address.
+    pub fn is_multicast(&self) -> bool {
+        self.0[0] == 0xff
+    }
+
+    /// Query whether the address is the "unspecified" address.
+    pub fn is_unspecified(&self) -> bool {
+        self.0 == [0x00; 16]
+    }
+
+    /// Query whether the address falls into the "link-local" range.
+    pub fn is_link_local(&self) -> bool {
+        self.0[0] == 0xfe && self.0[1] & 0xc0 == 0x80
+    }
+
+    /// Query whether the address is the "loopback" address.
+    pub fn is_loopback(&self) -> bool {
+        *self == Self::LOOPBACK
+    }
+
+    /// Return the embedded IPv4 address if this is an IPv4-mapped address.
+    pub fn as_ipv4_mapped(&self) -> Option<ipv4::Address> {
+        if self.0[0..10] == [0x00; 10] && self.0[10..12] == [0xff; 2] {
+            Some(ipv4::Address::from_bytes(&self.0[12..16]))
+        } else {
+            None
+        }
+    }
+
+    /// Return the solicited-node multicast address for this address.
+    pub fn solicited_node(&self) -> Address {
+        let mut bytes = [
+            0xff, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xff, 0x00,
+            0x00, 0x00,
+        ];
+        bytes[13..16].copy_from_slice(&self.0[13..16]);
+        Address(bytes)
+    }
+}
+
+/// Parse colon separated hex groups, return number of groups.
+fn parse_groups(s: &str, allow_ipv4: bool, parts: &mut [u16; 8]) -> Result<usize> {
+    if s.is_empty() {
+        return Ok(0);
+    }
+
+    let mut len = 0;
+    let mut segments = s.split(':').peekable();
+
+    while let Some(seg) = segments.next() {
+        if seg.contains('.') {
+            if !allow_ipv4 || segments.peek().is_some() || len > 6 {
+                return Err(Error::ParseIpv6AddressFailed);
+            }
+
+            let v4 = ipv4::Address::parse(seg)?;
+            parts[len] = NetworkEndian::read_u16(&v4.0[0..2]);
+            parts[len + 1] = NetworkEndian::read_u16(&v4.0[2..4]);
+            len += 2;
+        } else {
+            if seg.is_empty() || seg.len() > 4 || len >= 8 {
+                return Err(Error::ParseIpv6AddressFailed);
+            }
+
+            parts[len] = u16::from_str_radix(seg, 16)?;
+            len += 1;
+        }
+    }
+
+    Ok(len)
+}
+
+impl From<[u8; 16]> for Address {
+    fn from(v: [u8; 16]) -> Self {
+        Self(v)
+    }
+}
+
+impl From<Address> for [u8; 16] {
+    fn from(v: Address) -> Self {
+        v.0
+    }
+}
+
+impl From<&[u8]> for Address {
+    fn from(v: &[u8]) -> Self {
+        Address::from_bytes(v)
+    }
+}
+
+impl AsRef<[u8]> for Address {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::string::ToString;
+
+    use super::Address;
+
+    #[test]
+    fn test_parse_and_format() {
+        let cases = [
+            ("2001:db8::1", "2001:db8::1"),
+            ("2001:0db8:0000:0000:0000:0000:0000:0001", "2001:db8::1"),
+            ("::", "::"),
+            ("::1", "::1"),
+            ("fe80::", "fe80::"),
+            ("2001:db8:0:1:1:1:1:1", "2001:db8:0:1:1:1:1:1"),
+            ("2001:0:0:1:0:0:0:1", "2001:0:0:1::1"),
+            ("2001:db8:0:0:1:0:0:1", "2001:db8::1:0:0:1"),
+            ("::ffff:192.168.1.1", "::ffff:192.168.1.1"),
+            ("2001:DB8::AB", "2001:db8::ab"),
+        ];
+
+        for (input, output) in cases.iter() {
+            let addr = Address::parse(input).unwrap();
+            assert_eq!(addr.to_string(), *output);
+            assert_eq!(Address::parse(output).unwrap(), addr);
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        let cases = [
+            "",
+            ":",
+            "1::2::3",
+            "1:2:3:4:5:6:7",
+            "1:2:3:4:5:6:7:8:9",
+            "12345::",
+            "::1.2.3.4:1",
+            "1:2:3:4:5:6:7::8",
+        ];
+
+        for input in cases.iter() {
+            assert!(Address::parse(input).is_err(), "{}", input);
+        }
+    }
+}
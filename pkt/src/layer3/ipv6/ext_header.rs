@@ -0,0 +1,748 @@
+//! IPv6 extension headers.
+
+use core::fmt::{self, Display, Formatter};
+
+use crate::{layer3::Protocol, prelude::IntoInner, Error, Result};
+
+use super::Address;
+use byteorder::{ByteOrder, NetworkEndian};
+
+pub mod field {
+    pub const NXT_HDR: usize = 0;
+    pub const LENGTH: usize = 1;
+
+    /// Length of header in octets, `length` is in 8-octet units, not including the first 8 octets.
+    pub fn header_len(length: u8) -> usize {
+        (length as usize + 1) * 8
+    }
+
+    pub mod options {
+        use crate::utils::field::Field;
+
+        pub fn options(header_len: usize) -> Field {
+            2..header_len
+        }
+    }
+
+    pub mod routing {
+        use crate::utils::field::Field;
+
+        pub const TYPE: usize = 2;
+        pub const SEG_LEFT: usize = 3;
+        pub const RESERVED: Field = 4..8;
+
+        pub fn data(header_len: usize) -> Field {
+            RESERVED.end..header_len
+        }
+    }
+
+    pub mod fragment {
+        use crate::utils::field::Field;
+
+        pub const RESERVED: usize = 1;
+        pub const FR_OF_M: Field = 2..4;
+        pub const IDENT: Field = 4..8;
+
+        pub const HEADER_LEN: usize = 8;
+    }
+
+    pub const MIN_HEADER_LEN: usize = 8;
+}
+
+/// Option carried in hop-by-hop or destination options header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ipv6Option<'a> {
+    /// One octet of padding.
+    Pad1,
+    /// Multiple octets of padding.
+    PadN(u8),
+    /// Router alert (RFC 2711).
+    RouterAlert(u16),
+    /// Unknown option with type and data.
+    Unknown(u8, &'a [u8]),
+}
+
+pub mod consts {
+    pub const OPTION_PAD1: u8 = 0;
+    pub const OPTION_PADN: u8 = 1;
+    pub const OPTION_ROUTER_ALERT: u8 = 5;
+
+    /// Maximum extension headers walked in one chain.
+    pub const MAX_CHAIN_LEN: usize = 8;
+}
+
+/// Iterator for options in hop-by-hop or destination options header.
+pub struct OptionIter<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> OptionIter<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+}
+
+impl<'a> Iterator for OptionIter<'a> {
+    type Item = Result<Ipv6Option<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ty = *self.data.first()?;
+
+        if ty == consts::OPTION_PAD1 {
+            self.data = &self.data[1..];
+            return Some(Ok(Ipv6Option::Pad1));
+        }
+
+        if self.data.len() < 2 || self.data.len() < 2 + self.data[1] as usize {
+            self.data = &[];
+            return Some(Err(Error::WrongLengthForIpv6Option));
+        }
+
+        let len = self.data[1] as usize;
+        let value = &self.data[2..2 + len];
+        self.data = &self.data[2 + len..];
+
+        let option = match ty {
+            consts::OPTION_PADN => Ipv6Option::PadN(len as u8),
+            consts::OPTION_ROUTER_ALERT if len == 2 => {
+                Ipv6Option::RouterAlert(NetworkEndian::read_u16(value))
+            }
+            consts::OPTION_ROUTER_ALERT => return Some(Err(Error::WrongLengthForIpv6Option)),
+            _ => Ipv6Option::Unknown(ty, value),
+        };
+
+        Some(Ok(option))
+    }
+}
+
+/// Hop-by-hop options header or destination options header.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Options<T> {
+    buffer: T,
+}
+
+impl<T> IntoInner for Options<T> {
+    type Inner = T;
+
+    fn into_inner(self) -> Self::Inner {
+        self.buffer
+    }
+}
+
+impl<T: AsRef<[u8]>> Options<T> {
+    /// new unchecked header.
+    pub fn new_unchecked(buffer: T) -> Options<T> {
+        Options { buffer }
+    }
+
+    /// new checked header.
+    pub fn new_checked(buffer: T) -> Result<Options<T>> {
+        let header = Self::new_unchecked(buffer);
+        check_len(header.buffer.as_ref())?;
+        Ok(header)
+    }
+
+    /// Return the next header field.
+    #[inline]
+    pub fn next_header(&self) -> Protocol {
+        let data = self.buffer.as_ref();
+        Protocol::from(data[field::NXT_HDR])
+    }
+
+    /// Return the header length, in octets.
+    #[inline]
+    pub fn header_len(&self) -> usize {
+        let data = self.buffer.as_ref();
+        field::header_len(data[field::LENGTH])
+    }
+
+    /// Return the raw options area.
+    #[inline]
+    pub fn options(&self) -> &[u8] {
+        let data = self.buffer.as_ref();
+        &data[field::options::options(self.header_len())]
+    }
+
+    /// Iterate options in this header.
+    pub fn iter(&self) -> OptionIter<'_> {
+        OptionIter::new(self.options())
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Options<T> {
+    /// Set the next header field.
+    #[inline]
+    pub fn set_next_header(&mut self, value: Protocol) {
+        let data = self.buffer.as_mut();
+        data[field::NXT_HDR] = value.into()
+    }
+
+    /// Set the header length, in octets. Must be multiple of 8.
+    #[inline]
+    pub fn set_header_len(&mut self, value: usize) {
+        let data = self.buffer.as_mut();
+        data[field::LENGTH] = (value / 8 - 1) as u8
+    }
+
+    /// Return a mutable pointer to the options area.
+    #[inline]
+    pub fn options_mut(&mut self) -> &mut [u8] {
+        let range = field::options::options(self.header_len());
+        let data = self.buffer.as_mut();
+        &mut data[range]
+    }
+}
+
+/// Routing header type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoutingType {
+    /// Type 0, deprecated by RFC 5095.
+    Type0,
+    /// Type 2, used by Mobile IPv6.
+    Type2,
+    /// RPL source route header (RFC 6554).
+    Rpl,
+    /// Segment routing header (RFC 8754).
+    Srh,
+    Unknown(u8),
+}
+
+impl From<u8> for RoutingType {
+    fn from(v: u8) -> Self {
+        match v {
+            0 => Self::Type0,
+            2 => Self::Type2,
+            3 => Self::Rpl,
+            4 => Self::Srh,
+            _ => Self::Unknown(v),
+        }
+    }
+}
+
+impl From<RoutingType> for u8 {
+    fn from(v: RoutingType) -> u8 {
+        match v {
+            RoutingType::Type0 => 0,
+            RoutingType::Type2 => 2,
+            RoutingType::Rpl => 3,
+            RoutingType::Srh => 4,
+            RoutingType::Unknown(v) => v,
+        }
+    }
+}
+
+/// Routing header.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Routing<T> {
+    buffer: T,
+}
+
+impl<T> IntoInner for Routing<T> {
+    type Inner = T;
+
+    fn into_inner(self) -> Self::Inner {
+        self.buffer
+    }
+}
+
+impl<T: AsRef<[u8]>> Routing<T> {
+    /// new unchecked header.
+    pub fn new_unchecked(buffer: T) -> Routing<T> {
+        Routing { buffer }
+    }
+
+    /// new checked header.
+    pub fn new_checked(buffer: T) -> Result<Routing<T>> {
+        let header = Self::new_unchecked(buffer);
+        check_len(header.buffer.as_ref())?;
+        Ok(header)
+    }
+
+    /// Return the next header field.
+    #[inline]
+    pub fn next_header(&self) -> Protocol {
+        let data = self.buffer.as_ref();
+        Protocol::from(data[field::NXT_HDR])
+    }
+
+    /// Return the header length, in octets.
+    #[inline]
+    pub fn header_len(&self) -> usize {
+        let data = self.buffer.as_ref();
+        field::header_len(data[field::LENGTH])
+    }
+
+    /// Return the routing type field.
+    #[inline]
+    pub fn routing_type(&self) -> RoutingType {
+        let data = self.buffer.as_ref();
+        RoutingType::from(data[field::routing::TYPE])
+    }
+
+    /// Return the segments left field.
+    #[inline]
+    pub fn segments_left(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        data[field::routing::SEG_LEFT]
+    }
+
+    /// Return the type-specific data.
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        let data = self.buffer.as_ref();
+        &data[field::routing::data(self.header_len())]
+    }
+
+    /// Return the home address carried by a type 2 routing header.
+    pub fn home_address(&self) -> Option<Address> {
+        let data = self.data();
+        if self.routing_type() == RoutingType::Type2 && data.len() >= 16 {
+            Some(Address::from_bytes(&data[..16]))
+        } else {
+            None
+        }
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Routing<T> {
+    /// Set the next header field.
+    #[inline]
+    pub fn set_next_header(&mut self, value: Protocol) {
+        let data = self.buffer.as_mut();
+        data[field::NXT_HDR] = value.into()
+    }
+
+    /// Set the header length, in octets. Must be multiple of 8.
+    #[inline]
+    pub fn set_header_len(&mut self, value: usize) {
+        let data = self.buffer.as_mut();
+        data[field::LENGTH] = (value / 8 - 1) as u8
+    }
+
+    /// Set the routing type field.
+    #[inline]
+    pub fn set_routing_type(&mut self, value: RoutingType) {
+        let data = self.buffer.as_mut();
+        data[field::routing::TYPE] = value.into()
+    }
+
+    /// Set the segments left field.
+    #[inline]
+    pub fn set_segments_left(&mut self, value: u8) {
+        let data = self.buffer.as_mut();
+        data[field::routing::SEG_LEFT] = value
+    }
+
+    /// Return a mutable pointer to the type-specific data.
+    #[inline]
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        let range = field::routing::data(self.header_len());
+        let data = self.buffer.as_mut();
+        &mut data[range]
+    }
+}
+
+/// Fragment header.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Fragment<T> {
+    buffer: T,
+}
+
+impl<T> IntoInner for Fragment<T> {
+    type Inner = T;
+
+    fn into_inner(self) -> Self::Inner {
+        self.buffer
+    }
+}
+
+impl<T: AsRef<[u8]>> Fragment<T> {
+    /// new unchecked header.
+    pub fn new_unchecked(buffer: T) -> Fragment<T> {
+        Fragment { buffer }
+    }
+
+    /// new checked header.
+    pub fn new_checked(buffer: T) -> Result<Fragment<T>> {
+        let header = Self::new_unchecked(buffer);
+        if header.buffer.as_ref().len() < field::fragment::HEADER_LEN {
+            Err(Error::WrongLengthForIpv6ExtHeader)
+        } else {
+            Ok(header)
+        }
+    }
+
+    /// Return the next header field.
+    #[inline]
+    pub fn next_header(&self) -> Protocol {
+        let data = self.buffer.as_ref();
+        Protocol::from(data[field::NXT_HDR])
+    }
+
+    /// Return the header length, in octets.
+    #[inline]
+    pub fn header_len(&self) -> usize {
+        field::fragment::HEADER_LEN
+    }
+
+    /// Return the fragment offset, in octets.
+    #[inline]
+    pub fn frag_offset(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[field::fragment::FR_OF_M]) & 0xfff8
+    }
+
+    /// Return the "more fragments" flag.
+    #[inline]
+    pub fn more_frags(&self) -> bool {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[field::fragment::FR_OF_M]) & 0x1 != 0
+    }
+
+    /// Return the fragment identification field.
+    #[inline]
+    pub fn ident(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u32(&data[field::fragment::IDENT])
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Fragment<T> {
+    /// Set the next header field.
+    #[inline]
+    pub fn set_next_header(&mut self, value: Protocol) {
+        let data = self.buffer.as_mut();
+        data[field::NXT_HDR] = value.into();
+        data[field::fragment::RESERVED] = 0;
+    }
+
+    /// Set the fragment offset, in octets. Must be multiple of 8.
+    #[inline]
+    pub fn set_frag_offset(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        let raw = NetworkEndian::read_u16(&data[field::fragment::FR_OF_M]);
+        let raw = (raw & 0x7) | (value & 0xfff8);
+        NetworkEndian::write_u16(&mut data[field::fragment::FR_OF_M], raw);
+    }
+
+    /// Set the "more fragments" flag.
+    #[inline]
+    pub fn set_more_frags(&mut self, value: bool) {
+        let data = self.buffer.as_mut();
+        let raw = NetworkEndian::read_u16(&data[field::fragment::FR_OF_M]);
+        let raw = if value { raw | 0x1 } else { raw & !0x1 };
+        NetworkEndian::write_u16(&mut data[field::fragment::FR_OF_M], raw);
+    }
+
+    /// Set the fragment identification field.
+    #[inline]
+    pub fn set_ident(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[field::fragment::IDENT], value)
+    }
+}
+
+fn check_len(data: &[u8]) -> Result<()> {
+    if data.len() < field::MIN_HEADER_LEN || data.len() < field::header_len(data[field::LENGTH]) {
+        Err(Error::WrongLengthForIpv6ExtHeader)
+    } else {
+        Ok(())
+    }
+}
+
+/// Extension header in next-header chain.
+#[derive(Debug, Clone)]
+pub enum Header<'a> {
+    HopByHop(Options<&'a [u8]>),
+    Routing(Routing<&'a [u8]>),
+    Fragment(Fragment<&'a [u8]>),
+    DestOptions(Options<&'a [u8]>),
+}
+
+impl<'a> Header<'a> {
+    /// Return the next header field.
+    pub fn next_header(&self) -> Protocol {
+        match self {
+            Self::HopByHop(h) => h.next_header(),
+            Self::Routing(h) => h.next_header(),
+            Self::Fragment(h) => h.next_header(),
+            Self::DestOptions(h) => h.next_header(),
+        }
+    }
+
+    /// Return the header length, in octets.
+    pub fn header_len(&self) -> usize {
+        match self {
+            Self::HopByHop(h) => h.header_len(),
+            Self::Routing(h) => h.header_len(),
+            Self::Fragment(h) => h.header_len(),
+            Self::DestOptions(h) => h.header_len(),
+        }
+    }
+}
+
+impl<'a> Display for Header<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::HopByHop(_) => f.write_str("Hop-by-Hop Options: ")?,
+            Self::Routing(h) => f.write_fmt(format_args!(
+                "Routing: Type: {:?}, Segments Left: {}, ",
+                h.routing_type(),
+                h.segments_left()
+            ))?,
+            Self::Fragment(h) => f.write_fmt(format_args!(
+                "Fragment: Ident: {}, Offset: {}, More: {}, ",
+                h.ident(),
+                h.frag_offset(),
+                h.more_frags()
+            ))?,
+            Self::DestOptions(_) => f.write_str("Destination Options: ")?,
+        }
+
+        f.write_fmt(format_args!(
+            "Length: {}, Next Header: {:?}",
+            self.header_len(),
+            self.next_header()
+        ))
+    }
+}
+
+/// Walker over the next-header chain of an IPv6 packet.
+///
+/// Yield each extension header in order. When the iterator is exhausted without error,
+/// [upper_layer] return the upper-layer protocol and its offset in IPv6 payload.
+///
+/// The chain stops at a fragment header with non-zero offset, because the following
+/// headers are not carried by this fragment.
+///
+/// [upper_layer]: #method.upper_layer
+pub struct HeaderIter<'a> {
+    data: &'a [u8],
+    next_header: Protocol,
+    offset: usize,
+    count: usize,
+    seen_routing: bool,
+    seen_fragment: bool,
+    seen_dest_options: u8,
+    finished: bool,
+}
+
+impl<'a> HeaderIter<'a> {
+    /// Create walker from the next header field of IPv6 header and IPv6 payload.
+    pub fn new(next_header: Protocol, payload: &'a [u8]) -> Self {
+        Self {
+            data: payload,
+            next_header,
+            offset: 0,
+            count: 0,
+            seen_routing: false,
+            seen_fragment: false,
+            seen_dest_options: 0,
+            finished: false,
+        }
+    }
+
+    /// Return upper-layer protocol and payload offset once the chain is walked.
+    pub fn upper_layer(&self) -> Option<(Protocol, usize)> {
+        if self.finished {
+            Some((self.next_header, self.offset))
+        } else {
+            None
+        }
+    }
+
+    fn fail(&mut self, e: Error) -> Option<Result<Header<'a>>> {
+        self.data = &[];
+        self.next_header = Protocol::Ipv6NoNxt;
+        self.count = consts::MAX_CHAIN_LEN + 1;
+        Some(Err(e))
+    }
+}
+
+impl<'a> Iterator for HeaderIter<'a> {
+    type Item = Result<Header<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished || self.count > consts::MAX_CHAIN_LEN {
+            return None;
+        }
+
+        match self.next_header {
+            Protocol::HopByHop | Protocol::Ipv6Route | Protocol::Ipv6Frag | Protocol::Ipv6Opts => {}
+            _ => {
+                self.finished = true;
+                return None;
+            }
+        }
+
+        if self.count == consts::MAX_CHAIN_LEN {
+            return self.fail(Error::TooManyIpv6ExtHeaders);
+        }
+
+        let data = &self.data[self.offset..];
+
+        let (header, len) = match self.next_header {
+            Protocol::HopByHop => {
+                // Hop-by-Hop options must immediately follow IPv6 header, RFC 8200 section 4.1.
+                if self.count != 0 {
+                    return self.fail(Error::Ipv6HopByHopNotFirst);
+                }
+
+                match Options::new_checked(data) {
+                    Ok(h) => {
+                        let len = h.header_len();
+                        (Header::HopByHop(Options::new_unchecked(&data[..len])), len)
+                    }
+                    Err(e) => return self.fail(e),
+                }
+            }
+            Protocol::Ipv6Route => {
+                if self.seen_routing {
+                    return self.fail(Error::Ipv6ExtHeaderRepeated);
+                }
+                self.seen_routing = true;
+
+                match Routing::new_checked(data) {
+                    Ok(h) => {
+                        let len = h.header_len();
+                        (Header::Routing(Routing::new_unchecked(&data[..len])), len)
+                    }
+                    Err(e) => return self.fail(e),
+                }
+            }
+            Protocol::Ipv6Frag => {
+                if self.seen_fragment {
+                    return self.fail(Error::Ipv6ExtHeaderRepeated);
+                }
+                self.seen_fragment = true;
+
+                match Fragment::new_checked(data) {
+                    Ok(h) => {
+                        let len = h.header_len();
+                        (Header::Fragment(Fragment::new_unchecked(&data[..len])), len)
+                    }
+                    Err(e) => return self.fail(e),
+                }
+            }
+            _ => {
+                // Destination options may appear before routing header and before upper layer.
+                if self.seen_dest_options >= 2 {
+                    return self.fail(Error::Ipv6ExtHeaderRepeated);
+                }
+                self.seen_dest_options += 1;
+
+                match Options::new_checked(data) {
+                    Ok(h) => {
+                        let len = h.header_len();
+                        (
+                            Header::DestOptions(Options::new_unchecked(&data[..len])),
+                            len,
+                        )
+                    }
+                    Err(e) => return self.fail(e),
+                }
+            }
+        };
+
+        self.count += 1;
+        self.offset += len;
+        self.next_header = header.next_header();
+
+        if let Header::Fragment(f) = &header {
+            if f.frag_offset() != 0 {
+                self.finished = true;
+            }
+        }
+
+        Some(Ok(header))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Header, HeaderIter, Ipv6Option};
+    use crate::{layer3::Protocol, Error};
+
+    #[test]
+    fn test_walk_chain() {
+        let payload = [
+            // Hop-by-hop: next routing, router alert.
+            0x2b, 0x00, 0x05, 0x02, 0x00, 0x00, 0x01, 0x00, //
+            // Routing: next fragment, type 2, segments left 1.
+            0x2c, 0x02, 0x02, 0x01, 0x00, 0x00, 0x00, 0x00, //
+            0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00, //
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, //
+            // Fragment: next destination options, offset 0, more.
+            0x3c, 0x00, 0x00, 0x01, 0x12, 0x34, 0x56, 0x78, //
+            // Destination options: next udp, PadN.
+            0x11, 0x00, 0x01, 0x04, 0x00, 0x00, 0x00, 0x00, //
+            // Udp header.
+            0x00, 0x35, 0x00, 0x35, 0x00, 0x08, 0x00, 0x00,
+        ];
+
+        let mut iter = HeaderIter::new(Protocol::HopByHop, &payload);
+
+        match iter.next() {
+            Some(Ok(Header::HopByHop(h))) => {
+                let mut options = h.iter();
+                assert_eq!(options.next().unwrap().unwrap(), Ipv6Option::RouterAlert(0));
+                assert_eq!(options.next().unwrap().unwrap(), Ipv6Option::PadN(0));
+                assert!(options.next().is_none());
+            }
+            _ => panic!("expect hop-by-hop"),
+        }
+
+        match iter.next() {
+            Some(Ok(Header::Routing(h))) => {
+                assert_eq!(h.segments_left(), 1);
+                assert!(h.home_address().is_some());
+            }
+            _ => panic!("expect routing"),
+        }
+
+        match iter.next() {
+            Some(Ok(Header::Fragment(h))) => {
+                assert_eq!(h.ident(), 0x12345678);
+                assert!(h.more_frags());
+            }
+            _ => panic!("expect fragment"),
+        }
+
+        assert!(matches!(iter.next(), Some(Ok(Header::DestOptions(_)))));
+        assert!(iter.next().is_none());
+        assert_eq!(iter.upper_layer(), Some((Protocol::Udp, 48)));
+    }
+
+    #[test]
+    fn test_reject_malformed_chain() {
+        // Hop-by-hop not first.
+        let payload = [
+            0x00, 0x00, 0x01, 0x04, 0x00, 0x00, 0x00, 0x00, 0x3b, 0x00, 0, 0, 0, 0, 0, 0,
+        ];
+        let mut iter = HeaderIter::new(Protocol::Ipv6Opts, &payload);
+        assert!(iter.next().unwrap().is_ok());
+        assert!(matches!(
+            iter.next(),
+            Some(Err(Error::Ipv6HopByHopNotFirst))
+        ));
+        assert!(iter.next().is_none());
+        assert!(iter.upper_layer().is_none());
+
+        // Repeated routing header.
+        let payload = [0x2b, 0x00, 0, 0, 0, 0, 0, 0, 0x3b, 0x00, 0, 0, 0, 0, 0, 0];
+        let mut iter = HeaderIter::new(Protocol::Ipv6Route, &payload);
+        assert!(iter.next().unwrap().is_ok());
+        assert!(matches!(
+            iter.next(),
+            Some(Err(Error::Ipv6ExtHeaderRepeated))
+        ));
+
+        // Truncated header.
+        let payload = [0x11, 0x01, 0, 0, 0, 0, 0, 0];
+        let mut iter = HeaderIter::new(Protocol::Ipv6Opts, &payload);
+        assert!(matches!(
+            iter.next(),
+            Some(Err(Error::WrongLengthForIpv6ExtHeader))
+        ));
+    }
+}
@@ -1 +1,9 @@
+//! ipv6.
 
+mod address;
+pub use address::*;
+
+mod packet;
+pub use packet::*;
+
+pub mod ext_header;
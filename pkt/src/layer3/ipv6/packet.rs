@@ -0,0 +1,339 @@
+use core::fmt::{self, Display, Formatter};
+
+use crate::{layer3::Protocol, prelude::IntoInner, Error, Result};
+
+use super::{ext_header::HeaderIter, Address};
+use byteorder::{ByteOrder, NetworkEndian};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Packet<T> {
+    buffer: T,
+}
+
+impl<T: AsRef<[u8]>> Display for Packet<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("Ipv6 Packet:")?;
+        f.write_fmt(format_args!(
+            "Destination: {}, Source: {}, Payload Length: {}, Hop Limit: {}, Next Header: {:?}, Flow Label: {}",
+            self.dst_addr(),
+            self.src_addr(),
+            self.payload_len(),
+            self.hop_limit(),
+            self.next_header(),
+            self.flow_label(),
+        ))
+    }
+}
+
+pub mod field {
+    use crate::utils::field::Field;
+
+    pub const VER_TC_FLOW: Field = 0..4;
+    pub const LENGTH: Field = 4..6;
+    pub const NXT_HDR: usize = 6;
+    pub const HOP_LIMIT: usize = 7;
+    pub const SRC_ADDR: Field = 8..24;
+    pub const DST_ADDR: Field = 24..40;
+
+    pub const HEADER_LEN: usize = 40;
+}
+
+impl<T> IntoInner for Packet<T> {
+    type Inner = T;
+
+    fn into_inner(self) -> Self::Inner {
+        self.buffer
+    }
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    /// new unchecked packet.
+    pub fn new_unchecked(buffer: T) -> Packet<T> {
+        Packet { buffer }
+    }
+
+    /// new checked packet.
+    pub fn new_checked(buffer: T) -> Result<Packet<T>> {
+        let packet = Self::new_unchecked(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < field::HEADER_LEN || len < self.total_len() {
+            Err(Error::WrongLengthForIpv6Packet)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Return the header length, in octets.
+    #[inline]
+    pub fn header_len(&self) -> usize {
+        field::HEADER_LEN
+    }
+
+    /// Return the length of header and payload, in octets.
+    #[inline]
+    pub fn total_len(&self) -> usize {
+        field::HEADER_LEN + self.payload_len() as usize
+    }
+
+    /// Return the version field.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        data[field::VER_TC_FLOW.start] >> 4
+    }
+
+    /// Return the traffic class field.
+    #[inline]
+    pub fn traffic_class(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        ((NetworkEndian::read_u16(&data[0..2]) & 0x0ff0) >> 4) as u8
+    }
+
+    /// Return the flow label field.
+    #[inline]
+    pub fn flow_label(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u32(&data[field::VER_TC_FLOW]) & 0x000f_ffff
+    }
+
+    /// Return the payload length field.
+    #[inline]
+    pub fn payload_len(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[field::LENGTH])
+    }
+
+    /// Return the next header field.
+    #[inline]
+    pub fn next_header(&self) -> Protocol {
+        let data = self.buffer.as_ref();
+        Protocol::from(data[field::NXT_HDR])
+    }
+
+    /// Return the hop limit field.
+    #[inline]
+    pub fn hop_limit(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        data[field::HOP_LIMIT]
+    }
+
+    /// Return the source address field.
+    #[inline]
+    pub fn src_addr(&self) -> Address {
+        let data = self.buffer.as_ref();
+        Address::from_bytes(&data[field::SRC_ADDR])
+    }
+
+    /// Return the destination address field.
+    #[inline]
+    pub fn dst_addr(&self) -> Address {
+        let data = self.buffer.as_ref();
+        Address::from_bytes(&data[field::DST_ADDR])
+    }
+
+    #[inline]
+    pub fn payload(&self) -> &[u8] {
+        let range = field::HEADER_LEN..self.total_len();
+        let data = self.buffer.as_ref();
+        &data[range]
+    }
+
+    /// Walk extension headers of this packet.
+    pub fn ext_headers(&self) -> HeaderIter<'_> {
+        HeaderIter::new(self.next_header(), self.payload())
+    }
+
+    /// Return upper-layer protocol and its offset in payload, skipping extension headers.
+    pub fn upper_layer(&self) -> Result<(Protocol, usize)> {
+        let mut iter = self.ext_headers();
+
+        for header in &mut iter {
+            header?;
+        }
+
+        iter.upper_layer().ok_or(Error::TooManyIpv6ExtHeaders)
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    /// Set the version field.
+    #[inline]
+    pub fn set_version(&mut self, value: u8) {
+        let data = self.buffer.as_mut();
+        data[0] = (data[0] & !0xf0) | (value << 4);
+    }
+
+    /// Set the traffic class field.
+    #[inline]
+    pub fn set_traffic_class(&mut self, value: u8) {
+        let data = self.buffer.as_mut();
+        let raw = NetworkEndian::read_u16(&data[0..2]);
+        let raw = (raw & !0x0ff0) | ((value as u16) << 4);
+        NetworkEndian::write_u16(&mut data[0..2], raw);
+    }
+
+    /// Set the flow label field.
+    #[inline]
+    pub fn set_flow_label(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        let raw = NetworkEndian::read_u32(&data[field::VER_TC_FLOW]);
+        let raw = (raw & !0x000f_ffff) | (value & 0x000f_ffff);
+        NetworkEndian::write_u32(&mut data[field::VER_TC_FLOW], raw);
+    }
+
+    /// Set the payload length field.
+    #[inline]
+    pub fn set_payload_len(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[field::LENGTH], value)
+    }
+
+    /// Set the next header field.
+    #[inline]
+    pub fn set_next_header(&mut self, value: Protocol) {
+        let data = self.buffer.as_mut();
+        data[field::NXT_HDR] = value.into()
+    }
+
+    /// Set the hop limit field.
+    #[inline]
+    pub fn set_hop_limit(&mut self, value: u8) {
+        let data = self.buffer.as_mut();
+        data[field::HOP_LIMIT] = value
+    }
+
+    /// Set the source address field.
+    #[inline]
+    pub fn set_src_addr(&mut self, value: Address) {
+        let data = self.buffer.as_mut();
+        data[field::SRC_ADDR].copy_from_slice(value.as_bytes())
+    }
+
+    /// Set the destination address field.
+    #[inline]
+    pub fn set_dst_addr(&mut self, value: Address) {
+        let data = self.buffer.as_mut();
+        data[field::DST_ADDR].copy_from_slice(value.as_bytes())
+    }
+
+    /// Return a mutable pointer to the payload.
+    #[inline]
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let range = field::HEADER_LEN..self.total_len();
+        let data = self.buffer.as_mut();
+        &mut data[range]
+    }
+}
+
+/// High-level representation of IPv6 fixed header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repr {
+    pub src_addr: Address,
+    pub dst_addr: Address,
+    pub next_header: Protocol,
+    pub payload_len: usize,
+    pub hop_limit: u8,
+}
+
+impl Repr {
+    /// Parse an IPv6 packet and return a high-level representation.
+    pub fn parse<T: AsRef<[u8]>>(packet: &Packet<T>) -> Result<Repr> {
+        packet.check_len()?;
+
+        if packet.version() != 6 {
+            return Err(Error::UnknownIpVersionNumber);
+        }
+
+        Ok(Repr {
+            src_addr: packet.src_addr(),
+            dst_addr: packet.dst_addr(),
+            next_header: packet.next_header(),
+            payload_len: packet.payload_len() as usize,
+            hop_limit: packet.hop_limit(),
+        })
+    }
+
+    /// Return the length of header that will be emitted from this representation.
+    pub fn buffer_len(&self) -> usize {
+        field::HEADER_LEN
+    }
+
+    /// Emit this representation into an IPv6 packet, fill payload length.
+    pub fn emit<T: AsRef<[u8]> + AsMut<[u8]>>(&self, packet: &mut Packet<T>) {
+        packet.set_version(6);
+        packet.set_traffic_class(0);
+        packet.set_flow_label(0);
+        packet.set_payload_len(self.payload_len as u16);
+        packet.set_next_header(self.next_header);
+        packet.set_hop_limit(self.hop_limit);
+        packet.set_src_addr(self.src_addr);
+        packet.set_dst_addr(self.dst_addr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static PACKET_BYTES: [u8; 44] = [
+        0x6a, 0x51, 0x23, 0x45, 0x00, 0x04, 0x11, 0x40, 0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xfe, 0x80, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0xde, 0xad, 0xbe, 0xef,
+    ];
+
+    fn src_addr() -> Address {
+        Address::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)
+    }
+
+    fn dst_addr() -> Address {
+        Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 2)
+    }
+
+    #[test]
+    fn test_deconstruct() {
+        let pkt = Packet::new_checked(&PACKET_BYTES[..]).unwrap();
+        assert_eq!(pkt.version(), 6);
+        assert_eq!(pkt.traffic_class(), 0xa5);
+        assert_eq!(pkt.flow_label(), 0x12345);
+        assert_eq!(pkt.payload_len(), 4);
+        assert_eq!(pkt.total_len(), 44);
+        assert_eq!(pkt.next_header(), Protocol::Udp);
+        assert_eq!(pkt.hop_limit(), 0x40);
+        assert_eq!(pkt.src_addr(), src_addr());
+        assert_eq!(pkt.dst_addr(), dst_addr());
+        assert_eq!(pkt.payload(), &[0xde, 0xad, 0xbe, 0xef]);
+    }
+
+    #[test]
+    fn test_construct() {
+        let mut bytes = [0xffu8; 44];
+        let mut pkt = Packet::new_unchecked(&mut bytes[..]);
+        pkt.set_version(6);
+        pkt.set_traffic_class(0xa5);
+        pkt.set_flow_label(0x12345);
+        pkt.set_payload_len(4);
+        pkt.set_next_header(Protocol::Udp);
+        pkt.set_hop_limit(0x40);
+        pkt.set_src_addr(src_addr());
+        pkt.set_dst_addr(dst_addr());
+        pkt.payload_mut().copy_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(&bytes[..], &PACKET_BYTES[..]);
+    }
+
+    #[test]
+    fn test_check_len() {
+        assert!(matches!(
+            Packet::new_checked(&PACKET_BYTES[..39]),
+            Err(Error::WrongLengthForIpv6Packet)
+        ));
+        assert!(matches!(
+            Packet::new_checked(&PACKET_BYTES[..43]),
+            Err(Error::WrongLengthForIpv6Packet)
+        ));
+    }
+}
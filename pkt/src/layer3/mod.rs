@@ -2,7 +2,7 @@
 
 pub mod arp;
 pub mod ipv4;
-// pub mod ipv6;
+pub mod ipv6;
 
 mod address;
 pub use address::*;
@@ -1,11 +1,11 @@
 use crate::{Error, Result};
 
-use super::{arp, ipv4};
+use super::{arp, ipv4, ipv6};
 
 #[derive(Debug, Clone)]
 pub enum IpPacket<T> {
     IPv4(ipv4::Packet<T>),
-    Ipv6,
+    IPv6(ipv6::Packet<T>),
 }
 
 impl<T: AsRef<[u8]>> IpPacket<T> {
@@ -14,6 +14,9 @@ impl<T: AsRef<[u8]>> IpPacket<T> {
         if data[ipv4::field::VER_IHL] >> 4 == 4 {
             let pkt = ipv4::Packet::new_checked(t)?;
             Ok(IpPacket::IPv4(pkt))
+        } else if data[0] >> 4 == 6 {
+            let pkt = ipv6::Packet::new_checked(t)?;
+            Ok(IpPacket::IPv6(pkt))
         } else {
             Err(Error::UnknownIpVersionNumber)
         }
@@ -23,6 +26,6 @@ impl<T: AsRef<[u8]>> IpPacket<T> {
 pub enum Packet<T> {
     Arp(arp::Packet<T>),
     IPv4(ipv4::Packet<T>),
-    Ipv6,
+    IPv6(ipv6::Packet<T>),
     Unspecified,
 }
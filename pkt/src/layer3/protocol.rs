@@ -1,5 +1,5 @@
 /// Ip protocol type.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Protocol {
     HopByHop,
     Icmp,
@@ -2,9 +2,9 @@ use core::fmt::{self, Display, Formatter};
 
 use byteorder::{ByteOrder, NetworkEndian};
 
-use crate::{utils::checksum, Error, IntoInner, Result};
+use crate::{layer3::ipv4, utils::checksum, Error, IntoInner, Result};
 
-use super::Message;
+use super::{consts, Echo, Message, Timestamp};
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Packet<T> {
@@ -18,12 +18,43 @@ pub mod field {
     pub const CODE: usize = 1;
     pub const CHECKSUM: Field = 2..4;
 
-    // pub const UNUSED: Field = 4..8;
+    pub const UNUSED: Field = 4..8;
 
     pub const ECHO_IDENT: Field = 4..6;
     pub const ECHO_SEQNO: Field = 6..8;
 
+    pub const NEXT_HOP_MTU: Field = 6..8;
+    pub const GATEWAY: Field = 4..8;
+    pub const POINTER: usize = 4;
+
     pub const HEADER_END: usize = 8;
+
+    pub const TSTAMP_ORIGINATE: Field = 8..12;
+    pub const TSTAMP_RECEIVE: Field = 12..16;
+    pub const TSTAMP_TRANSMIT: Field = 16..20;
+    pub const TSTAMP_HEADER_END: usize = TSTAMP_TRANSMIT.end;
+
+    /// Octets of original datagram data carried after its IP header in error messages.
+    pub const ORIGINAL_DATA_LEN: usize = 8;
+}
+
+/// Return the length of original datagram carried by error message.
+///
+/// It is the IP header and the first 8 octets of data, limited by the length of `original`.
+pub fn original_datagram_len(original: &[u8]) -> usize {
+    let header_len = original
+        .first()
+        .map(|b| ((b & 0x0f) * 4) as usize)
+        .unwrap_or(0);
+    original.len().min(header_len + field::ORIGINAL_DATA_LEN)
+}
+
+/// Return the fixed header length of message type.
+fn header_len(ty: u8) -> usize {
+    match ty {
+        consts::message::TIMESTAMP | consts::message::TIMESTAMP_REPLY => field::TSTAMP_HEADER_END,
+        _ => field::HEADER_END,
+    }
 }
 
 impl<T> IntoInner for Packet<T> {
@@ -36,7 +67,7 @@ impl<T> IntoInner for Packet<T> {
 
 impl<T: AsRef<[u8]>> Display for Packet<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        f.write_str("UDP Packet:")?;
+        f.write_str("ICMPv4 Packet:")?;
         f.write_fmt(format_args!("Protocol {:?}", self.protocol()))
     }
 }
@@ -58,14 +89,10 @@ impl<T: AsRef<[u8]>> Packet<T> {
     }
 
     /// Ensure that no accessor method will panic if called.
-    /// Returns `Err(Error)` if the buffer is too short.
-    ///
-    /// The result of this check is invalidated by calling [set_header_len].
-    ///
-    /// [set_header_len]: #method.set_header_len
+    /// Returns `Err(Error)` if the buffer is too short for the message type.
     pub fn check_len(&self) -> Result<()> {
         let len = self.buffer.as_ref().len();
-        if len < field::HEADER_END {
+        if len < field::HEADER_END || len < self.header_len() {
             Err(Error::WrongLengthForBufferLength)
         } else {
             Ok(())
@@ -79,14 +106,30 @@ impl<T: AsRef<[u8]>> Packet<T> {
 
         let mut msg = Message::from_type_code(ty, code);
 
+        let data = self.buffer.as_ref();
+
         match &mut msg {
-            Message::EchoRequest(e) => {
-                e.ident = self.echo_ident();
-                e.seq_no = self.echo_seq_no();
+            Message::EchoRequest(e) | Message::EchoReply(e) => {
+                *e = Echo {
+                    ident: self.echo_ident(),
+                    seq_no: self.echo_seq_no(),
+                };
             }
-            Message::EchoReply(e) => {
-                e.ident = self.echo_ident();
-                e.seq_no = self.echo_seq_no();
+            Message::DstUnreachable(_, mtu) => {
+                *mtu = NetworkEndian::read_u16(&data[field::NEXT_HOP_MTU]);
+            }
+            Message::Redirect(_, gateway) => {
+                *gateway = ipv4::Address::from_bytes(&data[field::GATEWAY]);
+            }
+            Message::ParamProblem(_, pointer) => *pointer = data[field::POINTER],
+            Message::Timestamp(t) | Message::TimestampReply(t) => {
+                *t = Timestamp {
+                    ident: self.echo_ident(),
+                    seq_no: self.echo_seq_no(),
+                    originate: NetworkEndian::read_u32(&data[field::TSTAMP_ORIGINATE]),
+                    receive: NetworkEndian::read_u32(&data[field::TSTAMP_RECEIVE]),
+                    transmit: NetworkEndian::read_u32(&data[field::TSTAMP_TRANSMIT]),
+                };
             }
             _ => {}
         }
@@ -115,20 +158,20 @@ impl<T: AsRef<[u8]>> Packet<T> {
         NetworkEndian::read_u16(&data[field::CHECKSUM])
     }
 
-    /// Return the identifier field (for echo request and reply packets).
+    /// Return the identifier field (for echo and timestamp packets).
     ///
     /// # Panics
-    /// This function may panic if this packet is not an echo request or reply packet.
+    /// This function may panic if this packet is not an echo or timestamp packet.
     #[inline]
     fn echo_ident(&self) -> u16 {
         let data = self.buffer.as_ref();
         NetworkEndian::read_u16(&data[field::ECHO_IDENT])
     }
 
-    /// Return the sequence number field (for echo request and reply packets).
+    /// Return the sequence number field (for echo and timestamp packets).
     ///
     /// # Panics
-    /// This function may panic if this packet is not an echo request or reply packet.
+    /// This function may panic if this packet is not an echo or timestamp packet.
     #[inline]
     fn echo_seq_no(&self) -> u16 {
         let data = self.buffer.as_ref();
@@ -138,7 +181,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     /// Return the header length.
     /// The result depends on the value of the message type field.
     pub fn header_len(&self) -> usize {
-        field::HEADER_END
+        header_len(self.msg_type())
     }
 
     /// Validate the header checksum.
@@ -154,28 +197,74 @@ impl<T: AsRef<[u8]>> Packet<T> {
         checksum::data(data) == !0
     }
 
+    /// Return the data after fixed header.
+    ///
+    /// For echo messages it is the echo data, for error messages it is the original datagram.
     pub fn payload(&self) -> &[u8] {
         let data = self.buffer.as_ref();
         &data[self.header_len()..]
     }
+
+    /// Return the IP header of original datagram carried by error message.
+    ///
+    /// Only header fields can be accessed on returned packet, the datagram is truncated.
+    pub fn original_header(&self) -> Result<ipv4::Packet<&[u8]>> {
+        let payload = self.payload();
+        let header_len = payload
+            .first()
+            .map(|b| ((b & 0x0f) * 4) as usize)
+            .ok_or(Error::WrongLengthForIpv4Packet)?;
+
+        if header_len < ipv4::field::HEADER_LEN_WITHOUT_OPTION as usize
+            || payload.len() < header_len
+        {
+            return Err(Error::WrongLengthForIpv4Packet);
+        }
+
+        Ok(ipv4::Packet::new_unchecked(&payload[..header_len]))
+    }
+
+    /// Return the leading octets of data of original datagram carried by error message.
+    pub fn original_data(&self) -> Result<&[u8]> {
+        let header_len = self.original_header()?.header_len() as usize;
+        let data = &self.payload()[header_len..];
+        Ok(&data[..data.len().min(field::ORIGINAL_DATA_LEN)])
+    }
 }
 
 impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
-    /// Set protocol
+    /// Set protocol, write type, code and fixed fields of message.
     pub fn set_protocol(&mut self, protocol: Message) {
         let (ty, code) = protocol.to_type_code();
 
         self.set_msg_type(ty);
         self.set_msg_code(code);
 
+        let header_len = self.header_len();
+        let data = self.buffer.as_mut();
+
+        for b in data[field::UNUSED.start..header_len].iter_mut() {
+            *b = 0;
+        }
+
         match protocol {
-            Message::EchoRequest(v) => {
-                self.set_echo_ident(v.ident);
-                self.set_echo_seq_no(v.seq_no);
+            Message::EchoRequest(v) | Message::EchoReply(v) => {
+                NetworkEndian::write_u16(&mut data[field::ECHO_IDENT], v.ident);
+                NetworkEndian::write_u16(&mut data[field::ECHO_SEQNO], v.seq_no);
+            }
+            Message::DstUnreachable(_, mtu) => {
+                NetworkEndian::write_u16(&mut data[field::NEXT_HOP_MTU], mtu);
+            }
+            Message::Redirect(_, gateway) => {
+                data[field::GATEWAY].copy_from_slice(gateway.as_bytes());
             }
-            Message::EchoReply(v) => {
-                self.set_echo_ident(v.ident);
-                self.set_echo_seq_no(v.seq_no);
+            Message::ParamProblem(_, pointer) => data[field::POINTER] = pointer,
+            Message::Timestamp(t) | Message::TimestampReply(t) => {
+                NetworkEndian::write_u32(&mut data[field::TSTAMP_ORIGINATE], t.originate);
+                NetworkEndian::write_u32(&mut data[field::TSTAMP_RECEIVE], t.receive);
+                NetworkEndian::write_u32(&mut data[field::TSTAMP_TRANSMIT], t.transmit);
+                NetworkEndian::write_u16(&mut data[field::ECHO_IDENT], t.ident);
+                NetworkEndian::write_u16(&mut data[field::ECHO_SEQNO], t.seq_no);
             }
             _ => {}
         }
@@ -202,24 +291,15 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
         NetworkEndian::write_u16(&mut data[field::CHECKSUM], value)
     }
 
-    /// Set the identifier field (for echo request and reply packets).
-    ///
-    /// # Panics
-    /// This function may panic if this packet is not an echo request or reply packet.
-    #[inline]
-    fn set_echo_ident(&mut self, value: u16) {
-        let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::ECHO_IDENT], value)
-    }
-
-    /// Set the sequence number field (for echo request and reply packets).
+    /// Copy the IP header and leading data of original datagram into payload.
+    /// Return length written, see [original_datagram_len].
     ///
     /// # Panics
-    /// This function may panic if this packet is not an echo request or reply packet.
-    #[inline]
-    fn set_echo_seq_no(&mut self, value: u16) {
-        let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::ECHO_SEQNO], value)
+    /// This function panics if payload is shorter than [original_datagram_len].
+    pub fn set_original_datagram(&mut self, original: &[u8]) -> usize {
+        let len = original_datagram_len(original);
+        self.payload_mut()[..len].copy_from_slice(&original[..len]);
+        len
     }
 
     /// Compute and fill in the header checksum.
@@ -238,3 +318,169 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
         &mut data[range]
     }
 }
+
+/// High-level representation of ICMPv4 packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repr<'a> {
+    pub message: Message,
+    /// Data after fixed header, echo data or original datagram.
+    pub payload: &'a [u8],
+}
+
+impl<'a> Repr<'a> {
+    /// Parse an ICMPv4 packet and return a high-level representation, checksum is verified.
+    pub fn parse<T: AsRef<[u8]>>(packet: &'a Packet<T>) -> Result<Repr<'a>> {
+        packet.check_len()?;
+
+        if !packet.verify_checksum() {
+            return Err(Error::WrongChecksum);
+        }
+
+        Ok(Repr {
+            message: packet.protocol(),
+            payload: packet.payload(),
+        })
+    }
+
+    /// Return the length of packet that will be emitted from this representation.
+    pub fn buffer_len(&self) -> usize {
+        header_len(self.message.to_type_code().0) + self.payload.len()
+    }
+
+    /// Emit this representation into an ICMPv4 packet, fill checksum.
+    ///
+    /// # Panics
+    /// This function panics if the buffer is not [buffer_len] long.
+    ///
+    /// [buffer_len]: #method.buffer_len
+    pub fn emit<T: AsRef<[u8]> + AsMut<[u8]>>(&self, packet: &mut Packet<T>) {
+        packet.set_protocol(self.message.clone());
+        packet.payload_mut().copy_from_slice(self.payload);
+        packet.fill_checksum();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layer3::{ipv4, Protocol};
+    use crate::layer4::icmpv4::{DstUnreachable, ParamProblem, Redirect, TimeExceeded};
+
+    #[test]
+    fn test_repr() {
+        let original = [0x45u8, 0, 0, 28, 0, 0, 0, 0, 64, 17, 0, 0];
+        let gateway = ipv4::Address::new(192, 168, 1, 254);
+
+        let messages = [
+            Message::EchoReply(Echo {
+                ident: 0x1234,
+                seq_no: 7,
+            }),
+            Message::DstUnreachable(DstUnreachable::FragRequired, 576),
+            Message::DstUnreachable(DstUnreachable::PortUnreachable, 0),
+            Message::Redirect(Redirect::Host, gateway),
+            Message::TimeExceeded(TimeExceeded::TtlExpired),
+            Message::ParamProblem(ParamProblem::AtPointer, 8),
+            Message::RouterSolicit,
+        ];
+
+        for message in messages.iter() {
+            let repr = Repr {
+                message: message.clone(),
+                payload: &original,
+            };
+
+            let mut bytes = [0xffu8; 64];
+            let bytes = &mut bytes[..repr.buffer_len()];
+            let mut packet = Packet::new_unchecked(&mut bytes[..]);
+            repr.emit(&mut packet);
+
+            let packet = Packet::new_checked(&bytes[..]).unwrap();
+            assert_eq!(Repr::parse(&packet).unwrap(), repr);
+
+            // Corrupted checksum.
+            bytes[2] ^= 0xff;
+            let packet = Packet::new_checked(&bytes[..]).unwrap();
+            assert!(Repr::parse(&packet).is_err());
+        }
+    }
+
+    #[test]
+    fn test_echo_and_timestamp() {
+        let mut bytes = [0u8; 12];
+        let mut packet = Packet::new_unchecked(&mut bytes[..]);
+        packet.set_protocol(Message::EchoRequest(Echo {
+            ident: 0x1234,
+            seq_no: 7,
+        }));
+        packet.payload_mut().copy_from_slice(b"ping");
+        packet.fill_checksum();
+
+        let packet = Packet::new_checked(&bytes[..]).unwrap();
+        assert!(packet.verify_checksum());
+        assert_eq!(
+            packet.protocol(),
+            Message::EchoRequest(Echo {
+                ident: 0x1234,
+                seq_no: 7
+            })
+        );
+        assert_eq!(packet.payload(), b"ping");
+
+        let tstamp = Timestamp {
+            ident: 1,
+            seq_no: 2,
+            originate: 3,
+            receive: 4,
+            transmit: 5,
+        };
+        let mut bytes = [0u8; 20];
+        let mut packet = Packet::new_unchecked(&mut bytes[..]);
+        packet.set_protocol(Message::TimestampReply(tstamp.clone()));
+
+        let packet = Packet::new_checked(&bytes[..]).unwrap();
+        assert_eq!(packet.protocol(), Message::TimestampReply(tstamp));
+        assert!(Packet::new_checked(&bytes[..12]).is_err());
+    }
+
+    #[test]
+    fn test_error_original_datagram() {
+        let mut original = [0u8; 36];
+        let mut ip = ipv4::Packet::new_unchecked(&mut original[..]);
+        ip.set_version(4);
+        ip.set_header_len(20);
+        ip.set_total_len(36);
+        ip.set_protocol(Protocol::Udp);
+        ip.set_dst_addr(ipv4::Address::new(10, 0, 0, 2));
+        ip.payload_mut().copy_from_slice(&[1; 16]);
+
+        let len = original_datagram_len(&original);
+        assert_eq!(len, 28);
+
+        let mut bytes = [0u8; 36];
+        let mut packet = Packet::new_unchecked(&mut bytes[..8 + len]);
+        packet.set_protocol(Message::DstUnreachable(DstUnreachable::FragRequired, 1400));
+        packet.set_original_datagram(&original);
+        packet.fill_checksum();
+
+        let packet = Packet::new_checked(&bytes[..8 + len]).unwrap();
+        assert!(packet.protocol().is_error());
+        assert_eq!(
+            packet.protocol(),
+            Message::DstUnreachable(DstUnreachable::FragRequired, 1400)
+        );
+        let header = packet.original_header().unwrap();
+        assert_eq!(header.protocol(), Protocol::Udp);
+        assert_eq!(header.dst_addr(), ipv4::Address::new(10, 0, 0, 2));
+        assert_eq!(packet.original_data().unwrap(), &[1; 8]);
+
+        let mut bytes = [0u8; 8];
+        let gateway = ipv4::Address::new(192, 168, 1, 1);
+        let mut packet = Packet::new_unchecked(&mut bytes[..]);
+        packet.set_protocol(Message::Redirect(Redirect::Host, gateway));
+        assert_eq!(
+            packet.protocol(),
+            Message::Redirect(Redirect::Host, gateway)
+        );
+    }
+}
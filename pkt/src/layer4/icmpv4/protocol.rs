@@ -1,3 +1,5 @@
+use crate::layer3::ipv4::Address;
+
 pub mod consts {
     pub mod message {
         pub const ECHO_REPLY: u8 = 0;
@@ -51,35 +53,45 @@ pub mod consts {
 }
 
 /// Echo
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Echo {
     pub ident: u16,
     pub seq_no: u16,
 }
 
+/// Timestamp request and reply fields, timestamps in milliseconds since midnight UT.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Timestamp {
+    pub ident: u16,
+    pub seq_no: u16,
+    pub originate: u32,
+    pub receive: u32,
+    pub transmit: u32,
+}
+
 /// Internet protocol control message type.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Message {
     /// Echo request
     EchoRequest(Echo),
     /// Echo reply
     EchoReply(Echo),
-    /// Destination unreachable
-    DstUnreachable(DstUnreachable),
-    /// Message redirect
-    Redirect(Redirect),
+    /// Destination unreachable, carry the MTU of next-hop link for fragmentation required
+    DstUnreachable(DstUnreachable, u16),
+    /// Message redirect, carry the gateway address
+    Redirect(Redirect, Address),
     /// Router advertisement
     RouterAdvert,
     /// Router solicitation
     RouterSolicit,
     /// Time exceeded
     TimeExceeded(TimeExceeded),
-    /// Parameter problem
-    ParamProblem(ParamProblem),
+    /// Parameter problem, carry the pointer to error octet
+    ParamProblem(ParamProblem, u8),
     /// Timestamp
-    Timestamp,
+    Timestamp(Timestamp),
     /// Timestamp reply
-    TimestampReply,
+    TimestampReply(Timestamp),
     /// Unknown
     Unknown(u8, u8),
 }
@@ -89,16 +101,18 @@ impl Message {
         match ty {
             consts::message::ECHO_REPLY => Self::EchoReply(Echo::default()),
             consts::message::DST_UNREACHABLE => {
-                Self::DstUnreachable(DstUnreachable::from_code(code))
+                Self::DstUnreachable(DstUnreachable::from_code(code), 0)
+            }
+            consts::message::REDIRECT => {
+                Self::Redirect(Redirect::from_code(code), Address::default())
             }
-            consts::message::REDIRECT => Self::Redirect(Redirect::from_code(code)),
             consts::message::ECHO_REQUEST => Self::EchoRequest(Echo::default()),
             consts::message::ROUTER_ADVERT => Self::RouterAdvert,
             consts::message::ROUTER_SOLICIT => Self::RouterSolicit,
             consts::message::TIME_EXCEEDED => Self::TimeExceeded(TimeExceeded::from_code(code)),
-            consts::message::PARAM_PROBLEM => Self::ParamProblem(ParamProblem::from_code(code)),
-            consts::message::TIMESTAMP => Self::Timestamp,
-            consts::message::TIMESTAMP_REPLY => Self::TimestampReply,
+            consts::message::PARAM_PROBLEM => Self::ParamProblem(ParamProblem::from_code(code), 0),
+            consts::message::TIMESTAMP => Self::Timestamp(Timestamp::default()),
+            consts::message::TIMESTAMP_REPLY => Self::TimestampReply(Timestamp::default()),
             _ => Self::Unknown(ty, code),
         }
     }
@@ -107,20 +121,31 @@ impl Message {
         match self {
             Self::EchoRequest(_) => (consts::message::ECHO_REQUEST, 0),
             Self::EchoReply(_) => (consts::message::ECHO_REPLY, 0),
-            Self::Timestamp => (consts::message::TIMESTAMP, 0),
-            Self::TimestampReply => (consts::message::TIMESTAMP_REPLY, 0),
+            Self::Timestamp(_) => (consts::message::TIMESTAMP, 0),
+            Self::TimestampReply(_) => (consts::message::TIMESTAMP_REPLY, 0),
             Self::RouterSolicit => (consts::message::ROUTER_SOLICIT, 0),
             Self::RouterAdvert => (consts::message::ROUTER_ADVERT, 0),
-            Self::DstUnreachable(v) => (consts::message::DST_UNREACHABLE, v.to_code()),
-            Self::Redirect(v) => (consts::message::REDIRECT, v.to_code()),
+            Self::DstUnreachable(v, _) => (consts::message::DST_UNREACHABLE, v.to_code()),
+            Self::Redirect(v, _) => (consts::message::REDIRECT, v.to_code()),
             Self::TimeExceeded(v) => (consts::message::TIME_EXCEEDED, v.to_code()),
-            Self::ParamProblem(v) => (consts::message::PARAM_PROBLEM, v.to_code()),
+            Self::ParamProblem(v, _) => (consts::message::PARAM_PROBLEM, v.to_code()),
             Self::Unknown(v1, v2) => (*v1, *v2),
         }
     }
+
+    /// Query whether this message is an error message, which carry the original datagram.
+    pub fn is_error(&self) -> bool {
+        matches!(
+            self,
+            Self::DstUnreachable(_, _)
+                | Self::Redirect(_, _)
+                | Self::TimeExceeded(_)
+                | Self::ParamProblem(_, _)
+        )
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DstUnreachable {
     /// Destination network unreachable
     NetUnreachable,
@@ -205,7 +230,7 @@ impl DstUnreachable {
 }
 
 /// Internet protocol control message subtype for type "Redirect Message".
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Redirect {
     /// Redirect Datagram for the Network
     Net,
@@ -241,7 +266,7 @@ impl Redirect {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TimeExceeded {
     /// TTL expired in transit
     TtlExpired,
@@ -270,7 +295,7 @@ impl TimeExceeded {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ParamProblem {
     /// Pointer indicates the error
     AtPointer,
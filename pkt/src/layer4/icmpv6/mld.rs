@@ -0,0 +1,212 @@
+use byteorder::{ByteOrder, NetworkEndian};
+
+use crate::{layer3::ipv6::Address, prelude::IntoInner, Error, Result};
+
+pub mod field {
+    use crate::utils::field::Field;
+
+    pub const RECORD_TYPE: usize = 0;
+    pub const AUX_DATA_LEN: usize = 1;
+    pub const NUM_SOURCES: Field = 2..4;
+    pub const MCAST_ADDR: Field = 4..20;
+
+    pub const RECORD_HEADER_LEN: usize = MCAST_ADDR.end;
+
+    pub fn source(index: usize) -> Field {
+        let start = RECORD_HEADER_LEN + index * 16;
+        start..start + 16
+    }
+}
+
+/// Multicast address record type of MLDv2 report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordType {
+    ModeIsInclude,
+    ModeIsExclude,
+    ChangeToInclude,
+    ChangeToExclude,
+    AllowNewSources,
+    BlockOldSources,
+    Unknown(u8),
+}
+
+impl From<u8> for RecordType {
+    fn from(v: u8) -> Self {
+        match v {
+            1 => Self::ModeIsInclude,
+            2 => Self::ModeIsExclude,
+            3 => Self::ChangeToInclude,
+            4 => Self::ChangeToExclude,
+            5 => Self::AllowNewSources,
+            6 => Self::BlockOldSources,
+            _ => Self::Unknown(v),
+        }
+    }
+}
+
+impl From<RecordType> for u8 {
+    fn from(v: RecordType) -> u8 {
+        match v {
+            RecordType::ModeIsInclude => 1,
+            RecordType::ModeIsExclude => 2,
+            RecordType::ChangeToInclude => 3,
+            RecordType::ChangeToExclude => 4,
+            RecordType::AllowNewSources => 5,
+            RecordType::BlockOldSources => 6,
+            RecordType::Unknown(v) => v,
+        }
+    }
+}
+
+/// Multicast address record in MLDv2 report.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AddressRecord<T> {
+    buffer: T,
+}
+
+impl<T> IntoInner for AddressRecord<T> {
+    type Inner = T;
+
+    fn into_inner(self) -> Self::Inner {
+        self.buffer
+    }
+}
+
+impl<T: AsRef<[u8]>> AddressRecord<T> {
+    /// new unchecked record.
+    pub fn new_unchecked(buffer: T) -> AddressRecord<T> {
+        AddressRecord { buffer }
+    }
+
+    /// new checked record.
+    pub fn new_checked(buffer: T) -> Result<AddressRecord<T>> {
+        let record = Self::new_unchecked(buffer);
+        let len = record.buffer.as_ref().len();
+        if len < field::RECORD_HEADER_LEN || len < record.record_len() {
+            Err(Error::WrongLengthForMldRecord)
+        } else {
+            Ok(record)
+        }
+    }
+
+    /// Return the record type field.
+    #[inline]
+    pub fn record_type(&self) -> RecordType {
+        let data = self.buffer.as_ref();
+        RecordType::from(data[field::RECORD_TYPE])
+    }
+
+    /// Return the auxiliary data length, in octets.
+    #[inline]
+    pub fn aux_data_len(&self) -> usize {
+        let data = self.buffer.as_ref();
+        data[field::AUX_DATA_LEN] as usize * 4
+    }
+
+    /// Return the number of sources field.
+    #[inline]
+    pub fn num_sources(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[field::NUM_SOURCES])
+    }
+
+    /// Return the multicast address field.
+    #[inline]
+    pub fn mcast_addr(&self) -> Address {
+        let data = self.buffer.as_ref();
+        Address::from_bytes(&data[field::MCAST_ADDR])
+    }
+
+    /// Return the source address at index.
+    ///
+    /// # Panics
+    /// This function panics if index is not less than `num_sources()`.
+    #[inline]
+    pub fn source(&self, index: usize) -> Address {
+        assert!(index < self.num_sources() as usize);
+        let data = self.buffer.as_ref();
+        Address::from_bytes(&data[field::source(index)])
+    }
+
+    /// Return the total length of this record, in octets.
+    pub fn record_len(&self) -> usize {
+        field::source(self.num_sources() as usize).start + self.aux_data_len()
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> AddressRecord<T> {
+    /// Set the record type field.
+    #[inline]
+    pub fn set_record_type(&mut self, value: RecordType) {
+        let data = self.buffer.as_mut();
+        data[field::RECORD_TYPE] = value.into()
+    }
+
+    /// Set the auxiliary data length, in octets. Must be multiple of 4.
+    #[inline]
+    pub fn set_aux_data_len(&mut self, value: usize) {
+        let data = self.buffer.as_mut();
+        data[field::AUX_DATA_LEN] = (value / 4) as u8
+    }
+
+    /// Set the number of sources field.
+    #[inline]
+    pub fn set_num_sources(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[field::NUM_SOURCES], value)
+    }
+
+    /// Set the multicast address field.
+    #[inline]
+    pub fn set_mcast_addr(&mut self, value: Address) {
+        let data = self.buffer.as_mut();
+        data[field::MCAST_ADDR].copy_from_slice(value.as_bytes())
+    }
+
+    /// Set the source address at index.
+    #[inline]
+    pub fn set_source(&mut self, index: usize, value: Address) {
+        let data = self.buffer.as_mut();
+        data[field::source(index)].copy_from_slice(value.as_bytes())
+    }
+}
+
+/// Iterator over address records of MLDv2 report.
+pub struct AddressRecordIter<'a> {
+    data: &'a [u8],
+    remain: u16,
+}
+
+impl<'a> AddressRecordIter<'a> {
+    pub fn new(data: &'a [u8], num_records: u16) -> Self {
+        Self {
+            data,
+            remain: num_records,
+        }
+    }
+}
+
+impl<'a> Iterator for AddressRecordIter<'a> {
+    type Item = Result<AddressRecord<&'a [u8]>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remain == 0 {
+            return None;
+        }
+
+        self.remain -= 1;
+
+        match AddressRecord::new_checked(self.data) {
+            Ok(record) => {
+                let len = record.record_len();
+                let (current, rest) = self.data.split_at(len);
+                self.data = rest;
+                Some(Ok(AddressRecord::new_unchecked(current)))
+            }
+            Err(e) => {
+                self.remain = 0;
+                Some(Err(e))
+            }
+        }
+    }
+}
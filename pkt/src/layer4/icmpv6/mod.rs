@@ -0,0 +1,11 @@
+mod packet;
+pub use packet::*;
+
+mod protocol;
+pub use protocol::*;
+
+pub mod ndp;
+pub use ndp::{NdpOption, NdpOptionIter, PrefixInformation};
+
+pub mod mld;
+pub use mld::{AddressRecord, AddressRecordIter, RecordType};
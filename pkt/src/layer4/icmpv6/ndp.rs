@@ -0,0 +1,306 @@
+use byteorder::{ByteOrder, NetworkEndian};
+
+use crate::{layer2, layer3::ipv6::Address, Error, Result};
+
+pub mod consts {
+    pub const SOURCE_LINK_LAYER_ADDR: u8 = 1;
+    pub const TARGET_LINK_LAYER_ADDR: u8 = 2;
+    pub const PREFIX_INFORMATION: u8 = 3;
+    pub const REDIRECTED_HEADER: u8 = 4;
+    pub const MTU: u8 = 5;
+
+    pub const PREFIX_FLAG_ON_LINK: u8 = 0x80;
+    pub const PREFIX_FLAG_AUTONOMOUS: u8 = 0x40;
+}
+
+pub mod field {
+    use crate::utils::field::Field;
+
+    pub const TYPE: usize = 0;
+    pub const LENGTH: usize = 1;
+
+    pub const LINK_LAYER_ADDR: Field = 2..8;
+
+    pub const PREFIX_LEN: usize = 2;
+    pub const PREFIX_FLAGS: usize = 3;
+    pub const VALID_LIFETIME: Field = 4..8;
+    pub const PREFERRED_LIFETIME: Field = 8..12;
+    pub const PREFIX: Field = 16..32;
+
+    pub const REDIRECTED_HEADER: usize = 8;
+
+    pub const MTU: Field = 4..8;
+}
+
+/// Prefix information option body.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PrefixInformation {
+    pub prefix_len: u8,
+    pub on_link: bool,
+    pub autonomous: bool,
+    /// Valid lifetime, in seconds.
+    pub valid_lifetime: u32,
+    /// Preferred lifetime, in seconds.
+    pub preferred_lifetime: u32,
+    pub prefix: Address,
+}
+
+/// Neighbor discovery option.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NdpOption<'a> {
+    SourceLinkLayerAddr(layer2::Address),
+    TargetLinkLayerAddr(layer2::Address),
+    PrefixInformation(PrefixInformation),
+    /// Redirected header, carry the original packet.
+    RedirectedHeader(&'a [u8]),
+    Mtu(u32),
+    Unknown(u8, &'a [u8]),
+}
+
+impl<'a> NdpOption<'a> {
+    /// Parse one option from the start of buffer. Return option and its length in octets.
+    pub fn parse(data: &'a [u8]) -> Result<(Self, usize)> {
+        if data.len() < 2 || data[field::LENGTH] == 0 {
+            return Err(Error::WrongLengthForNdpOption);
+        }
+
+        let len = data[field::LENGTH] as usize * 8;
+        if data.len() < len {
+            return Err(Error::WrongLengthForNdpOption);
+        }
+
+        let data = &data[..len];
+
+        let option = match data[field::TYPE] {
+            consts::SOURCE_LINK_LAYER_ADDR if len == 8 => Self::SourceLinkLayerAddr(
+                layer2::Address::from_bytes(&data[field::LINK_LAYER_ADDR]),
+            ),
+            consts::TARGET_LINK_LAYER_ADDR if len == 8 => Self::TargetLinkLayerAddr(
+                layer2::Address::from_bytes(&data[field::LINK_LAYER_ADDR]),
+            ),
+            consts::PREFIX_INFORMATION => {
+                if len != field::PREFIX.end {
+                    return Err(Error::WrongLengthForNdpOption);
+                }
+
+                let flags = data[field::PREFIX_FLAGS];
+                Self::PrefixInformation(PrefixInformation {
+                    prefix_len: data[field::PREFIX_LEN],
+                    on_link: flags & consts::PREFIX_FLAG_ON_LINK != 0,
+                    autonomous: flags & consts::PREFIX_FLAG_AUTONOMOUS != 0,
+                    valid_lifetime: NetworkEndian::read_u32(&data[field::VALID_LIFETIME]),
+                    preferred_lifetime: NetworkEndian::read_u32(&data[field::PREFERRED_LIFETIME]),
+                    prefix: Address::from_bytes(&data[field::PREFIX]),
+                })
+            }
+            consts::REDIRECTED_HEADER => Self::RedirectedHeader(&data[field::REDIRECTED_HEADER..]),
+            consts::MTU => {
+                if len != field::MTU.end {
+                    return Err(Error::WrongLengthForNdpOption);
+                }
+
+                Self::Mtu(NetworkEndian::read_u32(&data[field::MTU]))
+            }
+            ty => Self::Unknown(ty, &data[2..]),
+        };
+
+        Ok((option, len))
+    }
+
+    /// Return the length of this option when emitted, in octets.
+    pub fn buffer_len(&self) -> usize {
+        match self {
+            Self::SourceLinkLayerAddr(_) | Self::TargetLinkLayerAddr(_) => 8,
+            Self::PrefixInformation(_) => field::PREFIX.end,
+            Self::RedirectedHeader(v) => pad8(field::REDIRECTED_HEADER + v.len()),
+            Self::Mtu(_) => field::MTU.end,
+            Self::Unknown(_, v) => pad8(2 + v.len()),
+        }
+    }
+
+    /// Emit this option into buffer. Return length written.
+    ///
+    /// Returns `Err(Error::WrongLengthForNdpOption)` if the option is longer than
+    /// the length field can describe.
+    ///
+    /// # Panics
+    /// This function panics if buffer is shorter than [buffer_len].
+    ///
+    /// [buffer_len]: #method.buffer_len
+    pub fn emit(&self, data: &mut [u8]) -> Result<usize> {
+        let len = self.buffer_len();
+        if len > u8::MAX as usize * 8 {
+            return Err(Error::WrongLengthForNdpOption);
+        }
+
+        let data = &mut data[..len];
+
+        for b in data.iter_mut() {
+            *b = 0;
+        }
+
+        data[field::LENGTH] = (len / 8) as u8;
+
+        match self {
+            Self::SourceLinkLayerAddr(addr) => {
+                data[field::TYPE] = consts::SOURCE_LINK_LAYER_ADDR;
+                data[field::LINK_LAYER_ADDR].copy_from_slice(addr.as_bytes());
+            }
+            Self::TargetLinkLayerAddr(addr) => {
+                data[field::TYPE] = consts::TARGET_LINK_LAYER_ADDR;
+                data[field::LINK_LAYER_ADDR].copy_from_slice(addr.as_bytes());
+            }
+            Self::PrefixInformation(info) => {
+                data[field::TYPE] = consts::PREFIX_INFORMATION;
+                data[field::PREFIX_LEN] = info.prefix_len;
+
+                let mut flags = 0;
+                if info.on_link {
+                    flags |= consts::PREFIX_FLAG_ON_LINK;
+                }
+                if info.autonomous {
+                    flags |= consts::PREFIX_FLAG_AUTONOMOUS;
+                }
+                data[field::PREFIX_FLAGS] = flags;
+
+                NetworkEndian::write_u32(&mut data[field::VALID_LIFETIME], info.valid_lifetime);
+                NetworkEndian::write_u32(
+                    &mut data[field::PREFERRED_LIFETIME],
+                    info.preferred_lifetime,
+                );
+                data[field::PREFIX].copy_from_slice(info.prefix.as_bytes());
+            }
+            Self::RedirectedHeader(v) => {
+                data[field::TYPE] = consts::REDIRECTED_HEADER;
+                let end = field::REDIRECTED_HEADER + v.len();
+                data[field::REDIRECTED_HEADER..end].copy_from_slice(v);
+            }
+            Self::Mtu(mtu) => {
+                data[field::TYPE] = consts::MTU;
+                NetworkEndian::write_u32(&mut data[field::MTU], *mtu);
+            }
+            Self::Unknown(ty, v) => {
+                data[field::TYPE] = *ty;
+                data[2..2 + v.len()].copy_from_slice(v);
+            }
+        }
+
+        Ok(len)
+    }
+}
+
+fn pad8(len: usize) -> usize {
+    len.div_ceil(8) * 8
+}
+
+/// Iterator over neighbor discovery options.
+pub struct NdpOptionIter<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> NdpOptionIter<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+}
+
+impl<'a> Iterator for NdpOptionIter<'a> {
+    type Item = Result<NdpOption<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+
+        match NdpOption::parse(self.data) {
+            Ok((option, len)) => {
+                self.data = &self.data[len..];
+                Some(Ok(option))
+            }
+            Err(e) => {
+                self.data = &[];
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options() {
+        let mac = layer2::Address::new(0x02, 0, 0, 0, 0, 1);
+        let info = PrefixInformation {
+            prefix_len: 64,
+            on_link: true,
+            autonomous: false,
+            valid_lifetime: 86400,
+            preferred_lifetime: 14400,
+            prefix: Address::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0),
+        };
+        let redirected = [0x60u8, 0, 0, 0, 0, 0, 0x3a, 0x40];
+
+        let options = [
+            NdpOption::SourceLinkLayerAddr(mac),
+            NdpOption::TargetLinkLayerAddr(mac),
+            NdpOption::PrefixInformation(info),
+            NdpOption::RedirectedHeader(&redirected),
+            NdpOption::Mtu(1280),
+            NdpOption::Unknown(0x20, &[1, 2, 3]),
+        ];
+
+        let mut buffer = [0u8; 96];
+        let mut len = 0;
+        for option in options.iter() {
+            let option_len = option.emit(&mut buffer[len..]).unwrap();
+            assert_eq!(option_len, option.buffer_len());
+            assert_eq!(option_len % 8, 0);
+            len += option_len;
+        }
+
+        let mut iter = NdpOptionIter::new(&buffer[..len]);
+        assert_eq!(
+            iter.next().unwrap().unwrap(),
+            NdpOption::SourceLinkLayerAddr(mac)
+        );
+        assert_eq!(
+            iter.next().unwrap().unwrap(),
+            NdpOption::TargetLinkLayerAddr(mac)
+        );
+        assert_eq!(iter.next().unwrap().unwrap(), options[2]);
+        assert_eq!(iter.next().unwrap().unwrap(), options[3]);
+        assert_eq!(iter.next().unwrap().unwrap(), NdpOption::Mtu(1280));
+        // Unknown option data is padded to 8 octets.
+        assert_eq!(
+            iter.next().unwrap().unwrap(),
+            NdpOption::Unknown(0x20, &[1, 2, 3, 0, 0, 0])
+        );
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        assert!(NdpOption::parse(&[1]).is_err());
+        // Zero length.
+        assert!(NdpOption::parse(&[1, 0, 0, 0, 0, 0, 0, 0]).is_err());
+        // Truncated.
+        assert!(NdpOption::parse(&[1, 2, 0, 0, 0, 0, 0, 0]).is_err());
+        // Wrong length for MTU.
+        assert!(NdpOption::parse(&[5, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).is_err());
+
+        // Length field can not describe more than 2040 octets.
+        let data = [0u8; 2040];
+        let mut buffer = [0u8; 2048];
+        assert_eq!(
+            NdpOption::Unknown(0x20, &data[..2038])
+                .emit(&mut buffer)
+                .unwrap(),
+            2040
+        );
+        assert!(NdpOption::Unknown(0x20, &data[..2039])
+            .emit(&mut buffer)
+            .is_err());
+    }
+}
@@ -0,0 +1,618 @@
+use core::fmt::{self, Display, Formatter};
+
+use byteorder::{ByteOrder, NetworkEndian};
+
+use crate::{
+    layer3::{self, ipv6},
+    utils::checksum,
+    Error, IntoInner, Result,
+};
+
+use super::{
+    consts, AddressRecordIter, Echo, Message, MldQuery, NdpOptionIter, NeighborAdvert, Redirect,
+    RouterAdvert,
+};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Packet<T> {
+    buffer: T,
+}
+
+pub mod field {
+    use crate::utils::field::Field;
+
+    pub const TYPE: usize = 0;
+    pub const CODE: usize = 1;
+    pub const CHECKSUM: Field = 2..4;
+
+    pub const UNUSED: Field = 4..8;
+    pub const MTU: Field = 4..8;
+    pub const POINTER: Field = 4..8;
+
+    pub const ECHO_IDENT: Field = 4..6;
+    pub const ECHO_SEQNO: Field = 6..8;
+
+    pub const HEADER_END: usize = 8;
+
+    pub const MLD_MAX_RESP_CODE: Field = 4..6;
+    pub const MLD_MCAST_ADDR: Field = 8..24;
+    pub const MLD_HEADER_END: usize = MLD_MCAST_ADDR.end;
+
+    pub const MLDV2_NUM_RECORDS: Field = 6..8;
+    pub const MLDV2_REPORT_HEADER_END: usize = 8;
+
+    pub const CUR_HOP_LIMIT: usize = 4;
+    pub const ROUTER_FLAGS: usize = 5;
+    pub const ROUTER_LIFETIME: Field = 6..8;
+    pub const REACHABLE_TIME: Field = 8..12;
+    pub const RETRANS_TIME: Field = 12..16;
+    pub const ROUTER_ADVERT_HEADER_END: usize = RETRANS_TIME.end;
+
+    pub const NEIGHBOR_FLAGS: usize = 4;
+    pub const TARGET_ADDR: Field = 8..24;
+    pub const NEIGHBOR_HEADER_END: usize = TARGET_ADDR.end;
+
+    pub const DEST_ADDR: Field = 24..40;
+    pub const REDIRECT_HEADER_END: usize = DEST_ADDR.end;
+}
+
+/// Return the fixed header length of message type.
+fn header_len(ty: u8) -> usize {
+    match ty {
+        consts::message::MLD_QUERY | consts::message::MLD_REPORT | consts::message::MLD_DONE => {
+            field::MLD_HEADER_END
+        }
+        consts::message::MLDV2_REPORT => field::MLDV2_REPORT_HEADER_END,
+        consts::message::ROUTER_ADVERT => field::ROUTER_ADVERT_HEADER_END,
+        consts::message::NEIGHBOR_SOLICIT | consts::message::NEIGHBOR_ADVERT => {
+            field::NEIGHBOR_HEADER_END
+        }
+        consts::message::REDIRECT => field::REDIRECT_HEADER_END,
+        _ => field::HEADER_END,
+    }
+}
+
+impl<T> IntoInner for Packet<T> {
+    type Inner = T;
+
+    fn into_inner(self) -> Self::Inner {
+        self.buffer
+    }
+}
+
+impl<T: AsRef<[u8]>> Display for Packet<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("ICMPv6 Packet:")?;
+        f.write_fmt(format_args!(
+            "Protocol {:?}, Length: {}",
+            self.protocol(),
+            self.buffer.as_ref().len()
+        ))
+    }
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    /// Imbue a raw octet buffer with ICMPv6 packet structure.
+    pub fn new_unchecked(buffer: T) -> Packet<T> {
+        Packet { buffer }
+    }
+
+    /// Shorthand for a combination of [new_unchecked] and [check_len].
+    ///
+    /// [new_unchecked]: #method.new_unchecked
+    /// [check_len]: #method.check_len
+    pub fn new_checked(buffer: T) -> Result<Packet<T>> {
+        let packet = Self::new_unchecked(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    /// Ensure that no accessor method will panic if called.
+    /// Returns `Err(Error)` if the buffer is too short for the message type.
+    pub fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < field::HEADER_END || len < self.header_len() {
+            Err(Error::WrongLengthForBufferLength)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// get Protocol
+    pub fn protocol(&self) -> Message {
+        let ty = self.msg_type();
+        let code = self.msg_code();
+
+        let mut msg = Message::from_type_code(ty, code);
+
+        let data = self.buffer.as_ref();
+
+        match &mut msg {
+            Message::EchoRequest(e) | Message::EchoReply(e) => {
+                *e = Echo {
+                    ident: NetworkEndian::read_u16(&data[field::ECHO_IDENT]),
+                    seq_no: NetworkEndian::read_u16(&data[field::ECHO_SEQNO]),
+                };
+            }
+            Message::PktTooBig(mtu) => *mtu = NetworkEndian::read_u32(&data[field::MTU]),
+            Message::ParamProblem(_, pointer) => {
+                *pointer = NetworkEndian::read_u32(&data[field::POINTER])
+            }
+            Message::MldQuery(q) => {
+                *q = MldQuery {
+                    max_resp_code: NetworkEndian::read_u16(&data[field::MLD_MAX_RESP_CODE]),
+                    mcast_addr: ipv6::Address::from_bytes(&data[field::MLD_MCAST_ADDR]),
+                };
+            }
+            Message::MldReport(addr) | Message::MldDone(addr) => {
+                *addr = ipv6::Address::from_bytes(&data[field::MLD_MCAST_ADDR]);
+            }
+            Message::Mldv2Report(num) => {
+                *num = NetworkEndian::read_u16(&data[field::MLDV2_NUM_RECORDS]);
+            }
+            Message::RouterAdvert(ra) => {
+                let flags = data[field::ROUTER_FLAGS];
+                *ra = RouterAdvert {
+                    hop_limit: data[field::CUR_HOP_LIMIT],
+                    managed: flags & consts::router_advert::FLAG_MANAGED != 0,
+                    other: flags & consts::router_advert::FLAG_OTHER != 0,
+                    router_lifetime: NetworkEndian::read_u16(&data[field::ROUTER_LIFETIME]),
+                    reachable_time: NetworkEndian::read_u32(&data[field::REACHABLE_TIME]),
+                    retrans_time: NetworkEndian::read_u32(&data[field::RETRANS_TIME]),
+                };
+            }
+            Message::NeighborSolicit(target) => {
+                *target = ipv6::Address::from_bytes(&data[field::TARGET_ADDR]);
+            }
+            Message::NeighborAdvert(na) => {
+                let flags = data[field::NEIGHBOR_FLAGS];
+                *na = NeighborAdvert {
+                    router: flags & consts::neighbor_advert::FLAG_ROUTER != 0,
+                    solicited: flags & consts::neighbor_advert::FLAG_SOLICITED != 0,
+                    override_: flags & consts::neighbor_advert::FLAG_OVERRIDE != 0,
+                    target_addr: ipv6::Address::from_bytes(&data[field::TARGET_ADDR]),
+                };
+            }
+            Message::Redirect(r) => {
+                *r = Redirect {
+                    target_addr: ipv6::Address::from_bytes(&data[field::TARGET_ADDR]),
+                    dst_addr: ipv6::Address::from_bytes(&data[field::DEST_ADDR]),
+                };
+            }
+            _ => {}
+        }
+
+        msg
+    }
+
+    /// Return the message type field.
+    #[inline]
+    fn msg_type(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        data[field::TYPE]
+    }
+
+    /// Return the message code field.
+    #[inline]
+    fn msg_code(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        data[field::CODE]
+    }
+
+    /// Return the checksum field.
+    #[inline]
+    pub fn checksum(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[field::CHECKSUM])
+    }
+
+    /// Return the header length.
+    /// The result depends on the value of the message type field.
+    pub fn header_len(&self) -> usize {
+        header_len(self.msg_type())
+    }
+
+    /// Validate the checksum over IPv6 pseudo header.
+    ///
+    /// # Fuzzing
+    /// This function always returns `true` when fuzzing.
+    pub fn verify_checksum(
+        &self,
+        src_addr: &layer3::Address,
+        dst_addr: &layer3::Address,
+    ) -> Result<bool> {
+        if cfg!(fuzzing) {
+            return Ok(true);
+        }
+
+        let data = self.buffer.as_ref();
+        Ok(checksum::combine(&[
+            checksum::pseudo_ip_header(
+                src_addr,
+                dst_addr,
+                layer3::Protocol::Icmpv6.into(),
+                data.len() as u32,
+            )?,
+            checksum::data(data),
+        ]) == !0)
+    }
+
+    /// Return the data after fixed header.
+    ///
+    /// For error messages it is the invoking packet, for neighbor discovery messages
+    /// it is the options, for MLDv2 report it is the address records.
+    pub fn payload(&self) -> &[u8] {
+        let data = self.buffer.as_ref();
+        &data[self.header_len()..]
+    }
+
+    /// Iterate neighbor discovery options.
+    pub fn ndp_options(&self) -> NdpOptionIter<'_> {
+        NdpOptionIter::new(self.payload())
+    }
+
+    /// Iterate address records of MLDv2 report.
+    pub fn mld_records(&self) -> AddressRecordIter<'_> {
+        let num = if self.msg_type() == consts::message::MLDV2_REPORT {
+            let data = self.buffer.as_ref();
+            NetworkEndian::read_u16(&data[field::MLDV2_NUM_RECORDS])
+        } else {
+            0
+        };
+
+        AddressRecordIter::new(self.payload(), num)
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    /// Set protocol, write type, code and fixed fields of message.
+    pub fn set_protocol(&mut self, protocol: Message) {
+        let (ty, code) = protocol.to_type_code();
+
+        self.set_msg_type(ty);
+        self.set_msg_code(code);
+
+        let header_len = self.header_len();
+        let data = self.buffer.as_mut();
+
+        for b in data[field::UNUSED.start..header_len].iter_mut() {
+            *b = 0;
+        }
+
+        match protocol {
+            Message::EchoRequest(e) | Message::EchoReply(e) => {
+                NetworkEndian::write_u16(&mut data[field::ECHO_IDENT], e.ident);
+                NetworkEndian::write_u16(&mut data[field::ECHO_SEQNO], e.seq_no);
+            }
+            Message::PktTooBig(mtu) => NetworkEndian::write_u32(&mut data[field::MTU], mtu),
+            Message::ParamProblem(_, pointer) => {
+                NetworkEndian::write_u32(&mut data[field::POINTER], pointer)
+            }
+            Message::MldQuery(q) => {
+                NetworkEndian::write_u16(&mut data[field::MLD_MAX_RESP_CODE], q.max_resp_code);
+                data[field::MLD_MCAST_ADDR].copy_from_slice(q.mcast_addr.as_bytes());
+            }
+            Message::MldReport(addr) | Message::MldDone(addr) => {
+                data[field::MLD_MCAST_ADDR].copy_from_slice(addr.as_bytes());
+            }
+            Message::Mldv2Report(num) => {
+                NetworkEndian::write_u16(&mut data[field::MLDV2_NUM_RECORDS], num);
+            }
+            Message::RouterAdvert(ra) => {
+                let mut flags = 0;
+                if ra.managed {
+                    flags |= consts::router_advert::FLAG_MANAGED;
+                }
+                if ra.other {
+                    flags |= consts::router_advert::FLAG_OTHER;
+                }
+                data[field::CUR_HOP_LIMIT] = ra.hop_limit;
+                data[field::ROUTER_FLAGS] = flags;
+                NetworkEndian::write_u16(&mut data[field::ROUTER_LIFETIME], ra.router_lifetime);
+                NetworkEndian::write_u32(&mut data[field::REACHABLE_TIME], ra.reachable_time);
+                NetworkEndian::write_u32(&mut data[field::RETRANS_TIME], ra.retrans_time);
+            }
+            Message::NeighborSolicit(target) => {
+                data[field::TARGET_ADDR].copy_from_slice(target.as_bytes());
+            }
+            Message::NeighborAdvert(na) => {
+                let mut flags = 0;
+                if na.router {
+                    flags |= consts::neighbor_advert::FLAG_ROUTER;
+                }
+                if na.solicited {
+                    flags |= consts::neighbor_advert::FLAG_SOLICITED;
+                }
+                if na.override_ {
+                    flags |= consts::neighbor_advert::FLAG_OVERRIDE;
+                }
+                data[field::NEIGHBOR_FLAGS] = flags;
+                data[field::TARGET_ADDR].copy_from_slice(na.target_addr.as_bytes());
+            }
+            Message::Redirect(r) => {
+                data[field::TARGET_ADDR].copy_from_slice(r.target_addr.as_bytes());
+                data[field::DEST_ADDR].copy_from_slice(r.dst_addr.as_bytes());
+            }
+            _ => {}
+        }
+    }
+
+    /// Set the message type field.
+    #[inline]
+    fn set_msg_type(&mut self, value: u8) {
+        let data = self.buffer.as_mut();
+        data[field::TYPE] = value
+    }
+
+    /// Set the message code field.
+    #[inline]
+    fn set_msg_code(&mut self, value: u8) {
+        let data = self.buffer.as_mut();
+        data[field::CODE] = value
+    }
+
+    /// Set the checksum field.
+    #[inline]
+    pub fn set_checksum(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[field::CHECKSUM], value)
+    }
+
+    /// Compute and fill in the checksum over IPv6 pseudo header.
+    pub fn fill_checksum(
+        &mut self,
+        src_addr: &layer3::Address,
+        dst_addr: &layer3::Address,
+    ) -> Result<()> {
+        self.set_checksum(0);
+        let checksum = {
+            let data = self.buffer.as_ref();
+            !checksum::combine(&[
+                checksum::pseudo_ip_header(
+                    src_addr,
+                    dst_addr,
+                    layer3::Protocol::Icmpv6.into(),
+                    data.len() as u32,
+                )?,
+                checksum::data(data),
+            ])
+        };
+        self.set_checksum(checksum);
+        Ok(())
+    }
+
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let range = self.header_len()..;
+        let data = self.buffer.as_mut();
+        &mut data[range]
+    }
+}
+
+/// High-level representation of ICMPv6 packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repr<'a> {
+    pub message: Message,
+    /// Data after fixed header, options, address records or invoking packet.
+    pub payload: &'a [u8],
+}
+
+impl<'a> Repr<'a> {
+    /// Parse an ICMPv6 packet and return a high-level representation, checksum is verified.
+    pub fn parse<T: AsRef<[u8]>>(
+        packet: &'a Packet<T>,
+        src_addr: &layer3::Address,
+        dst_addr: &layer3::Address,
+    ) -> Result<Repr<'a>> {
+        packet.check_len()?;
+
+        if !packet.verify_checksum(src_addr, dst_addr)? {
+            return Err(Error::WrongChecksum);
+        }
+
+        Ok(Repr {
+            message: packet.protocol(),
+            payload: packet.payload(),
+        })
+    }
+
+    /// Return the length of packet that will be emitted from this representation.
+    pub fn buffer_len(&self) -> usize {
+        header_len(self.message.to_type_code().0) + self.payload.len()
+    }
+
+    /// Emit this representation into an ICMPv6 packet, fill checksum.
+    ///
+    /// # Panics
+    /// This function panics if the buffer is not [buffer_len] long.
+    ///
+    /// [buffer_len]: #method.buffer_len
+    pub fn emit<T: AsRef<[u8]> + AsMut<[u8]>>(
+        &self,
+        packet: &mut Packet<T>,
+        src_addr: &layer3::Address,
+        dst_addr: &layer3::Address,
+    ) -> Result<()> {
+        packet.set_protocol(self.message.clone());
+        packet.payload_mut().copy_from_slice(self.payload);
+        packet.fill_checksum(src_addr, dst_addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Packet, Repr};
+    use crate::{
+        layer2,
+        layer3::{self, ipv6},
+        layer4::icmpv6::{
+            mld::RecordType, AddressRecord, DstUnreachable, Echo, Message, MldQuery, NdpOption,
+            NeighborAdvert, ParamProblem, Redirect, RouterAdvert, TimeExceeded,
+        },
+    };
+
+    fn addrs() -> (layer3::Address, layer3::Address) {
+        (
+            layer3::Address::Ipv6(ipv6::Address::parse("fe80::1").unwrap()),
+            layer3::Address::Ipv6(ipv6::Address::parse("ff02::1").unwrap()),
+        )
+    }
+
+    fn round_trip(message: Message, payload: &[u8]) {
+        let (src, dst) = addrs();
+        let repr = Repr {
+            message: message.clone(),
+            payload,
+        };
+
+        let mut buffer = [0xffu8; 128];
+        let buffer = &mut buffer[..repr.buffer_len()];
+        let mut pkt = Packet::new_unchecked(&mut buffer[..]);
+        repr.emit(&mut pkt, &src, &dst).unwrap();
+
+        let pkt = Packet::new_checked(&buffer[..]).unwrap();
+        assert_eq!(Repr::parse(&pkt, &src, &dst).unwrap(), repr);
+        assert_eq!(pkt.protocol(), message);
+        assert_eq!(message.is_error(), message.to_type_code().0 < 128);
+
+        // Checksum covers the pseudo header.
+        let other = layer3::Address::Ipv6(ipv6::Address::parse("ff02::2").unwrap());
+        assert!(Repr::parse(&pkt, &src, &other).is_err());
+    }
+
+    #[test]
+    fn test_messages() {
+        let invoking = [0x60u8, 0, 0, 0, 0, 8, 0x11, 0x40];
+        let target = ipv6::Address::parse("fe80::2").unwrap();
+        let group = ipv6::Address::parse("ff02::1:ff00:2").unwrap();
+
+        let messages = [
+            Message::DstUnreachable(DstUnreachable::PortUnreachable),
+            Message::DstUnreachable(DstUnreachable::Unknown(9)),
+            Message::PktTooBig(1280),
+            Message::TimeExceeded(TimeExceeded::HopLimitExceeded),
+            Message::TimeExceeded(TimeExceeded::FragReassemblyExceeded),
+            Message::ParamProblem(ParamProblem::UnrecognizedNxtHdr, 6),
+            Message::EchoRequest(Echo {
+                ident: 0x1234,
+                seq_no: 7,
+            }),
+            Message::EchoReply(Echo {
+                ident: 0x1234,
+                seq_no: 7,
+            }),
+            Message::MldQuery(MldQuery {
+                max_resp_code: 1000,
+                mcast_addr: group,
+            }),
+            Message::MldReport(group),
+            Message::MldDone(group),
+            Message::RouterSolicit,
+            Message::RouterAdvert(RouterAdvert {
+                hop_limit: 64,
+                managed: true,
+                other: false,
+                router_lifetime: 1800,
+                reachable_time: 30000,
+                retrans_time: 1000,
+            }),
+            Message::NeighborSolicit(target),
+            Message::NeighborAdvert(NeighborAdvert {
+                router: true,
+                solicited: false,
+                override_: true,
+                target_addr: target,
+            }),
+            Message::Redirect(Redirect {
+                target_addr: target,
+                dst_addr: group,
+            }),
+            Message::Unknown(200, 1),
+        ];
+
+        for message in messages.iter() {
+            round_trip(message.clone(), &invoking);
+        }
+    }
+
+    #[test]
+    fn test_mldv2_report() {
+        let (src, dst) = addrs();
+        let group = ipv6::Address::parse("ff02::fb").unwrap();
+        let source = ipv6::Address::parse("fe80::3").unwrap();
+
+        let mut records = [0u8; 56];
+        let mut record = AddressRecord::new_unchecked(&mut records[..36]);
+        record.set_record_type(RecordType::ModeIsInclude);
+        record.set_aux_data_len(0);
+        record.set_num_sources(1);
+        record.set_mcast_addr(group);
+        record.set_source(0, source);
+
+        let mut record = AddressRecord::new_unchecked(&mut records[36..]);
+        record.set_record_type(RecordType::ChangeToExclude);
+        record.set_aux_data_len(0);
+        record.set_num_sources(0);
+        record.set_mcast_addr(group);
+
+        let repr = Repr {
+            message: Message::Mldv2Report(2),
+            payload: &records,
+        };
+
+        let mut buffer = [0u8; 64];
+        let mut pkt = Packet::new_unchecked(&mut buffer[..]);
+        repr.emit(&mut pkt, &src, &dst).unwrap();
+
+        let pkt = Packet::new_checked(&buffer[..]).unwrap();
+        assert_eq!(Repr::parse(&pkt, &src, &dst).unwrap(), repr);
+
+        let mut iter = pkt.mld_records();
+        let record = iter.next().unwrap().unwrap();
+        assert_eq!(record.record_type(), RecordType::ModeIsInclude);
+        assert_eq!(record.mcast_addr(), group);
+        assert_eq!(record.source(0), source);
+        assert_eq!(record.record_len(), 36);
+
+        let record = iter.next().unwrap().unwrap();
+        assert_eq!(record.record_type(), RecordType::ChangeToExclude);
+        assert_eq!(record.num_sources(), 0);
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_neighbor_advert() {
+        let src = ipv6::Address::parse("fe80::1").unwrap();
+        let dst = ipv6::Address::parse("fe80::2").unwrap();
+        let mac = layer2::Address::new(0x02, 0, 0, 0, 0, 1);
+
+        let mut buffer = [0u8; 32];
+        let mut pkt = Packet::new_unchecked(&mut buffer[..]);
+
+        let na = NeighborAdvert {
+            router: false,
+            solicited: true,
+            override_: true,
+            target_addr: src,
+        };
+
+        pkt.set_protocol(Message::NeighborAdvert(na.clone()));
+        NdpOption::TargetLinkLayerAddr(mac)
+            .emit(pkt.payload_mut())
+            .unwrap();
+        pkt.fill_checksum(&layer3::Address::Ipv6(src), &layer3::Address::Ipv6(dst))
+            .unwrap();
+
+        let pkt = Packet::new_checked(&buffer[..]).unwrap();
+        assert!(pkt
+            .verify_checksum(&layer3::Address::Ipv6(src), &layer3::Address::Ipv6(dst))
+            .unwrap());
+        assert_eq!(pkt.protocol(), Message::NeighborAdvert(na));
+
+        let mut options = pkt.ndp_options();
+        assert_eq!(
+            options.next().unwrap().unwrap(),
+            NdpOption::TargetLinkLayerAddr(mac)
+        );
+        assert!(options.next().is_none());
+    }
+}
@@ -0,0 +1,294 @@
+use crate::layer3::ipv6::Address;
+
+pub mod consts {
+    pub mod message {
+        pub const DST_UNREACHABLE: u8 = 1;
+        pub const PKT_TOO_BIG: u8 = 2;
+        pub const TIME_EXCEEDED: u8 = 3;
+        pub const PARAM_PROBLEM: u8 = 4;
+        pub const ECHO_REQUEST: u8 = 128;
+        pub const ECHO_REPLY: u8 = 129;
+        pub const MLD_QUERY: u8 = 130;
+        pub const MLD_REPORT: u8 = 131;
+        pub const MLD_DONE: u8 = 132;
+        pub const ROUTER_SOLICIT: u8 = 133;
+        pub const ROUTER_ADVERT: u8 = 134;
+        pub const NEIGHBOR_SOLICIT: u8 = 135;
+        pub const NEIGHBOR_ADVERT: u8 = 136;
+        pub const REDIRECT: u8 = 137;
+        pub const MLDV2_REPORT: u8 = 143;
+    }
+
+    pub mod dst_unreachable {
+        pub const NO_ROUTE: u8 = 0;
+        pub const ADMIN_PROHIBIT: u8 = 1;
+        pub const BEYOND_SCOPE: u8 = 2;
+        pub const ADDR_UNREACHABLE: u8 = 3;
+        pub const PORT_UNREACHABLE: u8 = 4;
+        pub const FAILED_POLICY: u8 = 5;
+        pub const REJECT_ROUTE: u8 = 6;
+    }
+
+    pub mod time_exceeded {
+        pub const HOP_LIMIT_EXCEEDED: u8 = 0;
+        pub const FRAG_REASSEMBLY_EXCEEDED: u8 = 1;
+    }
+
+    pub mod param_problem {
+        pub const ERRONEOUS_HEADER_FIELD: u8 = 0;
+        pub const UNRECOGNIZED_NXT_HDR: u8 = 1;
+        pub const UNRECOGNIZED_OPTION: u8 = 2;
+    }
+
+    pub mod router_advert {
+        pub const FLAG_MANAGED: u8 = 0x80;
+        pub const FLAG_OTHER: u8 = 0x40;
+    }
+
+    pub mod neighbor_advert {
+        pub const FLAG_ROUTER: u8 = 0x80;
+        pub const FLAG_SOLICITED: u8 = 0x40;
+        pub const FLAG_OVERRIDE: u8 = 0x20;
+    }
+}
+
+/// Echo
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Echo {
+    pub ident: u16,
+    pub seq_no: u16,
+}
+
+/// Router advertisement fixed fields.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RouterAdvert {
+    pub hop_limit: u8,
+    pub managed: bool,
+    pub other: bool,
+    /// Router lifetime, in seconds.
+    pub router_lifetime: u16,
+    /// Reachable time, in milliseconds.
+    pub reachable_time: u32,
+    /// Retransmission timer, in milliseconds.
+    pub retrans_time: u32,
+}
+
+/// Neighbor advertisement fixed fields.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NeighborAdvert {
+    pub router: bool,
+    pub solicited: bool,
+    pub override_: bool,
+    pub target_addr: Address,
+}
+
+/// Redirect fixed fields.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Redirect {
+    pub target_addr: Address,
+    pub dst_addr: Address,
+}
+
+/// Multicast listener query fixed fields.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MldQuery {
+    /// Maximum response code, in milliseconds for MLDv1.
+    pub max_resp_code: u16,
+    pub mcast_addr: Address,
+}
+
+/// Internet protocol control message for IPv6 type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    /// Destination unreachable
+    DstUnreachable(DstUnreachable),
+    /// Packet too big, carry the MTU of next-hop link
+    PktTooBig(u32),
+    /// Time exceeded
+    TimeExceeded(TimeExceeded),
+    /// Parameter problem, carry the pointer to error octet
+    ParamProblem(ParamProblem, u32),
+    /// Echo request
+    EchoRequest(Echo),
+    /// Echo reply
+    EchoReply(Echo),
+    /// Multicast listener query
+    MldQuery(MldQuery),
+    /// Multicast listener report, version 1
+    MldReport(Address),
+    /// Multicast listener done
+    MldDone(Address),
+    /// Multicast listener report, version 2, carry number of address records
+    Mldv2Report(u16),
+    /// Router solicitation
+    RouterSolicit,
+    /// Router advertisement
+    RouterAdvert(RouterAdvert),
+    /// Neighbor solicitation, carry target address
+    NeighborSolicit(Address),
+    /// Neighbor advertisement
+    NeighborAdvert(NeighborAdvert),
+    /// Redirect
+    Redirect(Redirect),
+    /// Unknown
+    Unknown(u8, u8),
+}
+
+impl Message {
+    pub fn from_type_code(ty: u8, code: u8) -> Self {
+        match ty {
+            consts::message::DST_UNREACHABLE => {
+                Self::DstUnreachable(DstUnreachable::from_code(code))
+            }
+            consts::message::PKT_TOO_BIG => Self::PktTooBig(0),
+            consts::message::TIME_EXCEEDED => Self::TimeExceeded(TimeExceeded::from_code(code)),
+            consts::message::PARAM_PROBLEM => Self::ParamProblem(ParamProblem::from_code(code), 0),
+            consts::message::ECHO_REQUEST => Self::EchoRequest(Echo::default()),
+            consts::message::ECHO_REPLY => Self::EchoReply(Echo::default()),
+            consts::message::MLD_QUERY => Self::MldQuery(MldQuery::default()),
+            consts::message::MLD_REPORT => Self::MldReport(Address::default()),
+            consts::message::MLD_DONE => Self::MldDone(Address::default()),
+            consts::message::MLDV2_REPORT => Self::Mldv2Report(0),
+            consts::message::ROUTER_SOLICIT => Self::RouterSolicit,
+            consts::message::ROUTER_ADVERT => Self::RouterAdvert(RouterAdvert::default()),
+            consts::message::NEIGHBOR_SOLICIT => Self::NeighborSolicit(Address::default()),
+            consts::message::NEIGHBOR_ADVERT => Self::NeighborAdvert(NeighborAdvert::default()),
+            consts::message::REDIRECT => Self::Redirect(Redirect::default()),
+            _ => Self::Unknown(ty, code),
+        }
+    }
+
+    pub fn to_type_code(&self) -> (u8, u8) {
+        match self {
+            Self::DstUnreachable(v) => (consts::message::DST_UNREACHABLE, v.to_code()),
+            Self::PktTooBig(_) => (consts::message::PKT_TOO_BIG, 0),
+            Self::TimeExceeded(v) => (consts::message::TIME_EXCEEDED, v.to_code()),
+            Self::ParamProblem(v, _) => (consts::message::PARAM_PROBLEM, v.to_code()),
+            Self::EchoRequest(_) => (consts::message::ECHO_REQUEST, 0),
+            Self::EchoReply(_) => (consts::message::ECHO_REPLY, 0),
+            Self::MldQuery(_) => (consts::message::MLD_QUERY, 0),
+            Self::MldReport(_) => (consts::message::MLD_REPORT, 0),
+            Self::MldDone(_) => (consts::message::MLD_DONE, 0),
+            Self::Mldv2Report(_) => (consts::message::MLDV2_REPORT, 0),
+            Self::RouterSolicit => (consts::message::ROUTER_SOLICIT, 0),
+            Self::RouterAdvert(_) => (consts::message::ROUTER_ADVERT, 0),
+            Self::NeighborSolicit(_) => (consts::message::NEIGHBOR_SOLICIT, 0),
+            Self::NeighborAdvert(_) => (consts::message::NEIGHBOR_ADVERT, 0),
+            Self::Redirect(_) => (consts::message::REDIRECT, 0),
+            Self::Unknown(v1, v2) => (*v1, *v2),
+        }
+    }
+
+    /// Query whether this message is an error message.
+    pub fn is_error(&self) -> bool {
+        self.to_type_code().0 < consts::message::ECHO_REQUEST
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DstUnreachable {
+    /// No route to destination
+    NoRoute,
+    /// Communication with destination administratively prohibited
+    AdminProhibit,
+    /// Beyond scope of source address
+    BeyondScope,
+    /// Address unreachable
+    AddrUnreachable,
+    /// Port unreachable
+    PortUnreachable,
+    /// Source address failed ingress/egress policy
+    FailedPolicy,
+    /// Reject route to destination
+    RejectRoute,
+    /// Unknown
+    Unknown(u8),
+}
+
+impl DstUnreachable {
+    pub fn from_code(code: u8) -> Self {
+        match code {
+            consts::dst_unreachable::NO_ROUTE => Self::NoRoute,
+            consts::dst_unreachable::ADMIN_PROHIBIT => Self::AdminProhibit,
+            consts::dst_unreachable::BEYOND_SCOPE => Self::BeyondScope,
+            consts::dst_unreachable::ADDR_UNREACHABLE => Self::AddrUnreachable,
+            consts::dst_unreachable::PORT_UNREACHABLE => Self::PortUnreachable,
+            consts::dst_unreachable::FAILED_POLICY => Self::FailedPolicy,
+            consts::dst_unreachable::REJECT_ROUTE => Self::RejectRoute,
+            _ => Self::Unknown(code),
+        }
+    }
+
+    pub fn to_code(&self) -> u8 {
+        match self {
+            Self::NoRoute => consts::dst_unreachable::NO_ROUTE,
+            Self::AdminProhibit => consts::dst_unreachable::ADMIN_PROHIBIT,
+            Self::BeyondScope => consts::dst_unreachable::BEYOND_SCOPE,
+            Self::AddrUnreachable => consts::dst_unreachable::ADDR_UNREACHABLE,
+            Self::PortUnreachable => consts::dst_unreachable::PORT_UNREACHABLE,
+            Self::FailedPolicy => consts::dst_unreachable::FAILED_POLICY,
+            Self::RejectRoute => consts::dst_unreachable::REJECT_ROUTE,
+            Self::Unknown(n) => *n,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimeExceeded {
+    /// Hop limit exceeded in transit
+    HopLimitExceeded,
+    /// Fragment reassembly time exceeded
+    FragReassemblyExceeded,
+    /// Unknown
+    Unknown(u8),
+}
+
+impl TimeExceeded {
+    pub fn from_code(code: u8) -> Self {
+        match code {
+            consts::time_exceeded::HOP_LIMIT_EXCEEDED => Self::HopLimitExceeded,
+            consts::time_exceeded::FRAG_REASSEMBLY_EXCEEDED => Self::FragReassemblyExceeded,
+            _ => Self::Unknown(code),
+        }
+    }
+
+    pub fn to_code(&self) -> u8 {
+        match self {
+            Self::HopLimitExceeded => consts::time_exceeded::HOP_LIMIT_EXCEEDED,
+            Self::FragReassemblyExceeded => consts::time_exceeded::FRAG_REASSEMBLY_EXCEEDED,
+            Self::Unknown(u) => *u,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParamProblem {
+    /// Erroneous header field encountered
+    ErroneousHeaderField,
+    /// Unrecognized next header type encountered
+    UnrecognizedNxtHdr,
+    /// Unrecognized IPv6 option encountered
+    UnrecognizedOption,
+    /// Unknown
+    Unknown(u8),
+}
+
+impl ParamProblem {
+    pub fn from_code(code: u8) -> Self {
+        match code {
+            consts::param_problem::ERRONEOUS_HEADER_FIELD => Self::ErroneousHeaderField,
+            consts::param_problem::UNRECOGNIZED_NXT_HDR => Self::UnrecognizedNxtHdr,
+            consts::param_problem::UNRECOGNIZED_OPTION => Self::UnrecognizedOption,
+            _ => Self::Unknown(code),
+        }
+    }
+
+    pub fn to_code(&self) -> u8 {
+        match self {
+            Self::ErroneousHeaderField => consts::param_problem::ERRONEOUS_HEADER_FIELD,
+            Self::UnrecognizedNxtHdr => consts::param_problem::UNRECOGNIZED_NXT_HDR,
+            Self::UnrecognizedOption => consts::param_problem::UNRECOGNIZED_OPTION,
+            Self::Unknown(u) => *u,
+        }
+    }
+}
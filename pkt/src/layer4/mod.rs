@@ -1,3 +1,7 @@
 pub mod udp;
 
+pub mod tcp;
+
 pub mod icmpv4;
+
+pub mod icmpv6;
@@ -0,0 +1,5 @@
+mod packet;
+pub use packet::*;
+
+mod option;
+pub use option::*;
@@ -0,0 +1,221 @@
+use byteorder::{ByteOrder, NetworkEndian};
+
+use crate::{Error, Result};
+
+pub mod consts {
+    pub const KIND_END: u8 = 0x00;
+    pub const KIND_NOP: u8 = 0x01;
+    pub const KIND_MSS: u8 = 0x02;
+    pub const KIND_WS: u8 = 0x03;
+    pub const KIND_SACK_PERM: u8 = 0x04;
+    pub const KIND_SACK_RANGE: u8 = 0x05;
+    pub const KIND_TSTAMP: u8 = 0x08;
+
+    pub const LEN_MSS: usize = 4;
+    pub const LEN_WS: usize = 3;
+    pub const LEN_SACK_PERM: usize = 2;
+    pub const LEN_TSTAMP: usize = 10;
+
+    /// Maximum SACK blocks kept from one segment, and emitted into one.
+    pub const MAX_SACK_BLOCKS: usize = 3;
+
+    /// Maximum SACK blocks fit in option space without timestamps, RFC 2018 section 3.
+    pub const MAX_SACK_BLOCKS_IN_OPTION: usize = (40 - 2) / 8;
+}
+
+/// TCP option.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TcpOption<'a> {
+    EndOfList,
+    NoOperation,
+    MaxSegmentSize(u16),
+    WindowScale(u8),
+    SackPermitted,
+    /// SACK blocks as `(left edge, right edge)`.
+    SackRange([Option<(u32, u32)>; consts::MAX_SACK_BLOCKS]),
+    /// Timestamps as `(TSval, TSecr)`.
+    Timestamp(u32, u32),
+    Unknown(u8, &'a [u8]),
+}
+
+impl<'a> TcpOption<'a> {
+    /// Parse one option from the start of buffer. Return option and its length in octets.
+    pub fn parse(data: &'a [u8]) -> Result<(Self, usize)> {
+        let kind = *data.first().ok_or(Error::WrongLengthForTcpOption)?;
+
+        match kind {
+            consts::KIND_END => return Ok((Self::EndOfList, 1)),
+            consts::KIND_NOP => return Ok((Self::NoOperation, 1)),
+            _ => {}
+        }
+
+        if data.len() < 2 {
+            return Err(Error::WrongLengthForTcpOption);
+        }
+
+        let len = data[1] as usize;
+        if len < 2 || data.len() < len {
+            return Err(Error::WrongLengthForTcpOption);
+        }
+
+        let value = &data[2..len];
+
+        let option = match (kind, len) {
+            (consts::KIND_MSS, consts::LEN_MSS) => {
+                Self::MaxSegmentSize(NetworkEndian::read_u16(value))
+            }
+            (consts::KIND_WS, consts::LEN_WS) => Self::WindowScale(value[0]),
+            (consts::KIND_SACK_PERM, consts::LEN_SACK_PERM) => Self::SackPermitted,
+            (consts::KIND_SACK_RANGE, _) => {
+                if !value.len().is_multiple_of(8)
+                    || value.len() / 8 > consts::MAX_SACK_BLOCKS_IN_OPTION
+                {
+                    return Err(Error::WrongLengthForTcpOption);
+                }
+
+                // Blocks after the first ones are ignored, the first block is the most recent.
+                let mut blocks = [None; consts::MAX_SACK_BLOCKS];
+                for (block, raw) in blocks.iter_mut().zip(value.chunks(8)) {
+                    *block = Some((
+                        NetworkEndian::read_u32(&raw[0..4]),
+                        NetworkEndian::read_u32(&raw[4..8]),
+                    ));
+                }
+                Self::SackRange(blocks)
+            }
+            (consts::KIND_TSTAMP, consts::LEN_TSTAMP) => Self::Timestamp(
+                NetworkEndian::read_u32(&value[0..4]),
+                NetworkEndian::read_u32(&value[4..8]),
+            ),
+            (consts::KIND_MSS, _)
+            | (consts::KIND_WS, _)
+            | (consts::KIND_SACK_PERM, _)
+            | (consts::KIND_TSTAMP, _) => return Err(Error::WrongLengthForTcpOption),
+            (_, _) => Self::Unknown(kind, value),
+        };
+
+        Ok((option, len))
+    }
+
+    /// Return the length of this option when emitted, in octets.
+    pub fn buffer_len(&self) -> usize {
+        match self {
+            Self::EndOfList | Self::NoOperation => 1,
+            Self::MaxSegmentSize(_) => consts::LEN_MSS,
+            Self::WindowScale(_) => consts::LEN_WS,
+            Self::SackPermitted => consts::LEN_SACK_PERM,
+            Self::SackRange(blocks) => 2 + blocks.iter().flatten().count() * 8,
+            Self::Timestamp(_, _) => consts::LEN_TSTAMP,
+            Self::Unknown(_, data) => 2 + data.len(),
+        }
+    }
+
+    /// Emit this option into buffer. Return length written.
+    ///
+    /// # Panics
+    /// This function panics if buffer is shorter than [buffer_len].
+    ///
+    /// [buffer_len]: #method.buffer_len
+    pub fn emit(&self, data: &mut [u8]) -> usize {
+        let len = self.buffer_len();
+
+        match self {
+            Self::EndOfList => data[0] = consts::KIND_END,
+            Self::NoOperation => data[0] = consts::KIND_NOP,
+            _ => {
+                data[1] = len as u8;
+
+                match self {
+                    Self::MaxSegmentSize(mss) => {
+                        data[0] = consts::KIND_MSS;
+                        NetworkEndian::write_u16(&mut data[2..4], *mss);
+                    }
+                    Self::WindowScale(shift) => {
+                        data[0] = consts::KIND_WS;
+                        data[2] = *shift;
+                    }
+                    Self::SackPermitted => data[0] = consts::KIND_SACK_PERM,
+                    Self::SackRange(blocks) => {
+                        data[0] = consts::KIND_SACK_RANGE;
+                        for (i, (left, right)) in blocks.iter().flatten().enumerate() {
+                            let start = 2 + i * 8;
+                            NetworkEndian::write_u32(&mut data[start..start + 4], *left);
+                            NetworkEndian::write_u32(&mut data[start + 4..start + 8], *right);
+                        }
+                    }
+                    Self::Timestamp(tsval, tsecr) => {
+                        data[0] = consts::KIND_TSTAMP;
+                        NetworkEndian::write_u32(&mut data[2..6], *tsval);
+                        NetworkEndian::write_u32(&mut data[6..10], *tsecr);
+                    }
+                    Self::Unknown(kind, value) => {
+                        data[0] = *kind;
+                        data[2..len].copy_from_slice(value);
+                    }
+                    Self::EndOfList | Self::NoOperation => unreachable!(),
+                }
+            }
+        }
+
+        len
+    }
+}
+
+/// Iterator over TCP options, stop at end of option list.
+pub struct OptionIter<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> OptionIter<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+}
+
+impl<'a> Iterator for OptionIter<'a> {
+    type Item = Result<TcpOption<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+
+        match TcpOption::parse(self.data) {
+            Ok((TcpOption::EndOfList, _)) => {
+                self.data = &[];
+                None
+            }
+            Ok((option, len)) => {
+                self.data = &self.data[len..];
+                Some(Ok(option))
+            }
+            Err(e) => {
+                self.data = &[];
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Emit options into buffer, padding with end of list to a multiple of 4 octets.
+/// Return length written.
+pub fn emit_options(options: &[TcpOption<'_>], data: &mut [u8]) -> usize {
+    let mut len = 0;
+
+    for option in options {
+        len += option.emit(&mut data[len..]);
+    }
+
+    while !len.is_multiple_of(4) {
+        data[len] = consts::KIND_END;
+        len += 1;
+    }
+
+    len
+}
+
+/// Return the length of options emitted by [emit_options].
+pub fn options_len(options: &[TcpOption<'_>]) -> usize {
+    let len: usize = options.iter().map(|o| o.buffer_len()).sum();
+    len.div_ceil(4) * 4
+}
@@ -0,0 +1,764 @@
+use core::fmt::{self, Display, Formatter};
+
+use byteorder::{ByteOrder, NetworkEndian};
+
+use crate::{
+    layer3::{self, Address},
+    utils::checksum,
+    Error, IntoInner, Result,
+};
+
+use super::{consts::MAX_SACK_BLOCKS, emit_options, options_len, OptionIter, TcpOption};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Packet<T> {
+    buffer: T,
+}
+
+pub mod field {
+    use crate::utils::field::Field;
+
+    pub const SRC_PORT: Field = 0..2;
+    pub const DST_PORT: Field = 2..4;
+    pub const SEQ_NUM: Field = 4..8;
+    pub const ACK_NUM: Field = 8..12;
+    pub const FLAGS: Field = 12..14;
+    pub const WIN_SIZE: Field = 14..16;
+    pub const CHECKSUM: Field = 16..18;
+    pub const URGENT: Field = 18..20;
+
+    pub const HEADER_LEN: usize = URGENT.end;
+
+    /// Maximum header length, data offset is 4 bits in 32-bit words.
+    pub const MAX_HEADER_LEN: usize = 60;
+
+    pub fn options(header_len: u8) -> Field {
+        URGENT.end..header_len as usize
+    }
+
+    pub const FLG_FIN: u16 = 0x001;
+    pub const FLG_SYN: u16 = 0x002;
+    pub const FLG_RST: u16 = 0x004;
+    pub const FLG_PSH: u16 = 0x008;
+    pub const FLG_ACK: u16 = 0x010;
+    pub const FLG_URG: u16 = 0x020;
+    pub const FLG_ECE: u16 = 0x040;
+    pub const FLG_CWR: u16 = 0x080;
+    pub const FLG_NS: u16 = 0x100;
+
+    pub const FLG_MASK: u16 = 0x1ff;
+}
+
+impl<T> IntoInner for Packet<T> {
+    type Inner = T;
+
+    fn into_inner(self) -> T {
+        self.buffer
+    }
+}
+
+impl<T: AsRef<[u8]>> Display for Packet<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("TCP Packet:")?;
+        f.write_fmt(format_args!(
+            "Src port: {}, Dst port: {}, Seq: {}, Ack: {}, Window: {}, Length: {}",
+            self.src_port(),
+            self.dst_port(),
+            self.seq_number(),
+            self.ack_number(),
+            self.window_len(),
+            self.payload().len(),
+        ))?;
+
+        for (flag, name) in [
+            (self.syn(), " SYN"),
+            (self.ack(), " ACK"),
+            (self.fin(), " FIN"),
+            (self.rst(), " RST"),
+            (self.psh(), " PSH"),
+            (self.urg(), " URG"),
+        ] {
+            if flag {
+                f.write_str(name)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    /// Imbue a raw octet buffer with TCP packet structure.
+    pub fn new_unchecked(buffer: T) -> Packet<T> {
+        Packet { buffer }
+    }
+
+    /// Shorthand for a combination of new_unchecked and check_len.
+    pub fn new_checked(buffer: T) -> Result<Packet<T>> {
+        let packet = Self::new_unchecked(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    /// Ensure that no accessor method will panic if called.
+    fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < field::HEADER_LEN {
+            Err(Error::WrongLengthForTcpPacket)
+        } else {
+            let header_len = self.header_len() as usize;
+            if len < header_len || header_len < field::HEADER_LEN {
+                Err(Error::WrongLengthForTcpPacket)
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    /// Return the source port field.
+    #[inline]
+    pub fn src_port(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[field::SRC_PORT])
+    }
+
+    /// Return the destination port field.
+    #[inline]
+    pub fn dst_port(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[field::DST_PORT])
+    }
+
+    /// Return the sequence number field.
+    #[inline]
+    pub fn seq_number(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u32(&data[field::SEQ_NUM])
+    }
+
+    /// Return the acknowledgement number field.
+    #[inline]
+    pub fn ack_number(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u32(&data[field::ACK_NUM])
+    }
+
+    #[inline]
+    fn flag(&self, mask: u16) -> bool {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[field::FLAGS]) & mask != 0
+    }
+
+    /// Return the FIN flag.
+    #[inline]
+    pub fn fin(&self) -> bool {
+        self.flag(field::FLG_FIN)
+    }
+
+    /// Return the SYN flag.
+    #[inline]
+    pub fn syn(&self) -> bool {
+        self.flag(field::FLG_SYN)
+    }
+
+    /// Return the RST flag.
+    #[inline]
+    pub fn rst(&self) -> bool {
+        self.flag(field::FLG_RST)
+    }
+
+    /// Return the PSH flag.
+    #[inline]
+    pub fn psh(&self) -> bool {
+        self.flag(field::FLG_PSH)
+    }
+
+    /// Return the ACK flag.
+    #[inline]
+    pub fn ack(&self) -> bool {
+        self.flag(field::FLG_ACK)
+    }
+
+    /// Return the URG flag.
+    #[inline]
+    pub fn urg(&self) -> bool {
+        self.flag(field::FLG_URG)
+    }
+
+    /// Return the ECE flag.
+    #[inline]
+    pub fn ece(&self) -> bool {
+        self.flag(field::FLG_ECE)
+    }
+
+    /// Return the CWR flag.
+    #[inline]
+    pub fn cwr(&self) -> bool {
+        self.flag(field::FLG_CWR)
+    }
+
+    /// Return the NS flag.
+    #[inline]
+    pub fn ns(&self) -> bool {
+        self.flag(field::FLG_NS)
+    }
+
+    /// Return the header length, in octets.
+    #[inline]
+    pub fn header_len(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        ((NetworkEndian::read_u16(&data[field::FLAGS]) >> 12) * 4) as u8
+    }
+
+    /// Return the window size field.
+    #[inline]
+    pub fn window_len(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[field::WIN_SIZE])
+    }
+
+    /// Return the checksum field.
+    #[inline]
+    pub fn checksum(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[field::CHECKSUM])
+    }
+
+    /// Return the urgent pointer field.
+    #[inline]
+    pub fn urgent_at(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[field::URGENT])
+    }
+
+    /// Return the raw options area.
+    #[inline]
+    pub fn options(&self) -> &[u8] {
+        let data = self.buffer.as_ref();
+        &data[field::options(self.header_len())]
+    }
+
+    /// Iterate TCP options.
+    pub fn option_iter(&self) -> OptionIter<'_> {
+        OptionIter::new(self.options())
+    }
+
+    /// Return the length of the segment in sequence space, including SYN and FIN.
+    pub fn segment_len(&self) -> usize {
+        let mut len = self.payload().len();
+        if self.syn() {
+            len += 1;
+        }
+        if self.fin() {
+            len += 1;
+        }
+        len
+    }
+
+    /// Validate the checksum over IP pseudo header.
+    pub fn verify_checksum(
+        &self,
+        src_addr: &layer3::Address,
+        dst_addr: &layer3::Address,
+    ) -> Result<bool> {
+        let data = self.buffer.as_ref();
+        Ok(checksum::combine(&[
+            checksum::pseudo_ip_header(
+                src_addr,
+                dst_addr,
+                layer3::Protocol::Tcp.into(),
+                data.len() as u32,
+            )?,
+            checksum::data(data),
+        ]) == !0)
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        let data = self.buffer.as_ref();
+        &data[self.header_len() as usize..]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    /// Set the source port field.
+    #[inline]
+    pub fn set_src_port(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[field::SRC_PORT], value)
+    }
+
+    /// Set the destination port field.
+    #[inline]
+    pub fn set_dst_port(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[field::DST_PORT], value)
+    }
+
+    /// Set the sequence number field.
+    #[inline]
+    pub fn set_seq_number(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[field::SEQ_NUM], value)
+    }
+
+    /// Set the acknowledgement number field.
+    #[inline]
+    pub fn set_ack_number(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[field::ACK_NUM], value)
+    }
+
+    #[inline]
+    fn set_flag(&mut self, mask: u16, value: bool) {
+        let data = self.buffer.as_mut();
+        let raw = NetworkEndian::read_u16(&data[field::FLAGS]);
+        let raw = if value { raw | mask } else { raw & !mask };
+        NetworkEndian::write_u16(&mut data[field::FLAGS], raw)
+    }
+
+    /// Clear all flags.
+    #[inline]
+    pub fn clear_flags(&mut self) {
+        let data = self.buffer.as_mut();
+        let raw = NetworkEndian::read_u16(&data[field::FLAGS]);
+        NetworkEndian::write_u16(&mut data[field::FLAGS], raw & !field::FLG_MASK)
+    }
+
+    /// Set the FIN flag.
+    #[inline]
+    pub fn set_fin(&mut self, value: bool) {
+        self.set_flag(field::FLG_FIN, value)
+    }
+
+    /// Set the SYN flag.
+    #[inline]
+    pub fn set_syn(&mut self, value: bool) {
+        self.set_flag(field::FLG_SYN, value)
+    }
+
+    /// Set the RST flag.
+    #[inline]
+    pub fn set_rst(&mut self, value: bool) {
+        self.set_flag(field::FLG_RST, value)
+    }
+
+    /// Set the PSH flag.
+    #[inline]
+    pub fn set_psh(&mut self, value: bool) {
+        self.set_flag(field::FLG_PSH, value)
+    }
+
+    /// Set the ACK flag.
+    #[inline]
+    pub fn set_ack(&mut self, value: bool) {
+        self.set_flag(field::FLG_ACK, value)
+    }
+
+    /// Set the URG flag.
+    #[inline]
+    pub fn set_urg(&mut self, value: bool) {
+        self.set_flag(field::FLG_URG, value)
+    }
+
+    /// Set the ECE flag.
+    #[inline]
+    pub fn set_ece(&mut self, value: bool) {
+        self.set_flag(field::FLG_ECE, value)
+    }
+
+    /// Set the CWR flag.
+    #[inline]
+    pub fn set_cwr(&mut self, value: bool) {
+        self.set_flag(field::FLG_CWR, value)
+    }
+
+    /// Set the NS flag.
+    #[inline]
+    pub fn set_ns(&mut self, value: bool) {
+        self.set_flag(field::FLG_NS, value)
+    }
+
+    /// Set the header length, in octets.
+    #[inline]
+    pub fn set_header_len(&mut self, value: u8) {
+        let data = self.buffer.as_mut();
+        let raw = NetworkEndian::read_u16(&data[field::FLAGS]);
+        let raw = (raw & 0x0fff) | (((value / 4) as u16) << 12);
+        NetworkEndian::write_u16(&mut data[field::FLAGS], raw)
+    }
+
+    /// Set the window size field.
+    #[inline]
+    pub fn set_window_len(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[field::WIN_SIZE], value)
+    }
+
+    /// Set the checksum field.
+    #[inline]
+    pub fn set_checksum(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[field::CHECKSUM], value)
+    }
+
+    /// Set the urgent pointer field.
+    #[inline]
+    pub fn set_urgent_at(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[field::URGENT], value)
+    }
+
+    /// Return a mutable pointer to the options area.
+    #[inline]
+    pub fn options_mut(&mut self) -> &mut [u8] {
+        let range = field::options(self.header_len());
+        let data = self.buffer.as_mut();
+        &mut data[range]
+    }
+
+    /// Compute and fill in the checksum over IP pseudo header.
+    pub fn fill_checksum(&mut self, src_addr: &Address, dst_addr: &Address) -> Result<()> {
+        self.set_checksum(0);
+        let checksum = {
+            let data = self.buffer.as_ref();
+            !checksum::combine(&[
+                checksum::pseudo_ip_header(
+                    src_addr,
+                    dst_addr,
+                    layer3::Protocol::Tcp.into(),
+                    data.len() as u32,
+                )?,
+                checksum::data(data),
+            ])
+        };
+        self.set_checksum(checksum);
+        Ok(())
+    }
+
+    /// Return a mutable pointer to the payload.
+    #[inline]
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let header_len = self.header_len() as usize;
+        let data = self.buffer.as_mut();
+        &mut data[header_len..]
+    }
+}
+
+/// The control flags of TCP segment, at most one of them can be set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    None,
+    Psh,
+    Syn,
+    Fin,
+    Rst,
+}
+
+impl Control {
+    /// Return the length of control flag in sequence space.
+    pub fn len(self) -> usize {
+        match self {
+            Control::Syn | Control::Fin => 1,
+            _ => 0,
+        }
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// High-level representation of TCP segment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repr<'a> {
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub control: Control,
+    pub seq_number: u32,
+    pub ack_number: Option<u32>,
+    pub window_len: u16,
+    pub window_scale: Option<u8>,
+    pub max_seg_size: Option<u16>,
+    pub sack_permitted: bool,
+    pub sack_ranges: [Option<(u32, u32)>; MAX_SACK_BLOCKS],
+    /// Timestamps as `(TSval, TSecr)`.
+    pub timestamp: Option<(u32, u32)>,
+    pub payload: &'a [u8],
+}
+
+/// Maximum number of options emitted from one representation.
+const MAX_REPR_OPTIONS: usize = 5;
+
+impl<'a> Repr<'a> {
+    /// Parse a TCP segment and return a high-level representation, checksum is verified.
+    pub fn parse<T: AsRef<[u8]>>(
+        packet: &'a Packet<T>,
+        src_addr: &Address,
+        dst_addr: &Address,
+    ) -> Result<Repr<'a>> {
+        packet.check_len()?;
+
+        if !packet.verify_checksum(src_addr, dst_addr)? {
+            return Err(Error::WrongChecksum);
+        }
+
+        let control = match (packet.syn(), packet.fin(), packet.rst(), packet.psh()) {
+            (false, false, false, false) => Control::None,
+            (false, false, false, true) => Control::Psh,
+            (true, false, false, _) => Control::Syn,
+            (false, true, false, _) => Control::Fin,
+            (false, false, true, _) => Control::Rst,
+            _ => return Err(Error::IllegalTcpFlags),
+        };
+
+        let ack_number = if packet.ack() {
+            Some(packet.ack_number())
+        } else {
+            None
+        };
+
+        let mut repr = Repr {
+            src_port: packet.src_port(),
+            dst_port: packet.dst_port(),
+            control,
+            seq_number: packet.seq_number(),
+            ack_number,
+            window_len: packet.window_len(),
+            window_scale: None,
+            max_seg_size: None,
+            sack_permitted: false,
+            sack_ranges: [None; MAX_SACK_BLOCKS],
+            timestamp: None,
+            payload: packet.payload(),
+        };
+
+        for option in packet.option_iter() {
+            match option? {
+                TcpOption::MaxSegmentSize(v) => repr.max_seg_size = Some(v),
+                TcpOption::WindowScale(v) => repr.window_scale = Some(v),
+                TcpOption::SackPermitted => repr.sack_permitted = true,
+                TcpOption::SackRange(v) => repr.sack_ranges = v,
+                TcpOption::Timestamp(tsval, tsecr) => repr.timestamp = Some((tsval, tsecr)),
+                _ => {}
+            }
+        }
+
+        Ok(repr)
+    }
+
+    fn options(&self) -> ([TcpOption<'a>; MAX_REPR_OPTIONS], usize) {
+        let mut options = [TcpOption::NoOperation; MAX_REPR_OPTIONS];
+        let mut len = 0;
+
+        let mut push = |option| {
+            options[len] = option;
+            len += 1;
+        };
+
+        if let Some(v) = self.max_seg_size {
+            push(TcpOption::MaxSegmentSize(v));
+        }
+        if let Some(v) = self.window_scale {
+            push(TcpOption::WindowScale(v));
+        }
+        if self.sack_permitted {
+            push(TcpOption::SackPermitted);
+        }
+        if self.sack_ranges.iter().any(|r| r.is_some()) {
+            push(TcpOption::SackRange(self.sack_ranges));
+        }
+        if let Some((tsval, tsecr)) = self.timestamp {
+            push(TcpOption::Timestamp(tsval, tsecr));
+        }
+
+        (options, len)
+    }
+
+    /// Return the length of header that will be emitted from this representation.
+    pub fn header_len(&self) -> usize {
+        let (options, len) = self.options();
+        field::HEADER_LEN + options_len(&options[..len])
+    }
+
+    /// Return the length of segment that will be emitted from this representation.
+    pub fn buffer_len(&self) -> usize {
+        self.header_len() + self.payload.len()
+    }
+
+    /// Return the length of this segment in sequence space.
+    pub fn segment_len(&self) -> usize {
+        self.payload.len() + self.control.len()
+    }
+
+    /// Emit this representation into a TCP segment, fill header length and checksum.
+    ///
+    /// # Panics
+    /// This function panics if the buffer is not [buffer_len] long.
+    ///
+    /// [buffer_len]: #method.buffer_len
+    pub fn emit<T: AsRef<[u8]> + AsMut<[u8]>>(
+        &self,
+        packet: &mut Packet<T>,
+        src_addr: &Address,
+        dst_addr: &Address,
+    ) -> Result<()> {
+        let (options, len) = self.options();
+
+        packet.set_src_port(self.src_port);
+        packet.set_dst_port(self.dst_port);
+        packet.set_seq_number(self.seq_number);
+        packet.set_ack_number(self.ack_number.unwrap_or(0));
+        packet.set_window_len(self.window_len);
+        packet.set_header_len(self.header_len() as u8);
+        packet.clear_flags();
+        match self.control {
+            Control::None => {}
+            Control::Psh => packet.set_psh(true),
+            Control::Syn => packet.set_syn(true),
+            Control::Fin => packet.set_fin(true),
+            Control::Rst => packet.set_rst(true),
+        }
+        packet.set_ack(self.ack_number.is_some());
+        packet.set_urgent_at(0);
+        emit_options(&options[..len], packet.options_mut());
+        packet.payload_mut().copy_from_slice(self.payload);
+        packet.fill_checksum(src_addr, dst_addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Control, Packet, Repr};
+    use crate::{
+        layer3::{self, ipv4},
+        layer4::tcp::{emit_options, options_len, TcpOption},
+    };
+
+    #[test]
+    fn test_repr() {
+        let src = layer3::Address::Ipv4(ipv4::Address::new(192, 168, 1, 1));
+        let dst = layer3::Address::Ipv4(ipv4::Address::new(192, 168, 1, 2));
+
+        let syn = Repr {
+            src_port: 1234,
+            dst_port: 80,
+            control: Control::Syn,
+            seq_number: 0xdeadbeef,
+            ack_number: None,
+            window_len: 64240,
+            window_scale: Some(7),
+            max_seg_size: Some(1460),
+            sack_permitted: true,
+            sack_ranges: [None; 3],
+            timestamp: Some((1, 0)),
+            payload: &[],
+        };
+
+        let data = Repr {
+            control: Control::Fin,
+            ack_number: Some(0x1000),
+            window_scale: None,
+            max_seg_size: None,
+            sack_permitted: false,
+            sack_ranges: [Some((100, 200)), Some((300, 400)), None],
+            timestamp: Some((2, 1)),
+            payload: b"abcd",
+            ..syn.clone()
+        };
+
+        for repr in [syn, data].iter() {
+            let mut buffer = [0u8; 64];
+            let buffer = &mut buffer[..repr.buffer_len()];
+            let mut pkt = Packet::new_unchecked(&mut buffer[..]);
+            repr.emit(&mut pkt, &src, &dst).unwrap();
+
+            let pkt = Packet::new_checked(&buffer[..]).unwrap();
+            assert_eq!(pkt.header_len() as usize, repr.header_len());
+            assert_eq!(Repr::parse(&pkt, &src, &dst).unwrap(), *repr);
+            assert_eq!(pkt.segment_len(), repr.segment_len());
+        }
+
+        // Illegal combination of flags.
+        let mut buffer = [0u8; 20];
+        let mut pkt = Packet::new_unchecked(&mut buffer[..]);
+        pkt.set_header_len(20);
+        pkt.set_syn(true);
+        pkt.set_fin(true);
+        pkt.fill_checksum(&src, &dst).unwrap();
+        let pkt = Packet::new_checked(&buffer[..]).unwrap();
+        assert!(Repr::parse(&pkt, &src, &dst).is_err());
+    }
+
+    #[test]
+    fn test_emit_and_parse() {
+        let src = layer3::Address::Ipv4(ipv4::Address::new(192, 168, 1, 1));
+        let dst = layer3::Address::Ipv4(ipv4::Address::new(192, 168, 1, 2));
+
+        let options = [
+            TcpOption::MaxSegmentSize(1460),
+            TcpOption::SackPermitted,
+            TcpOption::Timestamp(1, 0),
+            TcpOption::NoOperation,
+            TcpOption::WindowScale(7),
+        ];
+        let header_len = 20 + options_len(&options);
+        assert_eq!(header_len, 40);
+
+        let mut buffer = [0u8; 44];
+        let mut pkt = Packet::new_unchecked(&mut buffer[..]);
+        pkt.set_src_port(1234);
+        pkt.set_dst_port(80);
+        pkt.set_seq_number(0xdeadbeef);
+        pkt.set_header_len(header_len as u8);
+        pkt.set_syn(true);
+        pkt.set_window_len(64240);
+        emit_options(&options, pkt.options_mut());
+        pkt.payload_mut().copy_from_slice(b"abcd");
+        pkt.fill_checksum(&src, &dst).unwrap();
+
+        let pkt = Packet::new_checked(&buffer[..]).unwrap();
+        assert!(pkt.verify_checksum(&src, &dst).unwrap());
+        assert!(pkt.syn() && !pkt.ack());
+        assert_eq!(pkt.seq_number(), 0xdeadbeef);
+        assert_eq!(pkt.segment_len(), 5);
+        assert_eq!(pkt.payload(), b"abcd");
+
+        let mut iter = pkt.option_iter();
+        for option in options.iter() {
+            assert_eq!(iter.next().unwrap().unwrap(), *option);
+        }
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_sack_range() {
+        let option = TcpOption::SackRange([Some((1, 2)), Some((3, 4)), None]);
+        let mut buffer = [0u8; 18];
+        assert_eq!(option.emit(&mut buffer), 18);
+        assert_eq!(TcpOption::parse(&buffer).unwrap(), (option, 18));
+    }
+
+    #[test]
+    fn test_sack_four_blocks() {
+        // Four blocks fit without timestamps, the first three are kept.
+        let mut buffer = [0u8; 34];
+        buffer[0] = 5;
+        buffer[1] = 34;
+        for (i, edge) in buffer[2..].chunks_mut(4).enumerate() {
+            edge.copy_from_slice(&(i as u32 + 1).to_be_bytes());
+        }
+
+        let (option, len) = TcpOption::parse(&buffer).unwrap();
+        assert_eq!(len, 34);
+        assert_eq!(
+            option,
+            TcpOption::SackRange([Some((1, 2)), Some((3, 4)), Some((5, 6))])
+        );
+
+        // Five blocks exceed option space.
+        let mut buffer = [0u8; 42];
+        buffer[0] = 5;
+        buffer[1] = 42;
+        assert!(TcpOption::parse(&buffer).is_err());
+    }
+}
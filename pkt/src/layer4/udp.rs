@@ -28,6 +28,9 @@ mod field {
     pub const HEADER_LEN: usize = CHECKSUM.end;
 }
 
+/// Length of UDP header.
+pub const HEADER_LEN: usize = field::HEADER_LEN;
+
 impl<T> IntoInner for Packet<T> {
     type Inner = T;
 
@@ -199,3 +202,154 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
         &mut data[field::payload(length)]
     }
 }
+
+/// High-level representation of UDP packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repr<'a> {
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub payload: &'a [u8],
+}
+
+impl<'a> Repr<'a> {
+    /// Parse an UDP packet and return a high-level representation, checksum is verified.
+    pub fn parse<T: AsRef<[u8]>>(
+        packet: &'a Packet<T>,
+        src_addr: &Address,
+        dst_addr: &Address,
+    ) -> Result<Repr<'a>> {
+        packet.check_len()?;
+
+        if !packet.verify_checksum(src_addr, dst_addr)? {
+            return Err(Error::WrongChecksum);
+        }
+
+        Ok(Repr {
+            src_port: packet.src_port(),
+            dst_port: packet.dst_port(),
+            payload: packet.payload(),
+        })
+    }
+
+    /// Return the length of packet that will be emitted from this representation.
+    pub fn buffer_len(&self) -> usize {
+        field::HEADER_LEN + self.payload.len()
+    }
+
+    /// Emit this representation into an UDP packet, fill length and checksum.
+    pub fn emit<T: AsRef<[u8]> + AsMut<[u8]>>(
+        &self,
+        packet: &mut Packet<T>,
+        src_addr: &Address,
+        dst_addr: &Address,
+    ) -> Result<()> {
+        packet.set_src_port(self.src_port);
+        packet.set_dst_port(self.dst_port);
+        packet.set_len(self.buffer_len() as u16);
+        packet.payload_mut().copy_from_slice(self.payload);
+        packet.fill_checksum(src_addr, dst_addr)
+    }
+
+    /// Emit header of this representation into `header` of `HEADER_LEN` octets, checksum
+    /// covers payload, which is not copied.
+    pub fn emit_header(
+        &self,
+        header: &mut [u8],
+        src_addr: &Address,
+        dst_addr: &Address,
+    ) -> Result<()> {
+        let mut packet = Packet::new_unchecked(&mut header[..field::HEADER_LEN]);
+        packet.set_src_port(self.src_port);
+        packet.set_dst_port(self.dst_port);
+        packet.set_len(self.buffer_len() as u16);
+        packet.set_checksum(0);
+
+        let checksum = !checksum::combine(&[
+            checksum::pseudo_ip_header(
+                src_addr,
+                dst_addr,
+                layer3::Protocol::Udp.into(),
+                self.buffer_len() as u32,
+            )?,
+            checksum::data(packet.buffer),
+            checksum::data(self.payload),
+        ]);
+        packet.set_checksum(if checksum == 0 { 0xffff } else { checksum });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Packet, Repr};
+    use crate::layer3::{self, ipv4, Protocol};
+    use crate::IntoInner;
+
+    #[test]
+    fn test_repr_round_trip() {
+        let src = ipv4::Address::new(10, 0, 0, 1);
+        let dst = ipv4::Address::new(10, 0, 0, 2);
+
+        let udp_repr = Repr {
+            src_port: 5353,
+            dst_port: 53,
+            payload: b"hello",
+        };
+        let ip_repr = ipv4::Repr {
+            src_addr: src,
+            dst_addr: dst,
+            protocol: Protocol::Udp,
+            payload_len: udp_repr.buffer_len(),
+            ttl: 64,
+            ident: 1,
+            dont_frag: true,
+        };
+
+        let mut bytes = [0u8; 33];
+        assert_eq!(ip_repr.buffer_len() + udp_repr.buffer_len(), bytes.len());
+
+        let mut ip_pkt = ipv4::Packet::new_unchecked(&mut bytes[..]);
+        ip_repr.emit(&mut ip_pkt);
+        let mut udp_pkt = Packet::new_unchecked(ip_pkt.payload_mut());
+        udp_repr
+            .emit(
+                &mut udp_pkt,
+                &layer3::Address::Ipv4(src),
+                &layer3::Address::Ipv4(dst),
+            )
+            .unwrap();
+
+        let ip_pkt = ipv4::Packet::new_checked(&bytes[..]).unwrap();
+        assert_eq!(ipv4::Repr::parse(&ip_pkt).unwrap(), ip_repr);
+
+        let udp_pkt = Packet::new_checked(ip_pkt.payload()).unwrap();
+        let parsed = Repr::parse(
+            &udp_pkt,
+            &layer3::Address::Ipv4(src),
+            &layer3::Address::Ipv4(dst),
+        )
+        .unwrap();
+        assert_eq!(parsed, udp_repr);
+
+        // Header emitted alone has the same checksum.
+        let mut header = [0u8; super::HEADER_LEN];
+        udp_repr
+            .emit_header(
+                &mut header,
+                &layer3::Address::Ipv4(src),
+                &layer3::Address::Ipv4(dst),
+            )
+            .unwrap();
+        assert_eq!(&header[..], &udp_pkt.into_inner()[..super::HEADER_LEN]);
+
+        let udp_pkt = Packet::new_checked(ip_pkt.payload()).unwrap();
+
+        // Wrong pseudo header must fail checksum.
+        assert!(Repr::parse(
+            &udp_pkt,
+            &layer3::Address::Ipv4(dst),
+            &layer3::Address::Ipv4(dst)
+        )
+        .is_err());
+    }
+}
@@ -1,6 +1,9 @@
 // #![feature(generic_associated_types)]
 #![no_std]
 
+#[cfg(test)]
+extern crate std;
+
 mod error;
 pub use error::*;
 
@@ -8,6 +11,8 @@ pub mod layer2;
 pub mod layer3;
 pub mod layer4;
 
+pub mod pcap;
+
 mod prelude;
 pub use prelude::*;
 
@@ -0,0 +1,174 @@
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+use super::{LinkType, Sink};
+use crate::{Error, Result};
+
+pub mod field {
+    use crate::utils::field::Field;
+
+    pub const MAGIC: Field = 0..4;
+    pub const VERSION_MAJOR: Field = 4..6;
+    pub const VERSION_MINOR: Field = 6..8;
+    pub const THISZONE: Field = 8..12;
+    pub const SIGFIGS: Field = 12..16;
+    pub const SNAPLEN: Field = 16..20;
+    pub const NETWORK: Field = 20..24;
+    pub const HEADER_LEN: usize = NETWORK.end;
+
+    pub const TS_SEC: Field = 0..4;
+    pub const TS_FRAC: Field = 4..8;
+    pub const INCL_LEN: Field = 8..12;
+    pub const ORIG_LEN: Field = 12..16;
+    pub const RECORD_HEADER_LEN: usize = ORIG_LEN.end;
+}
+
+pub mod consts {
+    pub const MAGIC_MICROS: u32 = 0xa1b2_c3d4;
+    pub const MAGIC_NANOS: u32 = 0xa1b2_3c4d;
+
+    pub const VERSION_MAJOR: u16 = 2;
+    pub const VERSION_MINOR: u16 = 4;
+}
+
+/// Writer of classic pcap file, in little endian with microsecond timestamps.
+#[derive(Debug)]
+pub struct PcapWriter<S> {
+    sink: S,
+    snaplen: u32,
+}
+
+impl<S: Sink> PcapWriter<S> {
+    /// Create writer and write file header into sink.
+    pub fn new(mut sink: S, link_type: LinkType, snaplen: u32) -> Result<Self> {
+        let mut header = [0u8; field::HEADER_LEN];
+        LittleEndian::write_u32(&mut header[field::MAGIC], consts::MAGIC_MICROS);
+        LittleEndian::write_u16(&mut header[field::VERSION_MAJOR], consts::VERSION_MAJOR);
+        LittleEndian::write_u16(&mut header[field::VERSION_MINOR], consts::VERSION_MINOR);
+        LittleEndian::write_u32(&mut header[field::SNAPLEN], snaplen);
+        LittleEndian::write_u32(&mut header[field::NETWORK], link_type.into());
+
+        sink.write_all(&header)?;
+
+        Ok(Self { sink, snaplen })
+    }
+
+    /// Write a packet record, packet longer than snaplen is truncated.
+    pub fn write_packet(&mut self, timestamp: u64, packet: &[u8]) -> Result<()> {
+        let incl_len = packet.len().min(self.snaplen as usize);
+
+        let mut header = [0u8; field::RECORD_HEADER_LEN];
+        LittleEndian::write_u32(&mut header[field::TS_SEC], (timestamp / 1_000_000) as u32);
+        LittleEndian::write_u32(&mut header[field::TS_FRAC], (timestamp % 1_000_000) as u32);
+        LittleEndian::write_u32(&mut header[field::INCL_LEN], incl_len as u32);
+        LittleEndian::write_u32(&mut header[field::ORIG_LEN], packet.len() as u32);
+
+        self.sink.write_all(&header)?;
+        self.sink.write_all(&packet[..incl_len])
+    }
+
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    pub fn sink_mut(&mut self) -> &mut S {
+        &mut self.sink
+    }
+
+    pub fn into_sink(self) -> S {
+        self.sink
+    }
+}
+
+/// Reader of classic pcap file, yield `(timestamp, packet)` records.
+#[derive(Debug, Clone)]
+pub struct PcapReader<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+    nanos: bool,
+    link_type: LinkType,
+    snaplen: u32,
+}
+
+impl<'a> PcapReader<'a> {
+    /// Parse file header, both byte orders and nanosecond timestamps are supported.
+    pub fn new(data: &'a [u8]) -> Result<Self> {
+        if data.len() < field::HEADER_LEN {
+            return Err(Error::WrongLengthForPcap);
+        }
+
+        let (big_endian, nanos) = match LittleEndian::read_u32(&data[field::MAGIC]) {
+            consts::MAGIC_MICROS => (false, false),
+            consts::MAGIC_NANOS => (false, true),
+            _ => match BigEndian::read_u32(&data[field::MAGIC]) {
+                consts::MAGIC_MICROS => (true, false),
+                consts::MAGIC_NANOS => (true, true),
+                _ => return Err(Error::UnknownPcapFormat),
+            },
+        };
+
+        let mut reader = Self {
+            data,
+            big_endian,
+            nanos,
+            link_type: LinkType::Unknown(0),
+            snaplen: 0,
+        };
+
+        reader.link_type = LinkType::from(reader.read_u32(&data[field::NETWORK]));
+        reader.snaplen = reader.read_u32(&data[field::SNAPLEN]);
+        reader.data = &data[field::HEADER_LEN..];
+
+        Ok(reader)
+    }
+
+    pub fn link_type(&self) -> LinkType {
+        self.link_type
+    }
+
+    pub fn snaplen(&self) -> u32 {
+        self.snaplen
+    }
+
+    fn read_u32(&self, buf: &[u8]) -> u32 {
+        if self.big_endian {
+            BigEndian::read_u32(buf)
+        } else {
+            LittleEndian::read_u32(buf)
+        }
+    }
+}
+
+impl<'a> Iterator for PcapReader<'a> {
+    type Item = Result<(u64, &'a [u8])>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+
+        let data = self.data;
+
+        if data.len() < field::RECORD_HEADER_LEN {
+            self.data = &[];
+            return Some(Err(Error::WrongLengthForPcap));
+        }
+
+        let sec = self.read_u32(&data[field::TS_SEC]) as u64;
+        let frac = self.read_u32(&data[field::TS_FRAC]) as u64;
+        let incl_len = self.read_u32(&data[field::INCL_LEN]) as usize;
+
+        let end = field::RECORD_HEADER_LEN + incl_len;
+        if data.len() < end {
+            self.data = &[];
+            return Some(Err(Error::WrongLengthForPcap));
+        }
+
+        let micros = if self.nanos { frac / 1000 } else { frac };
+
+        self.data = &data[end..];
+        Some(Ok((
+            sec * 1_000_000 + micros,
+            &data[field::RECORD_HEADER_LEN..end],
+        )))
+    }
+}
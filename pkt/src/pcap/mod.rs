@@ -0,0 +1,71 @@
+//! pcap and pcapng capture file.
+//!
+//! Writers serialise records into a caller provided [Sink], readers parse records
+//! from a byte slice. Timestamps are in microseconds.
+
+mod sink;
+pub use sink::*;
+
+pub mod classic;
+pub use classic::{PcapReader, PcapWriter};
+
+pub mod ng;
+pub use ng::{PcapngReader, PcapngWriter};
+
+use crate::Result;
+
+/// Writer of packet records, implemented by [PcapWriter] and [PcapngWriter].
+pub trait PacketWriter {
+    /// Write a packet record with timestamp in microseconds.
+    fn write_packet(&mut self, timestamp: u64, packet: &[u8]) -> Result<()>;
+}
+
+impl<S: Sink> PacketWriter for PcapWriter<S> {
+    fn write_packet(&mut self, timestamp: u64, packet: &[u8]) -> Result<()> {
+        PcapWriter::write_packet(self, timestamp, packet)
+    }
+}
+
+impl<S: Sink> PacketWriter for PcapngWriter<S> {
+    fn write_packet(&mut self, timestamp: u64, packet: &[u8]) -> Result<()> {
+        PcapngWriter::write_packet(self, timestamp, packet)
+    }
+}
+
+pub mod consts {
+    pub const LINKTYPE_ETHERNET: u32 = 1;
+    pub const LINKTYPE_RAW: u32 = 101;
+
+    /// Default maximum length of captured packet.
+    pub const DEFAULT_SNAPLEN: u32 = 65535;
+}
+
+/// Link-layer header type of capture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkType {
+    /// DLT_EN10MB
+    Ethernet,
+    /// DLT_RAW, raw IPv4 or IPv6 packet
+    Raw,
+    Unknown(u32),
+}
+
+impl From<u32> for LinkType {
+    fn from(v: u32) -> Self {
+        match v {
+            consts::LINKTYPE_ETHERNET => Self::Ethernet,
+            consts::LINKTYPE_RAW => Self::Raw,
+            _ => Self::Unknown(v),
+        }
+    }
+}
+
+impl From<LinkType> for u32 {
+    fn from(v: LinkType) -> u32 {
+        match v {
+            LinkType::Ethernet => consts::LINKTYPE_ETHERNET,
+            LinkType::Raw => consts::LINKTYPE_RAW,
+            LinkType::Unknown(v) => v,
+        }
+    }
+}
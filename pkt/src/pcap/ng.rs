@@ -0,0 +1,378 @@
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+use super::{LinkType, Sink};
+use crate::{Error, Result};
+
+pub mod field {
+    use crate::utils::field::Field;
+
+    pub const BLOCK_TYPE: Field = 0..4;
+    pub const BLOCK_LEN: Field = 4..8;
+    pub const BLOCK_BODY: usize = BLOCK_LEN.end;
+    /// Length of block type, leading and trailing block length.
+    pub const BLOCK_OVERHEAD: usize = 12;
+
+    pub const SHB_BYTE_ORDER: Field = 8..12;
+    pub const SHB_VERSION_MAJOR: Field = 12..14;
+    pub const SHB_VERSION_MINOR: Field = 14..16;
+    pub const SHB_SECTION_LEN: Field = 16..24;
+    pub const SHB_LEN: usize = 28;
+
+    pub const IDB_LINK_TYPE: Field = 8..10;
+    pub const IDB_SNAPLEN: Field = 12..16;
+    pub const IDB_OPTIONS: usize = IDB_SNAPLEN.end;
+    pub const IDB_LEN: usize = 20;
+
+    pub const EPB_INTERFACE_ID: Field = 8..12;
+    pub const EPB_TS_HIGH: Field = 12..16;
+    pub const EPB_TS_LOW: Field = 16..20;
+    pub const EPB_CAPTURED_LEN: Field = 20..24;
+    pub const EPB_ORIG_LEN: Field = 24..28;
+    pub const EPB_DATA: usize = EPB_ORIG_LEN.end;
+
+    pub const SPB_ORIG_LEN: Field = 8..12;
+    pub const SPB_DATA: usize = SPB_ORIG_LEN.end;
+
+    pub const OPTION_CODE: Field = 0..2;
+    pub const OPTION_LEN: Field = 2..4;
+    pub const OPTION_VALUE: usize = OPTION_LEN.end;
+}
+
+pub mod consts {
+    pub const BLOCK_SHB: u32 = 0x0a0d_0d0a;
+    pub const BLOCK_IDB: u32 = 0x0000_0001;
+    pub const BLOCK_SPB: u32 = 0x0000_0003;
+    pub const BLOCK_EPB: u32 = 0x0000_0006;
+
+    pub const BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+
+    pub const VERSION_MAJOR: u16 = 1;
+    pub const VERSION_MINOR: u16 = 0;
+
+    pub const OPT_END: u16 = 0;
+    pub const OPT_IF_TSRESOL: u16 = 9;
+
+    /// Default timestamp resolution is 10^-6 second.
+    pub const DEFAULT_TSRESOL: u8 = 6;
+}
+
+/// Round length up to 32-bit boundary.
+fn padded(len: usize) -> usize {
+    len.div_ceil(4) * 4
+}
+
+/// Writer of pcapng file with one section and one interface, in little endian with
+/// microsecond timestamps.
+#[derive(Debug)]
+pub struct PcapngWriter<S> {
+    sink: S,
+    snaplen: u32,
+}
+
+impl<S: Sink> PcapngWriter<S> {
+    /// Create writer and write section header and interface description into sink.
+    pub fn new(mut sink: S, link_type: LinkType, snaplen: u32) -> Result<Self> {
+        let mut shb = [0u8; field::SHB_LEN];
+        LittleEndian::write_u32(&mut shb[field::BLOCK_TYPE], consts::BLOCK_SHB);
+        LittleEndian::write_u32(&mut shb[field::BLOCK_LEN], field::SHB_LEN as u32);
+        LittleEndian::write_u32(&mut shb[field::SHB_BYTE_ORDER], consts::BYTE_ORDER_MAGIC);
+        LittleEndian::write_u16(&mut shb[field::SHB_VERSION_MAJOR], consts::VERSION_MAJOR);
+        LittleEndian::write_u16(&mut shb[field::SHB_VERSION_MINOR], consts::VERSION_MINOR);
+        // Section length is not specified.
+        LittleEndian::write_i64(&mut shb[field::SHB_SECTION_LEN], -1);
+        LittleEndian::write_u32(&mut shb[field::SHB_LEN - 4..], field::SHB_LEN as u32);
+        sink.write_all(&shb)?;
+
+        let mut idb = [0u8; field::IDB_LEN];
+        LittleEndian::write_u32(&mut idb[field::BLOCK_TYPE], consts::BLOCK_IDB);
+        LittleEndian::write_u32(&mut idb[field::BLOCK_LEN], field::IDB_LEN as u32);
+        LittleEndian::write_u16(&mut idb[field::IDB_LINK_TYPE], u32::from(link_type) as u16);
+        LittleEndian::write_u32(&mut idb[field::IDB_SNAPLEN], snaplen);
+        LittleEndian::write_u32(&mut idb[field::IDB_LEN - 4..], field::IDB_LEN as u32);
+        sink.write_all(&idb)?;
+
+        Ok(Self { sink, snaplen })
+    }
+
+    /// Write an enhanced packet block, packet longer than snaplen is truncated.
+    pub fn write_packet(&mut self, timestamp: u64, packet: &[u8]) -> Result<()> {
+        let captured_len = packet.len().min(self.snaplen as usize);
+        let block_len = field::EPB_DATA + padded(captured_len) + 4;
+
+        let mut header = [0u8; field::EPB_DATA];
+        LittleEndian::write_u32(&mut header[field::BLOCK_TYPE], consts::BLOCK_EPB);
+        LittleEndian::write_u32(&mut header[field::BLOCK_LEN], block_len as u32);
+        LittleEndian::write_u32(&mut header[field::EPB_TS_HIGH], (timestamp >> 32) as u32);
+        LittleEndian::write_u32(&mut header[field::EPB_TS_LOW], timestamp as u32);
+        LittleEndian::write_u32(&mut header[field::EPB_CAPTURED_LEN], captured_len as u32);
+        LittleEndian::write_u32(&mut header[field::EPB_ORIG_LEN], packet.len() as u32);
+        self.sink.write_all(&header)?;
+
+        self.sink.write_all(&packet[..captured_len])?;
+        self.sink
+            .write_all(&[0u8; 3][..padded(captured_len) - captured_len])?;
+
+        let mut trailer = [0u8; 4];
+        LittleEndian::write_u32(&mut trailer, block_len as u32);
+        self.sink.write_all(&trailer)
+    }
+
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    pub fn sink_mut(&mut self) -> &mut S {
+        &mut self.sink
+    }
+
+    pub fn into_sink(self) -> S {
+        self.sink
+    }
+}
+
+/// Reader of pcapng file, yield `(timestamp, packet)` records of enhanced and simple
+/// packet blocks.
+///
+/// Link type and timestamp resolution are taken from the first interface description.
+#[derive(Debug, Clone)]
+pub struct PcapngReader<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+    link_type: Option<LinkType>,
+    tsresol: u8,
+}
+
+impl<'a> PcapngReader<'a> {
+    /// Parse the leading section header block.
+    pub fn new(data: &'a [u8]) -> Result<Self> {
+        if data.len() < field::SHB_LEN
+            || LittleEndian::read_u32(&data[field::BLOCK_TYPE]) != consts::BLOCK_SHB
+        {
+            return Err(Error::UnknownPcapFormat);
+        }
+
+        let big_endian = match LittleEndian::read_u32(&data[field::SHB_BYTE_ORDER]) {
+            consts::BYTE_ORDER_MAGIC => false,
+            _ if BigEndian::read_u32(&data[field::SHB_BYTE_ORDER]) == consts::BYTE_ORDER_MAGIC => {
+                true
+            }
+            _ => return Err(Error::UnknownPcapFormat),
+        };
+
+        Ok(Self {
+            data,
+            big_endian,
+            link_type: None,
+            tsresol: consts::DEFAULT_TSRESOL,
+        })
+    }
+
+    /// Return the link type, available after the interface description is read.
+    pub fn link_type(&self) -> Option<LinkType> {
+        self.link_type
+    }
+
+    fn read_u16(&self, buf: &[u8]) -> u16 {
+        if self.big_endian {
+            BigEndian::read_u16(buf)
+        } else {
+            LittleEndian::read_u16(buf)
+        }
+    }
+
+    fn read_u32(&self, buf: &[u8]) -> u32 {
+        if self.big_endian {
+            BigEndian::read_u32(buf)
+        } else {
+            LittleEndian::read_u32(buf)
+        }
+    }
+
+    /// Convert raw timestamp into microseconds, saturate at `u64::MAX`.
+    fn to_micros(&self, raw: u64) -> u64 {
+        if self.tsresol & 0x80 != 0 {
+            // Negative power of 2.
+            let shift = (self.tsresol & 0x7f) as u32;
+            let micros = (raw as u128 * 1_000_000).checked_shr(shift).unwrap_or(0);
+            micros.min(u64::MAX as u128) as u64
+        } else if self.tsresol >= 6 {
+            // Resolution finer than 10^-19 second rounds every timestamp down to 0.
+            match 10u64.checked_pow((self.tsresol - 6) as u32) {
+                Some(divisor) => raw / divisor,
+                None => 0,
+            }
+        } else {
+            raw.saturating_mul(10u64.pow((6 - self.tsresol) as u32))
+        }
+    }
+
+    fn read_interface(&mut self, block: &[u8]) -> Result<()> {
+        if block.len() < field::IDB_LEN {
+            return Err(Error::WrongLengthForPcap);
+        }
+
+        if self.link_type.is_some() {
+            return Ok(());
+        }
+
+        self.link_type = Some(LinkType::from(
+            self.read_u16(&block[field::IDB_LINK_TYPE]) as u32
+        ));
+
+        let mut options = &block[field::IDB_OPTIONS..block.len() - 4];
+        while options.len() >= field::OPTION_VALUE {
+            let code = self.read_u16(&options[field::OPTION_CODE]);
+            let len = self.read_u16(&options[field::OPTION_LEN]) as usize;
+            let end = field::OPTION_VALUE + padded(len);
+
+            if code == consts::OPT_END || options.len() < end {
+                break;
+            }
+
+            if code == consts::OPT_IF_TSRESOL && len == 1 {
+                self.tsresol = options[field::OPTION_VALUE];
+            }
+
+            options = &options[end..];
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for PcapngReader<'a> {
+    type Item = Result<(u64, &'a [u8])>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.data.is_empty() {
+                return None;
+            }
+
+            let data = self.data;
+
+            if data.len() < field::BLOCK_OVERHEAD {
+                self.data = &[];
+                return Some(Err(Error::WrongLengthForPcap));
+            }
+
+            let block_type = self.read_u32(&data[field::BLOCK_TYPE]);
+            let block_len = self.read_u32(&data[field::BLOCK_LEN]) as usize;
+
+            if block_len < field::BLOCK_OVERHEAD || data.len() < block_len {
+                self.data = &[];
+                return Some(Err(Error::WrongLengthForPcap));
+            }
+
+            let block = &data[..block_len];
+            self.data = &data[block_len..];
+
+            match block_type {
+                consts::BLOCK_IDB => {
+                    if let Err(e) = self.read_interface(block) {
+                        self.data = &[];
+                        return Some(Err(e));
+                    }
+                }
+                consts::BLOCK_EPB => {
+                    if block_len < field::EPB_DATA + 4 {
+                        self.data = &[];
+                        return Some(Err(Error::WrongLengthForPcap));
+                    }
+
+                    let high = self.read_u32(&block[field::EPB_TS_HIGH]) as u64;
+                    let low = self.read_u32(&block[field::EPB_TS_LOW]) as u64;
+                    let captured_len = self.read_u32(&block[field::EPB_CAPTURED_LEN]) as usize;
+
+                    if block_len < field::EPB_DATA + captured_len + 4 {
+                        self.data = &[];
+                        return Some(Err(Error::WrongLengthForPcap));
+                    }
+
+                    let timestamp = self.to_micros((high << 32) | low);
+                    let packet = &block[field::EPB_DATA..field::EPB_DATA + captured_len];
+                    return Some(Ok((timestamp, packet)));
+                }
+                consts::BLOCK_SPB => {
+                    if block_len < field::SPB_DATA + 4 {
+                        self.data = &[];
+                        return Some(Err(Error::WrongLengthForPcap));
+                    }
+
+                    // Simple packet block has no captured length, take the whole body.
+                    let orig_len = self.read_u32(&block[field::SPB_ORIG_LEN]) as usize;
+                    let len = orig_len.min(block_len - field::SPB_DATA - 4);
+                    return Some(Ok((0, &block[field::SPB_DATA..field::SPB_DATA + len])));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pcap::{BufferSink, LinkType, PcapReader, PcapWriter, PcapngReader, PcapngWriter};
+
+    const FRAME: &[u8] = &[
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 1, 2, 3, 4, 5, 6, 0x08, 0x06, 0xaa,
+    ];
+
+    #[test]
+    fn test_pcap_round_trip() {
+        let mut writer =
+            PcapWriter::new(BufferSink::new([0u8; 128]), LinkType::Ethernet, 65535).unwrap();
+        writer.write_packet(1_500_000, FRAME).unwrap();
+        writer.write_packet(2_000_001, &FRAME[..4]).unwrap();
+
+        let sink = writer.into_sink();
+        let mut reader = PcapReader::new(sink.data()).unwrap();
+        assert_eq!(reader.link_type(), LinkType::Ethernet);
+        assert_eq!(reader.next().unwrap().unwrap(), (1_500_000, FRAME));
+        assert_eq!(reader.next().unwrap().unwrap(), (2_000_001, &FRAME[..4]));
+        assert!(reader.next().is_none());
+
+        // Sink full.
+        assert!(PcapWriter::new(BufferSink::new([0u8; 8]), LinkType::Raw, 65535).is_err());
+    }
+
+    #[test]
+    fn test_pcapng_round_trip() {
+        let mut writer = PcapngWriter::new(BufferSink::new([0u8; 256]), LinkType::Raw, 8).unwrap();
+        writer.write_packet(0x1_0000_0002, FRAME).unwrap();
+        writer.write_packet(3, &FRAME[..5]).unwrap();
+
+        let sink = writer.into_sink();
+        let mut reader = PcapngReader::new(sink.data()).unwrap();
+        assert_eq!(
+            reader.next().unwrap().unwrap(),
+            (0x1_0000_0002, &FRAME[..8])
+        );
+        assert_eq!(reader.link_type(), Some(LinkType::Raw));
+        assert_eq!(reader.next().unwrap().unwrap(), (3, &FRAME[..5]));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_timestamp_resolution() {
+        let writer = PcapngWriter::new(BufferSink::new([0u8; 64]), LinkType::Raw, 8).unwrap();
+        let sink = writer.into_sink();
+        let mut reader = PcapngReader::new(sink.data()).unwrap();
+
+        let cases = [
+            (9, 1_500_000_000, 1_500_000),
+            (26, u64::MAX, 0),
+            (127, u64::MAX, 0),
+            (0, 2, 2_000_000),
+            (0, u64::MAX, u64::MAX),
+            (0x80 | 10, 1024, 1_000_000),
+            (0x80, u64::MAX, u64::MAX),
+            (0xff, u64::MAX, 0),
+        ];
+
+        for (tsresol, raw, micros) in cases.iter() {
+            reader.tsresol = *tsresol;
+            assert_eq!(reader.to_micros(*raw), *micros);
+        }
+    }
+}
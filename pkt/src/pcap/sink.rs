@@ -0,0 +1,60 @@
+use crate::{Error, Result};
+
+/// Destination of capture writer.
+pub trait Sink {
+    /// Write all of data into sink.
+    fn write_all(&mut self, data: &[u8]) -> Result<()>;
+}
+
+impl<S: Sink + ?Sized> Sink for &mut S {
+    fn write_all(&mut self, data: &[u8]) -> Result<()> {
+        (**self).write_all(data)
+    }
+}
+
+/// Sink over a fixed byte buffer.
+#[derive(Debug)]
+pub struct BufferSink<T> {
+    buffer: T,
+    pos: usize,
+}
+
+impl<T: AsRef<[u8]>> BufferSink<T> {
+    pub fn new(buffer: T) -> Self {
+        Self { buffer, pos: 0 }
+    }
+
+    /// Return the written data.
+    pub fn data(&self) -> &[u8] {
+        &self.buffer.as_ref()[..self.pos]
+    }
+
+    /// Return the length of written data.
+    pub fn len(&self) -> usize {
+        self.pos
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos == 0
+    }
+
+    /// Drop written data.
+    pub fn clear(&mut self) {
+        self.pos = 0;
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Sink for BufferSink<T> {
+    fn write_all(&mut self, data: &[u8]) -> Result<()> {
+        let end = self.pos + data.len();
+        let buffer = self.buffer.as_mut();
+
+        if buffer.len() < end {
+            return Err(Error::PcapSinkFull);
+        }
+
+        buffer[self.pos..end].copy_from_slice(data);
+        self.pos = end;
+        Ok(())
+    }
+}
@@ -67,16 +67,17 @@ pub fn pseudo_ip_header(
                 data(&proto_len[..]),
             ]))
         }
-        /*         (&Address::Ipv6(src_addr), &Address::Ipv6(dst_addr)) => { */
-        /* let mut proto_len = [0u8; 8]; */
-        /* proto_len[7] = next_header.into(); */
-        /* NetworkEndian::write_u32(&mut proto_len[0..4], length); */
-        /* combine(&[ */
-        /*     data(src_addr.as_bytes()), */
-        /*     data(dst_addr.as_bytes()), */
-        /*     data(&proto_len[..]), */
-        /* ]) */
-        /* } */
+        (&Address::Ipv6(src_addr), &Address::Ipv6(dst_addr)) => {
+            let mut proto_len = [0u8; 8];
+            proto_len[7] = next_header;
+            NetworkEndian::write_u32(&mut proto_len[0..4], length);
+
+            Ok(combine(&[
+                data(src_addr.as_bytes()),
+                data(dst_addr.as_bytes()),
+                data(&proto_len[..]),
+            ]))
+        }
         _ => Err(Error::SrcAndDstMustSame),
     }
 }
@@ -7,5 +7,11 @@ fn main() -> std::io::Result<()> {
     let buffer = [1u8; 2000];
 
     socket.send(&buffer)?;
+
+    // Stack echoes datagram back in fragments.
+    let mut reply = [0u8; 4096];
+    let len = socket.recv(&mut reply)?;
+    assert_eq!(&reply[..len], &buffer[..]);
+
     Ok(())
 }
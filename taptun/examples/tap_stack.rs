@@ -1,10 +1,11 @@
 use auip::{
-    storage::dynamic::{Addrs, Arp, IpFragment},
-    Interface,
+    storage::dynamic::{Addrs, Arp, IpFragment, PacketRing, Routes, Sockets},
+    time::Instant,
+    Interface, Route, RouteStorage, SocketSet, UdpSocket,
 };
 use auip_pkt::{layer2, layer3};
 use auip_tap::TapTunDevice;
-use std::process::Command;
+use std::{process::Command, time::SystemTime};
 
 fn main() {
     env_logger::init();
@@ -40,12 +41,40 @@ fn main() {
 
     let ip_fragment = IpFragment::new(5);
 
-    let mut iface = Interface::new(device, addrs_storage, arp_storage, ip_fragment);
+    let mut routes = Routes::default();
+    let gateway = layer3::ipv4::Address::parse("192.168.69.100").unwrap();
+    routes.insert(Route::default_via(gateway, 0)).unwrap();
+
+    let mut iface = Interface::new(device, addrs_storage, arp_storage, ip_fragment, routes);
+
+    let mut rx_storage = PacketRing::new(4);
+    let mut tx_storage = PacketRing::new(4);
+    let mut udp = UdpSocket::new(&mut rx_storage, &mut tx_storage);
+    udp.bind(8080).unwrap();
+
+    let mut sockets = Sockets::default();
+    let handle = sockets.add(udp).unwrap();
+
+    let mut buffer = [0u8; 65536];
 
     loop {
         iface.device_mut().poll_read();
-        if let Err(e) = iface.poll() {
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap();
+        let now = Instant::from_millis(now.as_millis() as u64);
+
+        if let Err(e) = iface.poll(now, &mut sockets) {
             log::error!("{:?}", e);
         }
+
+        // Echo datagrams back, large ones are sent in fragments.
+        let udp = sockets.get_mut(handle).unwrap();
+        while let Ok(Some((len, remote))) = udp.recv_from(&mut buffer) {
+            log::info!("Echo {} bytes to {:?}", len, remote);
+            if let Err(e) = udp.send_to(&buffer[..len], remote) {
+                log::error!("{:?}", e);
+            }
+        }
     }
 }